@@ -0,0 +1,85 @@
+//! Scalar vs SIMD kernels. Compare the two groups with and without `--features simd`:
+//!
+//!     cargo bench --bench kernels
+//!     cargo bench --bench kernels --features simd
+//!
+//! The `blocks` group runs one hand-written kernel over the raw columns three ways: a single
+//! full-column loop, blocked iteration, and blocked iteration with prefetch hints.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use ddd_dod_soa::{Money, OrderId, OrderSoA, Status, DEFAULT_BLOCK_ROWS};
+use std::hint::black_box;
+
+fn populate(n: usize) -> OrderSoA {
+    let mut soa = OrderSoA::with_capacity(n);
+    for i in 0..n as u64 {
+        let st = Status::ALL[(i % 3) as usize];
+        soa.push(OrderId(i), Money((i % 1000) as f64 * 0.5), st, i);
+    }
+    soa
+}
+
+fn sum_by_status(c: &mut Criterion) {
+    let mut g = c.benchmark_group("sum_by_status");
+    for n in [10_000usize, 1_000_000] {
+        let soa = populate(n);
+        g.throughput(Throughput::Elements(n as u64));
+        g.bench_with_input(BenchmarkId::new("scalar", n), &soa, |b, soa| {
+            b.iter(|| soa.sum_by_status_scalar(black_box(Status::Completed)))
+        });
+        g.bench_with_input(BenchmarkId::new("dispatch", n), &soa, |b, soa| {
+            b.iter(|| soa.sum_by_status(black_box(Status::Completed)))
+        });
+    }
+    g.finish();
+}
+
+fn filter_indices(c: &mut Criterion) {
+    let mut g = c.benchmark_group("filter_indices");
+    for n in [10_000usize, 1_000_000] {
+        let soa = populate(n);
+        g.throughput(Throughput::Elements(n as u64));
+        g.bench_with_input(BenchmarkId::new("scalar", n), &soa, |b, soa| {
+            b.iter(|| soa.filter_indices_scalar(black_box(Money(250.0)), Status::Pending))
+        });
+        g.bench_with_input(BenchmarkId::new("dispatch", n), &soa, |b, soa| {
+            b.iter(|| soa.filter_indices(black_box(Money(250.0)), Status::Pending))
+        });
+    }
+    g.finish();
+}
+
+/// Completed total, then the largest completed amount: two passes over the same rows.
+fn two_pass(statuses: &[Status], amounts: &[f64]) -> (f64, f64) {
+    let rows = || statuses.iter().zip(amounts);
+    let done = |&(&s, _): &(&Status, &f64)| s == Status::Completed;
+    let total = rows().filter(done).map(|(_, &a)| a).sum();
+    let max = rows().filter(done).map(|(_, &a)| a).fold(0.0, f64::max);
+    (total, max)
+}
+
+fn blocks(c: &mut Criterion) {
+    let mut g = c.benchmark_group("blocks");
+    for n in [1_000_000usize, 10_000_000] {
+        let soa = populate(n);
+        g.throughput(Throughput::Elements(n as u64));
+        g.bench_with_input(BenchmarkId::new("full_column", n), &soa, |b, soa| {
+            b.iter(|| two_pass(black_box(soa.statuses()), soa.amounts()))
+        });
+        for ahead in [0, 2] {
+            let id = BenchmarkId::new(format!("blocked_prefetch_{ahead}"), n);
+            g.bench_with_input(id, &soa, |b, soa| {
+                b.iter(|| {
+                    soa.blocks(DEFAULT_BLOCK_ROWS)
+                        .prefetch(black_box(ahead))
+                        .map(|blk| two_pass(blk.statuses, blk.amounts))
+                        .fold((0.0, 0.0), |(t, m), (bt, bm)| (t + bt, f64::max(m, bm)))
+                })
+            });
+        }
+    }
+    g.finish();
+}
+
+criterion_group!(benches, sum_by_status, filter_indices, blocks);
+criterion_main!(benches);
@@ -0,0 +1,130 @@
+//! AoS (`OrderAoS`) vs SoA (`OrderSoA`) vs sharded SoA (`ShardedOrderStore`) on the same
+//! workloads:
+//!
+//!     cargo bench --bench layouts
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use ddd_dod_soa::{Money, OrderAoS, OrderId, OrderSoA, ShardedOrderStore, Status};
+use std::hint::black_box;
+
+const SIZES: [usize; 3] = [10_000, 100_000, 1_000_000];
+const SHARDS: usize = 8;
+
+fn row(i: u64) -> (OrderId, Money, Status, u64) {
+    (
+        OrderId(i),
+        Money((i % 1000) as f64 * 0.5),
+        Status::ALL[(i % 3) as usize],
+        i,
+    )
+}
+
+fn aos(n: usize) -> OrderAoS {
+    let mut s = OrderAoS::with_capacity(n);
+    for i in 0..n as u64 {
+        let (id, m, st, ts) = row(i);
+        s.push(id, m, st, ts);
+    }
+    s
+}
+
+fn soa(n: usize) -> OrderSoA {
+    let mut s = OrderSoA::with_capacity(n);
+    for i in 0..n as u64 {
+        let (id, m, st, ts) = row(i);
+        s.push(id, m, st, ts);
+    }
+    s
+}
+
+fn sharded(n: usize) -> ShardedOrderStore {
+    let s = ShardedOrderStore::with_shards(SHARDS, n / SHARDS + 1);
+    for i in 0..n as u64 {
+        let (id, m, st, ts) = row(i);
+        s.add(id, m, st, ts).unwrap();
+    }
+    s
+}
+
+fn push(c: &mut Criterion) {
+    let mut g = c.benchmark_group("push");
+    g.sample_size(10);
+    for n in SIZES {
+        g.throughput(Throughput::Elements(n as u64));
+        g.bench_function(BenchmarkId::new("aos", n), |b| b.iter(|| aos(black_box(n))));
+        g.bench_function(BenchmarkId::new("soa", n), |b| b.iter(|| soa(black_box(n))));
+        g.bench_function(BenchmarkId::new("sharded", n), |b| {
+            b.iter(|| sharded(black_box(n)))
+        });
+    }
+    g.finish();
+}
+
+fn sum_by_status(c: &mut Criterion) {
+    let mut g = c.benchmark_group("layout_sum_by_status");
+    for n in SIZES {
+        let (a, s, sh) = (aos(n), soa(n), sharded(n));
+        g.throughput(Throughput::Elements(n as u64));
+        g.bench_function(BenchmarkId::new("aos", n), |b| {
+            b.iter(|| a.sum_by_status(black_box(Status::Completed)))
+        });
+        g.bench_function(BenchmarkId::new("soa", n), |b| {
+            b.iter(|| s.sum_by_status(black_box(Status::Completed)))
+        });
+        g.bench_function(BenchmarkId::new("sharded", n), |b| {
+            b.iter(|| sh.sum_by_status(black_box(Status::Completed)))
+        });
+    }
+    g.finish();
+}
+
+fn filtered_scan(c: &mut Criterion) {
+    let mut g = c.benchmark_group("layout_filter_indices");
+    for n in SIZES {
+        let (a, s, sh) = (aos(n), soa(n), sharded(n));
+        g.throughput(Throughput::Elements(n as u64));
+        g.bench_function(BenchmarkId::new("aos", n), |b| {
+            b.iter(|| a.filter_indices(black_box(Money(250.0)), Status::Pending))
+        });
+        g.bench_function(BenchmarkId::new("soa", n), |b| {
+            b.iter(|| s.filter_indices(black_box(Money(250.0)), Status::Pending))
+        });
+        g.bench_function(BenchmarkId::new("sharded", n), |b| {
+            b.iter(|| sh.filter_indices(black_box(Money(250.0)), Status::Pending))
+        });
+    }
+    g.finish();
+}
+
+fn retain(c: &mut Criterion) {
+    let mut g = c.benchmark_group("layout_retain");
+    g.sample_size(10);
+    for n in SIZES {
+        g.throughput(Throughput::Elements(n as u64));
+        g.bench_function(BenchmarkId::new("aos", n), |b| {
+            b.iter_batched(
+                || aos(n),
+                |mut a| a.retain(|r| r.status != Status::Cancelled),
+                BatchSize::LargeInput,
+            )
+        });
+        g.bench_function(BenchmarkId::new("soa", n), |b| {
+            b.iter_batched(
+                || soa(n),
+                |mut s| s.retain(|v| v.status() != Status::Cancelled),
+                BatchSize::LargeInput,
+            )
+        });
+        g.bench_function(BenchmarkId::new("sharded", n), |b| {
+            b.iter_batched(
+                || sharded(n),
+                |sh| sh.retain(|v| v.status() != Status::Cancelled),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    g.finish();
+}
+
+criterion_group!(benches, push, sum_by_status, filtered_scan, retain);
+criterion_main!(benches);
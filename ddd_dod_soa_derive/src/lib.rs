@@ -0,0 +1,167 @@
+//! `#[derive(Soa)]`: generate the SoA kernel and its zero-copy views from a plain domain struct.
+//!
+//! For `struct Order { id: u64, amount: f64 }` this emits, next to the struct:
+//!
+//! - `OrderSoA` — one `Vec` column per field, kept the same length;
+//! - `OrderView<'a>` — read-only row view with one getter per field;
+//! - `OrderMut<'a>` — mutable row view with getters and `set_<field>` setters;
+//! - `push`, `get`, `view`, `view_mut`, `iter`, `retain`, `len`, `is_empty`, `with_capacity`.
+//!
+//! Fields must be `Copy` (views return values, not references). Only non-generic structs with
+//! named fields are supported.
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields};
+
+#[proc_macro_derive(Soa)]
+pub fn derive_soa(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    if !input.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &input.generics,
+            "#[derive(Soa)] does not support generic structs",
+        ));
+    }
+    let fields = match &input.data {
+        Data::Struct(s) => match &s.fields {
+            Fields::Named(f) => &f.named,
+            _ => {
+                return Err(Error::new(
+                    Span::call_site(),
+                    "#[derive(Soa)] requires a struct with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new(
+                Span::call_site(),
+                "#[derive(Soa)] can only be used on structs",
+            ))
+        }
+    };
+    if fields.is_empty() {
+        return Err(Error::new(
+            Span::call_site(),
+            "#[derive(Soa)] needs at least one field",
+        ));
+    }
+
+    let vis = &input.vis;
+    let name = &input.ident;
+    let soa = format_ident!("{}SoA", name);
+    let view = format_ident!("{}View", name);
+    let mutv = format_ident!("{}Mut", name);
+
+    let names: Vec<_> = fields.iter().map(|f| f.ident.clone().unwrap()).collect();
+    let tys: Vec<_> = fields.iter().map(|f| &f.ty).collect();
+    let setters: Vec<_> = names.iter().map(|n| format_ident!("set_{}", n)).collect();
+    let first = &names[0];
+
+    let soa_doc = format!("Struct-of-Arrays storage for [`{name}`], one column per field.");
+    let view_doc = format!("Zero-copy read-only view of one [`{soa}`] row.");
+    let mut_doc = format!("Zero-copy mutable view of one [`{soa}`] row.");
+
+    Ok(quote! {
+        #[doc = #soa_doc]
+        #[derive(Default, Clone, Debug)]
+        #vis struct #soa {
+            #( #names: ::std::vec::Vec<#tys>, )*
+        }
+
+        impl #soa {
+            pub fn with_capacity(cap: usize) -> Self {
+                Self { #( #names: ::std::vec::Vec::with_capacity(cap), )* }
+            }
+
+            #[inline]
+            pub fn len(&self) -> usize {
+                self.#first.len()
+            }
+
+            #[inline]
+            pub fn is_empty(&self) -> bool {
+                self.len() == 0
+            }
+
+            /// Scatter a row into the columns; returns its row index.
+            pub fn push(&mut self, row: #name) -> usize {
+                #( self.#names.push(row.#names); )*
+                self.len() - 1
+            }
+
+            /// Gather row `idx` back into an owned value.
+            pub fn get(&self, idx: usize) -> #name {
+                #name { #( #names: self.#names[idx], )* }
+            }
+
+            pub fn view(&self, idx: usize) -> #view<'_> {
+                #view { soa: self, idx }
+            }
+
+            pub fn view_mut(&mut self, idx: usize) -> #mutv<'_> {
+                #mutv { soa: self, idx }
+            }
+
+            pub fn iter(&self) -> impl ::std::iter::Iterator<Item = #view<'_>> {
+                (0..self.len()).map(move |i| self.view(i))
+            }
+
+            /// Compact in place, keeping rows whose predicate returns true. Keeps columns aligned.
+            pub fn retain<F: Fn(#view<'_>) -> bool>(&mut self, f: F) {
+                let mut write = 0usize;
+                for read in 0..self.len() {
+                    if f(self.view(read)) {
+                        if write != read {
+                            #( self.#names[write] = self.#names[read]; )*
+                        }
+                        write += 1;
+                    }
+                }
+                #( self.#names.truncate(write); )*
+            }
+        }
+
+        #[doc = #view_doc]
+        #[derive(Copy, Clone)]
+        #vis struct #view<'a> {
+            soa: &'a #soa,
+            idx: usize,
+        }
+
+        impl<'a> #view<'a> {
+            #(
+                #[inline]
+                pub fn #names(&self) -> #tys {
+                    self.soa.#names[self.idx]
+                }
+            )*
+        }
+
+        #[doc = #mut_doc]
+        #vis struct #mutv<'a> {
+            soa: &'a mut #soa,
+            idx: usize,
+        }
+
+        impl<'a> #mutv<'a> {
+            #(
+                #[inline]
+                pub fn #names(&self) -> #tys {
+                    self.soa.#names[self.idx]
+                }
+                #[inline]
+                pub fn #setters(&mut self, v: #tys) {
+                    self.soa.#names[self.idx] = v;
+                }
+            )*
+        }
+    })
+}
@@ -0,0 +1,11 @@
+//! Arbitrary bytes as a stream of push / remove / update / retain / compact / sort operations
+//! against `OrderSoA`, checked against a naive model after every step.
+//!
+//! `cargo +nightly fuzz run kernel_ops`; replay a crash with
+//! `cargo +nightly fuzz run kernel_ops fuzz/artifacts/kernel_ops/<file>`.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| ddd_dod_soa::fuzzing::run(data));
@@ -0,0 +1,108 @@
+#ifndef DDD_DOD_SOA_H
+#define DDD_DOD_SOA_H
+
+/* Generated by cbindgen from src/ffi.rs; do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// A store of orders; create it with `dds_store_new`.
+typedef struct OrderSoA OrderSoA;
+
+// Result code of the fallible calls.
+typedef enum DdsResult {
+  DdsResult_Ok = 0,
+  // A required pointer argument was null.
+  DdsResult_NullPointer = 1,
+  // A status code outside 0..=2.
+  DdsResult_InvalidStatus = 2,
+  // No live row has the given id.
+  DdsResult_UnknownId = 3,
+} DdsResult;
+
+// A new, empty store. Free it with `dds_store_free`.
+OrderSoA *dds_store_new(void);
+
+// A new, empty store with room for `capacity` rows. Free it with `dds_store_free`.
+OrderSoA *dds_store_with_capacity(size_t capacity);
+
+// Free a store. Null is ignored.
+//
+// # Safety
+// `store` must come from `dds_store_new` / `dds_store_with_capacity` and not be used again.
+void dds_store_free(OrderSoA *store);
+
+// Append an order in the default currency.
+//
+// # Safety
+// `store` must be null or a live store pointer not used concurrently.
+enum DdsResult dds_store_push(OrderSoA *store,
+                              uint64_t id,
+                              double amount,
+                              uint8_t status_code,
+                              uint64_t ts);
+
+// Tombstone the order with `id`.
+//
+// # Safety
+// As for `dds_store_push`.
+enum DdsResult dds_store_remove(OrderSoA *store, uint64_t id);
+
+// Drop tombstoned rows so every column index is a live order.
+//
+// # Safety
+// As for `dds_store_push`.
+enum DdsResult dds_store_compact(OrderSoA *store);
+
+// Physical row count (the length of every column), tombstones included. 0 for null.
+//
+// # Safety
+// `store` must be null or a live store pointer.
+size_t dds_store_len(const OrderSoA *store);
+
+// Live order count. 0 for null.
+//
+// # Safety
+// As for `dds_store_len`.
+size_t dds_store_live_len(const OrderSoA *store);
+
+// Whether row `idx` holds a live order; false for null or out of range.
+//
+// # Safety
+// As for `dds_store_len`.
+bool dds_store_is_live(const OrderSoA *store, size_t idx);
+
+// Write the total amount of live orders with `status_code` to `out`.
+//
+// # Safety
+// `store` must be null or a live store pointer; `out` must be null or writable.
+enum DdsResult dds_store_sum_by_status(const OrderSoA *store, uint8_t status_code, double *out);
+
+// The order id column (`len` rows). Valid until the store is next mutated.
+//
+// # Safety
+// `store` must be null or a live store pointer; `len` must be null or writable.
+const uint64_t *dds_store_ids(const OrderSoA *store, size_t *len);
+
+// The amount column (`len` rows). Valid until the store is next mutated.
+//
+// # Safety
+// As for `dds_store_ids`.
+const double *dds_store_amounts(const OrderSoA *store, size_t *len);
+
+// The status column as discriminants (`len` rows). Valid until the store is next mutated.
+//
+// # Safety
+// As for `dds_store_ids`.
+const uint8_t *dds_store_statuses(const OrderSoA *store, size_t *len);
+
+// The timestamp column (`len` rows). Valid until the store is next mutated.
+//
+// # Safety
+// As for `dds_store_ids`.
+const uint64_t *dds_store_timestamps(const OrderSoA *store, size_t *len);
+
+#endif  /* DDD_DOD_SOA_H */
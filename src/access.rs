@@ -0,0 +1,93 @@
+//! Least-privilege handles onto a store.
+//!
+//! `OrderStore::read_only` hands out an `OrderStoreReadOnly`: a point-in-time handle that
+//! dereferences to `&OrderSoA`, so every query and kernel is available and no write is.
+//! `restricted::<S>()` narrows further to a `RestrictedView` over only the columns in `S` (the
+//! markers of `cols`), e.g. amounts and timestamps for a reporting layer that must not see
+//! customers. Both share the store's kernel through an `Arc`: taking one is O(1), they are
+//! `Send + Sync` and `'static`, and later writes to the store copy-on-write rather than show
+//! through.
+
+use crate::{OrderSoA, OrderStore, Projection, Select};
+use std::fmt;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// Read-only snapshot of an `OrderStore`; see the module docs.
+#[derive(Clone, Debug)]
+pub struct OrderStoreReadOnly {
+    soa: Arc<OrderSoA>,
+}
+
+impl OrderStoreReadOnly {
+    /// Narrow to the columns in `S`.
+    pub fn restrict<S: for<'a> Select<'a>>(&self) -> RestrictedView<S> {
+        RestrictedView::new(Arc::clone(&self.soa))
+    }
+}
+
+impl Deref for OrderStoreReadOnly {
+    type Target = OrderSoA;
+
+    fn deref(&self) -> &OrderSoA {
+        &self.soa
+    }
+}
+
+/// Snapshot exposing only the columns in `S`, e.g. `RestrictedView<(cols::Amount,
+/// cols::Timestamp)>`. Rows are the tuples `Projection` yields; tombstoned rows stay hidden.
+pub struct RestrictedView<S> {
+    soa: Arc<OrderSoA>,
+    _cols: PhantomData<fn() -> S>,
+}
+
+impl<S> Clone for RestrictedView<S> {
+    fn clone(&self) -> Self {
+        Self::new(Arc::clone(&self.soa))
+    }
+}
+
+impl<S> fmt::Debug for RestrictedView<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RestrictedView")
+            .field("columns", &std::any::type_name::<S>())
+            .field("len", &self.soa.len())
+            .finish()
+    }
+}
+
+impl<S> RestrictedView<S> {
+    fn new(soa: Arc<OrderSoA>) -> Self {
+        RestrictedView {
+            soa,
+            _cols: PhantomData,
+        }
+    }
+
+    /// Live orders.
+    pub fn live_len(&self) -> usize {
+        self.soa.live_len()
+    }
+}
+
+impl<S: for<'a> Select<'a>> RestrictedView<S> {
+    /// The permitted columns, for iteration, lookups by position and custom kernels.
+    pub fn projection(&self) -> Projection<'_, S> {
+        self.soa.project::<S>()
+    }
+}
+
+impl OrderStore {
+    /// A read-only handle on the current state; see `OrderStoreReadOnly`.
+    pub fn read_only(&self) -> OrderStoreReadOnly {
+        OrderStoreReadOnly {
+            soa: Arc::clone(&self.inner),
+        }
+    }
+
+    /// A handle on the current state that can read only the columns in `S`.
+    pub fn restricted<S: for<'a> Select<'a>>(&self) -> RestrictedView<S> {
+        RestrictedView::new(Arc::clone(&self.inner))
+    }
+}
@@ -0,0 +1,92 @@
+//! Message-broker adapters: order events in from a topic, domain events out to one.
+//!
+//! An `EventSource` is the consuming end, e.g. a Kafka consumer subscribed to an order topic:
+//! `OrderStore::consume_events` polls it, projects each event with `apply`, and commits the
+//! source's position once the whole batch is applied, so a crash between the two replays the
+//! batch rather than losing it. An event the store rejects (an unknown order, say) is reported
+//! and skipped instead of wedging the partition.
+//!
+//! An `EventSink` is the producing end for the store's `Outbox`. `publish_outbox` hands it every
+//! pending event, waits for `flush`, and only then acknowledges them, keeping the outbox's
+//! ordered at-least-once guarantee even for sinks that buffer sends.
+//!
+//! The traits are transport-agnostic; `kafka` (feature `kafka`) implements them over `rdkafka`.
+
+use crate::{OrderEvent, OrderStore, StoreError};
+use std::convert::Infallible;
+
+#[cfg(feature = "kafka")]
+pub mod kafka;
+
+/// Where order events come from.
+pub trait EventSource {
+    type Error;
+
+    /// Up to `max` events received since the last poll, oldest first; empty if none arrived
+    /// within the source's own wait time.
+    fn poll_events(&mut self, max: usize) -> Result<Vec<OrderEvent>, Self::Error>;
+
+    /// Record every event returned so far as processed.
+    fn commit(&mut self) -> Result<(), Self::Error>;
+}
+
+/// Where the store's domain events go.
+pub trait EventSink {
+    type Error;
+
+    /// Hand over one event; `seq` identifies it for deduplication downstream. Sinks may buffer.
+    fn send(&mut self, seq: u64, event: &OrderEvent) -> Result<(), Self::Error>;
+
+    /// Return once every event sent so far is durably accepted.
+    fn flush(&mut self) -> Result<(), Self::Error>;
+}
+
+/// Outcome of one `consume_events` call.
+#[derive(Debug, Default, PartialEq)]
+pub struct Consumed {
+    pub applied: usize,
+    /// Events the store refused, with the reason. They count as processed.
+    pub rejected: Vec<(OrderEvent, StoreError)>,
+}
+
+impl OrderStore {
+    /// Poll up to `max` events from `source`, apply them in order, then commit; see the module
+    /// docs. A source error before the commit leaves the batch uncommitted.
+    pub fn consume_events<S: EventSource + ?Sized>(
+        &mut self,
+        source: &mut S,
+        max: usize,
+    ) -> Result<Consumed, S::Error> {
+        let mut consumed = Consumed::default();
+        let events = source.poll_events(max)?;
+        if events.is_empty() {
+            return Ok(consumed);
+        }
+        for event in events {
+            match self.apply(&event) {
+                Ok(()) => consumed.applied += 1,
+                Err(e) => consumed.rejected.push((event, e)),
+            }
+        }
+        source.commit()?;
+        Ok(consumed)
+    }
+
+    /// Send every pending outbox event to `sink`, flush it, then acknowledge them; returns how
+    /// many were published. On error nothing is acknowledged, so the next call sends the same
+    /// events again. Without `with_outbox` there is nothing to publish.
+    pub fn publish_outbox<S: EventSink + ?Sized>(
+        &mut self,
+        sink: &mut S,
+    ) -> Result<usize, S::Error> {
+        let Some(outbox) = self.outbox.as_mut() else {
+            return Ok(0);
+        };
+        for (seq, event) in outbox.pending() {
+            sink.send(seq, event)?;
+        }
+        sink.flush()?;
+        let mut ack = |_: u64, _: &OrderEvent| Ok::<(), Infallible>(());
+        Ok(outbox.deliver(&mut ack).delivered)
+    }
+}
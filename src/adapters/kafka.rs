@@ -0,0 +1,153 @@
+//! Kafka adapters over `rdkafka` (feature `kafka`).
+//!
+//! Events travel as JSON (the `serde` encoding of `OrderEvent`), keyed by the decimal order id
+//! so every event of one order lands in the same partition and keeps its order. Both ends wrap a
+//! client the caller configured, so brokers, security and tuning stay in the caller's hands. The
+//! consumer must run with `enable.auto.commit=false`: `EventSource::commit` commits its
+//! position explicitly once the store has applied a batch.
+
+use crate::adapters::{EventSink, EventSource};
+use crate::OrderEvent;
+use rdkafka::consumer::{BaseConsumer, CommitMode, Consumer};
+use rdkafka::error::KafkaError;
+use rdkafka::message::Message;
+use rdkafka::producer::{BaseProducer, BaseRecord, Producer};
+use std::fmt;
+use std::time::Duration;
+
+/// Failures of the Kafka adapters.
+#[derive(Debug)]
+pub enum KafkaAdapterError {
+    Kafka(KafkaError),
+    /// A message whose payload is not an encoded `OrderEvent`. The consumer has moved past it.
+    Decode {
+        partition: i32,
+        offset: i64,
+        error: serde_json::Error,
+    },
+}
+
+impl fmt::Display for KafkaAdapterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KafkaAdapterError::Kafka(e) => write!(f, "kafka: {e}"),
+            KafkaAdapterError::Decode {
+                partition,
+                offset,
+                error,
+            } => write!(
+                f,
+                "undecodable event at partition {partition}, offset {offset}: {error}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for KafkaAdapterError {}
+
+impl From<KafkaError> for KafkaAdapterError {
+    fn from(e: KafkaError) -> Self {
+        KafkaAdapterError::Kafka(e)
+    }
+}
+
+/// Message key and payload for `event`.
+pub(crate) fn encode(event: &OrderEvent) -> (String, Vec<u8>) {
+    let payload = serde_json::to_vec(event).expect("order events always serialize");
+    (event.order_id().0.to_string(), payload)
+}
+
+pub(crate) fn decode(payload: &[u8]) -> Result<OrderEvent, serde_json::Error> {
+    serde_json::from_slice(payload)
+}
+
+/// `EventSource` over a subscribed consumer.
+pub struct KafkaEventSource {
+    consumer: BaseConsumer,
+    wait: Duration,
+}
+
+impl KafkaEventSource {
+    /// `consumer` must already be subscribed. A poll waits up to `wait` for its first message.
+    pub fn new(consumer: BaseConsumer, wait: Duration) -> Self {
+        Self { consumer, wait }
+    }
+
+    pub fn consumer(&self) -> &BaseConsumer {
+        &self.consumer
+    }
+}
+
+impl EventSource for KafkaEventSource {
+    type Error = KafkaAdapterError;
+
+    fn poll_events(&mut self, max: usize) -> Result<Vec<OrderEvent>, KafkaAdapterError> {
+        let mut events = Vec::new();
+        let mut wait = self.wait;
+        while events.len() < max {
+            let Some(msg) = self.consumer.poll(wait) else {
+                break;
+            };
+            let msg = msg?;
+            // Tombstones and other empty messages carry no event.
+            if let Some(payload) = msg.payload() {
+                let event = decode(payload).map_err(|error| KafkaAdapterError::Decode {
+                    partition: msg.partition(),
+                    offset: msg.offset(),
+                    error,
+                })?;
+                events.push(event);
+            }
+            // Drain what is already buffered, but wait only for the first message.
+            wait = Duration::ZERO;
+        }
+        Ok(events)
+    }
+
+    fn commit(&mut self) -> Result<(), KafkaAdapterError> {
+        match self.consumer.commit_consumer_state(CommitMode::Sync) {
+            // Nothing consumed since the last commit.
+            Err(KafkaError::ConsumerCommit(rdkafka::types::RDKafkaErrorCode::NoOffset)) => Ok(()),
+            other => Ok(other?),
+        }
+    }
+}
+
+/// `EventSink` producing to one topic.
+pub struct KafkaEventSink {
+    producer: BaseProducer,
+    topic: String,
+    flush_timeout: Duration,
+}
+
+impl KafkaEventSink {
+    /// `flush` fails if the brokers have not acknowledged every send within `flush_timeout`.
+    pub fn new(producer: BaseProducer, topic: impl Into<String>, flush_timeout: Duration) -> Self {
+        Self {
+            producer,
+            topic: topic.into(),
+            flush_timeout,
+        }
+    }
+
+    pub fn producer(&self) -> &BaseProducer {
+        &self.producer
+    }
+}
+
+impl EventSink for KafkaEventSink {
+    type Error = KafkaAdapterError;
+
+    fn send(&mut self, _seq: u64, event: &OrderEvent) -> Result<(), KafkaAdapterError> {
+        let (key, payload) = encode(event);
+        let record = BaseRecord::to(&self.topic).key(&key).payload(&payload);
+        self.producer.send(record).map_err(|(e, _)| e)?;
+        // Serve delivery callbacks so the local queue drains as we go.
+        self.producer.poll(Duration::ZERO);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), KafkaAdapterError> {
+        Ok(self.producer.flush(self.flush_timeout)?)
+    }
+}
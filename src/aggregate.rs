@@ -0,0 +1,196 @@
+//! Aggregate root: the only sanctioned way to change an order's lifecycle.
+//!
+//! `OrderMut::set_status` writes whatever it is given; `OrderAggregate` checks each command
+//! against the order's current state in the store's `StateMachine` (the built-in lifecycle unless
+//! one is configured) first and writes back through the same zero-copy view, state code included.
+//! Each command is checked by the store's validators like `update_with`, and each successful one
+//! is also recorded as an `OrderEvent` in the store's outbox, if it has one.
+
+use crate::audit::RowStamp;
+use crate::query_cache;
+use crate::state_machine::{machine_of, state_of, StateColumn};
+use crate::validation::Validators;
+use crate::views::Tracking;
+use crate::{
+    ChangeFeed, ChangeKind, Field, Money, OrderEvent, OrderId, OrderMut, OrderRow, OrderStore,
+    Outbox, RowChange, RowHandle, Status, StoreError,
+};
+
+/// A loaded order, borrowed mutably from its store for the duration of a command.
+pub struct OrderAggregate<'a> {
+    row: OrderMut<'a>,
+    states: Option<&'a mut StateColumn>,
+    stamp: RowStamp<'a>,
+    handle: RowHandle,
+    changes: &'a mut ChangeFeed,
+    tracking: &'a mut Tracking,
+    validators: &'a Validators,
+    /// The row as last reported to `tracking`.
+    before: OrderRow,
+    outbox: Option<&'a mut Outbox>,
+}
+
+impl OrderStore {
+    /// Load the aggregate for `id`. Loading is not a write: each command that changes the order
+    /// bumps its version and audit stamp, one that fails leaves them alone.
+    pub fn load(&mut self, id: OrderId) -> Result<OrderAggregate<'_>, StoreError> {
+        let idx = self.index_of(id)?;
+        let owned = query_cache::write(&mut self.inner, &mut self.epoch, self.context);
+        let handle = owned.handle_at(idx);
+        let before = OrderRow::from(owned.view_at(idx));
+        let (row, states, stamp) = owned.view_mut_with_states(idx);
+        Ok(OrderAggregate {
+            row,
+            states,
+            stamp,
+            handle,
+            changes: &mut self.changes,
+            tracking: &mut self.tracking,
+            validators: &self.validators,
+            before,
+            outbox: self.outbox.as_mut(),
+        })
+    }
+}
+
+impl OrderAggregate<'_> {
+    pub fn id(&self) -> OrderId {
+        self.row.id()
+    }
+
+    pub fn status(&self) -> Status {
+        self.row.status()
+    }
+
+    pub fn amount(&self) -> Money {
+        self.row.amount()
+    }
+
+    /// Mark a pending order completed.
+    pub fn complete(&mut self) -> Result<(), StoreError> {
+        self.transition(Status::Completed)
+    }
+
+    /// Cancel a pending order.
+    pub fn cancel(&mut self) -> Result<(), StoreError> {
+        self.transition(Status::Cancelled)
+    }
+
+    /// Re-price an order; only pending orders can change amount.
+    pub fn adjust_amount(&mut self, amount: Money) -> Result<(), StoreError> {
+        let id = self.id();
+        if self.status() != Status::Pending {
+            return Err(StoreError::OrderClosed(id));
+        }
+        if amount.0.is_nan() || amount.0 < 0.0 {
+            return Err(StoreError::InvalidAmount(id));
+        }
+        self.row.check_reprice(amount)?;
+        if amount != self.amount() {
+            self.validate(OrderRow {
+                amount,
+                ..self.before
+            })?;
+            self.row.set_amount(amount);
+            self.changed(Field::Amount);
+            self.record(OrderEvent::AmountChanged { id, amount });
+        }
+        Ok(())
+    }
+
+    /// Refund `amount`, up to what was paid: the whole amount of a completed order, the fulfilled
+    /// part of any other. Fails with `RefundExceedsPaid` past that and `InvalidAmount` unless
+    /// `amount` is positive. Records `OrderEvent::Refunded`.
+    pub fn refund(&mut self, amount: Money) -> Result<(), StoreError> {
+        self.validate(self.before)?;
+        self.row.record_refund(amount)?;
+        self.stamp.touch();
+        let id = self.id();
+        self.record(OrderEvent::Refunded { id, amount });
+        Ok(())
+    }
+
+    /// Fulfill `amount` more of a pending order, up to its amount. Fails with `OrderClosed` if
+    /// the order is not pending, `FulfillmentExceedsAmount` past its amount and `InvalidAmount`
+    /// unless `amount` is positive. Records `OrderEvent::PartiallyFulfilled`; completing the
+    /// order stays a separate `complete`.
+    pub fn partially_fulfill(&mut self, amount: Money) -> Result<(), StoreError> {
+        self.validate(self.before)?;
+        self.row.record_fulfillment(amount)?;
+        self.stamp.touch();
+        let id = self.id();
+        self.record(OrderEvent::PartiallyFulfilled { id, amount });
+        Ok(())
+    }
+
+    pub fn amount_refunded(&self) -> Money {
+        Money(self.row.amount_refunded())
+    }
+
+    pub fn amount_fulfilled(&self) -> Money {
+        Money(self.row.amount_fulfilled())
+    }
+
+    /// Move to the first state filed under `to` that the store's state machine allows from the
+    /// current one; see `OrderStore::with_state_machine`.
+    fn transition(&mut self, to: Status) -> Result<(), StoreError> {
+        let from = self.status();
+        let idx = self.row.idx;
+        let states = self.states.as_deref();
+        let from_state = state_of(states, idx, from);
+        let Some(next) = machine_of(states).next_under(from_state, to) else {
+            return Err(StoreError::IllegalTransition {
+                id: self.id(),
+                from,
+                to,
+            });
+        };
+        self.validate(OrderRow {
+            status: to,
+            ..self.before
+        })?;
+        if let Some(s) = &mut self.states {
+            s.codes[idx] = next.0;
+        }
+        self.row.set_status(to);
+        self.changed(Field::Status);
+        let id = self.id();
+        self.record(match to {
+            Status::Cancelled => OrderEvent::Cancelled { id },
+            status => OrderEvent::StatusChanged { id, status },
+        });
+        Ok(())
+    }
+
+    /// Run the store's validators over the row a command is about to leave.
+    fn validate(&self, after: OrderRow) -> Result<(), StoreError> {
+        self.validators.check(after, Some(self.before))
+    }
+
+    fn record(&mut self, event: OrderEvent) {
+        if let Some(outbox) = &mut self.outbox {
+            outbox.record(event);
+        }
+    }
+
+    fn changed(&mut self, field: Field) {
+        self.stamp.touch();
+        self.changes
+            .emit(self.handle, ChangeKind::Updated { field });
+        let r = &self.row;
+        let after = OrderRow {
+            id: r.id(),
+            amount: r.amount(),
+            currency: r.currency(),
+            status: r.status(),
+            ts: r.timestamp(),
+            customer: r.customer(),
+        };
+        self.tracking.apply(&RowChange {
+            handle: self.handle,
+            before: Some(self.before),
+            after: Some(after),
+        });
+        self.before = after;
+    }
+}
@@ -0,0 +1,390 @@
+//! Analytical kernels: single-pass scans over the columns producing summaries.
+
+use crate::summation::Neumaier;
+use crate::{CustomerId, Money, OrderSoA, OrderView, Status};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet};
+
+/// Count/total/min/max for one group of rows.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Aggregate {
+    pub count: usize,
+    pub total: Money,
+    /// `None` for an empty group.
+    pub min: Option<Money>,
+    /// `None` for an empty group.
+    pub max: Option<Money>,
+}
+
+impl Aggregate {
+    #[inline]
+    pub fn observe(&mut self, amount: f64) {
+        self.count += 1;
+        self.total.0 += amount;
+        self.min = Some(Money(self.min.map_or(amount, |m| m.0.min(amount))));
+        self.max = Some(Money(self.max.map_or(amount, |m| m.0.max(amount))));
+    }
+
+    /// Fold in an aggregate computed over disjoint rows (e.g. another shard).
+    pub fn merge(&mut self, other: &Aggregate) {
+        self.count += other.count;
+        self.total.0 += other.total.0;
+        self.min = match (self.min, other.min) {
+            (Some(a), Some(b)) => Some(Money(a.0.min(b.0))),
+            (a, b) => a.or(b),
+        };
+        self.max = match (self.max, other.max) {
+            (Some(a), Some(b)) => Some(Money(a.0.max(b.0))),
+            (a, b) => a.or(b),
+        };
+    }
+
+    /// Arithmetic mean, `None` for an empty group.
+    pub fn mean(&self) -> Option<Money> {
+        (self.count > 0).then(|| Money(self.total.0 / self.count as f64))
+    }
+}
+
+/// Per-status aggregates, indexed by `Status`.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct StatusGroups([Aggregate; Status::ALL.len()]);
+
+impl StatusGroups {
+    pub fn get(&self, status: Status) -> &Aggregate {
+        &self.0[status as usize]
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Status, &Aggregate)> {
+        Status::ALL.into_iter().zip(self.0.iter())
+    }
+
+    pub(crate) fn get_mut(&mut self, status: Status) -> &mut Aggregate {
+        &mut self.0[status as usize]
+    }
+
+    #[inline]
+    pub(crate) fn observe(&mut self, status: Status, amount: f64) {
+        self.0[status as usize].observe(amount);
+    }
+
+    /// Group-wise `Aggregate::merge`.
+    pub fn merge(&mut self, other: &StatusGroups) {
+        for (a, b) in self.0.iter_mut().zip(&other.0) {
+            a.merge(b);
+        }
+    }
+}
+
+/// Per-customer aggregates, in order of each customer's first live row.
+///
+/// Built by hash aggregation: the table maps a customer to a dense slot, and the slots' ids and
+/// aggregates sit in two parallel vectors, so iterating, merging and ranking the groups never
+/// walks the hash table.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CustomerGroups {
+    slots: HashMap<CustomerId, usize>,
+    customers: Vec<CustomerId>,
+    aggregates: Vec<Aggregate>,
+}
+
+impl CustomerGroups {
+    /// Empty groups with room for `customers` distinct customers before the table grows.
+    pub fn with_capacity(customers: usize) -> Self {
+        CustomerGroups {
+            slots: HashMap::with_capacity(customers),
+            customers: Vec::with_capacity(customers),
+            aggregates: Vec::with_capacity(customers),
+        }
+    }
+
+    /// Number of customers.
+    pub fn len(&self) -> usize {
+        self.customers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.customers.is_empty()
+    }
+
+    pub fn get(&self, customer: CustomerId) -> Option<&Aggregate> {
+        self.slots.get(&customer).map(|&s| &self.aggregates[s])
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (CustomerId, &Aggregate)> {
+        self.customers.iter().copied().zip(&self.aggregates)
+    }
+
+    #[inline]
+    fn slot(&mut self, customer: CustomerId) -> &mut Aggregate {
+        let next = self.customers.len();
+        let slot = *self.slots.entry(customer).or_insert(next);
+        if slot == next {
+            self.customers.push(customer);
+            self.aggregates.push(Aggregate::default());
+        }
+        &mut self.aggregates[slot]
+    }
+
+    #[inline]
+    pub(crate) fn observe(&mut self, customer: CustomerId, amount: f64) {
+        self.slot(customer).observe(amount);
+    }
+
+    /// Group-wise `Aggregate::merge`; customers new to `self` are appended.
+    pub fn merge(&mut self, other: &CustomerGroups) {
+        self.slots.reserve(other.len());
+        for (customer, agg) in other.iter() {
+            self.slot(customer).merge(agg);
+        }
+    }
+
+    /// The `k` customers with the largest totals, largest first (ties: in group order), with
+    /// their aggregates. A `k`-element heap over the groups, as in `top_k_by_amount`.
+    pub fn top(&self, k: usize) -> Vec<(CustomerId, Aggregate)> {
+        if k == 0 {
+            return Vec::new();
+        }
+        let mut heap = BinaryHeap::with_capacity(k.min(self.len()));
+        for (slot, agg) in self.aggregates.iter().enumerate() {
+            let r = Reverse(Ranked(agg.total.0, slot));
+            if heap.len() < k {
+                heap.push(r);
+            } else if heap.peek().is_some_and(|min| r < *min) {
+                heap.pop();
+                heap.push(r);
+            }
+        }
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|Reverse(Ranked(_, slot))| (self.customers[slot], self.aggregates[slot]))
+            .collect()
+    }
+}
+
+/// Rows sampled to size the `group_by_customer` table.
+const CUSTOMER_SAMPLE: usize = 1024;
+
+/// Orders falling in one time window `[start, start + window)`.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct WindowAggregate {
+    pub start: u64,
+    pub count: usize,
+    pub total: Money,
+}
+
+impl WindowAggregate {
+    /// Average amount; windows are only reported when non-empty.
+    pub fn mean(&self) -> Money {
+        Money(self.total.0 / self.count as f64)
+    }
+}
+
+/// Equal-width histogram of amounts over `[lo, hi]`; the last bucket includes `hi`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Histogram {
+    pub lo: Money,
+    pub hi: Money,
+    pub counts: Vec<usize>,
+}
+
+impl Histogram {
+    pub fn bucket_width(&self) -> f64 {
+        (self.hi.0 - self.lo.0) / self.counts.len().max(1) as f64
+    }
+
+    /// Lower bound of bucket `b`.
+    pub fn bucket_start(&self, b: usize) -> Money {
+        Money(self.lo.0 + b as f64 * self.bucket_width())
+    }
+}
+
+/// Amount with its row, totally ordered by amount (ties by row) for the top-k heap.
+#[derive(Copy, Clone, PartialEq)]
+struct Ranked(f64, usize);
+
+impl Eq for Ranked {}
+
+impl Ord for Ranked {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0).then(other.1.cmp(&self.1))
+    }
+}
+
+impl PartialOrd for Ranked {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl OrderSoA {
+    /// Totals, counts, min, max (and via `Aggregate::mean`, means) for every status in one pass
+    /// over the status and amount columns.
+    pub fn group_by_status(&self) -> StatusGroups {
+        let mut groups = StatusGroups::default();
+        for (i, (&st, &amt)) in self.statuses.iter().zip(&self.amounts).enumerate() {
+            if self.is_live(i) {
+                groups.observe(st, amt);
+            }
+        }
+        groups
+    }
+
+    /// Count, total, min and max of live orders per customer in one pass over the customer and
+    /// amount columns. The hash table is pre-sized from the distinct customers in an evenly
+    /// spaced sample of rows, so it rarely rehashes mid-scan. Orders never assigned a customer
+    /// are grouped under `CustomerId::default()`.
+    pub fn group_by_customer(&self) -> CustomerGroups {
+        let mut groups = CustomerGroups::with_capacity(self.estimate_customers());
+        for (i, (&c, &amt)) in self.customers.iter().zip(&self.amounts).enumerate() {
+            if self.is_live(i) {
+                groups.observe(c, amt);
+            }
+        }
+        groups
+    }
+
+    /// The `k` customers with the largest order totals, largest first; see `CustomerGroups::top`.
+    pub fn top_customers(&self, k: usize) -> Vec<(CustomerId, Aggregate)> {
+        self.group_by_customer().top(k)
+    }
+
+    /// Distinct customers, extrapolated from a sample of `CUSTOMER_SAMPLE` rows: if the sample
+    /// repeats no customer the column may be all distinct, otherwise assume the sample saw most.
+    fn estimate_customers(&self) -> usize {
+        let n = self.len();
+        let step = n.div_ceil(CUSTOMER_SAMPLE).max(1);
+        let sampled: HashSet<CustomerId> = self.customers.iter().step_by(step).copied().collect();
+        let seen = n.div_ceil(step);
+        if sampled.len() == seen {
+            n
+        } else {
+            (sampled.len() * 2).min(n)
+        }
+    }
+
+    /// Zero-copy views of live rows with `from <= timestamp < to`.
+    pub fn filter_by_time_range(&self, from: u64, to: u64) -> impl Iterator<Item = OrderView<'_>> {
+        self.timestamps
+            .iter()
+            .enumerate()
+            .filter(move |&(i, &ts)| ts >= from && ts < to && self.is_live(i))
+            .map(|(i, _)| self.view_at(i))
+    }
+
+    /// Sum amounts with the given status and `from <= timestamp < to`, scanning only the three
+    /// columns involved.
+    pub fn sum_in_range(&self, from: u64, to: u64, status: Status) -> Money {
+        let mut acc = Neumaier::default();
+        for i in 0..self.len() {
+            let ts = self.timestamps[i];
+            if ts >= from && ts < to && self.statuses[i] == status && self.is_live(i) {
+                acc = acc.add(self.amounts[i]);
+            }
+        }
+        Money(acc.total())
+    }
+
+    /// Bucket live orders into windows of `window` timestamp units aligned to multiples of
+    /// `window`, optionally only those with `status`. One pass over the timestamp, amount (and
+    /// status) columns; non-empty windows are returned in time order.
+    ///
+    /// # Panics
+    /// If `window` is zero.
+    pub fn rollup_by_window(&self, window: u64, status: Option<Status>) -> Vec<WindowAggregate> {
+        assert!(window > 0, "rollup window must be non-zero");
+        let mut buckets: BTreeMap<u64, WindowAggregate> = BTreeMap::new();
+        for i in 0..self.len() {
+            if status.is_some_and(|s| self.statuses[i] != s) || !self.is_live(i) {
+                continue;
+            }
+            let start = self.timestamps[i] / window * window;
+            let w = buckets.entry(start).or_insert(WindowAggregate {
+                start,
+                ..WindowAggregate::default()
+            });
+            w.count += 1;
+            w.total.0 += self.amounts[i];
+        }
+        buckets.into_values().collect()
+    }
+
+    /// Rows of the `k` largest amounts, largest first (ties: lower row first). One pass over the
+    /// amounts column with a `k`-element heap, so memory stays `O(k)`.
+    pub fn top_k_by_amount(&self, k: usize) -> Vec<usize> {
+        if k == 0 {
+            return Vec::new();
+        }
+        let mut heap = BinaryHeap::with_capacity(k.min(self.live_len()));
+        for (i, &amt) in self.amounts.iter().enumerate() {
+            if !self.is_live(i) {
+                continue;
+            }
+            let r = Reverse(Ranked(amt, i));
+            if heap.len() < k {
+                heap.push(r);
+            } else if heap.peek().is_some_and(|min| r < *min) {
+                heap.pop();
+                heap.push(r);
+            }
+        }
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|Reverse(r)| r.1)
+            .collect()
+    }
+
+    /// Nearest-rank percentile of live amounts, `p` in `[0, 100]`. Uses an `O(n)` selection on a
+    /// copy of the column instead of a full sort; `None` when empty or `p` is out of range.
+    pub fn percentile_amount(&self, p: f64) -> Option<Money> {
+        if !(0.0..=100.0).contains(&p) {
+            return None;
+        }
+        let mut live: Vec<f64> = match self.dead_mask() {
+            None => self.amounts.clone(),
+            Some(_) => (0..self.len())
+                .filter(|&i| self.is_live(i))
+                .map(|i| self.amounts[i])
+                .collect(),
+        };
+        if live.is_empty() {
+            return None;
+        }
+        let rank = ((p / 100.0 * live.len() as f64).ceil() as usize).clamp(1, live.len());
+        let (_, &mut v, _) = live.select_nth_unstable_by(rank - 1, f64::total_cmp);
+        Some(Money(v))
+    }
+
+    /// Count live amounts into `buckets` equal-width buckets spanning their min..=max, in two
+    /// passes over the amounts column.
+    pub fn histogram_amount(&self, buckets: usize) -> Histogram {
+        let live = || {
+            self.amounts
+                .iter()
+                .enumerate()
+                .filter(|&(i, _)| self.is_live(i))
+                .map(|(_, &a)| a)
+        };
+        let Some((lo, hi)) = live().fold(None, |acc: Option<(f64, f64)>, a| {
+            Some(acc.map_or((a, a), |(lo, hi)| (lo.min(a), hi.max(a))))
+        }) else {
+            return Histogram::default();
+        };
+        let mut counts = vec![0; buckets];
+        if buckets > 0 {
+            let width = (hi - lo) / buckets as f64;
+            for a in live() {
+                let b = if width > 0.0 {
+                    ((a - lo) / width) as usize
+                } else {
+                    0
+                };
+                counts[b.min(buckets - 1)] += 1;
+            }
+        }
+        Histogram {
+            lo: Money(lo),
+            hi: Money(hi),
+            counts,
+        }
+    }
+}
@@ -0,0 +1,64 @@
+//! Array-of-Structs reference layout.
+//!
+//! The straightforward `Vec<OrderRow>` design the SoA kernel is meant to beat. It implements the
+//! same core kernels so benchmarks (`cargo bench --bench layouts`) and tests can compare layouts
+//! like for like; it is not meant for production use.
+
+use crate::{Money, OrderId, OrderRow, Status};
+
+/// Orders stored row by row.
+#[derive(Clone, Debug, Default)]
+pub struct OrderAoS {
+    rows: Vec<OrderRow>,
+}
+
+impl OrderAoS {
+    pub fn with_capacity(cap: usize) -> Self {
+        Self {
+            rows: Vec::with_capacity(cap),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    pub fn push(&mut self, id: OrderId, amount: Money, status: Status, ts: u64) -> usize {
+        self.rows.push(OrderRow::new(id, amount, status, ts));
+        self.rows.len() - 1
+    }
+
+    pub fn rows(&self) -> &[OrderRow] {
+        &self.rows
+    }
+
+    /// Same contract as `OrderSoA::sum_by_status`; every field of every row is pulled through
+    /// the cache.
+    pub fn sum_by_status(&self, status: Status) -> Money {
+        Money(
+            self.rows
+                .iter()
+                .filter(|r| r.status == status)
+                .map(|r| r.amount.0)
+                .sum(),
+        )
+    }
+
+    /// Same contract as `OrderSoA::filter_indices`.
+    pub fn filter_indices(&self, min_amount: Money, status: Status) -> Vec<usize> {
+        self.rows
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| r.amount.0 >= min_amount.0 && r.status == status)
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    pub fn retain<F: FnMut(&OrderRow) -> bool>(&mut self, f: F) {
+        self.rows.retain(f);
+    }
+}
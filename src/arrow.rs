@@ -0,0 +1,224 @@
+//! Arrow interop (feature `arrow`): `OrderSoA` ⇄ `RecordBatch`.
+//!
+//! The kernel is already columnar, so export is a matter of handing column memory to Arrow
+//! buffers. When the batch can share ownership of the columns (`OrderSoA::into_record_batch`,
+//! `OrderStore::to_record_batch`) no row data is copied: the buffers point straight into the
+//! `Vec`s and keep an `Arc<OrderSoA>` alive. Copy-on-write in `OrderStore` guarantees the shared
+//! columns are never mutated underneath the batch. Tombstoned rows force a compacted copy.
+//!
+//! | column      | Arrow type                   |
+//! |-------------|------------------------------|
+//! | `id`        | `UInt64`                     |
+//! | `amount`    | `Float64`                    |
+//! | `status`    | `Dictionary(UInt8, Utf8)`    |
+//! | `timestamp` | `UInt64` (epoch millis)      |
+//! | `currency`  | `FixedSizeBinary(3)`         |
+//! | `customer`  | `UInt64`                     |
+
+use crate::{Currency, CustomerId, OrderId, OrderSoA, OrderStore, Status};
+use arrow_array::types::UInt8Type;
+use arrow_array::{
+    Array, ArrayRef, DictionaryArray, FixedSizeBinaryArray, Float64Array, RecordBatch, StringArray,
+    UInt64Array, UInt8Array,
+};
+use arrow_buffer::{Buffer, ScalarBuffer};
+use arrow_schema::{ArrowError, DataType, Field, Schema, SchemaRef};
+use std::ptr::NonNull;
+use std::sync::{Arc, OnceLock};
+
+/// Arrow schema of an exported `OrderSoA`.
+pub fn order_schema() -> SchemaRef {
+    static SCHEMA: OnceLock<SchemaRef> = OnceLock::new();
+    SCHEMA
+        .get_or_init(|| {
+            Arc::new(Schema::new(vec![
+                Field::new("id", DataType::UInt64, false),
+                Field::new("amount", DataType::Float64, false),
+                Field::new_dictionary("status", DataType::UInt8, DataType::Utf8, false),
+                Field::new("timestamp", DataType::UInt64, false),
+                Field::new("currency", DataType::FixedSizeBinary(3), false),
+                Field::new("customer", DataType::UInt64, false),
+            ]))
+        })
+        .clone()
+}
+
+/// Column bytes, for types whose in-memory layout has no padding.
+fn bytes_of<T: Copy>(col: &[T]) -> &[u8] {
+    // SAFETY: callers only pass `OrderId`/`CustomerId`/`u64`/`f64`/`Status`/`Currency` columns,
+    // which are plain integers/floats/byte arrays without padding; the length is the slice's
+    // byte size.
+    unsafe { std::slice::from_raw_parts(col.as_ptr().cast::<u8>(), std::mem::size_of_val(col)) }
+}
+
+/// Buffer aliasing a column owned by `owner`, without copying.
+fn shared<T: Copy>(owner: &Arc<OrderSoA>, col: &[T]) -> Buffer {
+    let ptr = NonNull::new(col.as_ptr() as *mut u8).expect("Vec pointers are non-null");
+    // SAFETY: `col` lives inside `owner`'s column vectors, which are never mutated or freed
+    // while this Arc is alive: all kernel writes go through `Arc::make_mut`, which clones when
+    // the Arc is shared.
+    unsafe { Buffer::from_custom_allocation(ptr, std::mem::size_of_val(col), owner.clone()) }
+}
+
+fn assemble(n: usize, cols: [Buffer; 6]) -> Result<RecordBatch, ArrowError> {
+    let [ids, amounts, statuses, timestamps, currencies, customers] = cols;
+    let names: ArrayRef = Arc::new(StringArray::from_iter_values(Status::ALL.map(Status::name)));
+    let status = DictionaryArray::<UInt8Type>::try_new(
+        UInt8Array::new(ScalarBuffer::new(statuses, 0, n), None),
+        names,
+    )?;
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(UInt64Array::new(ScalarBuffer::new(ids, 0, n), None)),
+        Arc::new(Float64Array::new(ScalarBuffer::new(amounts, 0, n), None)),
+        Arc::new(status),
+        Arc::new(UInt64Array::new(ScalarBuffer::new(timestamps, 0, n), None)),
+        Arc::new(FixedSizeBinaryArray::try_new(3, currencies, None)?),
+        Arc::new(UInt64Array::new(ScalarBuffer::new(customers, 0, n), None)),
+    ];
+    RecordBatch::try_new(order_schema(), columns)
+}
+
+fn shared_batch(soa: Arc<OrderSoA>) -> Result<RecordBatch, ArrowError> {
+    debug_assert_eq!(soa.tombstones, 0);
+    assemble(
+        soa.len(),
+        [
+            shared(&soa, &soa.ids),
+            shared(&soa, &soa.amounts),
+            shared(&soa, &soa.statuses),
+            shared(&soa, &soa.timestamps),
+            shared(&soa, &soa.currencies),
+            shared(&soa, &soa.customers),
+        ],
+    )
+}
+
+fn column<'a, A: Array + 'static>(batch: &'a RecordBatch, name: &str) -> Result<&'a A, ArrowError> {
+    let col = batch
+        .column_by_name(name)
+        .ok_or_else(|| ArrowError::SchemaError(format!("missing column `{name}`")))?;
+    if col.null_count() > 0 {
+        return Err(ArrowError::InvalidArgumentError(format!(
+            "column `{name}` contains nulls"
+        )));
+    }
+    col.as_any().downcast_ref::<A>().ok_or_else(|| {
+        ArrowError::SchemaError(format!(
+            "column `{name}` has unexpected type {}",
+            col.data_type()
+        ))
+    })
+}
+
+impl OrderSoA {
+    /// Export live rows as a `RecordBatch`, copying each column once.
+    pub fn to_record_batch(&self) -> Result<RecordBatch, ArrowError> {
+        if self.tombstones > 0 {
+            let mut live = self.clone();
+            live.compact();
+            return live.into_record_batch();
+        }
+        assemble(
+            self.len(),
+            [
+                Buffer::from(bytes_of(&self.ids)),
+                Buffer::from(bytes_of(&self.amounts)),
+                Buffer::from(bytes_of(&self.statuses)),
+                Buffer::from(bytes_of(&self.timestamps)),
+                Buffer::from(bytes_of(&self.currencies)),
+                Buffer::from(bytes_of(&self.customers)),
+            ],
+        )
+    }
+
+    /// Export by moving the columns into the batch: zero-copy unless tombstones need compacting.
+    pub fn into_record_batch(mut self) -> Result<RecordBatch, ArrowError> {
+        if self.tombstones > 0 {
+            self.compact();
+        }
+        shared_batch(Arc::new(self))
+    }
+
+    /// Import a batch with the `order_schema()` columns (by name; extra columns are ignored).
+    /// `currency` and `customer` are optional and default to `Currency::default()` and
+    /// `CustomerId::default()`.
+    pub fn from_record_batch(batch: &RecordBatch) -> Result<OrderSoA, ArrowError> {
+        let ids = column::<UInt64Array>(batch, "id")?;
+        let amounts = column::<Float64Array>(batch, "amount")?;
+        let status = column::<DictionaryArray<UInt8Type>>(batch, "status")?;
+        let timestamps = column::<UInt64Array>(batch, "timestamp")?;
+
+        // Map dictionary codes through their names rather than trusting the code order.
+        let names = status
+            .values()
+            .as_any()
+            .downcast_ref::<StringArray>()
+            .ok_or_else(|| ArrowError::SchemaError("status dictionary must be Utf8".into()))?;
+        let lookup = names
+            .iter()
+            .map(|name| {
+                name.and_then(Status::from_name).ok_or_else(|| {
+                    ArrowError::InvalidArgumentError(format!("unknown status {name:?}"))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let statuses = status
+            .keys()
+            .values()
+            .iter()
+            .map(|&k| {
+                lookup.get(k as usize).copied().ok_or_else(|| {
+                    ArrowError::InvalidArgumentError(format!("status key {k} out of range"))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let currencies = match batch.column_by_name("currency") {
+            None => vec![Currency::default(); batch.num_rows()],
+            Some(_) => {
+                let col = column::<FixedSizeBinaryArray>(batch, "currency")?;
+                if col.value_length() != 3 {
+                    return Err(ArrowError::SchemaError(
+                        "currency must be FixedSizeBinary(3)".into(),
+                    ));
+                }
+                col.iter()
+                    .map(|v| Currency(v.unwrap().try_into().unwrap()))
+                    .collect()
+            }
+        };
+
+        let soa = OrderSoA::from_columns(
+            ids.values().iter().map(|&id| OrderId(id)).collect(),
+            amounts.values().to_vec(),
+            statuses,
+            timestamps.values().to_vec(),
+            currencies,
+        );
+        match batch.column_by_name("customer") {
+            None => soa,
+            Some(_) => {
+                let col = column::<UInt64Array>(batch, "customer")?;
+                soa.and_then(|soa| {
+                    soa.with_customers(col.values().iter().map(|&c| CustomerId(c)).collect())
+                })
+            }
+        }
+        .map_err(|e| ArrowError::InvalidArgumentError(e.to_string()))
+    }
+}
+
+impl OrderStore {
+    /// Zero-copy export of the current snapshot: the batch shares the store's columns and keeps
+    /// them alive; later writes to the store copy-on-write instead of touching the batch.
+    pub fn to_record_batch(&self) -> Result<RecordBatch, ArrowError> {
+        if self.inner.tombstones > 0 {
+            return self.inner.to_record_batch();
+        }
+        shared_batch(self.inner.clone())
+    }
+
+    pub fn from_record_batch(batch: &RecordBatch) -> Result<OrderStore, ArrowError> {
+        OrderSoA::from_record_batch(batch).map(OrderStore::from)
+    }
+}
@@ -0,0 +1,86 @@
+//! Async repository façade (feature `async`).
+//!
+//! `AsyncOrderRepository` is the store API as async handlers want it: owned rows out (views
+//! cannot be held across an `.await`) and futures that are `Send`. `AsyncOrderStore` implements
+//! it over a shared `ConcurrentOrderStore`: reads take a lock-free snapshot and never wait, and
+//! writes, which serialize on the store's writer lock, run on tokio's blocking pool so they never
+//! stall an executor thread.
+
+use crate::{ConcurrentOrderStore, Money, OrderId, OrderRow, Status, StoreError};
+use std::future::Future;
+use std::sync::Arc;
+
+/// Order repository for async callers.
+pub trait AsyncOrderRepository {
+    fn add(
+        &self,
+        id: OrderId,
+        amount: Money,
+        status: Status,
+        ts: u64,
+    ) -> impl Future<Output = Result<(), StoreError>> + Send;
+
+    fn find_by_id(&self, id: OrderId) -> impl Future<Output = Option<OrderRow>> + Send;
+
+    fn find_by_status(&self, status: Status) -> impl Future<Output = Vec<OrderRow>> + Send;
+}
+
+/// Cheaply cloneable handle to a `ConcurrentOrderStore`, one per handler or task.
+#[derive(Clone, Default)]
+pub struct AsyncOrderStore {
+    inner: Arc<ConcurrentOrderStore>,
+}
+
+impl AsyncOrderStore {
+    pub fn new(store: ConcurrentOrderStore) -> Self {
+        Self {
+            inner: Arc::new(store),
+        }
+    }
+
+    /// The underlying store, for snapshots and kernels.
+    pub fn store(&self) -> &ConcurrentOrderStore {
+        &self.inner
+    }
+
+    /// Run a write on the blocking pool, re-raising its panic if it had one.
+    async fn write<R: Send + 'static>(
+        &self,
+        f: impl FnOnce(&ConcurrentOrderStore) -> R + Send + 'static,
+    ) -> R {
+        let inner = self.inner.clone();
+        match tokio::task::spawn_blocking(move || f(&inner)).await {
+            Ok(r) => r,
+            Err(e) => std::panic::resume_unwind(e.into_panic()),
+        }
+    }
+}
+
+impl AsyncOrderRepository for AsyncOrderStore {
+    async fn add(
+        &self,
+        id: OrderId,
+        amount: Money,
+        status: Status,
+        ts: u64,
+    ) -> Result<(), StoreError> {
+        if amount.0.is_nan() || amount.0 < 0.0 {
+            return Err(StoreError::InvalidAmount(id));
+        }
+        self.write(move |s| s.add(id, amount, status, ts)).await;
+        Ok(())
+    }
+
+    async fn find_by_id(&self, id: OrderId) -> Option<OrderRow> {
+        self.inner.snapshot().find_by_id(id).map(OrderRow::from)
+    }
+
+    async fn find_by_status(&self, status: Status) -> Vec<OrderRow> {
+        let snap = self.inner.snapshot();
+        snap.segments()
+            .into_iter()
+            .flat_map(|seg| seg.find_by_status(status))
+            .map(OrderRow::from)
+            .collect()
+    }
+}
@@ -0,0 +1,308 @@
+//! Who changed what, and when.
+//!
+//! `OrderStore::with_audit` adds three columns to the kernel: `created_at`, `updated_at` and
+//! `updated_by`. From then on every row the kernel appends is stamped with the store clock's time
+//! and the current `Context`'s actor, and every row it writes to (wherever the row's version
+//! moves) has `updated_at` / `updated_by` refreshed, so writes through `kernel_mut` are covered as
+//! well. The actor comes from the store's `Context`: set it for good with `set_context`, or for a
+//! scope with `in_context`. Rows stored before auditing was enabled read as created and updated
+//! at time 0 by `ActorId::SYSTEM`.
+//!
+//! The audit columns live in memory only: snapshots, Arrow / Parquet / CSV export and the
+//! C and Python bindings leave them out.
+
+use crate::clock::SharedClock;
+use crate::{query_cache, OrderSoA, OrderStore, OrderView};
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+
+/// Identity of whoever performs a write, e.g. a user or service account id.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ActorId(pub u64);
+
+impl ActorId {
+    /// The store itself: the actor when none was set.
+    pub const SYSTEM: ActorId = ActorId(0);
+}
+
+/// Ambient information attached to the writes a store performs.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Context {
+    pub actor: ActorId,
+}
+
+impl Context {
+    pub fn new(actor: ActorId) -> Self {
+        Context { actor }
+    }
+}
+
+/// A row's audit stamps; see `OrderView::audit`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct AuditInfo {
+    pub created_at: u64,
+    pub updated_at: u64,
+    pub updated_by: ActorId,
+}
+
+/// The audit columns, row-aligned with the kernel's, plus where stamps come from.
+#[derive(Clone)]
+pub(crate) struct AuditColumns {
+    pub(crate) created_at: Vec<u64>,
+    pub(crate) updated_at: Vec<u64>,
+    pub(crate) updated_by: Vec<ActorId>,
+    clock: SharedClock,
+    /// The store's `Context` actor as of its last write; see `query_cache::write`.
+    pub(crate) actor: ActorId,
+}
+
+impl AuditColumns {
+    /// Columns for `len` existing rows, which get the "unknown" stamp.
+    fn new(len: usize, clock: SharedClock, actor: ActorId) -> Self {
+        AuditColumns {
+            created_at: vec![0; len],
+            updated_at: vec![0; len],
+            updated_by: vec![ActorId::SYSTEM; len],
+            clock,
+            actor,
+        }
+    }
+
+    /// Empty columns stamping like these, for a kernel split off from this one.
+    pub(crate) fn empty_like(&self) -> Self {
+        AuditColumns::new(0, self.clock.clone(), self.actor)
+    }
+
+    /// The current time and actor.
+    pub(crate) fn stamp(&self) -> (u64, ActorId) {
+        (self.clock.0.now(), self.actor)
+    }
+
+    /// Append `n` rows created now.
+    pub(crate) fn push_new(&mut self, n: usize) {
+        let (now, actor) = self.stamp();
+        self.created_at.extend(std::iter::repeat_n(now, n));
+        self.updated_at.extend(std::iter::repeat_n(now, n));
+        self.updated_by.extend(std::iter::repeat_n(actor, n));
+    }
+
+    pub(crate) fn touch(&mut self, idx: usize, (now, actor): (u64, ActorId)) {
+        self.updated_at[idx] = now;
+        self.updated_by[idx] = actor;
+    }
+
+    pub(crate) fn get(&self, idx: usize) -> AuditInfo {
+        AuditInfo {
+            created_at: self.created_at[idx],
+            updated_at: self.updated_at[idx],
+            updated_by: self.updated_by[idx],
+        }
+    }
+
+    pub(crate) fn set(&mut self, idx: usize, info: AuditInfo) {
+        self.created_at[idx] = info.created_at;
+        self.updated_at[idx] = info.updated_at;
+        self.updated_by[idx] = info.updated_by;
+    }
+
+    pub(crate) fn swap_remove(&mut self, idx: usize) {
+        self.created_at.swap_remove(idx);
+        self.updated_at.swap_remove(idx);
+        self.updated_by.swap_remove(idx);
+    }
+
+    pub(crate) fn remove(&mut self, idx: usize) {
+        self.created_at.remove(idx);
+        self.updated_at.remove(idx);
+        self.updated_by.remove(idx);
+    }
+
+    /// `new[i] = old[perm[i]]`.
+    pub(crate) fn gather(&mut self, perm: &[usize]) {
+        *self = self.gathered(perm);
+    }
+
+    /// The stamps of `rows`, in that order, stamping like these.
+    pub(crate) fn gathered(&self, rows: &[usize]) -> Self {
+        AuditColumns {
+            created_at: rows.iter().map(|&i| self.created_at[i]).collect(),
+            updated_at: rows.iter().map(|&i| self.updated_at[i]).collect(),
+            updated_by: rows.iter().map(|&i| self.updated_by[i]).collect(),
+            ..self.empty_like()
+        }
+    }
+
+    pub(crate) fn truncate(&mut self, len: usize) {
+        self.created_at.truncate(len);
+        self.updated_at.truncate(len);
+        self.updated_by.truncate(len);
+    }
+
+    /// Append `other`'s `n` rows, or `n` "unknown" stamps if it was not audited.
+    pub(crate) fn append(&mut self, other: Option<AuditColumns>, n: usize) {
+        match other {
+            Some(mut other) => {
+                self.created_at.append(&mut other.created_at);
+                self.updated_at.append(&mut other.updated_at);
+                self.updated_by.append(&mut other.updated_by);
+            }
+            None => {
+                self.created_at.extend(std::iter::repeat_n(0, n));
+                self.updated_at.extend(std::iter::repeat_n(0, n));
+                self.updated_by
+                    .extend(std::iter::repeat_n(ActorId::SYSTEM, n));
+            }
+        }
+    }
+
+    pub(crate) fn split_off(&mut self, at: usize) -> AuditColumns {
+        AuditColumns {
+            created_at: self.created_at.split_off(at),
+            updated_at: self.updated_at.split_off(at),
+            updated_by: self.updated_by.split_off(at),
+            ..self.empty_like()
+        }
+    }
+
+    pub(crate) fn reserve(&mut self, additional: usize) {
+        self.created_at.reserve(additional);
+        self.updated_at.reserve(additional);
+        self.updated_by.reserve(additional);
+    }
+
+    pub(crate) fn shrink_to_fit(&mut self) {
+        self.created_at.shrink_to_fit();
+        self.updated_at.shrink_to_fit();
+        self.updated_by.shrink_to_fit();
+    }
+}
+
+impl OrderSoA {
+    /// Whether the kernel keeps audit columns.
+    pub fn is_audited(&self) -> bool {
+        self.audit.is_some()
+    }
+
+    /// Live rows updated (or created) at or after `ts`, in row order. Empty if the kernel is not
+    /// audited.
+    pub fn modified_since(&self, ts: u64) -> impl Iterator<Item = OrderView<'_>> {
+        let updated = self.audit.as_ref().map_or(&[][..], |a| &a.updated_at[..]);
+        (0..updated.len())
+            .filter(move |&i| updated[i] >= ts && self.is_live(i))
+            .map(|i| self.view_at(i))
+    }
+
+    /// Refresh row `idx`'s update stamps, if audited.
+    #[inline]
+    pub(crate) fn touch_audit(&mut self, idx: usize) {
+        if let Some(a) = &mut self.audit {
+            let stamp = a.stamp();
+            a.touch(idx, stamp);
+        }
+    }
+}
+
+/// A row's version and audit stamp, borrowed apart from its other columns so that a command
+/// can count its write once it has succeeded; see `OrderStore::load`.
+pub(crate) struct RowStamp<'a> {
+    pub(crate) idx: usize,
+    pub(crate) version: &'a mut u64,
+    pub(crate) audit: Option<&'a mut AuditColumns>,
+}
+
+impl RowStamp<'_> {
+    /// Count a write: bump the version and refresh the audit stamp.
+    pub(crate) fn touch(&mut self) {
+        *self.version += 1;
+        if let Some(a) = &mut self.audit {
+            let stamp = a.stamp();
+            a.touch(self.idx, stamp);
+        }
+    }
+}
+
+impl OrderView<'_> {
+    /// When the row was created and last updated, and by whom; `None` if the kernel is not
+    /// audited.
+    pub fn audit(&self) -> Option<AuditInfo> {
+        self.soa.audit.as_ref().map(|a| a.get(self.idx))
+    }
+}
+
+impl OrderStore {
+    /// Builder flag: keep audit columns, stamped from the store clock and `Context`.
+    pub fn with_audit(mut self) -> Self {
+        let soa = query_cache::write(&mut self.inner, &mut self.epoch, self.context);
+        if soa.audit.is_none() {
+            soa.audit = Some(AuditColumns::new(
+                soa.len(),
+                self.clock.clone(),
+                self.context.actor,
+            ));
+        }
+        self
+    }
+
+    /// Point the audit columns, if any, at the store's current clock.
+    pub(crate) fn sync_audit_clock(&mut self) {
+        if self.inner.audit.is_some() {
+            if let Some(a) = &mut Arc::make_mut(&mut self.inner).audit {
+                a.clock = self.clock.clone();
+            }
+        }
+    }
+
+    /// The context later writes are attributed to.
+    pub fn context(&self) -> Context {
+        self.context
+    }
+
+    /// Attribute later writes to `ctx`. Without `with_audit` there is nothing to attribute and
+    /// the context is ignored. The context lives on the store and reaches the audit columns with
+    /// the next write, so switching actors never copies a kernel that readers still share.
+    pub fn set_context(&mut self, ctx: Context) {
+        self.context = ctx;
+    }
+
+    /// Attribute the writes made through the returned guard to `ctx`; the previous context is
+    /// restored when it drops.
+    pub fn in_context(&mut self, ctx: Context) -> InContext<'_> {
+        let previous = self.context();
+        self.set_context(ctx);
+        InContext {
+            store: self,
+            previous,
+        }
+    }
+
+    /// Live orders updated (or created) at or after `ts`; see `OrderSoA::modified_since`.
+    pub fn modified_since(&self, ts: u64) -> impl Iterator<Item = OrderView<'_>> {
+        self.inner.modified_since(ts)
+    }
+}
+
+/// The store, with writes attributed to a scoped `Context`; see `OrderStore::in_context`.
+pub struct InContext<'a> {
+    store: &'a mut OrderStore,
+    previous: Context,
+}
+
+impl Deref for InContext<'_> {
+    type Target = OrderStore;
+
+    fn deref(&self) -> &OrderStore {
+        self.store
+    }
+}
+
+impl DerefMut for InContext<'_> {
+    fn deref_mut(&mut self) -> &mut OrderStore {
+        self.store
+    }
+}
+
+impl Drop for InContext<'_> {
+    fn drop(&mut self) {
+        self.store.set_context(self.previous);
+    }
+}
@@ -0,0 +1,136 @@
+//! Reproducible synthetic workloads for benchmarks (feature `bench-support`).
+//!
+//! `synthetic_orders(n, seed, status_mix, amount_dist)` builds the same kernel for the same
+//! arguments on every platform and crate version: the generator is a self-contained SplitMix64
+//! rather than an external RNG whose streams may change between releases. Ids are `0..n` and
+//! timestamps ascend with the id, so generated kernels are also valid time-series inputs.
+//!
+//! ```
+//! use ddd_dod_soa::bench_support::{synthetic_orders, AmountDist, StatusMix};
+//!
+//! let amounts = AmountDist::Exponential { mean: 80.0 };
+//! let soa = synthetic_orders(10_000, 42, StatusMix::new(2, 7, 1), amounts);
+//! assert_eq!(soa.len(), 10_000);
+//! ```
+
+use crate::{Money, OrderId, OrderRow, OrderSoA, Status};
+
+/// Relative weights of the statuses, indexed like `Status::ALL`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct StatusMix {
+    weights: [u32; 3],
+}
+
+impl StatusMix {
+    /// Every status equally likely.
+    pub const UNIFORM: StatusMix = StatusMix { weights: [1, 1, 1] };
+
+    /// # Panics
+    /// If every weight is zero.
+    pub fn new(pending: u32, completed: u32, cancelled: u32) -> Self {
+        let weights = [pending, completed, cancelled];
+        assert!(
+            weights.iter().any(|&w| w > 0),
+            "status mix needs a non-zero weight"
+        );
+        StatusMix { weights }
+    }
+
+    fn pick(&self, r: u64) -> Status {
+        let total: u64 = self.weights.iter().map(|&w| u64::from(w)).sum();
+        let mut x = r % total;
+        for (s, &w) in Status::ALL.into_iter().zip(&self.weights) {
+            match x.checked_sub(u64::from(w)) {
+                Some(rest) => x = rest,
+                None => return s,
+            }
+        }
+        unreachable!("x < total")
+    }
+}
+
+impl Default for StatusMix {
+    fn default() -> Self {
+        StatusMix::UNIFORM
+    }
+}
+
+/// Distribution of generated amounts, rounded to cents.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AmountDist {
+    Constant(f64),
+    /// Uniform in `[min, max)`.
+    Uniform {
+        min: f64,
+        max: f64,
+    },
+    /// Right-skewed, like real basket values: many small orders, a long tail of large ones.
+    Exponential {
+        mean: f64,
+    },
+}
+
+impl AmountDist {
+    fn sample(&self, r: u64) -> f64 {
+        // 53 random bits → a float in [0, 1).
+        let u = (r >> 11) as f64 / (1u64 << 53) as f64;
+        let x = match *self {
+            AmountDist::Constant(a) => a,
+            AmountDist::Uniform { min, max } => min + u * (max - min),
+            AmountDist::Exponential { mean } => -mean * (1.0 - u).ln(),
+        };
+        (x * 100.0).round() / 100.0
+    }
+}
+
+impl Default for AmountDist {
+    fn default() -> Self {
+        AmountDist::Uniform {
+            min: 0.0,
+            max: 500.0,
+        }
+    }
+}
+
+/// SplitMix64: tiny, fast, and stable by construction.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
+
+/// The rows `synthetic_orders` stores, for feeding other stores or writing out.
+pub fn synthetic_rows(
+    n: usize,
+    seed: u64,
+    status_mix: StatusMix,
+    amount_dist: AmountDist,
+) -> impl Iterator<Item = OrderRow> {
+    let mut rng = SplitMix64(seed);
+    (0..n as u64).map(move |i| {
+        let status = status_mix.pick(rng.next());
+        let amount = Money(amount_dist.sample(rng.next()));
+        OrderRow::new(OrderId(i), amount, status, i)
+    })
+}
+
+/// A kernel of `n` orders drawn from `status_mix` and `amount_dist`; equal arguments give equal
+/// kernels.
+pub fn synthetic_orders(
+    n: usize,
+    seed: u64,
+    status_mix: StatusMix,
+    amount_dist: AmountDist,
+) -> OrderSoA {
+    let mut soa = OrderSoA::with_capacity(n);
+    for r in synthetic_rows(n, seed, status_mix, amount_dist) {
+        soa.push(r.id, r.amount, r.status, r.ts);
+    }
+    soa
+}
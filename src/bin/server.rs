@@ -0,0 +1,202 @@
+//! HTTP service over a `ConcurrentOrderStore` (feature `server`).
+//!
+//! Run with `cargo run --features server --bin server [ADDR]` (default `127.0.0.1:3000`).
+//!
+//! | Method | Path                 | Does                                              |
+//! |--------|----------------------|---------------------------------------------------|
+//! | POST   | `/orders`            | add one order (JSON body); 409 on a duplicate id  |
+//! | GET    | `/orders/{id}`       | one order, or 404                                 |
+//! | GET    | `/orders`            | one page of matching orders, plus the next cursor |
+//! | GET    | `/orders/stream`     | every matching order as NDJSON, in chunks         |
+//! | GET    | `/aggregates/status` | count and total per status                        |
+//!
+//! The list and stream endpoints filter on the optional `status` and `min_amount` query
+//! parameters; the list also takes `cursor` and `limit`. Reads run against a lock-free snapshot,
+//! so a long stream never blocks writers and sees one consistent state. The store only appends
+//! and merging keeps row order, so a cursor (a row position) stays exact across writes.
+
+use axum::body::{Body, Bytes};
+use axum::extract::{Path, Query, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use ddd_dod_soa::{
+    ConcurrentOrderStore, Currency, CustomerId, Money, OrderId, OrderRow, OrderSnapshot, OrderView,
+    Status, StoreError,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Orders serialized per streamed chunk.
+const STREAM_CHUNK: usize = 1024;
+const DEFAULT_PAGE: usize = 100;
+const MAX_PAGE: usize = 1000;
+
+type Store = Arc<ConcurrentOrderStore>;
+
+#[derive(Deserialize)]
+struct NewOrder {
+    id: u64,
+    amount: f64,
+    status: Status,
+    ts: u64,
+    #[serde(default)]
+    currency: Currency,
+    #[serde(default)]
+    customer: u64,
+}
+
+/// Filters shared by the page and stream endpoints.
+#[derive(Copy, Clone, Default, Deserialize)]
+struct Filter {
+    status: Option<Status>,
+    min_amount: Option<f64>,
+}
+
+impl Filter {
+    fn matches(&self, v: &OrderView<'_>) -> bool {
+        self.status.is_none_or(|s| v.status() == s)
+            && self.min_amount.is_none_or(|m| v.amount().0 >= m)
+    }
+}
+
+#[derive(Deserialize)]
+struct PageParams {
+    // Not `#[serde(flatten)] Filter`: flattened query values lose their types.
+    status: Option<Status>,
+    min_amount: Option<f64>,
+    #[serde(default)]
+    cursor: usize,
+    limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct Page {
+    orders: Vec<OrderRow>,
+    /// Pass back as `cursor` for the next page; `null` on the last page.
+    next: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct StatusAggregate {
+    status: Status,
+    count: usize,
+    total: Money,
+}
+
+/// Views of `snap`'s orders from row position `from` on (base segment, then tail), with their
+/// positions. Starting mid-way costs nothing.
+fn scan_from(snap: &OrderSnapshot, from: usize) -> impl Iterator<Item = (usize, OrderView<'_>)> {
+    let [base, tail] = snap.segments();
+    (from..base.len() + tail.len()).filter_map(move |pos| {
+        let (soa, i) = match pos.checked_sub(base.len()) {
+            None => (base, pos),
+            Some(i) => (tail, i),
+        };
+        soa.is_live(i).then(|| (pos, soa.view_at(i)))
+    })
+}
+
+async fn add_order(State(store): State<Store>, Json(o): Json<NewOrder>) -> Response {
+    let row = OrderRow {
+        currency: o.currency,
+        customer: CustomerId(o.customer),
+        ..OrderRow::new(OrderId(o.id), Money(o.amount), o.status, o.ts)
+    };
+    match store.add_batch([row]) {
+        Ok(()) => (StatusCode::CREATED, Json(row)).into_response(),
+        Err(e @ StoreError::DuplicateId(_)) => {
+            (StatusCode::CONFLICT, e.to_string()).into_response()
+        }
+        Err(e) => (StatusCode::UNPROCESSABLE_ENTITY, e.to_string()).into_response(),
+    }
+}
+
+async fn get_order(State(store): State<Store>, Path(id): Path<u64>) -> Response {
+    let snap = store.snapshot();
+    match snap.find_by_id(OrderId(id)) {
+        Some(v) => Json(OrderRow::from(v)).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+async fn list_orders(State(store): State<Store>, Query(p): Query<PageParams>) -> Json<Page> {
+    let limit = p.limit.unwrap_or(DEFAULT_PAGE).clamp(1, MAX_PAGE);
+    let filter = Filter {
+        status: p.status,
+        min_amount: p.min_amount,
+    };
+    let snap = store.snapshot();
+    let mut orders = Vec::with_capacity(limit);
+    let mut next = None;
+    for (pos, v) in scan_from(&snap, p.cursor).filter(|(_, v)| filter.matches(v)) {
+        if orders.len() == limit {
+            next = Some(pos);
+            break;
+        }
+        orders.push(OrderRow::from(v));
+    }
+    Json(Page { orders, next })
+}
+
+async fn stream_orders(State(store): State<Store>, Query(filter): Query<Filter>) -> Response {
+    let snap = store.snapshot();
+    // Each poll serializes the next chunk from the snapshot, so memory stays bounded by one
+    // chunk however many orders match.
+    let chunks = futures_util::stream::unfold(0, move |from| {
+        let snap = Arc::clone(&snap);
+        async move {
+            let mut buf = Vec::new();
+            let mut end = from;
+            for (pos, v) in scan_from(&snap, from).take(STREAM_CHUNK) {
+                end = pos + 1;
+                if filter.matches(&v) {
+                    serde_json::to_writer(&mut buf, &OrderRow::from(v)).ok()?;
+                    buf.push(b'\n');
+                }
+            }
+            (end > from).then(|| (Ok::<_, std::io::Error>(Bytes::from(buf)), end))
+        }
+    });
+    (
+        [(header::CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(chunks),
+    )
+        .into_response()
+}
+
+async fn status_aggregates(State(store): State<Store>) -> Json<Vec<StatusAggregate>> {
+    let snap = store.snapshot();
+    let mut groups = snap.segments()[0].group_by_status();
+    groups.merge(&snap.segments()[1].group_by_status());
+    Json(
+        groups
+            .iter()
+            .map(|(status, agg)| StatusAggregate {
+                status,
+                count: agg.count,
+                total: agg.total,
+            })
+            .collect(),
+    )
+}
+
+fn app(store: Store) -> Router {
+    Router::new()
+        .route("/orders", get(list_orders).post(add_order))
+        .route("/orders/stream", get(stream_orders))
+        .route("/orders/{id}", get(get_order))
+        .route("/aggregates/status", get(status_aggregates))
+        .with_state(store)
+}
+
+#[tokio::main]
+async fn main() -> std::io::Result<()> {
+    let addr = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "127.0.0.1:3000".to_owned());
+    let listener = tokio::net::TcpListener::bind(&addr).await?;
+    println!("serving orders on http://{}", listener.local_addr()?);
+    axum::serve(listener, app(Arc::new(ConcurrentOrderStore::new()))).await
+}
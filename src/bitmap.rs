@@ -0,0 +1,215 @@
+//! Packed bit vector, one bit per row.
+//!
+//! Used for per-row flags that must stay aligned with the columns (tombstones, null validity)
+//! and as the selection vectors produced by the column predicate kernels. `&`, `|` and `!`
+//! combine selections a word at a time.
+
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, Not};
+
+/// Growable bitset backed by `u64` words. Bits past `len` in the last word are always zero.
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
+pub struct Bitmap {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl Bitmap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(bits: usize) -> Self {
+        Self {
+            words: Vec::with_capacity(bits.div_ceil(64)),
+            len: 0,
+        }
+    }
+
+    /// `len` bits, all set to `value`.
+    pub fn filled(len: usize, value: bool) -> Self {
+        let fill = if value { u64::MAX } else { 0 };
+        let mut bm = Self {
+            words: vec![fill; len.div_ceil(64)],
+            len,
+        };
+        bm.clear_tail();
+        bm
+    }
+
+    /// `len` bits with bit `i` set to `f(i)`, packed a word at a time.
+    pub fn from_fn(len: usize, mut f: impl FnMut(usize) -> bool) -> Self {
+        let words = (0..len.div_ceil(64))
+            .map(|wi| {
+                let base = wi * 64;
+                (0..(len - base).min(64)).fold(0u64, |w, b| w | (f(base + b) as u64) << b)
+            })
+            .collect();
+        Self { words, len }
+    }
+
+    /// Bits backed by `words`, least-significant bit first; bits past `len` are cleared.
+    pub(crate) fn from_words(words: Vec<u64>, len: usize) -> Self {
+        debug_assert_eq!(words.len(), len.div_ceil(64));
+        let mut bm = Self { words, len };
+        bm.clear_tail();
+        bm
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Backing words, least-significant bit first.
+    #[inline]
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    pub fn push(&mut self, value: bool) {
+        if self.len.is_multiple_of(64) {
+            self.words.push(0);
+        }
+        self.len += 1;
+        self.set(self.len - 1, value);
+    }
+
+    #[inline]
+    pub fn get(&self, i: usize) -> bool {
+        assert!(i < self.len, "bit {i} out of bounds (len {})", self.len);
+        self.words[i / 64] >> (i % 64) & 1 == 1
+    }
+
+    #[inline]
+    pub fn set(&mut self, i: usize, value: bool) {
+        assert!(i < self.len, "bit {i} out of bounds (len {})", self.len);
+        let (w, b) = (i / 64, i % 64);
+        if value {
+            self.words[w] |= 1 << b;
+        } else {
+            self.words[w] &= !(1 << b);
+        }
+    }
+
+    /// Room for at least `additional` more bits without reallocating.
+    pub fn reserve(&mut self, additional: usize) {
+        let words = (self.len + additional).div_ceil(64);
+        self.words.reserve(words.saturating_sub(self.words.len()));
+    }
+
+    pub fn shrink_to_fit(&mut self) {
+        self.words.shrink_to_fit();
+    }
+
+    /// Allocated backing words.
+    pub fn word_capacity(&self) -> usize {
+        self.words.capacity()
+    }
+
+    pub fn truncate(&mut self, len: usize) {
+        if len < self.len {
+            self.len = len;
+            self.words.truncate(len.div_ceil(64));
+            self.clear_tail();
+        }
+    }
+
+    /// Clear every bit without changing the length.
+    pub fn clear_all(&mut self) {
+        self.words.iter_mut().for_each(|w| *w = 0);
+    }
+
+    /// Number of set bits (word-level popcount).
+    pub fn count_ones(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    pub fn any(&self) -> bool {
+        self.words.iter().any(|&w| w != 0)
+    }
+
+    /// Positions of set bits, ascending.
+    pub fn iter_ones(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(wi, &w)| {
+            let mut bits = w;
+            std::iter::from_fn(move || {
+                if bits == 0 {
+                    return None;
+                }
+                let b = bits.trailing_zeros() as usize;
+                bits &= bits - 1;
+                Some(wi * 64 + b)
+            })
+        })
+    }
+
+    fn clear_tail(&mut self) {
+        if !self.len.is_multiple_of(64) {
+            if let Some(last) = self.words.last_mut() {
+                *last &= (1u64 << (self.len % 64)) - 1;
+            }
+        }
+    }
+}
+
+impl BitAndAssign<&Bitmap> for Bitmap {
+    /// # Panics
+    /// If the lengths differ.
+    fn bitand_assign(&mut self, rhs: &Bitmap) {
+        assert_eq!(self.len, rhs.len, "bitmap length mismatch");
+        self.words
+            .iter_mut()
+            .zip(&rhs.words)
+            .for_each(|(a, b)| *a &= b);
+    }
+}
+
+impl BitOrAssign<&Bitmap> for Bitmap {
+    /// # Panics
+    /// If the lengths differ.
+    fn bitor_assign(&mut self, rhs: &Bitmap) {
+        assert_eq!(self.len, rhs.len, "bitmap length mismatch");
+        self.words
+            .iter_mut()
+            .zip(&rhs.words)
+            .for_each(|(a, b)| *a |= b);
+    }
+}
+
+impl BitAnd for &Bitmap {
+    type Output = Bitmap;
+
+    fn bitand(self, rhs: &Bitmap) -> Bitmap {
+        let mut out = self.clone();
+        out &= rhs;
+        out
+    }
+}
+
+impl BitOr for &Bitmap {
+    type Output = Bitmap;
+
+    fn bitor(self, rhs: &Bitmap) -> Bitmap {
+        let mut out = self.clone();
+        out |= rhs;
+        out
+    }
+}
+
+impl Not for &Bitmap {
+    type Output = Bitmap;
+
+    fn not(self) -> Bitmap {
+        let mut out = Bitmap {
+            words: self.words.iter().map(|w| !w).collect(),
+            len: self.len,
+        };
+        out.clear_tail();
+        out
+    }
+}
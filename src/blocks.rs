@@ -0,0 +1,161 @@
+//! Blocked iteration for custom kernels.
+//!
+//! `OrderSoA::blocks` cuts the columns into runs of a fixed number of rows and yields each run as
+//! a `ColumnBlock` of equal-length slices, so a hand-written kernel can make several passes over
+//! one cache-sized tile (say, a filter then an aggregate) before moving on, instead of streaming
+//! the full columns once per pass. The default, `DEFAULT_BLOCK_ROWS`, keeps a block's amount and
+//! status columns within a 48 KiB L1 data cache.
+//!
+//! `Blocks::prefetch(ahead)` additionally asks the CPU to start loading the amount and status
+//! columns of the block `ahead` positions further on while the current one is processed (x86-64
+//! only; a no-op elsewhere and with `no-unsafe`).
+//!
+//! `cargo bench --bench kernels -- blocks` runs a two-pass kernel (a total, then a maximum, of
+//! completed amounts) over the full columns and block by block. Once the columns outgrow the
+//! caches (10M rows) the blocked version is about 10% faster, since its second pass hits L1; on
+//! columns that already fit in cache (1M rows) it is about 20% slower. Prefetch hints made both
+//! cases slower: a sequential scan is exactly what the hardware prefetcher already handles, and
+//! one hint per cache line costs more than it hides. Leave them off unless a kernel's access
+//! pattern defeats the hardware prefetcher, and measure.
+
+use crate::{Bitmap, Currency, CustomerId, OrderId, OrderSoA, Status};
+use std::iter::FusedIterator;
+use std::ops::Range;
+
+/// Rows per block that keep the amount and status columns of a block in a 48 KiB L1 cache.
+pub const DEFAULT_BLOCK_ROWS: usize = 4096;
+
+/// Cache line size assumed by the prefetch hints.
+#[cfg(all(target_arch = "x86_64", not(feature = "no-unsafe")))]
+const CACHE_LINE: usize = 64;
+
+/// One run of rows of every column; see the module docs. Slices are indexed from the block's
+/// first row, and tombstoned rows are included: check `is_live`.
+#[derive(Copy, Clone, Debug)]
+pub struct ColumnBlock<'a> {
+    pub ids: &'a [OrderId],
+    pub amounts: &'a [f64],
+    pub statuses: &'a [Status],
+    pub timestamps: &'a [u64],
+    pub currencies: &'a [Currency],
+    pub customers: &'a [CustomerId],
+    start: usize,
+    dead: Option<&'a Bitmap>,
+}
+
+impl ColumnBlock<'_> {
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// The kernel rows the block covers.
+    pub fn rows(&self) -> Range<usize> {
+        self.start..self.start + self.len()
+    }
+
+    /// Whether the block's `i`-th row is live.
+    #[inline]
+    pub fn is_live(&self, i: usize) -> bool {
+        self.dead.is_none_or(|d| !d.get(self.start + i))
+    }
+}
+
+/// Iterator over a kernel's `ColumnBlock`s, in row order; see `OrderSoA::blocks`.
+#[derive(Clone, Debug)]
+pub struct Blocks<'a> {
+    soa: &'a OrderSoA,
+    size: usize,
+    next: usize,
+    ahead: usize,
+}
+
+impl<'a> Blocks<'a> {
+    /// Builder: hint the CPU to load the block `ahead` positions ahead of each one yielded; zero
+    /// turns hints off. See the module docs.
+    pub fn prefetch(mut self, ahead: usize) -> Self {
+        self.ahead = ahead;
+        self
+    }
+
+    fn block(&self, rows: Range<usize>) -> ColumnBlock<'a> {
+        let soa = self.soa;
+        ColumnBlock {
+            ids: &soa.ids[rows.clone()],
+            amounts: &soa.amounts[rows.clone()],
+            statuses: &soa.statuses[rows.clone()],
+            timestamps: &soa.timestamps[rows.clone()],
+            currencies: &soa.currencies[rows.clone()],
+            customers: &soa.customers[rows.clone()],
+            start: rows.start,
+            dead: soa.dead_mask(),
+        }
+    }
+}
+
+impl<'a> Iterator for Blocks<'a> {
+    type Item = ColumnBlock<'a>;
+
+    fn next(&mut self) -> Option<ColumnBlock<'a>> {
+        let len = self.soa.len();
+        if self.next >= len {
+            return None;
+        }
+        let start = self.next;
+        self.next = (start + self.size).min(len);
+        if self.ahead > 0 {
+            let from = start.saturating_add(self.ahead.saturating_mul(self.size));
+            if from < len {
+                let ahead = self.block(from..from.saturating_add(self.size).min(len));
+                prefetch(ahead.amounts);
+                prefetch(ahead.statuses);
+            }
+        }
+        Some(self.block(start..self.next))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let n = (self.soa.len() - self.next).div_ceil(self.size);
+        (n, Some(n))
+    }
+}
+
+impl ExactSizeIterator for Blocks<'_> {}
+
+impl FusedIterator for Blocks<'_> {}
+
+/// Ask the CPU to pull `data` into cache, one hint per cache line.
+#[inline]
+fn prefetch<T>(data: &[T]) {
+    #[cfg(all(target_arch = "x86_64", not(feature = "no-unsafe")))]
+    {
+        use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+        let base = data.as_ptr().cast::<i8>();
+        for offset in (0..std::mem::size_of_val(data)).step_by(CACHE_LINE) {
+            // SAFETY: `offset` stays within `data`, and a prefetch never faults or changes
+            // memory; SSE is part of the x86-64 baseline.
+            unsafe { _mm_prefetch::<_MM_HINT_T0>(base.add(offset)) };
+        }
+    }
+    #[cfg(not(all(target_arch = "x86_64", not(feature = "no-unsafe"))))]
+    let _ = data;
+}
+
+impl OrderSoA {
+    /// The columns in runs of `block_size` rows (the last may be shorter); see the module docs.
+    ///
+    /// # Panics
+    /// If `block_size` is zero.
+    pub fn blocks(&self, block_size: usize) -> Blocks<'_> {
+        assert!(block_size > 0, "block size must be non-zero");
+        Blocks {
+            soa: self,
+            size: block_size,
+            next: 0,
+            ahead: 0,
+        }
+    }
+}
@@ -0,0 +1,128 @@
+//! One place to configure a store: `OrderStore::builder()`.
+//!
+//! The builder collects capacity, shard count, `IdPolicy`, indexes, validators and the clock,
+//! then produces either an `OrderStore` (`build`) or a `ShardedOrderStore` (`build_sharded`), so
+//! the same configuration can be moved between the two. Sharded stores take no clock yet.
+
+use crate::clock::{Clock, SharedClock};
+use crate::routing::ShardRouter;
+use crate::validation::{Validator, Validators};
+use crate::{IdPolicy, IndexSpec, ModuloRouter, OrderSoA, OrderStore, ShardedOrderStore};
+use crossbeam_utils::CachePadded;
+use std::sync::{Arc, RwLock};
+
+/// Configuration for `OrderStore` / `ShardedOrderStore`.
+#[derive(Clone, Debug)]
+pub struct OrderStoreBuilder {
+    capacity: usize,
+    shards: usize,
+    router: Arc<dyn ShardRouter>,
+    id_policy: IdPolicy,
+    status_index: bool,
+    indexes: Vec<IndexSpec>,
+    validators: Validators,
+    clock: SharedClock,
+}
+
+impl Default for OrderStoreBuilder {
+    fn default() -> Self {
+        Self {
+            capacity: 0,
+            shards: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            router: Arc::new(ModuloRouter),
+            id_policy: IdPolicy::default(),
+            status_index: false,
+            indexes: Vec::new(),
+            validators: Validators::default(),
+            clock: SharedClock::default(),
+        }
+    }
+}
+
+impl OrderStore {
+    pub fn builder() -> OrderStoreBuilder {
+        OrderStoreBuilder::default()
+    }
+}
+
+impl OrderStoreBuilder {
+    /// Rows to reserve up front (split evenly across shards for `build_sharded`).
+    pub fn capacity(mut self, rows: usize) -> Self {
+        self.capacity = rows;
+        self
+    }
+
+    /// Shard count for `build_sharded`; defaults to the available parallelism.
+    pub fn shards(mut self, n: usize) -> Self {
+        self.shards = n.max(1);
+        self
+    }
+
+    /// How `build_sharded` places ids on shards; defaults to `ModuloRouter`.
+    pub fn router(mut self, router: impl ShardRouter + 'static) -> Self {
+        self.router = Arc::new(router);
+        self
+    }
+
+    /// What inserting an already stored id does; see `IdPolicy`.
+    pub fn id_policy(mut self, policy: IdPolicy) -> Self {
+        self.id_policy = policy;
+        self
+    }
+
+    /// Serve `find_by_status` from an inverted status index.
+    pub fn status_index(mut self, enabled: bool) -> Self {
+        self.status_index = enabled;
+        self
+    }
+
+    /// Maintain the composite index `spec`; see `IndexSpec`.
+    pub fn index(mut self, spec: IndexSpec) -> Self {
+        self.indexes.push(spec);
+        self
+    }
+
+    /// Add a domain invariant checked on every add and update; see `Validator`.
+    pub fn validator(mut self, v: impl Validator + 'static) -> Self {
+        self.validators.push(v);
+        self
+    }
+
+    /// Where `build` stores read the current time; see `OrderStore::with_clock`.
+    pub fn clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = SharedClock(Arc::new(clock));
+        self
+    }
+
+    fn kernel(&self, capacity: usize) -> OrderSoA {
+        let mut soa = OrderSoA::with_capacity(capacity);
+        for spec in &self.indexes {
+            soa.add_index(spec.clone());
+        }
+        if self.status_index {
+            soa.with_status_index()
+        } else {
+            soa
+        }
+    }
+
+    pub fn build(self) -> OrderStore {
+        let mut store = OrderStore::from(self.kernel(self.capacity)).with_id_policy(self.id_policy);
+        store.validators = self.validators;
+        store.clock = self.clock;
+        store
+    }
+
+    pub fn build_sharded(self) -> ShardedOrderStore {
+        let per_shard = self.capacity.div_ceil(self.shards);
+        ShardedOrderStore {
+            shards: (0..self.shards)
+                .map(|_| CachePadded::new(RwLock::new(self.kernel(per_shard))))
+                .collect(),
+            counters: (0..self.shards).map(|_| Default::default()).collect(),
+            router: self.router,
+            id_policy: self.id_policy,
+            validators: self.validators,
+        }
+    }
+}
@@ -0,0 +1,107 @@
+//! Bulk status transitions.
+//!
+//! Batch jobs (nightly cancellation of stale pending orders, closing out a settled day) move many
+//! orders from one status to another. `transition_status` does that in one pass over the status
+//! column, consulting the predicate only for rows already in `from`, instead of going through a
+//! mutable view per row: the status index is invalidated once and the audit stamp is taken once.
+//! The store version also validates every rewritten row before changing any, and reports each
+//! change to subscribers and views as `update_with` would.
+
+use crate::{
+    query_cache, AuditColumns, ChangeKind, Field, OrderRow, OrderSoA, OrderStore, OrderView,
+    RowChange, Status, StoreError,
+};
+
+impl OrderSoA {
+    /// Live rows in `from` that `pred` accepts, ascending; none if `from == to`.
+    pub(crate) fn transition_candidates(
+        &self,
+        from: Status,
+        to: Status,
+        pred: impl Fn(OrderView<'_>) -> bool,
+    ) -> Vec<usize> {
+        if from == to {
+            return Vec::new();
+        }
+        (0..self.len())
+            .filter(|&i| self.statuses[i] == from && self.is_live(i) && pred(self.view_at(i)))
+            .collect()
+    }
+
+    /// Set `rows` to `to`, bumping their versions and audit stamps.
+    fn rewrite_statuses(&mut self, rows: &[usize], to: Status) {
+        if rows.is_empty() {
+            return;
+        }
+        self.invalidate_indexes();
+        let stamp = self.audit.as_ref().map(AuditColumns::stamp);
+        for &i in rows {
+            self.statuses[i] = to;
+            self.versions[i] += 1;
+            if let (Some(a), Some(stamp)) = (&mut self.audit, stamp) {
+                a.touch(i, stamp);
+            }
+        }
+    }
+
+    /// Move every live order in `from` that `pred` accepts to `to`; returns how many moved. Like
+    /// `set_status`, this does not consult `Status::can_transition_to` or a state machine.
+    pub fn transition_status(
+        &mut self,
+        from: Status,
+        to: Status,
+        pred: impl Fn(OrderView<'_>) -> bool,
+    ) -> usize {
+        let rows = self.transition_candidates(from, to, pred);
+        self.rewrite_statuses(&rows, to);
+        rows.len()
+    }
+}
+
+impl OrderStore {
+    /// `OrderSoA::transition_status` through the store. Every rewritten row is checked by the
+    /// validators first, and if any is rejected nothing changes; then each emits a
+    /// `Field::Status` update to subscribers and is folded into the views.
+    pub fn transition_status(
+        &mut self,
+        from: Status,
+        to: Status,
+        pred: impl Fn(OrderView<'_>) -> bool,
+    ) -> Result<usize, StoreError> {
+        let rows = self.inner.transition_candidates(from, to, pred);
+        if rows.is_empty() {
+            return Ok(0);
+        }
+        let observed =
+            self.changes.is_active() || !self.validators.is_empty() || self.tracking.is_active();
+        let before: Vec<OrderRow> = if observed {
+            rows.iter()
+                .map(|&i| OrderRow::from(self.inner.view_at(i)))
+                .collect()
+        } else {
+            Vec::new()
+        };
+        for &row in &before {
+            self.validators
+                .check(OrderRow { status: to, ..row }, Some(row))?;
+        }
+        query_cache::write(&mut self.inner, &mut self.epoch, self.context)
+            .rewrite_statuses(&rows, to);
+        for (&i, &before) in rows.iter().zip(&before) {
+            let handle = self.inner.handle_at(i);
+            let kind = ChangeKind::Updated {
+                field: Field::Status,
+            };
+            self.changes.emit(handle, kind);
+            self.track(RowChange {
+                handle,
+                before: Some(before),
+                after: Some(OrderRow {
+                    status: to,
+                    ..before
+                }),
+            });
+        }
+        Ok(rows.len())
+    }
+}
@@ -0,0 +1,116 @@
+//! Incrementally maintained per-status aggregates for `OrderStore`.
+//!
+//! With `with_cached_aggregates`, every insert, update and delete made through the store's API
+//! adjusts a cached `StatusGroups`, so `sum_by_status` and `group_by_status` answer in O(1)
+//! instead of scanning. Counts and totals update exactly (up to float rounding); removing a
+//! group's current minimum or maximum only marks that group's extrema stale, and reads rescan
+//! that one group until the next rebuild. Writes through `kernel_mut` bypass the bookkeeping and
+//! mark the whole cache stale: reads fall back to a scan until the next tracked write or
+//! `refresh_views` rebuilds it. The cache is the built-in case of the store's materialized
+//! views (see `views`) and is fed by the same row-change hook.
+
+use crate::{Aggregate, Money, OrderSoA, OrderStore, RowChange, Status, StatusGroups};
+
+/// The cached groups plus what is known to be out of date.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct CachedAggregates {
+    groups: StatusGroups,
+    extrema_stale: [bool; Status::ALL.len()],
+}
+
+impl CachedAggregates {
+    pub(crate) fn scan(soa: &OrderSoA) -> Self {
+        Self {
+            groups: soa.group_by_status(),
+            ..Self::default()
+        }
+    }
+
+    fn insert(&mut self, status: Status, amount: Money) {
+        self.groups.observe(status, amount.0);
+    }
+
+    fn delete(&mut self, status: Status, amount: Money) {
+        let s = status as usize;
+        let group = self.groups.get_mut(status);
+        if group.count <= 1 {
+            *group = Aggregate::default();
+            self.extrema_stale[s] = false;
+            return;
+        }
+        group.count -= 1;
+        group.total.0 -= amount.0;
+        if group.min == Some(amount) || group.max == Some(amount) {
+            self.extrema_stale[s] = true;
+        }
+    }
+
+    /// A row changed from `before` to `after` (status, amount).
+    fn update(&mut self, before: (Status, Money), after: (Status, Money)) {
+        if before != after {
+            self.delete(before.0, before.1);
+            self.insert(after.0, after.1);
+        }
+    }
+
+    pub(crate) fn apply(&mut self, change: &RowChange) {
+        match (&change.before, &change.after) {
+            (Some(b), Some(a)) => self.update((b.status, b.amount), (a.status, a.amount)),
+            (Some(b), None) => self.delete(b.status, b.amount),
+            (None, Some(a)) => self.insert(a.status, a.amount),
+            (None, None) => {}
+        }
+    }
+
+    /// The groups, with stale extrema recomputed from `soa`.
+    fn groups(&self, soa: &OrderSoA) -> StatusGroups {
+        let mut groups = self.groups;
+        for s in Status::ALL
+            .into_iter()
+            .filter(|&s| self.extrema_stale[s as usize])
+        {
+            let fresh = soa
+                .find_by_status(s)
+                .fold(Aggregate::default(), |mut a, v| {
+                    a.observe(v.amount().0);
+                    a
+                });
+            let group = groups.get_mut(s);
+            group.min = fresh.min;
+            group.max = fresh.max;
+        }
+        groups
+    }
+}
+
+impl OrderStore {
+    /// Builder flag: maintain per-status aggregates incrementally; see `sum_by_status`.
+    pub fn with_cached_aggregates(mut self) -> Self {
+        self.tracking.aggregates = Some(CachedAggregates::scan(&self.inner));
+        self
+    }
+
+    /// The cache, if enabled and current.
+    fn aggregates(&self) -> Option<&CachedAggregates> {
+        self.tracking
+            .aggregates
+            .as_ref()
+            .filter(|_| !self.tracking.stale)
+    }
+
+    /// Total amount per status: O(1) from the cache when enabled and current, a scan otherwise.
+    pub fn sum_by_status(&self, status: Status) -> Money {
+        match self.aggregates() {
+            Some(cache) => cache.groups.get(status).total,
+            None => self.inner.sum_by_status(status),
+        }
+    }
+
+    /// See `OrderSoA::group_by_status`; served from the cache when enabled and current.
+    pub fn group_by_status(&self) -> StatusGroups {
+        match self.aggregates() {
+            Some(cache) => cache.groups(&self.inner),
+            None => self.inner.group_by_status(),
+        }
+    }
+}
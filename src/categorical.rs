@@ -0,0 +1,204 @@
+//! Dictionary-encoded categorical columns with variants defined at runtime.
+//!
+//! `Status` is fixed at compile time; fields like sales channel or region are not. A
+//! `CategoricalColumn` stores one `u16` code per row and keeps the distinct names in a
+//! `CategoryDict` behind an `Arc`, so cloned columns share one dictionary until either side
+//! defines a new category (copy-on-write, as in `OrderStore`). Group-bys index straight into a
+//! per-code array instead of hashing names.
+
+use crate::dyn_soa::DynColumn;
+use crate::Aggregate;
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Code of a category within its dictionary.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Category(pub u16);
+
+/// Category names by code. Code 0 is always the empty name, given to rows never assigned one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CategoryDict {
+    names: Vec<Box<str>>,
+    codes: HashMap<Box<str>, Category>,
+}
+
+impl Default for CategoryDict {
+    fn default() -> Self {
+        Self {
+            names: vec!["".into()],
+            codes: HashMap::from([("".into(), Category(0))]),
+        }
+    }
+}
+
+impl CategoryDict {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of categories, including the empty one.
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Always `false`: the empty category is always defined.
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn code(&self, name: &str) -> Option<Category> {
+        self.codes.get(name).copied()
+    }
+
+    pub fn name(&self, code: Category) -> Option<&str> {
+        self.names.get(code.0 as usize).map(|n| &**n)
+    }
+
+    /// Names in code order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.names.iter().map(|n| &**n)
+    }
+
+    /// The code for `name`, defining it if new.
+    ///
+    /// # Panics
+    /// If the dictionary already holds `u16::MAX + 1` categories.
+    pub fn define(&mut self, name: &str) -> Category {
+        if let Some(code) = self.code(name) {
+            return code;
+        }
+        let code = Category(u16::try_from(self.names.len()).expect("more than 65536 categories"));
+        self.names.push(name.into());
+        self.codes.insert(name.into(), code);
+        code
+    }
+}
+
+/// Row-aligned `u16` codes over a shared `CategoryDict`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CategoricalColumn {
+    dict: Arc<CategoryDict>,
+    codes: Vec<u16>,
+}
+
+impl CategoricalColumn {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// An empty column sharing `dict`, e.g. the same region dictionary across stores.
+    pub fn with_dict(dict: Arc<CategoryDict>) -> Self {
+        Self {
+            dict,
+            codes: Vec::new(),
+        }
+    }
+
+    pub fn dict(&self) -> &Arc<CategoryDict> {
+        &self.dict
+    }
+
+    pub fn len(&self) -> usize {
+        self.codes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.codes.is_empty()
+    }
+
+    /// The raw code column, for kernels.
+    pub fn codes(&self) -> &[u16] {
+        &self.codes
+    }
+
+    /// The code for `name`, defining it if new. Defining copies the dictionary first if it is
+    /// shared.
+    pub fn intern(&mut self, name: &str) -> Category {
+        match self.dict.code(name) {
+            Some(code) => code,
+            None => Arc::make_mut(&mut self.dict).define(name),
+        }
+    }
+
+    /// Append a row by name; returns its row.
+    pub fn push(&mut self, name: &str) -> usize {
+        let code = self.intern(name);
+        self.push_code(code)
+    }
+
+    /// Append a row by code.
+    ///
+    /// # Panics
+    /// If `code` is not defined in the dictionary.
+    pub fn push_code(&mut self, code: Category) -> usize {
+        assert!(
+            (code.0 as usize) < self.dict.len(),
+            "undefined category {code:?}"
+        );
+        self.codes.push(code.0);
+        self.codes.len() - 1
+    }
+
+    pub fn set(&mut self, i: usize, name: &str) {
+        let code = self.intern(name);
+        self.codes[i] = code.0;
+    }
+
+    #[inline]
+    pub fn get(&self, i: usize) -> Category {
+        Category(self.codes[i])
+    }
+
+    #[inline]
+    pub fn name(&self, i: usize) -> &str {
+        &self.dict.names[self.codes[i] as usize]
+    }
+
+    /// Rows per category, indexed by code.
+    pub fn counts(&self) -> Vec<usize> {
+        let mut counts = vec![0; self.dict.len()];
+        for &c in &self.codes {
+            counts[c as usize] += 1;
+        }
+        counts
+    }
+
+    /// Aggregate `values` (row-aligned with this column) per category, for rows where `keep`
+    /// holds; indexed by code. One pass over the codes and values.
+    pub fn group_by(&self, values: &[f64], keep: impl Fn(usize) -> bool) -> Vec<Aggregate> {
+        debug_assert_eq!(values.len(), self.len());
+        let mut groups = vec![Aggregate::default(); self.dict.len()];
+        for (i, (&c, &v)) in self.codes.iter().zip(values).enumerate() {
+            if keep(i) {
+                groups[c as usize].observe(v);
+            }
+        }
+        groups
+    }
+}
+
+impl DynColumn for CategoricalColumn {
+    fn len(&self) -> usize {
+        self.codes.len()
+    }
+    fn push_default(&mut self) {
+        self.codes.push(0);
+    }
+    fn retain_mask(&mut self, keep: &[bool]) {
+        let mut i = 0;
+        self.codes.retain(|_| {
+            i += 1;
+            keep[i - 1]
+        });
+    }
+    fn type_name(&self) -> &'static str {
+        "category"
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
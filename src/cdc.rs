@@ -0,0 +1,109 @@
+//! Change-data capture: a feed of row-level changes for downstream projections.
+//!
+//! `OrderStore::subscribe` hands out a channel receiving a `ChangeEvent` for every insert,
+//! changed field and delete made through the store's API, in order, so caches, indexes and UIs
+//! can update incrementally instead of rescanning. Writes made through `kernel_mut` bypass the
+//! feed. Compaction moves rows to new handles; each moved row is reported as `Deleted` under its
+//! old handle followed by `Inserted` under the new one.
+//!
+//! A cloned store starts without subscribers, and a `Transaction` holds its changes back until
+//! it commits.
+
+use crate::{OrderRow, OrderStore, RowHandle};
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+/// A column of an order row, as reported by `ChangeKind::Updated`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Field {
+    Amount,
+    Currency,
+    Status,
+    Timestamp,
+    Customer,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ChangeKind {
+    Inserted,
+    /// One event per field whose value actually changed.
+    Updated {
+        field: Field,
+    },
+    Deleted,
+}
+
+/// One row-level change.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ChangeEvent {
+    pub handle: RowHandle,
+    pub kind: ChangeKind,
+}
+
+/// Subscribers of one store; see the module docs.
+#[derive(Debug, Default)]
+pub(crate) struct ChangeFeed {
+    subscribers: Vec<Sender<ChangeEvent>>,
+    /// Held back for a transaction, which publishes them on commit.
+    held: Option<Vec<ChangeEvent>>,
+}
+
+impl Clone for ChangeFeed {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl ChangeFeed {
+    /// A feed that holds every change until `take_held`.
+    pub(crate) fn holding() -> Self {
+        Self {
+            subscribers: Vec::new(),
+            held: Some(Vec::new()),
+        }
+    }
+
+    /// Whether anyone would see an event; writers skip computing diffs otherwise.
+    #[inline]
+    pub(crate) fn is_active(&self) -> bool {
+        !self.subscribers.is_empty() || self.held.is_some()
+    }
+
+    pub(crate) fn emit(&mut self, handle: RowHandle, kind: ChangeKind) {
+        let event = ChangeEvent { handle, kind };
+        if let Some(held) = &mut self.held {
+            held.push(event);
+            return;
+        }
+        // Receivers that hung up are dropped.
+        self.subscribers.retain(|tx| tx.send(event).is_ok());
+    }
+
+    /// Report every field that differs between two images of the row at `handle`.
+    pub(crate) fn emit_diff(&mut self, handle: RowHandle, before: &OrderRow, after: &OrderRow) {
+        let changed = [
+            (Field::Amount, before.amount != after.amount),
+            (Field::Currency, before.currency != after.currency),
+            (Field::Status, before.status != after.status),
+            (Field::Timestamp, before.ts != after.ts),
+            (Field::Customer, before.customer != after.customer),
+        ];
+        for (field, differs) in changed {
+            if differs {
+                self.emit(handle, ChangeKind::Updated { field });
+            }
+        }
+    }
+
+    pub(crate) fn take_held(&mut self) -> Vec<ChangeEvent> {
+        self.held.take().unwrap_or_default()
+    }
+}
+
+impl OrderStore {
+    /// Receive every later change made through this store. Dropping the receiver unsubscribes.
+    pub fn subscribe(&mut self) -> Receiver<ChangeEvent> {
+        let (tx, rx) = channel();
+        self.changes.subscribers.push(tx);
+        rx
+    }
+}
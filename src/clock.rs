@@ -0,0 +1,131 @@
+//! Where "now" comes from.
+//!
+//! Order timestamps are plain `u64`s (seconds since the Unix epoch by convention). Code that
+//! needs the current time — `OrderStore::add_now`, expiry, time windows — asks the store's
+//! `Clock` instead of the OS, so tests can pin time with a `FixedClock` and move it explicitly.
+
+use crate::{Money, OrderId, OrderStore, RowHandle, Status, StoreError};
+use std::fmt;
+use std::panic::{RefUnwindSafe, UnwindSafe};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A source of timestamps.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> u64;
+}
+
+impl<F: Fn() -> u64 + Send + Sync> Clock for F {
+    fn now(&self) -> u64 {
+        self()
+    }
+}
+
+/// Wall-clock seconds since the Unix epoch. May jump backwards if the OS clock is adjusted; wrap
+/// it in `MonotonicClock` where that matters.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs())
+    }
+}
+
+/// A clock that only moves when told to. Clones share the same time, so a test can keep one and
+/// hand another to the store.
+#[derive(Clone, Debug, Default)]
+pub struct FixedClock(Arc<AtomicU64>);
+
+impl FixedClock {
+    pub fn new(now: u64) -> Self {
+        Self(Arc::new(AtomicU64::new(now)))
+    }
+
+    pub fn set(&self, now: u64) {
+        self.0.store(now, Ordering::Relaxed);
+    }
+
+    pub fn advance(&self, by: u64) {
+        self.0.fetch_add(by, Ordering::Relaxed);
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Never reads earlier than a previous reading: a backwards step of `C` repeats the latest time
+/// instead.
+#[derive(Debug, Default)]
+pub struct MonotonicClock<C> {
+    inner: C,
+    latest: AtomicU64,
+}
+
+impl<C: Clock> MonotonicClock<C> {
+    pub fn new(inner: C) -> Self {
+        Self {
+            inner,
+            latest: AtomicU64::new(0),
+        }
+    }
+}
+
+impl<C: Clock> Clock for MonotonicClock<C> {
+    fn now(&self) -> u64 {
+        let now = self.inner.now();
+        self.latest.fetch_max(now, Ordering::Relaxed).max(now)
+    }
+}
+
+/// The clock a store reads; `SystemClock` unless configured otherwise.
+#[derive(Clone)]
+pub(crate) struct SharedClock(pub(crate) Arc<dyn Clock>);
+
+impl Default for SharedClock {
+    fn default() -> Self {
+        Self(Arc::new(SystemClock))
+    }
+}
+
+// A clock only hands out readings: a panic inside one cannot leave the store (or a kernel
+// holding the clock for its audit columns) half-written.
+impl UnwindSafe for SharedClock {}
+impl RefUnwindSafe for SharedClock {}
+
+impl fmt::Debug for SharedClock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "clock at {}", self.0.now())
+    }
+}
+
+impl OrderStore {
+    /// Builder flag: read the current time from `clock`; see `add_now`.
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = SharedClock(Arc::new(clock));
+        self.sync_audit_clock();
+        self
+    }
+
+    /// The store clock's current time.
+    pub fn now(&self) -> u64 {
+        self.clock.0.now()
+    }
+
+    /// `add`, timestamped with the store clock's current time.
+    pub fn add_now(
+        &mut self,
+        id: OrderId,
+        amount: Money,
+        status: Status,
+    ) -> Result<RowHandle, StoreError> {
+        let ts = self.now();
+        self.add(id, amount, status, ts)
+    }
+}
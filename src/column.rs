@@ -0,0 +1,246 @@
+//! Storage-independent columns and the kernels written against them.
+//!
+//! `Column<T>` is the read side every column backend shares: a length, random access and an
+//! in-order scan of the values. It is implemented by plain slices and `Vec`s (which also covers
+//! memory-mapped data viewed as a slice), `ChunkedVec`, and the compressed `DeltaColumn`,
+//! `RleColumn` and `DictColumn`. The kernels here (`sum`, `sum_where`, `filter`, `filter_where`,
+//! `min`, `max`) are written once against the trait, so the same algorithm runs on any backend;
+//! over slices the scan is a plain zipped iterator loop that the compiler vectorizes, and over
+//! encoded backends values are decoded on the fly, never materialized.
+//!
+//! Multi-column kernels expect columns of equal length, as the kernels' columns always are. The
+//! optional `skip` bitmap (e.g. a kernel's tombstones) leaves out the rows it has set.
+
+use crate::compression::{DeltaIter, DictIter, RleIter};
+use crate::{Bitmap, ChunkedVec, DeltaColumn, DictColumn, RleColumn, Summation};
+use std::hash::Hash;
+use std::iter::{Copied, Flatten};
+use std::slice;
+
+/// Read access to a column of `T`s, whatever its storage; see the module docs.
+pub trait Column<T: Copy> {
+    /// In-order scan of the values.
+    type Scan<'a>: Iterator<Item = T>
+    where
+        Self: 'a;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Value of row `i`. O(1) for slices, chunked and dictionary columns; delta and run-length
+    /// columns decode up to row `i`.
+    fn value(&self, i: usize) -> Option<T>;
+
+    /// Every value, in row order.
+    fn scan(&self) -> Self::Scan<'_>;
+}
+
+impl<T: Copy> Column<T> for [T] {
+    type Scan<'a>
+        = Copied<slice::Iter<'a, T>>
+    where
+        T: 'a;
+
+    fn len(&self) -> usize {
+        <[T]>::len(self)
+    }
+    fn value(&self, i: usize) -> Option<T> {
+        <[T]>::get(self, i).copied()
+    }
+    fn scan(&self) -> Self::Scan<'_> {
+        self.iter().copied()
+    }
+}
+
+impl<T: Copy> Column<T> for Vec<T> {
+    type Scan<'a>
+        = Copied<slice::Iter<'a, T>>
+    where
+        T: 'a;
+
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+    fn value(&self, i: usize) -> Option<T> {
+        self.as_slice().get(i).copied()
+    }
+    fn scan(&self) -> Self::Scan<'_> {
+        self.iter().copied()
+    }
+}
+
+impl<T: Copy> Column<T> for ChunkedVec<T> {
+    type Scan<'a>
+        = Copied<Flatten<slice::Iter<'a, Vec<T>>>>
+    where
+        T: 'a;
+
+    fn len(&self) -> usize {
+        ChunkedVec::len(self)
+    }
+    fn value(&self, i: usize) -> Option<T> {
+        ChunkedVec::get(self, i).copied()
+    }
+    fn scan(&self) -> Self::Scan<'_> {
+        self.iter().copied()
+    }
+}
+
+impl Column<u64> for DeltaColumn {
+    type Scan<'a> = DeltaIter<'a>;
+
+    fn len(&self) -> usize {
+        DeltaColumn::len(self)
+    }
+    fn value(&self, i: usize) -> Option<u64> {
+        self.iter().nth(i)
+    }
+    fn scan(&self) -> DeltaIter<'_> {
+        self.iter()
+    }
+}
+
+impl<T: Copy + PartialEq> Column<T> for RleColumn<T> {
+    type Scan<'a>
+        = RleIter<'a, T>
+    where
+        T: 'a;
+
+    fn len(&self) -> usize {
+        RleColumn::len(self)
+    }
+    fn value(&self, mut i: usize) -> Option<T> {
+        for &(v, n) in self.runs() {
+            if i < n as usize {
+                return Some(v);
+            }
+            i -= n as usize;
+        }
+        None
+    }
+    fn scan(&self) -> RleIter<'_, T> {
+        self.iter()
+    }
+}
+
+impl<T: Copy + Eq + Hash> Column<T> for DictColumn<T> {
+    type Scan<'a>
+        = DictIter<'a, T>
+    where
+        T: 'a;
+
+    fn len(&self) -> usize {
+        DictColumn::len(self)
+    }
+    fn value(&self, i: usize) -> Option<T> {
+        DictColumn::get(self, i)
+    }
+    fn scan(&self) -> DictIter<'_, T> {
+        self.iter()
+    }
+}
+
+/// Sum of every value, accumulated per `mode`.
+pub fn sum<C: Column<f64> + ?Sized>(col: &C, skip: Option<&Bitmap>, mode: Summation) -> f64 {
+    match skip {
+        None => mode.sum(col.scan()),
+        Some(skip) => mode.sum(
+            col.scan()
+                .enumerate()
+                .filter(|&(i, _)| !skip.get(i))
+                .map(|(_, v)| v),
+        ),
+    }
+}
+
+/// Sum of `values[i]` over the rows whose `keys[i]` passes `keep`, e.g. amounts by status,
+/// accumulated per `mode`.
+pub fn sum_where<K, V, T>(
+    keys: &K,
+    values: &V,
+    skip: Option<&Bitmap>,
+    mode: Summation,
+    keep: impl Fn(T) -> bool,
+) -> f64
+where
+    K: Column<T> + ?Sized,
+    V: Column<f64> + ?Sized,
+    T: Copy,
+{
+    let rows = keys.scan().zip(values.scan());
+    match skip {
+        None => mode.sum(rows.filter(|&(k, _)| keep(k)).map(|(_, v)| v)),
+        Some(skip) => mode.sum(
+            rows.enumerate()
+                .filter(|&(i, (k, _))| keep(k) && !skip.get(i))
+                .map(|(_, (_, v))| v),
+        ),
+    }
+}
+
+/// Rows whose value passes `keep`, ascending.
+pub fn filter<C, T>(col: &C, skip: Option<&Bitmap>, keep: impl Fn(T) -> bool) -> Vec<usize>
+where
+    C: Column<T> + ?Sized,
+    T: Copy,
+{
+    let rows = col.scan().enumerate().filter(|&(_, v)| keep(v));
+    match skip {
+        None => rows.map(|(i, _)| i).collect(),
+        Some(skip) => rows.map(|(i, _)| i).filter(|&i| !skip.get(i)).collect(),
+    }
+}
+
+/// Rows whose values in `a` and `b` together pass `keep`, ascending.
+pub fn filter_where<A, B, T, U>(
+    a: &A,
+    b: &B,
+    skip: Option<&Bitmap>,
+    keep: impl Fn(T, U) -> bool,
+) -> Vec<usize>
+where
+    A: Column<T> + ?Sized,
+    B: Column<U> + ?Sized,
+    T: Copy,
+    U: Copy,
+{
+    let rows = a.scan().zip(b.scan()).enumerate();
+    let rows = rows.filter(|&(_, (x, y))| keep(x, y)).map(|(i, _)| i);
+    match skip {
+        None => rows.collect(),
+        Some(skip) => rows.filter(|&i| !skip.get(i)).collect(),
+    }
+}
+
+/// The value comparing lowest; the first of equals. `None` if no row is left.
+pub fn min<C, T>(col: &C, skip: Option<&Bitmap>) -> Option<T>
+where
+    C: Column<T> + ?Sized,
+    T: Copy + PartialOrd,
+{
+    extreme(col, skip, |v, best| v < best)
+}
+
+/// The value comparing highest; the first of equals. `None` if no row is left.
+pub fn max<C, T>(col: &C, skip: Option<&Bitmap>) -> Option<T>
+where
+    C: Column<T> + ?Sized,
+    T: Copy + PartialOrd,
+{
+    extreme(col, skip, |v, best| v > best)
+}
+
+fn extreme<C, T>(col: &C, skip: Option<&Bitmap>, better: impl Fn(T, T) -> bool) -> Option<T>
+where
+    C: Column<T> + ?Sized,
+    T: Copy,
+{
+    col.scan()
+        .enumerate()
+        .filter(|&(i, _)| skip.is_none_or(|s| !s.get(i)))
+        .map(|(_, v)| v)
+        .reduce(|best, v| if better(v, best) { v } else { best })
+}
@@ -0,0 +1,378 @@
+//! Composite secondary indexes.
+//!
+//! An `IndexSpec` names an ordered list of key columns, e.g. `(status, timestamp)`. The kernel
+//! keeps each registered index as one sorted array of `(key, row)` entries, so a query that pins
+//! the leading columns and bounds the next one ("pending orders placed in the last hour") is two
+//! binary searches plus a contiguous walk instead of a full scan.
+//!
+//! Maintenance follows the status index: an index is built on first use, appends insert into a
+//! built index in place, and each setter of a `view_mut_at` view (and so `update_with`, `apply`
+//! and aggregate commands) moves the row's entry as it writes. Any other mutation (removal,
+//! `for_each_mut`, `columns_mut`, compaction, sorting) drops it to be rebuilt by the next scan.
+
+use crate::memory::ColumnMemory;
+use crate::{
+    ColumnError, Currency, CustomerId, Money, OrderSoA, OrderStore, OrderView, Status, StatusIndex,
+};
+use std::ops::{Bound, RangeBounds};
+use std::sync::OnceLock;
+
+/// A column an index can be keyed on.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum IndexColumn {
+    Status,
+    Currency,
+    Customer,
+    Timestamp,
+    Amount,
+}
+
+/// Most key columns an index can have: each column at most once.
+const MAX_KEY: usize = 5;
+
+/// Order-preserving encoding of every key column into one `u64`.
+type Key = [u64; MAX_KEY];
+
+/// Values that can pin or bound an index column. Pass the type matching the column: a
+/// `Status` for `IndexColumn::Status`, a `u64` for `IndexColumn::Timestamp`, and so on.
+pub trait IndexKey: Copy {
+    /// Encoding whose `u64` order matches the value's order.
+    fn index_key(self) -> u64;
+}
+
+impl IndexKey for Status {
+    fn index_key(self) -> u64 {
+        self as u64
+    }
+}
+
+impl IndexKey for Currency {
+    fn index_key(self) -> u64 {
+        let [a, b, c] = self.0;
+        u64::from_be_bytes([0, 0, 0, 0, 0, a, b, c])
+    }
+}
+
+impl IndexKey for CustomerId {
+    fn index_key(self) -> u64 {
+        self.0
+    }
+}
+
+impl IndexKey for u64 {
+    fn index_key(self) -> u64 {
+        self
+    }
+}
+
+impl IndexKey for Money {
+    /// IEEE-754 bits with the sign flipped (and negatives inverted), so they sort numerically.
+    fn index_key(self) -> u64 {
+        let bits = self.0.to_bits();
+        if bits >> 63 == 1 {
+            !bits
+        } else {
+            bits | 1 << 63
+        }
+    }
+}
+
+/// Definition of a composite index: a name plus its key columns, most significant first.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IndexSpec {
+    name: String,
+    columns: Vec<IndexColumn>,
+}
+
+impl IndexSpec {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            columns: Vec::new(),
+        }
+    }
+
+    /// Append a key column; a column already in the key is ignored.
+    pub fn on(mut self, column: IndexColumn) -> Self {
+        if !self.columns.contains(&column) {
+            self.columns.push(column);
+        }
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn columns(&self) -> &[IndexColumn] {
+        &self.columns
+    }
+}
+
+/// A registered index and, once built, its sorted entries.
+#[derive(Clone, Debug)]
+pub(crate) struct CompositeIndex {
+    spec: IndexSpec,
+    entries: OnceLock<Vec<(Key, usize)>>,
+}
+
+/// The columns of one row an index can be keyed on.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct RowKey {
+    pub(crate) status: Status,
+    pub(crate) currency: Currency,
+    pub(crate) customer: CustomerId,
+    pub(crate) ts: u64,
+    pub(crate) amount: f64,
+}
+
+impl CompositeIndex {
+    fn key(&self, soa: &OrderSoA, i: usize) -> Key {
+        self.key_of(soa.row_key(i))
+    }
+
+    fn key_of(&self, row: RowKey) -> Key {
+        let mut key = [0; MAX_KEY];
+        for (k, col) in key.iter_mut().zip(&self.spec.columns) {
+            *k = match col {
+                IndexColumn::Status => row.status.index_key(),
+                IndexColumn::Currency => row.currency.index_key(),
+                IndexColumn::Customer => row.customer.index_key(),
+                IndexColumn::Timestamp => row.ts,
+                IndexColumn::Amount => Money(row.amount).index_key(),
+            };
+        }
+        key
+    }
+
+    fn entries(&self, soa: &OrderSoA) -> &[(Key, usize)] {
+        self.entries.get_or_init(|| {
+            let mut entries: Vec<_> = (0..soa.len())
+                .filter(|&i| soa.is_live(i))
+                .map(|i| (self.key(soa, i), i))
+                .collect();
+            entries.sort_unstable();
+            entries
+        })
+    }
+}
+
+impl OrderSoA {
+    /// Register `spec`, replacing any index with the same name. Built lazily on first scan.
+    pub fn add_index(&mut self, spec: IndexSpec) {
+        self.indexes.retain(|ix| ix.spec.name != spec.name);
+        self.indexes.push(CompositeIndex {
+            spec,
+            entries: OnceLock::new(),
+        });
+    }
+
+    /// Builder flag: see `add_index`.
+    pub fn with_index(mut self, spec: IndexSpec) -> Self {
+        self.add_index(spec);
+        self
+    }
+
+    pub fn index_specs(&self) -> impl Iterator<Item = &IndexSpec> {
+        self.indexes.iter().map(|ix| &ix.spec)
+    }
+
+    /// Start a range scan over the index `name`.
+    pub fn index_scan(&self, name: &str) -> Result<IndexScan<'_>, ColumnError> {
+        let index = self
+            .indexes
+            .iter()
+            .find(|ix| ix.spec.name == name)
+            .ok_or_else(|| ColumnError::Missing(name.to_owned()))?;
+        Ok(IndexScan {
+            soa: self,
+            index,
+            lo: [0; MAX_KEY],
+            hi: [u64::MAX; MAX_KEY],
+            pinned: 0,
+            empty: false,
+        })
+    }
+
+    /// Add the just-appended `rows` to every built index.
+    pub(crate) fn index_appended(&mut self, rows: std::ops::Range<usize>) {
+        let mut indexes = std::mem::take(&mut self.indexes);
+        for ix in &mut indexes {
+            let Some(mut entries) = ix.entries.take() else {
+                continue;
+            };
+            if rows.len() == 1 {
+                let entry = (ix.key(self, rows.start), rows.start);
+                let at = entries.partition_point(|e| *e < entry);
+                entries.insert(at, entry);
+            } else {
+                entries.extend(rows.clone().map(|i| (ix.key(self, i), i)));
+                entries.sort_unstable();
+            }
+            ix.entries = OnceLock::from(entries);
+        }
+        self.indexes = indexes;
+    }
+
+    pub(crate) fn row_key(&self, i: usize) -> RowKey {
+        RowKey {
+            status: self.statuses[i],
+            currency: self.currencies[i],
+            customer: self.customers[i],
+            ts: self.timestamps[i],
+            amount: self.amounts[i],
+        }
+    }
+
+    /// Built composite indexes, summed.
+    pub(crate) fn composite_index_memory(&self) -> ColumnMemory {
+        let (len, capacity) = self
+            .indexes
+            .iter()
+            .filter_map(|ix| ix.entries.get())
+            .fold((0, 0), |(len, cap), e| (len + e.len(), cap + e.capacity()));
+        ColumnMemory::sized(
+            "composite_indexes",
+            len,
+            capacity,
+            std::mem::size_of::<(Key, usize)>(),
+        )
+    }
+
+    /// Drop every built secondary index; each is rebuilt on its next use.
+    pub(crate) fn invalidate_indexes(&mut self) {
+        self.status_index.take();
+        for ix in &mut self.indexes {
+            ix.entries.take();
+        }
+    }
+}
+
+/// The built indexes a mutable row view keeps current as its setters write; see `OrderMut`.
+pub(crate) struct IndexUpkeep<'a> {
+    pub(crate) row: usize,
+    pub(crate) status_index: Option<&'a mut StatusIndex>,
+    pub(crate) indexes: &'a mut [CompositeIndex],
+}
+
+impl IndexUpkeep<'_> {
+    /// Move the row from its entries for `before` to those for `after`.
+    pub(crate) fn refile(&mut self, before: RowKey, after: RowKey) {
+        if after == before {
+            return;
+        }
+        let row = self.row;
+        if let Some(ix) = self.status_index.as_deref_mut() {
+            if after.status != before.status {
+                let list = &mut ix[before.status as usize];
+                if let Ok(at) = list.binary_search(&row) {
+                    list.remove(at);
+                }
+                let list = &mut ix[after.status as usize];
+                if let Err(at) = list.binary_search(&row) {
+                    list.insert(at, row);
+                }
+            }
+        }
+        for ix in self.indexes.iter_mut() {
+            let (old, new) = (ix.key_of(before), ix.key_of(after));
+            let Some(entries) = ix.entries.get_mut().filter(|_| old != new) else {
+                continue;
+            };
+            if let Ok(at) = entries.binary_search(&(old, row)) {
+                entries.remove(at);
+            }
+            let entry = (new, row);
+            let at = entries.partition_point(|e| *e < entry);
+            entries.insert(at, entry);
+        }
+    }
+}
+
+impl OrderStore {
+    /// Builder flag: maintain the composite index `spec`; see `IndexSpec`.
+    pub fn with_index(mut self, spec: IndexSpec) -> Self {
+        self.kernel_mut().add_index(spec);
+        self
+    }
+
+    /// See `OrderSoA::index_scan`.
+    pub fn index_scan(&self, name: &str) -> Result<IndexScan<'_>, ColumnError> {
+        self.inner.index_scan(name)
+    }
+}
+
+/// A range of one composite index: equality on a key prefix, then optionally a range on the
+/// next column. Rows come back in key order (ties in storage order).
+#[derive(Copy, Clone, Debug)]
+#[must_use = "a scan does nothing until `indices` or `views` runs it"]
+pub struct IndexScan<'a> {
+    soa: &'a OrderSoA,
+    index: &'a CompositeIndex,
+    lo: Key,
+    hi: Key,
+    /// Key columns fixed so far.
+    pinned: usize,
+    empty: bool,
+}
+
+impl<'a> IndexScan<'a> {
+    /// Pin the next key column to `value`.
+    ///
+    /// # Panics
+    /// If every key column is already constrained.
+    pub fn eq(mut self, value: impl IndexKey) -> Self {
+        assert!(
+            self.pinned < self.index.spec.columns.len(),
+            "index `{}` has no more key columns",
+            self.index.spec.name
+        );
+        self.lo[self.pinned] = value.index_key();
+        self.hi[self.pinned] = value.index_key();
+        self.pinned += 1;
+        self
+    }
+
+    /// Bound the next key column to `range`; no column can be constrained after it.
+    pub fn range<K: IndexKey>(mut self, range: impl RangeBounds<K>) -> Self {
+        let lo = match range.start_bound() {
+            Bound::Included(v) => Some(v.index_key()),
+            Bound::Excluded(v) => v.index_key().checked_add(1),
+            Bound::Unbounded => Some(0),
+        };
+        let hi = match range.end_bound() {
+            Bound::Included(v) => Some(v.index_key()),
+            Bound::Excluded(v) => v.index_key().checked_sub(1),
+            Bound::Unbounded => Some(u64::MAX),
+        };
+        match (lo, hi) {
+            (Some(lo), Some(hi)) if lo <= hi => {
+                self = self.eq(lo);
+                self.hi[self.pinned - 1] = hi;
+            }
+            _ => self.empty = true,
+        }
+        self.pinned = MAX_KEY;
+        self
+    }
+
+    /// Matching rows, in index order.
+    pub fn indices(&self) -> impl Iterator<Item = usize> + 'a {
+        let entries = self.index.entries(self.soa);
+        let (start, end) = if self.empty {
+            (0, 0)
+        } else {
+            (
+                entries.partition_point(|(k, _)| *k < self.lo),
+                entries.partition_point(|(k, _)| *k <= self.hi),
+            )
+        };
+        entries[start..end.max(start)].iter().map(|&(_, i)| i)
+    }
+
+    /// Zero-copy views of the matching rows, in index order.
+    pub fn views(&self) -> impl Iterator<Item = OrderView<'a>> + 'a {
+        let soa = self.soa;
+        self.indices().map(move |i| soa.view_at(i))
+    }
+}
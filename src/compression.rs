@@ -0,0 +1,373 @@
+//! Compressed cold segments.
+//!
+//! `OrderSoA::compress` freezes the live rows into a `CompressedSegment`, encoding each column
+//! with the scheme that suits its data:
+//!
+//! | column      | encoding                                    |
+//! |-------------|---------------------------------------------|
+//! | `id`        | delta + zigzag varint (ids are mostly dense) |
+//! | `timestamp` | delta + zigzag varint                        |
+//! | `status`    | run-length                                   |
+//! | `currency`  | run-length                                   |
+//! | `customer`  | dictionary (distinct ids + `u32` codes)      |
+//! | `amount`    | raw `f64`                                    |
+//!
+//! Ids are delta-encoded rather than dictionary-encoded: every live id is distinct, so a
+//! dictionary would store each one again plus a code per row, while the deltas of mostly dense
+//! ids pack into a byte or two. The dictionary goes to customer ids, which repeat.
+//!
+//! Scans decode on the fly (`iter`), or work on the encoded form directly where the encoding
+//! allows it (`sum_by_status` walks status runs and sums contiguous amount slices).
+//! `decompress` rebuilds an ordinary kernel. Line items are not carried.
+//! `SegmentedOrderStore::with_compression` applies `compress` to each segment as it freezes.
+
+use crate::{Currency, CustomerId, Money, OrderId, OrderRow, OrderSoA, Status, Summation};
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::mem::size_of;
+
+/// `u64`s stored as zigzag-encoded deltas from the previous value, LEB128 varint packed.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DeltaColumn {
+    bytes: Vec<u8>,
+    len: usize,
+}
+
+impl DeltaColumn {
+    pub fn encode(values: impl IntoIterator<Item = u64>) -> Self {
+        let mut col = Self::default();
+        let mut prev = 0u64;
+        for v in values {
+            let delta = v.wrapping_sub(prev) as i64;
+            let mut zz = ((delta << 1) ^ (delta >> 63)) as u64;
+            loop {
+                let byte = (zz & 0x7f) as u8;
+                zz >>= 7;
+                if zz == 0 {
+                    col.bytes.push(byte);
+                    break;
+                }
+                col.bytes.push(byte | 0x80);
+            }
+            prev = v;
+            col.len += 1;
+        }
+        col
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn iter(&self) -> DeltaIter<'_> {
+        DeltaIter {
+            bytes: &self.bytes,
+            prev: 0,
+            left: self.len,
+        }
+    }
+
+    pub fn encoded_bytes(&self) -> usize {
+        self.bytes.len()
+    }
+}
+
+/// Decoding iterator over a `DeltaColumn`.
+#[derive(Clone, Debug)]
+pub struct DeltaIter<'a> {
+    /// Encoded values not yet decoded.
+    bytes: &'a [u8],
+    prev: u64,
+    left: usize,
+}
+
+impl Iterator for DeltaIter<'_> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        if self.left == 0 {
+            return None;
+        }
+        let (mut zz, mut shift, mut pos) = (0u64, 0, 0);
+        loop {
+            let byte = self.bytes[pos];
+            pos += 1;
+            zz |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                break;
+            }
+            shift += 7;
+        }
+        self.bytes = &self.bytes[pos..];
+        self.left -= 1;
+        let delta = ((zz >> 1) as i64) ^ -((zz & 1) as i64);
+        self.prev = self.prev.wrapping_add(delta as u64);
+        Some(self.prev)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.left, Some(self.left))
+    }
+}
+
+/// Run-length encoded values: `(value, run length)` pairs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RleColumn<T> {
+    runs: Vec<(T, u32)>,
+}
+
+impl<T> Default for RleColumn<T> {
+    fn default() -> Self {
+        Self { runs: Vec::new() }
+    }
+}
+
+impl<T: Copy + PartialEq> RleColumn<T> {
+    pub fn encode(values: impl IntoIterator<Item = T>) -> Self {
+        let mut runs: Vec<(T, u32)> = Vec::new();
+        for v in values {
+            match runs.last_mut() {
+                Some((last, n)) if *last == v && *n < u32::MAX => *n += 1,
+                _ => runs.push((v, 1)),
+            }
+        }
+        Self { runs }
+    }
+
+    pub fn len(&self) -> usize {
+        self.runs.iter().map(|&(_, n)| n as usize).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.runs.is_empty()
+    }
+
+    pub fn runs(&self) -> &[(T, u32)] {
+        &self.runs
+    }
+
+    pub fn iter(&self) -> RleIter<'_, T> {
+        RleIter {
+            runs: self.runs.iter(),
+            current: None,
+        }
+    }
+
+    pub fn encoded_bytes(&self) -> usize {
+        self.runs.len() * size_of::<(T, u32)>()
+    }
+}
+
+/// Expanding iterator over an `RleColumn`.
+#[derive(Clone, Debug)]
+pub struct RleIter<'a, T> {
+    runs: std::slice::Iter<'a, (T, u32)>,
+    /// The run being expanded and how much of it is left.
+    current: Option<(T, u32)>,
+}
+
+impl<T: Copy> Iterator for RleIter<'_, T> {
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<T> {
+        loop {
+            match &mut self.current {
+                Some((v, n)) if *n > 0 => {
+                    *n -= 1;
+                    return Some(*v);
+                }
+                _ => self.current = Some(*self.runs.next()?),
+            }
+        }
+    }
+}
+
+/// Distinct values plus one code per row.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DictColumn<T> {
+    values: Vec<T>,
+    codes: Vec<u32>,
+}
+
+impl<T> Default for DictColumn<T> {
+    fn default() -> Self {
+        Self {
+            values: Vec::new(),
+            codes: Vec::new(),
+        }
+    }
+}
+
+impl<T: Copy + Eq + Hash> DictColumn<T> {
+    pub fn encode(values: impl IntoIterator<Item = T>) -> Self {
+        let mut col = Self::default();
+        let mut lookup = HashMap::new();
+        for v in values {
+            let code = *lookup.entry(v).or_insert_with(|| {
+                col.values.push(v);
+                (col.values.len() - 1) as u32
+            });
+            col.codes.push(code);
+        }
+        col
+    }
+
+    pub fn len(&self) -> usize {
+        self.codes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.codes.is_empty()
+    }
+
+    /// The distinct values, in first-seen order.
+    pub fn values(&self) -> &[T] {
+        &self.values
+    }
+
+    pub fn iter(&self) -> DictIter<'_, T> {
+        DictIter {
+            values: &self.values,
+            codes: self.codes.iter(),
+        }
+    }
+
+    /// Value of row `i`.
+    pub fn get(&self, i: usize) -> Option<T> {
+        self.codes.get(i).map(|&c| self.values[c as usize])
+    }
+
+    pub fn encoded_bytes(&self) -> usize {
+        self.values.len() * size_of::<T>() + self.codes.len() * size_of::<u32>()
+    }
+}
+
+/// Decoding iterator over a `DictColumn`.
+#[derive(Clone, Debug)]
+pub struct DictIter<'a, T> {
+    values: &'a [T],
+    codes: std::slice::Iter<'a, u32>,
+}
+
+impl<T: Copy> Iterator for DictIter<'_, T> {
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<T> {
+        self.codes.next().map(|&c| self.values[c as usize])
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.codes.size_hint()
+    }
+}
+
+/// Immutable, compressed copy of a kernel's live rows.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CompressedSegment {
+    ids: DeltaColumn,
+    amounts: Vec<f64>,
+    statuses: RleColumn<Status>,
+    timestamps: DeltaColumn,
+    currencies: RleColumn<Currency>,
+    customers: DictColumn<CustomerId>,
+}
+
+impl CompressedSegment {
+    pub fn len(&self) -> usize {
+        self.amounts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.amounts.is_empty()
+    }
+
+    /// Bytes held by the encoded columns.
+    pub fn compressed_bytes(&self) -> usize {
+        self.ids.encoded_bytes()
+            + self.amounts.len() * size_of::<f64>()
+            + self.statuses.encoded_bytes()
+            + self.timestamps.encoded_bytes()
+            + self.currencies.encoded_bytes()
+            + self.customers.encoded_bytes()
+    }
+
+    /// Bytes the same rows occupy in the raw `OrderSoA` columns.
+    pub fn raw_bytes(&self) -> usize {
+        self.len()
+            * (size_of::<OrderId>()
+                + size_of::<f64>()
+                + size_of::<Status>()
+                + size_of::<u64>()
+                + size_of::<Currency>()
+                + size_of::<CustomerId>())
+    }
+
+    /// Decode every row, in the order they were compressed.
+    pub fn iter(&self) -> impl Iterator<Item = OrderRow> + '_ {
+        self.ids
+            .iter()
+            .zip(&self.amounts)
+            .zip(self.statuses.iter())
+            .zip(self.timestamps.iter())
+            .zip(self.currencies.iter())
+            .zip(self.customers.iter())
+            .map(
+                |(((((id, &amount), status), ts), currency), customer)| OrderRow {
+                    id: OrderId(id),
+                    amount: Money(amount),
+                    currency,
+                    status,
+                    ts,
+                    customer,
+                },
+            )
+    }
+
+    /// Total amount with `status`, summing whole runs of the status column without decoding it.
+    pub fn sum_by_status(&self, status: Status) -> Money {
+        let mut start = 0;
+        let mut matching = Vec::new();
+        for &(s, n) in self.statuses.runs() {
+            let end = start + n as usize;
+            if s == status {
+                matching.push(&self.amounts[start..end]);
+            }
+            start = end;
+        }
+        Money(Summation::default().sum(matching.into_iter().flatten().copied()))
+    }
+
+    /// Rebuild an uncompressed kernel (fresh indexes and handles).
+    pub fn decompress(&self) -> OrderSoA {
+        OrderSoA::from_columns(
+            self.ids.iter().map(OrderId).collect(),
+            self.amounts.clone(),
+            self.statuses.iter().collect(),
+            self.timestamps.iter().collect(),
+            self.currencies.iter().collect(),
+        )
+        .and_then(|soa| soa.with_customers(self.customers.iter().collect()))
+        .expect("segment columns have equal lengths")
+    }
+}
+
+impl OrderSoA {
+    /// Freeze the live rows into a compressed, read-only segment.
+    pub fn compress(&self) -> CompressedSegment {
+        let live = || (0..self.len()).filter(|&i| self.is_live(i));
+        CompressedSegment {
+            ids: DeltaColumn::encode(live().map(|i| self.ids[i].0)),
+            amounts: live().map(|i| self.amounts[i]).collect(),
+            statuses: RleColumn::encode(live().map(|i| self.statuses[i])),
+            timestamps: DeltaColumn::encode(live().map(|i| self.timestamps[i])),
+            currencies: RleColumn::encode(live().map(|i| self.currencies[i])),
+            customers: DictColumn::encode(live().map(|i| self.customers[i])),
+        }
+    }
+}
@@ -0,0 +1,153 @@
+//! Many-reader / single-writer store with lock-free snapshot reads.
+//!
+//! `OrderStore` relies on `Arc::make_mut`, so a write while any reader still holds the `Arc`
+//! clones the whole SoA. Here the state is split into a large immutable `base` segment and a
+//! small `tail` that takes appends. A write copies only the tail and publishes the new pair with
+//! one atomic pointer swap; once the tail reaches the merge threshold it is folded into a fresh
+//! base. Readers never block: `snapshot()` is an `ArcSwap` load and the snapshot stays valid (and
+//! unchanged) for as long as it is held.
+
+use crate::{Money, OrderId, OrderRow, OrderSoA, OrderView, Status, StoreError};
+use arc_swap::ArcSwap;
+use std::sync::{Arc, Mutex};
+
+/// Tail length at which a write folds the tail into the base.
+pub const DEFAULT_MERGE_THRESHOLD: usize = 1024;
+
+/// Immutable point-in-time view of a `ConcurrentOrderStore`.
+#[derive(Clone, Default)]
+pub struct OrderSnapshot {
+    base: Arc<OrderSoA>,
+    tail: Arc<OrderSoA>,
+}
+
+impl OrderSnapshot {
+    /// Number of live orders.
+    pub fn len(&self) -> usize {
+        self.base.live_len() + self.tail.live_len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The merged segment followed by the append segment, for running kernels per segment.
+    pub fn segments(&self) -> [&OrderSoA; 2] {
+        [&self.base, &self.tail]
+    }
+
+    /// Zero-copy views of every live order, oldest segment first.
+    pub fn iter(&self) -> impl Iterator<Item = OrderView<'_>> {
+        self.base.iter().chain(self.tail.iter())
+    }
+
+    /// Lookup by id; the newer segment wins.
+    pub fn find_by_id(&self, id: OrderId) -> Option<OrderView<'_>> {
+        self.tail
+            .find_by_id(id)
+            .or_else(|| self.base.find_by_id(id))
+    }
+
+    pub fn sum_by_status(&self, status: Status) -> Money {
+        self.base.sum_by_status(status) + self.tail.sum_by_status(status)
+    }
+}
+
+/// Store shared by reference across threads: any number of readers, writes serialized.
+pub struct ConcurrentOrderStore {
+    current: ArcSwap<OrderSnapshot>,
+    writer: Mutex<()>,
+    merge_threshold: usize,
+}
+
+impl Default for ConcurrentOrderStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConcurrentOrderStore {
+    pub fn new() -> Self {
+        Self {
+            current: ArcSwap::from_pointee(OrderSnapshot::default()),
+            writer: Mutex::new(()),
+            merge_threshold: DEFAULT_MERGE_THRESHOLD,
+        }
+    }
+
+    /// Builder flag: fold the tail into the base once it holds `n` rows. Smaller tails make
+    /// writes cheaper; larger ones make merges rarer.
+    pub fn with_merge_threshold(mut self, n: usize) -> Self {
+        self.merge_threshold = n.max(1);
+        self
+    }
+
+    /// Current state; never blocks, and later writes do not affect it.
+    pub fn snapshot(&self) -> Arc<OrderSnapshot> {
+        self.current.load_full()
+    }
+
+    pub fn add(&self, id: OrderId, amount: Money, status: Status, ts: u64) {
+        self.write(|_, tail| {
+            tail.push(id, amount, status, ts);
+            Ok(())
+        })
+        .expect("plain appends cannot fail");
+    }
+
+    /// Validated bulk insert, published as a single swap. All-or-nothing, and ids are checked
+    /// against both segments.
+    pub fn add_batch(&self, rows: impl IntoIterator<Item = OrderRow>) -> Result<(), StoreError> {
+        let rows: Vec<OrderRow> = rows.into_iter().collect();
+        self.write(|base, tail| {
+            if let Some(r) = rows.iter().find(|r| base.contains_id(r.id)) {
+                return Err(StoreError::DuplicateId(r.id));
+            }
+            tail.extend_from_rows(rows).map(|_| ())
+        })
+    }
+
+    /// Fold the tail into the base now.
+    pub fn merge(&self) {
+        let _guard = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        let cur = self.current.load_full();
+        self.current.store(Arc::new(merged(&cur)));
+    }
+
+    /// Run `f` on a private copy of the tail (with the base for reference) and publish the result.
+    fn write(
+        &self,
+        f: impl FnOnce(&OrderSoA, &mut OrderSoA) -> Result<(), StoreError>,
+    ) -> Result<(), StoreError> {
+        let _guard = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        let cur = self.current.load_full();
+        let mut tail = OrderSoA::clone(&cur.tail);
+        f(&cur.base, &mut tail)?;
+        let next = OrderSnapshot {
+            base: cur.base.clone(),
+            tail: Arc::new(tail),
+        };
+        let next = if next.tail.len() >= self.merge_threshold {
+            merged(&next)
+        } else {
+            next
+        };
+        self.current.store(Arc::new(next));
+        Ok(())
+    }
+}
+
+/// `snap` with its tail appended to a copy of its base.
+fn merged(snap: &OrderSnapshot) -> OrderSnapshot {
+    if snap.tail.is_empty() {
+        return snap.clone();
+    }
+    let mut base = OrderSoA::clone(&snap.base);
+    for v in snap.tail.iter() {
+        base.push_row(OrderRow::from(v));
+    }
+    OrderSnapshot {
+        base: Arc::new(base),
+        tail: Arc::default(),
+    }
+}
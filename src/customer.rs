@@ -0,0 +1,154 @@
+//! Customer dimension and the order ⋈ customer join.
+//!
+//! Customers live in their own SoA, keyed by `CustomerId`; orders reference them through their
+//! `customers` column. `join_orders_customers` is a hash join: the customer id index is the
+//! build side, the order column is probed row by row, and every match is a pair of zero-copy
+//! views into the two stores.
+
+use crate::{OrderSoA, OrderView, StrColumn};
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct CustomerId(pub u64);
+
+/// Column store of customers.
+#[derive(Default, Clone)]
+pub struct CustomerSoA {
+    ids: Vec<CustomerId>,
+    names: StrColumn, // one arena for all names
+    regions: Vec<u16>,
+    id_index: HashMap<CustomerId, usize>, // primary key -> latest row holding it
+}
+
+impl fmt::Debug for CustomerSoA {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CustomerSoA")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl CustomerSoA {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(cap: usize) -> Self {
+        Self {
+            ids: Vec::with_capacity(cap),
+            names: StrColumn::new(),
+            regions: Vec::with_capacity(cap),
+            id_index: HashMap::with_capacity(cap),
+        }
+    }
+
+    /// Append a customer; returns its row. A repeated id shadows the earlier row in lookups and
+    /// joins, as in `OrderSoA`.
+    pub fn push(&mut self, id: CustomerId, name: &str, region: u16) -> usize {
+        self.ids.push(id);
+        self.names.push(name);
+        self.regions.push(region);
+        let idx = self.ids.len() - 1;
+        self.id_index.insert(id, idx);
+        idx
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// O(1) lookup through the id index.
+    pub fn find_by_id(&self, id: CustomerId) -> Option<CustomerView<'_>> {
+        self.id_index.get(&id).map(|&idx| self.view_at(idx))
+    }
+
+    /// # Panics
+    /// If `idx` is out of bounds.
+    pub fn view_at(&self, idx: usize) -> CustomerView<'_> {
+        assert!(idx < self.len(), "customer row {idx} out of bounds");
+        CustomerView { soa: self, idx }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = CustomerView<'_>> {
+        (0..self.len()).map(|idx| CustomerView { soa: self, idx })
+    }
+}
+
+/// Zero-copy view of one customer row.
+#[derive(Copy, Clone)]
+pub struct CustomerView<'a> {
+    soa: &'a CustomerSoA,
+    idx: usize,
+}
+
+impl fmt::Debug for CustomerView<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CustomerView")
+            .field("id", &self.id())
+            .field("name", &self.name())
+            .field("region", &self.region())
+            .finish()
+    }
+}
+
+impl<'a> CustomerView<'a> {
+    #[inline]
+    pub fn id(&self) -> CustomerId {
+        self.soa.ids[self.idx]
+    }
+    #[inline]
+    pub fn name(&self) -> &'a str {
+        self.soa.names.get(self.idx)
+    }
+    #[inline]
+    pub fn region(&self) -> u16 {
+        self.soa.regions[self.idx]
+    }
+}
+
+/// One joined row: an order and the customer it references.
+#[derive(Copy, Clone, Debug)]
+pub struct OrderCustomerView<'a> {
+    order: OrderView<'a>,
+    customer: CustomerView<'a>,
+}
+
+impl<'a> OrderCustomerView<'a> {
+    pub fn order(&self) -> OrderView<'a> {
+        self.order
+    }
+
+    pub fn customer(&self) -> CustomerView<'a> {
+        self.customer
+    }
+}
+
+/// Inner join of live orders with their customers, in order-row order. Orders whose customer is
+/// unknown are dropped. One pass over the order customer column probing the customer id index;
+/// nothing is copied or allocated per row.
+pub fn join_orders_customers<'a>(
+    orders: &'a OrderSoA,
+    customers: &'a CustomerSoA,
+) -> impl Iterator<Item = OrderCustomerView<'a>> + 'a {
+    orders
+        .customers
+        .iter()
+        .enumerate()
+        .filter(move |&(i, _)| orders.is_live(i))
+        .filter_map(move |(i, c)| {
+            customers.id_index.get(c).map(|&idx| OrderCustomerView {
+                order: orders.view_at(i),
+                customer: CustomerView {
+                    soa: customers,
+                    idx,
+                },
+            })
+        })
+}
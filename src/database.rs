@@ -0,0 +1,139 @@
+//! A small in-memory domain database: several repositories behind one façade.
+//!
+//! `Database` hosts the order store together with the customer and payment tables, each an SoA
+//! of its own. Every table sits behind an `Arc`, so `snapshot` is a handful of reference-count
+//! bumps and yields one consistent point-in-time view across all of them, and `begin` opens a
+//! `DbTransaction` that spans every table: writes go to a copy-on-write working copy and
+//! `commit` publishes them together, so an order and the payment that settles it appear (or
+//! vanish on rollback) as one unit. Order change subscribers hear about the writes on commit,
+//! as with `OrderStore::begin`.
+
+use crate::{CustomerSoA, OrderSoA, OrderStore, PaymentSoA};
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
+
+/// Orders, customers and payments; see the module docs.
+#[derive(Clone, Default)]
+pub struct Database {
+    orders: OrderStore,
+    customers: Arc<CustomerSoA>,
+    payments: Arc<PaymentSoA>,
+}
+
+impl Database {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Host an already configured order store (validators, views, outbox, ...).
+    pub fn with_orders(mut self, orders: OrderStore) -> Self {
+        self.orders = orders;
+        self
+    }
+
+    pub fn orders(&self) -> &OrderStore {
+        &self.orders
+    }
+
+    pub fn orders_mut(&mut self) -> &mut OrderStore {
+        &mut self.orders
+    }
+
+    pub fn customers(&self) -> &CustomerSoA {
+        &self.customers
+    }
+
+    /// Copy-on-write: clones the table if a snapshot still shares it.
+    pub fn customers_mut(&mut self) -> &mut CustomerSoA {
+        Arc::make_mut(&mut self.customers)
+    }
+
+    pub fn payments(&self) -> &PaymentSoA {
+        &self.payments
+    }
+
+    /// Copy-on-write: clones the table if a snapshot still shares it.
+    pub fn payments_mut(&mut self) -> &mut PaymentSoA {
+        Arc::make_mut(&mut self.payments)
+    }
+
+    /// Every table as of now; later writes to the database do not show through.
+    pub fn snapshot(&self) -> DatabaseSnapshot {
+        DatabaseSnapshot {
+            orders: Arc::clone(&self.orders.inner),
+            customers: Arc::clone(&self.customers),
+            payments: Arc::clone(&self.payments),
+        }
+    }
+
+    /// Start a transaction over every table. The database is borrowed until it commits or rolls
+    /// back.
+    pub fn begin(&mut self) -> DbTransaction<'_> {
+        let working = Database {
+            orders: self.orders.fork(),
+            customers: Arc::clone(&self.customers),
+            payments: Arc::clone(&self.payments),
+        };
+        DbTransaction { db: self, working }
+    }
+}
+
+/// Point-in-time view of every table of a `Database`.
+#[derive(Clone, Debug, Default)]
+pub struct DatabaseSnapshot {
+    orders: Arc<OrderSoA>,
+    customers: Arc<CustomerSoA>,
+    payments: Arc<PaymentSoA>,
+}
+
+impl DatabaseSnapshot {
+    pub fn orders(&self) -> &OrderSoA {
+        &self.orders
+    }
+
+    pub fn customers(&self) -> &CustomerSoA {
+        &self.customers
+    }
+
+    pub fn payments(&self) -> &PaymentSoA {
+        &self.payments
+    }
+}
+
+/// Pending changes to a `Database`; dereferences to the working copy.
+#[must_use = "a transaction is rolled back unless `commit` is called"]
+pub struct DbTransaction<'a> {
+    db: &'a mut Database,
+    working: Database,
+}
+
+impl DbTransaction<'_> {
+    /// Publish the changes to every table at once.
+    pub fn commit(self) {
+        let Database {
+            orders,
+            customers,
+            payments,
+        } = self.working;
+        self.db.orders.join(orders);
+        self.db.customers = customers;
+        self.db.payments = payments;
+    }
+
+    /// Discard every change; the same as dropping the transaction.
+    pub fn rollback(self) {}
+}
+
+impl Deref for DbTransaction<'_> {
+    type Target = Database;
+
+    fn deref(&self) -> &Database {
+        &self.working
+    }
+}
+
+impl DerefMut for DbTransaction<'_> {
+    fn deref_mut(&mut self) -> &mut Database {
+        &mut self.working
+    }
+}
@@ -0,0 +1,54 @@
+//! Deduplication by order id.
+//!
+//! A kernel fed from an at-least-once stream (a retried producer, a replayed partition) can hold
+//! several rows for one id, each a delivery of the same order at some point in its life.
+//! `dedup_by_id` keeps one row per id, chosen by timestamp, and compacts the rest away.
+
+use crate::{OrderId, OrderSoA};
+use std::collections::HashMap;
+
+/// Which of an id's rows `OrderSoA::dedup_by_id` keeps.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum KeepPolicy {
+    /// The row with the latest timestamp; of equal timestamps, the last row.
+    #[default]
+    Latest,
+    /// The row with the earliest timestamp; of equal timestamps, the first row.
+    Earliest,
+}
+
+impl OrderSoA {
+    /// Remove every live row whose id another live row holds, keeping the one `keep` picks;
+    /// returns how many rows were removed. One pass over the id and timestamp columns picks the
+    /// survivors; if there were duplicates the kernel is then compacted as by `retain`, so
+    /// tombstoned rows are reclaimed too and handles to rows that moved or were dropped go stale.
+    pub fn dedup_by_id(&mut self, keep: KeepPolicy) -> usize {
+        let mut winners: HashMap<OrderId, usize> = HashMap::with_capacity(self.id_index.len());
+        let mut duplicates = 0;
+        for i in 0..self.len() {
+            if !self.is_live(i) {
+                continue;
+            }
+            let ts = self.timestamps[i];
+            match winners.get_mut(&self.ids[i]) {
+                None => {
+                    winners.insert(self.ids[i], i);
+                }
+                Some(w) => {
+                    duplicates += 1;
+                    let replace = match keep {
+                        KeepPolicy::Latest => ts >= self.timestamps[*w],
+                        KeepPolicy::Earliest => ts < self.timestamps[*w],
+                    };
+                    if replace {
+                        *w = i;
+                    }
+                }
+            }
+        }
+        if duplicates > 0 {
+            self.compact_where(|soa, i| winners[&soa.ids[i]] == i);
+        }
+        duplicates
+    }
+}
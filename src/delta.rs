@@ -0,0 +1,145 @@
+//! Deltas between two states of a store, for keeping replicas in sync.
+//!
+//! A primary keeps the `read_only` snapshot it last shipped; `diff(&shipped)` then lists the
+//! orders added, updated and deleted since, matched by id, and the replica replays that with
+//! `apply_delta` instead of loading a full snapshot. Like snapshots, deltas carry the order
+//! columns only: line items, audit stamps and handles are local to each store.
+
+use crate::{
+    query_cache, ChangeKind, OrderId, OrderRow, OrderSoA, OrderStore, RowChange, StoreError,
+};
+use std::collections::HashSet;
+
+/// What changed between two states of a store; see `OrderStore::diff`.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StoreDelta {
+    /// Live now, absent (or deleted) before; in row order.
+    pub added: Vec<OrderRow>,
+    /// Live in both, with a different value now; the new value, in row order.
+    pub updated: Vec<OrderRow>,
+    /// Live before, absent (or deleted) now.
+    pub deleted: Vec<OrderId>,
+}
+
+impl StoreDelta {
+    /// Orders the delta touches.
+    pub fn len(&self) -> usize {
+        self.added.len() + self.updated.len() + self.deleted.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl OrderSoA {
+    /// The changes that turn `older` into `self`; see `StoreDelta`.
+    pub fn diff(&self, older: &OrderSoA) -> StoreDelta {
+        let mut delta = StoreDelta::default();
+        for (&id, &idx) in self.id_index.iter() {
+            let row = OrderRow::from(self.view_at(idx));
+            match older.find_by_id(id) {
+                None => delta.added.push(row),
+                Some(before) if OrderRow::from(before) != row => delta.updated.push(row),
+                Some(_) => {}
+            }
+        }
+        let order = |r: &OrderRow| self.id_index[&r.id];
+        delta.added.sort_by_key(order);
+        delta.updated.sort_by_key(order);
+        delta.deleted = (0..older.len())
+            .filter(|&i| older.is_live(i) && !self.contains_id(older.ids[i]))
+            .map(|i| older.ids[i])
+            .collect();
+        delta
+    }
+}
+
+impl OrderStore {
+    /// The changes since `older`, e.g. a `read_only` snapshot of this store taken earlier.
+    pub fn diff(&self, older: &OrderSoA) -> StoreDelta {
+        self.inner.diff(older)
+    }
+
+    /// Replay `delta`: delete, then overwrite, then append. Every id and row is checked first
+    /// (deleted and updated orders must be stored, added ones must not be, validators must accept
+    /// every row), so on error nothing is applied. Subscribers and views see the individual
+    /// changes.
+    pub fn apply_delta(&mut self, delta: &StoreDelta) -> Result<(), StoreError> {
+        self.check_delta_ids(delta)?;
+        for &row in &delta.updated {
+            let before = OrderRow::from(self.inner.view_at(self.index_of(row.id)?));
+            self.validators.check(row, Some(before))?;
+        }
+        for &row in &delta.added {
+            if row.amount.0.is_nan() || row.amount.0 < 0.0 {
+                return Err(StoreError::InvalidAmount(row.id));
+            }
+            self.validators.check(row, None)?;
+        }
+        self.write_delta(delta)
+    }
+
+    /// Replay `delta` like `apply_delta`, checking only that its ids fit: the rows are another
+    /// store's state, already accepted there, e.g. a replication leader's.
+    pub(crate) fn replay_delta(&mut self, delta: &StoreDelta) -> Result<(), StoreError> {
+        self.check_delta_ids(delta)?;
+        self.write_delta(delta)
+    }
+
+    /// Deleted and updated ids must be stored, added ones must not be (unless deleted first).
+    fn check_delta_ids(&self, delta: &StoreDelta) -> Result<(), StoreError> {
+        let deleted: HashSet<OrderId> = delta.deleted.iter().copied().collect();
+        for &id in &delta.deleted {
+            self.index_of(id)?;
+        }
+        for row in &delta.updated {
+            if deleted.contains(&row.id) {
+                return Err(StoreError::UnknownId(row.id));
+            }
+            self.index_of(row.id)?;
+        }
+        let mut seen = HashSet::with_capacity(delta.added.len());
+        for row in &delta.added {
+            if (self.inner.contains_id(row.id) && !deleted.contains(&row.id))
+                || !seen.insert(row.id)
+            {
+                return Err(StoreError::DuplicateId(row.id));
+            }
+        }
+        Ok(())
+    }
+
+    /// The writes of a checked delta.
+    fn write_delta(&mut self, delta: &StoreDelta) -> Result<(), StoreError> {
+        for &id in &delta.deleted {
+            let h = self.inner.handle_at(self.index_of(id)?);
+            self.remove(h)?;
+        }
+        for &row in &delta.updated {
+            let idx = self.index_of(row.id)?;
+            let before = OrderRow::from(self.inner.view_at(idx));
+            let owned = query_cache::write(&mut self.inner, &mut self.epoch, self.context);
+            owned.write_row(idx, row);
+            let handle = owned.handle_at(idx);
+            self.changes.emit_diff(handle, &before, &row);
+            self.track(RowChange {
+                handle,
+                before: Some(before),
+                after: Some(row),
+            });
+        }
+        for &row in &delta.added {
+            let owned = query_cache::write(&mut self.inner, &mut self.epoch, self.context);
+            let handle = owned.push_row(row);
+            self.changes.emit(handle, ChangeKind::Inserted);
+            self.track(RowChange {
+                handle,
+                before: None,
+                after: Some(row),
+            });
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,364 @@
+//! Runtime-extensible columns.
+//!
+//! `DynSoA` pairs the fixed `OrderSoA` kernel with user-registered columns (`discount: f64`,
+//! `region: u16`, ...) kept row-aligned with it. Extension columns are ordinary `Vec<T>`s behind a
+//! type-erased `DynColumn`, so they keep the SoA layout; the type is checked once per access
+//! against the type the column was registered with.
+
+use crate::categorical::CategoricalColumn;
+use crate::option_column::OptionColumn;
+use crate::str_column::StrColumn;
+use crate::{Aggregate, Money, OrderId, OrderMut, OrderSoA, OrderView, Status};
+use std::any::{type_name, Any};
+use std::fmt;
+
+/// Type-erased column storage. Implemented for `Vec<T>`; the row-shape operations are all
+/// `DynSoA` needs to keep extension columns aligned with the kernel.
+pub trait DynColumn: Send + Sync {
+    fn len(&self) -> usize;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Append one default value.
+    fn push_default(&mut self);
+    /// Keep the rows whose `keep` flag is set, preserving order.
+    fn retain_mask(&mut self, keep: &[bool]);
+    /// Name of the element type, for error messages.
+    fn type_name(&self) -> &'static str;
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Default + Send + Sync + 'static> DynColumn for Vec<T> {
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+    fn push_default(&mut self) {
+        self.push(T::default());
+    }
+    fn retain_mask(&mut self, keep: &[bool]) {
+        let mut i = 0;
+        self.retain(|_| {
+            i += 1;
+            keep[i - 1]
+        });
+    }
+    fn type_name(&self) -> &'static str {
+        type_name::<T>()
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Failures looking up or registering an extension column.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ColumnError {
+    /// A column with this name is already registered.
+    Duplicate(String),
+    /// No column with this name.
+    Missing(String),
+    /// The column exists but holds a different element type.
+    TypeMismatch { name: String, stored: &'static str },
+}
+
+impl fmt::Display for ColumnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColumnError::Duplicate(name) => write!(f, "column `{name}` already exists"),
+            ColumnError::Missing(name) => write!(f, "no column named `{name}`"),
+            ColumnError::TypeMismatch { name, stored } => {
+                write!(f, "column `{name}` holds {stored}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ColumnError {}
+
+/// Named, type-erased columns aligned row-for-row with an `OrderSoA`.
+#[derive(Default)]
+struct Extensions {
+    columns: Vec<(String, Box<dyn DynColumn>)>,
+}
+
+impl Extensions {
+    fn find(&self, name: &str) -> Result<&dyn DynColumn, ColumnError> {
+        self.columns
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, c)| c.as_ref())
+            .ok_or_else(|| ColumnError::Missing(name.to_owned()))
+    }
+
+    fn find_mut(&mut self, name: &str) -> Result<&mut (dyn DynColumn + 'static), ColumnError> {
+        self.columns
+            .iter_mut()
+            .find(|(n, _)| n == name)
+            .map(|(_, c)| c.as_mut())
+            .ok_or_else(|| ColumnError::Missing(name.to_owned()))
+    }
+
+    /// The column `name` as its concrete storage type `C`.
+    fn downcast<C: 'static>(&self, name: &str) -> Result<&C, ColumnError> {
+        let col = self.find(name)?;
+        col.as_any()
+            .downcast_ref::<C>()
+            .ok_or_else(|| ColumnError::TypeMismatch {
+                name: name.to_owned(),
+                stored: col.type_name(),
+            })
+    }
+
+    fn downcast_mut<C: 'static>(&mut self, name: &str) -> Result<&mut C, ColumnError> {
+        let col = self.find_mut(name)?;
+        let stored = col.type_name();
+        col.as_any_mut()
+            .downcast_mut::<C>()
+            .ok_or_else(|| ColumnError::TypeMismatch {
+                name: name.to_owned(),
+                stored,
+            })
+    }
+}
+
+/// `OrderSoA` plus columns registered at runtime.
+///
+/// Kernels run on `orders()` unchanged; rows are added and dropped only through `DynSoA` so the
+/// extension columns can never fall out of step with the kernel.
+#[derive(Default)]
+pub struct DynSoA {
+    orders: OrderSoA,
+    ext: Extensions,
+}
+
+impl DynSoA {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a column; existing rows get `T::default()`.
+    pub fn add_column<T: Default + Send + Sync + 'static>(
+        &mut self,
+        name: &str,
+    ) -> Result<(), ColumnError> {
+        if self.ext.find(name).is_ok() {
+            return Err(ColumnError::Duplicate(name.to_owned()));
+        }
+        let mut col: Vec<T> = Vec::with_capacity(self.orders.len());
+        col.resize_with(self.orders.len(), T::default);
+        self.ext.columns.push((name.to_owned(), Box::new(col)));
+        Ok(())
+    }
+
+    /// Register a nullable column; existing rows are null.
+    pub fn add_optional_column<T: Copy + Default + Send + Sync + 'static>(
+        &mut self,
+        name: &str,
+    ) -> Result<(), ColumnError> {
+        if self.ext.find(name).is_ok() {
+            return Err(ColumnError::Duplicate(name.to_owned()));
+        }
+        let col: OptionColumn<T> = std::iter::repeat_n(None, self.orders.len()).collect();
+        self.ext.columns.push((name.to_owned(), Box::new(col)));
+        Ok(())
+    }
+
+    /// Register a string column stored in a byte arena (see `StrColumn`); existing rows are `""`.
+    pub fn add_str_column(&mut self, name: &str, interned: bool) -> Result<(), ColumnError> {
+        if self.ext.find(name).is_ok() {
+            return Err(ColumnError::Duplicate(name.to_owned()));
+        }
+        let mut col = if interned {
+            StrColumn::interned()
+        } else {
+            StrColumn::new()
+        };
+        (0..self.orders.len()).for_each(|_| col.push_default());
+        self.ext.columns.push((name.to_owned(), Box::new(col)));
+        Ok(())
+    }
+
+    /// Register a dictionary-encoded column whose categories are defined as values arrive;
+    /// existing rows get the empty category.
+    pub fn add_categorical_column(&mut self, name: &str) -> Result<(), ColumnError> {
+        if self.ext.find(name).is_ok() {
+            return Err(ColumnError::Duplicate(name.to_owned()));
+        }
+        let mut col = CategoricalColumn::new();
+        (0..self.orders.len()).for_each(|_| col.push_default());
+        self.ext.columns.push((name.to_owned(), Box::new(col)));
+        Ok(())
+    }
+
+    /// Registered column names, in registration order.
+    pub fn column_names(&self) -> impl Iterator<Item = &str> {
+        self.ext.columns.iter().map(|(n, _)| n.as_str())
+    }
+
+    /// A whole extension column, for kernels over it.
+    pub fn column<T: 'static>(&self, name: &str) -> Result<&[T], ColumnError> {
+        self.ext.downcast::<Vec<T>>(name).map(Vec::as_slice)
+    }
+
+    /// A whole string column.
+    pub fn str_column(&self, name: &str) -> Result<&StrColumn, ColumnError> {
+        self.ext.downcast(name)
+    }
+
+    /// A whole categorical column, with its dictionary.
+    pub fn categorical_column(&self, name: &str) -> Result<&CategoricalColumn, ColumnError> {
+        self.ext.downcast(name)
+    }
+
+    /// Amount aggregates of live orders per category of column `name`, keyed by category name;
+    /// categories with no live rows are omitted. Groups on the `u16` codes, then names them.
+    pub fn group_by_category(&self, name: &str) -> Result<Vec<(&str, Aggregate)>, ColumnError> {
+        let col = self.categorical_column(name)?;
+        let groups = col.group_by(&self.orders.amounts, |i| self.orders.is_live(i));
+        Ok(col
+            .dict()
+            .names()
+            .zip(groups)
+            .filter(|(_, g)| g.count > 0)
+            .collect())
+    }
+
+    /// A whole nullable column, for its null-skipping kernels.
+    pub fn optional_column<T: 'static>(&self, name: &str) -> Result<&OptionColumn<T>, ColumnError> {
+        self.ext.downcast(name)
+    }
+
+    /// The fixed columns and their kernels.
+    pub fn orders(&self) -> &OrderSoA {
+        &self.orders
+    }
+
+    pub fn len(&self) -> usize {
+        self.orders.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.orders.is_empty()
+    }
+
+    /// Append an order; extension columns get their defaults. Returns the row index.
+    pub fn push(&mut self, id: OrderId, amount: Money, status: Status, ts: u64) -> usize {
+        let h = self.orders.push(id, amount, status, ts);
+        for (_, col) in &mut self.ext.columns {
+            col.push_default();
+        }
+        h.index
+    }
+
+    pub fn view(&self, idx: usize) -> DynView<'_> {
+        DynView {
+            order: self.orders.view_at(idx),
+            ext: &self.ext,
+            idx,
+        }
+    }
+
+    pub fn view_mut(&mut self, idx: usize) -> DynMut<'_> {
+        DynMut {
+            order: self.orders.view_mut_at(idx),
+            ext: &mut self.ext,
+            idx,
+        }
+    }
+
+    /// Keep rows matching `f`, compacting the kernel and every extension column alike.
+    pub fn retain<F: Fn(DynView<'_>) -> bool>(&mut self, f: F) {
+        let keep: Vec<bool> = (0..self.len())
+            .map(|i| self.orders.is_live(i) && f(self.view(i)))
+            .collect();
+        self.orders.compact_where(|_, i| keep[i]);
+        for (_, col) in &mut self.ext.columns {
+            col.retain_mask(&keep);
+        }
+    }
+}
+
+/// Read view of one row: the fixed fields via `order()`, extension fields by name.
+pub struct DynView<'a> {
+    order: OrderView<'a>,
+    ext: &'a Extensions,
+    idx: usize,
+}
+
+impl<'a> DynView<'a> {
+    pub fn order(&self) -> OrderView<'a> {
+        self.order
+    }
+
+    pub fn get<T: 'static>(&self, name: &str) -> Result<&'a T, ColumnError> {
+        Ok(&self.ext.downcast::<Vec<T>>(name)?[self.idx])
+    }
+
+    /// Field of a string column, borrowed from its arena.
+    pub fn get_str(&self, name: &str) -> Result<&'a str, ColumnError> {
+        Ok(self.ext.downcast::<StrColumn>(name)?.get(self.idx))
+    }
+
+    /// Category name of a categorical column.
+    pub fn get_category(&self, name: &str) -> Result<&'a str, ColumnError> {
+        Ok(self.ext.downcast::<CategoricalColumn>(name)?.name(self.idx))
+    }
+
+    /// Field of a nullable column.
+    pub fn get_opt<T: Copy + Default + 'static>(
+        &self,
+        name: &str,
+    ) -> Result<Option<T>, ColumnError> {
+        Ok(self.ext.downcast::<OptionColumn<T>>(name)?.get(self.idx))
+    }
+}
+
+/// Mutable view of one row.
+pub struct DynMut<'a> {
+    order: OrderMut<'a>,
+    ext: &'a mut Extensions,
+    idx: usize,
+}
+
+impl<'a> DynMut<'a> {
+    pub fn order(&mut self) -> &mut OrderMut<'a> {
+        &mut self.order
+    }
+
+    pub fn set<T: 'static>(&mut self, name: &str, value: T) -> Result<(), ColumnError> {
+        self.ext.downcast_mut::<Vec<T>>(name)?[self.idx] = value;
+        Ok(())
+    }
+
+    /// Set a field of a string column.
+    pub fn set_str(&mut self, name: &str, value: &str) -> Result<(), ColumnError> {
+        self.ext
+            .downcast_mut::<StrColumn>(name)?
+            .set(self.idx, value);
+        Ok(())
+    }
+
+    /// Set a categorical field by name, defining the category if new.
+    pub fn set_category(&mut self, name: &str, value: &str) -> Result<(), ColumnError> {
+        self.ext
+            .downcast_mut::<CategoricalColumn>(name)?
+            .set(self.idx, value);
+        Ok(())
+    }
+
+    /// Set or clear a field of a nullable column.
+    pub fn set_opt<T: Copy + Default + 'static>(
+        &mut self,
+        name: &str,
+        value: Option<T>,
+    ) -> Result<(), ColumnError> {
+        self.ext
+            .downcast_mut::<OptionColumn<T>>(name)?
+            .set(self.idx, value);
+        Ok(())
+    }
+}
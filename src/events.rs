@@ -0,0 +1,122 @@
+//! Event sourcing: commands produce `OrderEvent`s, the SoA is their projection.
+//!
+//! An `EventLog` is append-only; `OrderStore::replay` folds it into a fresh store, so any state
+//! the store has held can be rebuilt from the log alone.
+
+use crate::{Money, OrderId, OrderRow, OrderStore, Status, StoreError};
+
+/// A fact about an order, in the order it happened.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OrderEvent {
+    Created(OrderRow),
+    AmountChanged { id: OrderId, amount: Money },
+    StatusChanged { id: OrderId, status: Status },
+    Cancelled { id: OrderId },
+    Refunded { id: OrderId, amount: Money },
+    PartiallyFulfilled { id: OrderId, amount: Money },
+}
+
+impl OrderEvent {
+    /// The order the event is about.
+    pub fn order_id(&self) -> OrderId {
+        match *self {
+            OrderEvent::Created(row) => row.id,
+            OrderEvent::AmountChanged { id, .. }
+            | OrderEvent::StatusChanged { id, .. }
+            | OrderEvent::Cancelled { id }
+            | OrderEvent::Refunded { id, .. }
+            | OrderEvent::PartiallyFulfilled { id, .. } => id,
+        }
+    }
+}
+
+/// Append-only sequence of events. There is no way to edit or drop a recorded event.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct EventLog {
+    events: Vec<OrderEvent>,
+}
+
+impl EventLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record an event; returns its sequence number.
+    pub fn append(&mut self, event: OrderEvent) -> usize {
+        self.events.push(event);
+        self.events.len() - 1
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Every recorded event, oldest first.
+    pub fn events(&self) -> &[OrderEvent] {
+        &self.events
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &OrderEvent> {
+        self.events.iter()
+    }
+}
+
+impl OrderStore {
+    /// Project one event onto the store.
+    ///
+    /// `Created` is validated like `add_batch`; the other events must name a stored order.
+    /// A rejected event leaves the store unchanged.
+    pub fn apply(&mut self, event: &OrderEvent) -> Result<(), StoreError> {
+        match *event {
+            OrderEvent::Created(row) => {
+                self.add_batch([row])?;
+            }
+            OrderEvent::AmountChanged { id, amount } => {
+                let idx = self.index_of(id)?;
+                if amount.0.is_nan() || amount.0 < 0.0 {
+                    return Err(StoreError::InvalidAmount(id));
+                }
+                self.inner.view_at(idx).check_reprice(amount)?;
+                if self.inner.amounts[idx] != amount.0 {
+                    self.update_with(id, |mut o| o.set_amount(amount))?;
+                }
+            }
+            OrderEvent::StatusChanged { id, status } => {
+                self.update_with(id, |mut o| o.set_status(status))?;
+            }
+            OrderEvent::Cancelled { id } => {
+                self.update_with(id, |mut o| o.set_status(Status::Cancelled))?;
+            }
+            OrderEvent::Refunded { id, amount } => {
+                // Checked on a view first, so a rejected refund does not bump the version.
+                self.find_by_id(id)
+                    .ok_or(StoreError::UnknownId(id))?
+                    .check_refund(amount)?;
+                self.update_with(id, |mut o| o.record_refund(amount))??;
+            }
+            OrderEvent::PartiallyFulfilled { id, amount } => {
+                self.find_by_id(id)
+                    .ok_or(StoreError::UnknownId(id))?
+                    .check_fulfillment(amount)?;
+                self.update_with(id, |mut o| o.record_fulfillment(amount))??;
+            }
+        }
+        Ok(())
+    }
+
+    /// Rebuild a store by applying every event in `log`, stopping at the first rejected one.
+    pub fn replay(log: &EventLog) -> Result<OrderStore, StoreError> {
+        let mut store = OrderStore::new();
+        for event in log.iter() {
+            store.apply(event)?;
+        }
+        Ok(store)
+    }
+}
@@ -0,0 +1,117 @@
+//! Exact amounts: a fixed-point amount column alongside the float one.
+//!
+//! The kernel's amount column is `f64`, which suits the hot-path kernels (SIMD sums, filters,
+//! zero-copy Arrow / C / NumPy export) but not a ledger: every stored value is the nearest float
+//! to the amount entered, and sums round at every add. `with_exact_amounts` adds a second column
+//! holding each amount as an `i64` count of `1 / EXACT_SCALE` major units (ten-thousandths, so
+//! every currency's minor unit is a whole number of them). The `Money` façade is unchanged:
+//! `amount()` still reads the float, while `money()`, `sum_minor_by_status`,
+//! `sum_by_status_per_currency` and `sum_by_status_exact` read the fixed-point column and add in
+//! integers, so their totals are exact.
+//!
+//! Amounts written as `TypedMoney` (`push_money`, `set_money`) are stored without passing
+//! through a float; amounts written as `Money` are rounded once, to the nearest ten-thousandth.
+//! The float column may still be written directly (`columns_mut`); a fixed-point value is only
+//! trusted while its float agrees with it to within the float's precision, and is otherwise
+//! re-derived from the float. Values are limited to about ±9.2e14 major units.
+//!
+//! Like the audit columns, the fixed-point column lives in memory only: snapshots and exports
+//! carry the float column, and a kernel loaded from them derives the fixed-point values again.
+
+use crate::{query_cache, Currency, Money, OrderSoA, OrderStore, Status, TypedMoney};
+
+/// Fixed-point units per major unit in the exact amount column.
+pub const EXACT_SCALE: i64 = 10_000;
+
+/// The nearest fixed-point value to a float amount.
+#[inline]
+pub(crate) fn fixed_of_money(amount: f64) -> i64 {
+    (amount * EXACT_SCALE as f64).round() as i64
+}
+
+/// A currency-tagged amount in fixed point; no rounding is involved.
+#[inline]
+pub(crate) fn fixed_of_typed(m: TypedMoney) -> i64 {
+    m.minor_units
+        .saturating_mul(EXACT_SCALE / m.currency.minor_per_major())
+}
+
+/// Round a fixed-point value to `currency`'s minor units, half away from zero like
+/// `TypedMoney::from_money`.
+fn minor_of_fixed(fixed: i64, currency: Currency) -> i64 {
+    let per = EXACT_SCALE / currency.minor_per_major();
+    let (q, r) = (fixed / per, fixed % per);
+    if 2 * r.abs() >= per {
+        q + fixed.signum()
+    } else {
+        q
+    }
+}
+
+impl OrderSoA {
+    /// Builder flag: keep the exact fixed-point amount column; see the module docs. Existing
+    /// rows have their amounts rounded to it.
+    pub fn with_exact_amounts(mut self) -> Self {
+        self.keep_exact_amounts();
+        self
+    }
+
+    fn keep_exact_amounts(&mut self) {
+        if self.exact_amounts.is_none() {
+            self.exact_amounts = Some(self.amounts.iter().map(|&a| fixed_of_money(a)).collect());
+        }
+    }
+
+    /// Whether the kernel keeps the exact amount column.
+    pub fn has_exact_amounts(&self) -> bool {
+        self.exact_amounts.is_some()
+    }
+
+    /// Row `idx`'s amount in fixed point: the exact column's value while it agrees with the
+    /// float column, the rounded float otherwise.
+    #[inline]
+    pub(crate) fn fixed_at(&self, idx: usize) -> i64 {
+        let amount = self.amounts[idx];
+        match &self.exact_amounts {
+            Some(exact) => {
+                let fixed = exact[idx];
+                let approx = fixed as f64 / EXACT_SCALE as f64;
+                if (approx - amount).abs() <= amount.abs() * f64::EPSILON {
+                    fixed
+                } else {
+                    fixed_of_money(amount)
+                }
+            }
+            None => fixed_of_money(amount),
+        }
+    }
+
+    /// Row `idx`'s amount in minor units of `currency`.
+    #[inline]
+    pub(crate) fn minor_at(&self, idx: usize, currency: Currency) -> TypedMoney {
+        if self.exact_amounts.is_some() {
+            TypedMoney::new(minor_of_fixed(self.fixed_at(idx), currency), currency)
+        } else {
+            TypedMoney::from_money(Money(self.amounts[idx]), currency)
+        }
+    }
+
+    /// Sum amounts for a given status in integer fixed point, rounding once at the end. Rows
+    /// are read from the exact column if kept, otherwise each float is first rounded to the
+    /// nearest ten-thousandth. Currencies are added together, as in `sum_by_status`.
+    pub fn sum_by_status_exact(&self, status: Status) -> Money {
+        let total: i128 = (0..self.len())
+            .filter(|&i| self.statuses[i] == status && self.is_live(i))
+            .map(|i| self.fixed_at(i) as i128)
+            .sum();
+        Money(total as f64 / EXACT_SCALE as f64)
+    }
+}
+
+impl OrderStore {
+    /// Builder flag: keep the exact fixed-point amount column; see `OrderSoA::with_exact_amounts`.
+    pub fn with_exact_amounts(mut self) -> Self {
+        query_cache::write(&mut self.inner, &mut self.epoch, self.context).keep_exact_amounts();
+        self
+    }
+}
@@ -0,0 +1,125 @@
+//! Expiry of stale pending orders.
+//!
+//! `OrderStore::expire_pending_older_than` cancels every pending order placed before a cutoff.
+//! It finds them through a `(status, timestamp)` composite index registered on first use, so a
+//! sweep is two binary searches and a walk over the expired entries rather than a scan of the
+//! whole store. Appends and single-row writes (including the sweep's own cancels) move entries
+//! in the built index rather than dropping it; only removals and bulk mutations (`for_each_mut`,
+//! `columns_mut`, compaction, sorting) leave the next sweep to rebuild it. Each expiry is an
+//! ordinary aggregate `cancel`: it reaches the change feed, materialized views and the outbox
+//! like any other command, and the `Cancelled` events are returned for callers that keep an
+//! `EventLog`.
+//!
+//! With the `std-thread` feature, `ExpirySweeper` runs the sweep periodically on a background
+//! thread against a shared store, with the cutoff taken from the store's `Clock`.
+
+use crate::query_cache;
+use crate::{IndexColumn, IndexSpec, OrderEvent, OrderStore, Status};
+
+/// Name of the index `expire_pending_older_than` registers and scans.
+pub const EXPIRY_INDEX: &str = "expiry_status_ts";
+
+impl OrderStore {
+    /// Cancel every pending order with `timestamp < cutoff_ts`; returns the events emitted, in
+    /// timestamp order.
+    pub fn expire_pending_older_than(&mut self, cutoff_ts: u64) -> Vec<OrderEvent> {
+        if self.inner.index_scan(EXPIRY_INDEX).is_err() {
+            // Adding an index changes no row, so views and aggregates stay current.
+            query_cache::write(&mut self.inner, &mut self.epoch, self.context).add_index(
+                IndexSpec::new(EXPIRY_INDEX)
+                    .on(IndexColumn::Status)
+                    .on(IndexColumn::Timestamp),
+            );
+        }
+        let soa = &self.inner;
+        let expired: Vec<_> = soa
+            .index_scan(EXPIRY_INDEX)
+            .expect("registered above")
+            .eq(Status::Pending)
+            .range(..cutoff_ts)
+            .indices()
+            .map(|i| (i, soa.view_at(i).id()))
+            // Rows shadowed by a later row with the same id are not reachable by id; skip them.
+            .filter(|(i, id)| soa.id_index.get(id) == Some(i))
+            .map(|(_, id)| id)
+            .collect();
+
+        let mut events = Vec::with_capacity(expired.len());
+        for id in expired {
+            let cancelled = self.load(id).and_then(|mut order| order.cancel());
+            if cancelled.is_ok() {
+                events.push(OrderEvent::Cancelled { id });
+            }
+        }
+        events
+    }
+}
+
+#[cfg(feature = "std-thread")]
+pub use sweeper::ExpirySweeper;
+
+#[cfg(feature = "std-thread")]
+mod sweeper {
+    use crate::{OrderEvent, OrderStore};
+    use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+    use std::sync::{Arc, Mutex};
+    use std::thread::JoinHandle;
+    use std::time::Duration;
+
+    /// Background thread expiring pending orders older than `max_age` every `interval`. Stops
+    /// when dropped.
+    #[derive(Debug)]
+    pub struct ExpirySweeper {
+        stop: Option<Sender<()>>,
+        thread: Option<JoinHandle<()>>,
+    }
+
+    impl ExpirySweeper {
+        /// Start sweeping `store`. `on_expired` receives each non-empty batch of events, e.g. to
+        /// append them to an `EventLog`.
+        pub fn spawn(
+            store: Arc<Mutex<OrderStore>>,
+            interval: Duration,
+            max_age: u64,
+            mut on_expired: impl FnMut(Vec<OrderEvent>) + Send + 'static,
+        ) -> Self {
+            let (stop, stopped) = mpsc::channel::<()>();
+            let thread = std::thread::spawn(move || loop {
+                let mut guard = store.lock().unwrap_or_else(|e| e.into_inner());
+                let cutoff = guard.now().saturating_sub(max_age);
+                let events = guard.expire_pending_older_than(cutoff);
+                drop(guard);
+                if !events.is_empty() {
+                    on_expired(events);
+                }
+                match stopped.recv_timeout(interval) {
+                    Err(RecvTimeoutError::Timeout) => {}
+                    _ => return,
+                }
+            });
+            Self {
+                stop: Some(stop),
+                thread: Some(thread),
+            }
+        }
+
+        /// Stop sweeping and wait for an in-progress sweep to finish.
+        pub fn stop(mut self) {
+            self.shutdown();
+        }
+
+        fn shutdown(&mut self) {
+            // Dropping the sender wakes the thread with `Disconnected`.
+            self.stop.take();
+            if let Some(thread) = self.thread.take() {
+                let _ = thread.join();
+            }
+        }
+    }
+
+    impl Drop for ExpirySweeper {
+        fn drop(&mut self) {
+            self.shutdown();
+        }
+    }
+}
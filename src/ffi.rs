@@ -0,0 +1,236 @@
+//! C ABI over the kernel (feature `ffi`).
+//!
+//! A host creates an `OrderSoA` with `dds_store_new`, appends rows with `dds_store_push`, runs
+//! kernels such as `dds_store_sum_by_status`, and frees it with `dds_store_free`. The column
+//! getters hand out a pointer and length into the kernel's own storage, so a C, C++ or Python
+//! (ctypes, cffi, NumPy) host reads the columns without a copy. Such a pointer is valid until the
+//! next call that mutates the store. Tombstoned rows stay in the columns; check
+//! `dds_store_is_live`, or call `dds_store_compact` first.
+//!
+//! Statuses cross the boundary as their `Status` discriminant (0 pending, 1 completed,
+//! 2 cancelled). The C header is `include/ddd_dod_soa.h`, generated with
+//! `cbindgen --config cbindgen.toml --output include/ddd_dod_soa.h src/ffi.rs`. Build a linkable
+//! library with `cargo rustc --release --features ffi --crate-type cdylib` (or `staticlib`).
+
+use crate::{Money, OrderId, OrderSoA, Status};
+use std::ptr;
+
+/// Result code of the fallible calls.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DdsResult {
+    Ok = 0,
+    /// A required pointer argument was null.
+    NullPointer = 1,
+    /// A status code outside 0..=2.
+    InvalidStatus = 2,
+    /// No live row has the given id.
+    UnknownId = 3,
+}
+
+fn status(code: u8) -> Option<Status> {
+    Status::ALL.get(usize::from(code)).copied()
+}
+
+/// A new, empty store. Free it with `dds_store_free`.
+#[no_mangle]
+pub extern "C" fn dds_store_new() -> *mut OrderSoA {
+    dds_store_with_capacity(0)
+}
+
+/// A new, empty store with room for `capacity` rows. Free it with `dds_store_free`.
+#[no_mangle]
+pub extern "C" fn dds_store_with_capacity(capacity: usize) -> *mut OrderSoA {
+    Box::into_raw(Box::new(OrderSoA::with_capacity(capacity)))
+}
+
+/// Free a store. Null is ignored.
+///
+/// # Safety
+/// `store` must come from `dds_store_new` / `dds_store_with_capacity` and not be used again.
+#[no_mangle]
+pub unsafe extern "C" fn dds_store_free(store: *mut OrderSoA) {
+    if !store.is_null() {
+        // SAFETY: the caller passes a pointer from `Box::into_raw` exactly once.
+        drop(unsafe { Box::from_raw(store) });
+    }
+}
+
+/// Append an order in the default currency.
+///
+/// # Safety
+/// `store` must be null or a live store pointer not used concurrently.
+#[no_mangle]
+pub unsafe extern "C" fn dds_store_push(
+    store: *mut OrderSoA,
+    id: u64,
+    amount: f64,
+    status_code: u8,
+    ts: u64,
+) -> DdsResult {
+    // SAFETY: the caller guarantees a live, unaliased store or null.
+    let Some(soa) = (unsafe { store.as_mut() }) else {
+        return DdsResult::NullPointer;
+    };
+    let Some(st) = status(status_code) else {
+        return DdsResult::InvalidStatus;
+    };
+    soa.push(OrderId(id), Money(amount), st, ts);
+    DdsResult::Ok
+}
+
+/// Tombstone the order with `id`.
+///
+/// # Safety
+/// As for `dds_store_push`.
+#[no_mangle]
+pub unsafe extern "C" fn dds_store_remove(store: *mut OrderSoA, id: u64) -> DdsResult {
+    // SAFETY: the caller guarantees a live, unaliased store or null.
+    let Some(soa) = (unsafe { store.as_mut() }) else {
+        return DdsResult::NullPointer;
+    };
+    match soa
+        .id_index
+        .get(&OrderId(id))
+        .map(|&idx| soa.handle_at(idx))
+    {
+        Some(h) => soa
+            .remove(h)
+            .map_or(DdsResult::UnknownId, |()| DdsResult::Ok),
+        None => DdsResult::UnknownId,
+    }
+}
+
+/// Drop tombstoned rows so every column index is a live order.
+///
+/// # Safety
+/// As for `dds_store_push`.
+#[no_mangle]
+pub unsafe extern "C" fn dds_store_compact(store: *mut OrderSoA) -> DdsResult {
+    // SAFETY: the caller guarantees a live, unaliased store or null.
+    let Some(soa) = (unsafe { store.as_mut() }) else {
+        return DdsResult::NullPointer;
+    };
+    soa.compact();
+    DdsResult::Ok
+}
+
+/// Physical row count (the length of every column), tombstones included. 0 for null.
+///
+/// # Safety
+/// `store` must be null or a live store pointer.
+#[no_mangle]
+pub unsafe extern "C" fn dds_store_len(store: *const OrderSoA) -> usize {
+    // SAFETY: the caller guarantees a live store or null.
+    unsafe { store.as_ref() }.map_or(0, OrderSoA::len)
+}
+
+/// Live order count. 0 for null.
+///
+/// # Safety
+/// As for `dds_store_len`.
+#[no_mangle]
+pub unsafe extern "C" fn dds_store_live_len(store: *const OrderSoA) -> usize {
+    // SAFETY: the caller guarantees a live store or null.
+    unsafe { store.as_ref() }.map_or(0, OrderSoA::live_len)
+}
+
+/// Whether row `idx` holds a live order; false for null or out of range.
+///
+/// # Safety
+/// As for `dds_store_len`.
+#[no_mangle]
+pub unsafe extern "C" fn dds_store_is_live(store: *const OrderSoA, idx: usize) -> bool {
+    // SAFETY: the caller guarantees a live store or null.
+    unsafe { store.as_ref() }.is_some_and(|soa| idx < soa.len() && soa.is_live(idx))
+}
+
+/// Write the total amount of live orders with `status_code` to `out`.
+///
+/// # Safety
+/// `store` must be null or a live store pointer; `out` must be null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn dds_store_sum_by_status(
+    store: *const OrderSoA,
+    status_code: u8,
+    out: *mut f64,
+) -> DdsResult {
+    // SAFETY: the caller guarantees a live store or null.
+    let Some(soa) = (unsafe { store.as_ref() }) else {
+        return DdsResult::NullPointer;
+    };
+    if out.is_null() {
+        return DdsResult::NullPointer;
+    }
+    let Some(st) = status(status_code) else {
+        return DdsResult::InvalidStatus;
+    };
+    // SAFETY: `out` is non-null and writable per the contract.
+    unsafe { out.write(soa.sum_by_status(st).0) };
+    DdsResult::Ok
+}
+
+/// Expose `col` through `len`; null (length 0) for a null store.
+///
+/// # Safety
+/// `store` must be null or a live store pointer; `len` must be null or writable.
+unsafe fn column<T>(
+    store: *const OrderSoA,
+    len: *mut usize,
+    col: impl FnOnce(&OrderSoA) -> &[T],
+) -> *const T {
+    // SAFETY: the caller guarantees a live store or null.
+    let slice = unsafe { store.as_ref() }.map_or(&[][..], col);
+    if !len.is_null() {
+        // SAFETY: `len` is non-null and writable per the contract.
+        unsafe { len.write(slice.len()) };
+    }
+    if slice.is_empty() {
+        ptr::null()
+    } else {
+        slice.as_ptr()
+    }
+}
+
+/// The order id column (`len` rows). Valid until the store is next mutated.
+///
+/// # Safety
+/// `store` must be null or a live store pointer; `len` must be null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn dds_store_ids(store: *const OrderSoA, len: *mut usize) -> *const u64 {
+    // SAFETY: forwarded contract; `OrderId` is `repr(transparent)` over `u64`.
+    unsafe { column(store, len, |s| &s.ids[..]) }.cast()
+}
+
+/// The amount column (`len` rows). Valid until the store is next mutated.
+///
+/// # Safety
+/// As for `dds_store_ids`.
+#[no_mangle]
+pub unsafe extern "C" fn dds_store_amounts(store: *const OrderSoA, len: *mut usize) -> *const f64 {
+    // SAFETY: forwarded contract.
+    unsafe { column(store, len, |s| &s.amounts[..]) }
+}
+
+/// The status column as discriminants (`len` rows). Valid until the store is next mutated.
+///
+/// # Safety
+/// As for `dds_store_ids`.
+#[no_mangle]
+pub unsafe extern "C" fn dds_store_statuses(store: *const OrderSoA, len: *mut usize) -> *const u8 {
+    // SAFETY: forwarded contract; `Status` is `repr(u8)`.
+    unsafe { column(store, len, |s| &s.statuses[..]) }.cast()
+}
+
+/// The timestamp column (`len` rows). Valid until the store is next mutated.
+///
+/// # Safety
+/// As for `dds_store_ids`.
+#[no_mangle]
+pub unsafe extern "C" fn dds_store_timestamps(
+    store: *const OrderSoA,
+    len: *mut usize,
+) -> *const u64 {
+    // SAFETY: forwarded contract.
+    unsafe { column(store, len, |s| &s.timestamps[..]) }
+}
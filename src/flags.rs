@@ -0,0 +1,211 @@
+//! Bit-sliced flag columns.
+//!
+//! Yes/no attributes such as gift wrapping, express shipping or a fraud hold cost one bit per
+//! row each. A `FlagColumn` stores every flag as its own bit plane (a `Bitmap`), so "orders with
+//! both EXPRESS and GIFT" is an AND of two planes 64 rows at a time, and counting them is a
+//! popcount per word: no row is ever looked at individually. Selections come back as `Bitmap`s
+//! that combine with the kernel's predicate kernels, e.g. `soa.and_live(sel)` to drop tombstoned
+//! rows, given a column kept row-aligned with the kernel.
+
+use crate::dyn_soa::DynColumn;
+use crate::Bitmap;
+use std::any::{type_name, Any};
+use std::ops::{BitAnd, BitOr, BitOrAssign, Not};
+
+/// A set of up to eight per-order flags.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct OrderFlags(pub u8);
+
+impl OrderFlags {
+    pub const NONE: OrderFlags = OrderFlags(0);
+    pub const GIFT: OrderFlags = OrderFlags(1 << 0);
+    pub const EXPRESS: OrderFlags = OrderFlags(1 << 1);
+    pub const FRAUD_HOLD: OrderFlags = OrderFlags(1 << 2);
+
+    /// Flags a column can hold.
+    pub const BITS: usize = u8::BITS as usize;
+
+    pub fn contains(self, other: OrderFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub fn intersects(self, other: OrderFlags) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Bit positions of the set flags, ascending.
+    fn bits(self) -> impl Iterator<Item = usize> {
+        (0..Self::BITS).filter(move |&b| self.0 >> b & 1 == 1)
+    }
+}
+
+impl BitOr for OrderFlags {
+    type Output = OrderFlags;
+
+    fn bitor(self, rhs: OrderFlags) -> OrderFlags {
+        OrderFlags(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for OrderFlags {
+    fn bitor_assign(&mut self, rhs: OrderFlags) {
+        self.0 |= rhs.0;
+    }
+}
+
+impl BitAnd for OrderFlags {
+    type Output = OrderFlags;
+
+    fn bitand(self, rhs: OrderFlags) -> OrderFlags {
+        OrderFlags(self.0 & rhs.0)
+    }
+}
+
+impl Not for OrderFlags {
+    type Output = OrderFlags;
+
+    fn not(self) -> OrderFlags {
+        OrderFlags(!self.0)
+    }
+}
+
+/// One `OrderFlags` per row, stored as eight bit planes; see the module docs.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FlagColumn {
+    planes: [Bitmap; OrderFlags::BITS],
+}
+
+impl FlagColumn {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(cap: usize) -> Self {
+        Self {
+            planes: std::array::from_fn(|_| Bitmap::with_capacity(cap)),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.planes[0].len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn push(&mut self, flags: OrderFlags) {
+        for (b, plane) in self.planes.iter_mut().enumerate() {
+            plane.push(flags.0 >> b & 1 == 1);
+        }
+    }
+
+    pub fn get(&self, i: usize) -> OrderFlags {
+        let bits = self.planes.iter().enumerate();
+        OrderFlags(bits.fold(0, |acc, (b, plane)| acc | u8::from(plane.get(i)) << b))
+    }
+
+    pub fn set(&mut self, i: usize, flags: OrderFlags) {
+        for (b, plane) in self.planes.iter_mut().enumerate() {
+            plane.set(i, flags.0 >> b & 1 == 1);
+        }
+    }
+
+    /// Raise (`on`) or clear every flag in `flags` on row `i`, leaving the others.
+    pub fn update(&mut self, i: usize, flags: OrderFlags, on: bool) {
+        for b in flags.bits() {
+            self.planes[b].set(i, on);
+        }
+    }
+
+    /// The plane of a single flag, e.g. to combine with other selections.
+    ///
+    /// # Panics
+    /// If `flag` is not exactly one flag.
+    pub fn plane(&self, flag: OrderFlags) -> &Bitmap {
+        assert_eq!(flag.0.count_ones(), 1, "{flag:?} is not a single flag");
+        &self.planes[flag.0.trailing_zeros() as usize]
+    }
+
+    /// Word `wi` of the rows holding all (`all`) or any of `mask`'s flags. Bits past `len` stay
+    /// clear: every plane keeps them clear, and the all-of seed is cut to the column's length.
+    #[inline]
+    fn word(&self, wi: usize, mask: OrderFlags, all: bool) -> u64 {
+        if all {
+            let tail = self.len() - wi * 64;
+            let seed = if tail >= 64 {
+                u64::MAX
+            } else {
+                (1 << tail) - 1
+            };
+            mask.bits()
+                .fold(seed, |w, b| w & self.planes[b].words()[wi])
+        } else {
+            mask.bits().fold(0, |w, b| w | self.planes[b].words()[wi])
+        }
+    }
+
+    fn words(&self, mask: OrderFlags, all: bool) -> impl Iterator<Item = u64> + '_ {
+        (0..self.len().div_ceil(64)).map(move |wi| self.word(wi, mask, all))
+    }
+
+    /// Rows with every flag in `mask` set (all rows for an empty mask).
+    pub fn count_with_flags(&self, mask: OrderFlags) -> usize {
+        self.words(mask, true)
+            .map(|w| w.count_ones() as usize)
+            .sum()
+    }
+
+    /// Rows with at least one flag in `mask` set.
+    pub fn count_with_any_flags(&self, mask: OrderFlags) -> usize {
+        self.words(mask, false)
+            .map(|w| w.count_ones() as usize)
+            .sum()
+    }
+
+    /// Selection of the rows with every flag in `mask` set.
+    pub fn filter_flags_all(&self, mask: OrderFlags) -> Bitmap {
+        Bitmap::from_words(self.words(mask, true).collect(), self.len())
+    }
+
+    /// Selection of the rows with at least one flag in `mask` set.
+    pub fn filter_flags_any(&self, mask: OrderFlags) -> Bitmap {
+        Bitmap::from_words(self.words(mask, false).collect(), self.len())
+    }
+}
+
+impl FromIterator<OrderFlags> for FlagColumn {
+    fn from_iter<I: IntoIterator<Item = OrderFlags>>(iter: I) -> Self {
+        let mut col = Self::new();
+        iter.into_iter().for_each(|f| col.push(f));
+        col
+    }
+}
+
+impl DynColumn for FlagColumn {
+    fn len(&self) -> usize {
+        FlagColumn::len(self)
+    }
+    fn push_default(&mut self) {
+        self.push(OrderFlags::NONE);
+    }
+    fn retain_mask(&mut self, keep: &[bool]) {
+        *self = (0..self.len())
+            .filter(|&i| keep[i])
+            .map(|i| self.get(i))
+            .collect();
+    }
+    fn type_name(&self) -> &'static str {
+        type_name::<OrderFlags>()
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
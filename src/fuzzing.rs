@@ -0,0 +1,186 @@
+//! Model checking of kernel operations, shared by the property tests and the `cargo-fuzz`
+//! targets under `fuzz/` (feature `fuzzing`; not a stable API).
+//!
+//! Operations run against an `OrderSoA` and a naive AoS model side by side. After every step
+//! the kernel must keep all columns the same length, every live handle must resolve to the row
+//! the model expects, and lookups and aggregates must match the model. The kernels' unchecked
+//! fast paths lean on exactly these invariants. `run` decodes an arbitrary byte string into an
+//! operation stream, so any input is a valid (and reproducible) test case.
+
+use crate::{Money, OrderId, OrderRow, OrderSoA, RowHandle, Status};
+
+/// One kernel operation. Row positions are taken modulo the model's live row count.
+#[derive(Clone, Debug)]
+pub enum Op {
+    Push {
+        id: u64,
+        amount: u32,
+        status: Status,
+    },
+    Remove(usize),
+    SetStatus(usize, Status),
+    SetAmount(usize, u32),
+    Retain(Status),
+    Compact,
+    SortByAmount,
+    SortByTimestamp,
+}
+
+/// Live rows in kernel order, with the handle the kernel issued for each.
+pub type Model = Vec<(RowHandle, OrderRow)>;
+
+/// Assert the kernel's structure and every query against the model.
+pub fn check(soa: &OrderSoA, model: &Model) {
+    let n = soa.len();
+    assert_eq!(soa.ids.len(), n);
+    assert_eq!(soa.amounts.len(), n);
+    assert_eq!(soa.statuses.len(), n);
+    assert_eq!(soa.timestamps.len(), n);
+    assert_eq!(soa.currencies.len(), n);
+    assert_eq!(soa.customers.len(), n);
+    assert_eq!(soa.item_offsets.len(), n);
+    assert_eq!(soa.generations.len(), n);
+    assert_eq!(soa.versions.len(), n);
+    assert_eq!(soa.deleted.len(), n);
+    assert_eq!(soa.live_len(), model.len());
+
+    let rows: Vec<OrderRow> = model.iter().map(|&(_, r)| r).collect();
+    assert_eq!(soa.iter().map(OrderRow::from).collect::<Vec<_>>(), rows);
+    for &(h, r) in model {
+        assert_eq!(OrderRow::from(soa.view(h).unwrap()), r);
+    }
+    for id in rows.iter().map(|r| r.id) {
+        let latest = rows.iter().rev().find(|r| r.id == id).copied();
+        assert_eq!(soa.find_by_id(id).map(OrderRow::from), latest);
+    }
+    for s in Status::ALL {
+        let matching = || rows.iter().filter(move |r| r.status == s);
+        assert_eq!(
+            soa.sum_by_status(s),
+            Money(matching().map(|r| r.amount.0).sum())
+        );
+        assert_eq!(soa.find_by_status(s).count(), matching().count());
+        assert_eq!(soa.group_by_status().get(s).count, matching().count());
+        let filtered: Vec<OrderRow> = soa
+            .filter_indices(Money(50.0), s)
+            .into_iter()
+            .map(|i| OrderRow::from(soa.view_at(i)))
+            .collect();
+        let expected: Vec<OrderRow> = matching().filter(|r| r.amount.0 >= 50.0).copied().collect();
+        assert_eq!(filtered, expected);
+    }
+}
+
+/// After rows moved, every old handle must either still name its row or be rejected; then
+/// refresh the model's handles from the new positions.
+fn rehandle(soa: &OrderSoA, model: &mut Model) {
+    for (h, r) in model.iter() {
+        if let Ok(v) = soa.view(*h) {
+            assert_eq!(OrderRow::from(v), *r);
+        }
+    }
+    let live: Vec<usize> = (0..soa.len()).filter(|&i| soa.is_live(i)).collect();
+    for ((h, _), idx) in model.iter_mut().zip(live) {
+        *h = soa.handle_at(idx);
+    }
+}
+
+/// Apply `op` to both sides. `ts` is the last timestamp issued; pushes take the next one.
+pub fn apply(soa: &mut OrderSoA, model: &mut Model, ts: &mut u64, op: Op) {
+    match op {
+        Op::Push { id, amount, status } => {
+            *ts += 1;
+            // Whole-number amounts keep float sums exact in any summation order (the SIMD
+            // kernels use several accumulators).
+            let row = OrderRow::new(OrderId(id), Money(f64::from(amount)), status, *ts);
+            let h = soa.push(row.id, row.amount, row.status, row.ts);
+            model.push((h, row));
+        }
+        Op::Remove(k) if !model.is_empty() => {
+            let (h, _) = model.remove(k % model.len());
+            soa.remove(h).unwrap();
+            assert!(soa.remove(h).is_err());
+        }
+        Op::SetStatus(k, s) if !model.is_empty() => {
+            let k = k % model.len();
+            soa.view_mut(model[k].0).unwrap().set_status(s);
+            model[k].1.status = s;
+        }
+        Op::SetAmount(k, amount) if !model.is_empty() => {
+            let k = k % model.len();
+            let amount = Money(f64::from(amount));
+            soa.view_mut(model[k].0).unwrap().set_amount(amount);
+            model[k].1.amount = amount;
+        }
+        Op::Retain(s) => {
+            soa.retain(|v| v.status() != s);
+            model.retain(|(_, r)| r.status != s);
+            rehandle(soa, model);
+        }
+        Op::Compact => {
+            soa.compact();
+            rehandle(soa, model);
+        }
+        Op::SortByAmount => {
+            soa.sort_by_amount();
+            model.sort_by(|a, b| a.1.amount.0.total_cmp(&b.1.amount.0));
+            rehandle(soa, model);
+        }
+        Op::SortByTimestamp => {
+            soa.sort_by_timestamp();
+            model.sort_by_key(|(_, r)| r.ts);
+            rehandle(soa, model);
+        }
+        _ => {}
+    }
+}
+
+fn status_from(b: u8) -> Status {
+    Status::ALL[usize::from(b) % Status::ALL.len()]
+}
+
+/// Decode one operation from `tag` and the argument bytes after it; `None` if they run out.
+fn decode_op(tag: u8, bytes: &mut impl Iterator<Item = u8>) -> Option<Op> {
+    let mut arg = || bytes.next();
+    Some(match tag % 8 {
+        0 => Op::Push {
+            id: u64::from(arg()? % 20),
+            amount: u32::from(arg()? % 100),
+            status: status_from(arg()?),
+        },
+        1 => Op::Remove(arg()?.into()),
+        2 => Op::SetStatus(arg()?.into(), status_from(arg()?)),
+        3 => Op::SetAmount(arg()?.into(), u32::from(arg()? % 100)),
+        4 => Op::Retain(status_from(arg()?)),
+        5 => Op::Compact,
+        6 => Op::SortByAmount,
+        _ => Op::SortByTimestamp,
+    })
+}
+
+/// Decode `data` into operations: a tag byte, then one byte per argument. A trailing partial
+/// operation is dropped. Ids and amounts stay small so pushes collide and filters split.
+pub fn decode(data: &[u8]) -> Vec<Op> {
+    let mut bytes = data.iter().copied();
+    std::iter::from_fn(|| {
+        let tag = bytes.next()?;
+        decode_op(tag, &mut bytes)
+    })
+    .collect()
+}
+
+/// Run the operations `data` decodes to against a kernel (status-indexed if the first byte is
+/// odd), checking it against the model after every step. Panics on any divergence.
+pub fn run(data: &[u8]) {
+    let mut soa = match data.first() {
+        Some(b) if b % 2 == 1 => OrderSoA::default().with_status_index(),
+        _ => OrderSoA::default(),
+    };
+    let data = data.get(1..).unwrap_or_default();
+    let mut model = Model::new();
+    let mut ts = 0;
+    for op in decode(data) {
+        apply(&mut soa, &mut model, &mut ts, op);
+        check(&soa, &model);
+    }
+}
@@ -0,0 +1,83 @@
+//! Gather and scatter through index lists.
+//!
+//! Kernels such as `filter_indices` answer with row positions. Consuming them through `view_at`
+//! in a caller loop touches every column once per row; `gather` instead copies the listed rows
+//! into a new compact kernel one column at a time, and `scatter_update` applies a mutation to
+//! the listed rows with the per-write bookkeeping (index invalidation, audit stamp) done once.
+
+use crate::{AuditColumns, Bitmap, OrderMut, OrderSoA};
+
+impl OrderSoA {
+    /// A new kernel holding the rows at `indices`, in that order (a row listed twice is copied
+    /// twice). Line items, versions, audit stamps, exact amounts, states and settlements come
+    /// along; tombstoned rows stay tombstoned.
+    /// The new kernel keeps this one's index configuration, with fresh handles.
+    ///
+    /// # Panics
+    /// If an index is out of bounds.
+    pub fn gather(&self, indices: &[usize]) -> OrderSoA {
+        fn pick<T: Copy>(col: &[T], indices: &[usize]) -> Vec<T> {
+            indices.iter().map(|&i| col[i]).collect()
+        }
+        let mut out = self.empty_like(0);
+        out.ids = pick(&self.ids, indices);
+        out.amounts = pick(&self.amounts, indices);
+        out.statuses = pick(&self.statuses, indices);
+        out.timestamps = pick(&self.timestamps, indices);
+        out.currencies = pick(&self.currencies, indices);
+        out.customers = pick(&self.customers, indices);
+        out.versions = pick(&self.versions, indices);
+        out.audit = self.audit.as_ref().map(|a| a.gathered(indices));
+        out.exact_amounts = self.exact_amounts.as_ref().map(|e| pick(e, indices));
+        out.settlements = self.settlements.as_ref().map(|s| s.gathered(indices));
+        if let Some(s) = &mut out.states {
+            s.codes = indices.iter().map(|&i| self.state_at(i).0).collect();
+        }
+        out.item_offsets = indices
+            .iter()
+            .map(|&i| out.items.copy_from(&self.items, self.item_offsets[i]))
+            .collect();
+        out.deleted = Bitmap::from_fn(indices.len(), |k| self.deleted.get(indices[k]));
+        out.tombstones = out.deleted.count_ones();
+        out.generations = vec![0; indices.len()];
+        out.rebuild_id_index();
+        out
+    }
+
+    /// Apply `f` to the live rows at `indices`, in that order (a row listed twice is visited
+    /// twice); tombstoned rows are skipped. Like `for_each_mut`, every visited row's version is
+    /// bumped and indexes are rebuilt on the next query.
+    ///
+    /// # Panics
+    /// If an index is out of bounds.
+    pub fn scatter_update<F: FnMut(OrderMut<'_>)>(&mut self, indices: &[usize], mut f: F) {
+        self.invalidate_indexes();
+        let stamp = self.audit.as_ref().map(AuditColumns::stamp);
+        for &idx in indices {
+            assert!(
+                idx < self.len(),
+                "row {idx} out of bounds (len {})",
+                self.len()
+            );
+            if !self.is_live(idx) {
+                continue;
+            }
+            self.versions[idx] += 1;
+            if let (Some(a), Some(stamp)) = (&mut self.audit, stamp) {
+                a.touch(idx, stamp);
+            }
+            f(OrderMut {
+                ids: &mut self.ids,
+                amounts: &mut self.amounts,
+                exact_amounts: self.exact_amounts.as_deref_mut(),
+                settlements: &mut self.settlements,
+                statuses: &mut self.statuses,
+                timestamps: &mut self.timestamps,
+                currencies: &mut self.currencies,
+                customers: &mut self.customers,
+                idx,
+                upkeep: None,
+            });
+        }
+    }
+}
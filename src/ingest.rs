@@ -0,0 +1,161 @@
+//! Shard-parallel bulk ingestion for `ShardedOrderStore`.
+//!
+//! `ingest` takes the receiving end of a channel of `IngestMsg`s and starts one router thread
+//! plus one worker per shard. The router sends each row to the worker owning its id over a
+//! bounded channel, so a slow shard applies backpressure all the way to the producers instead of
+//! buffering without limit. A worker drains whatever is queued and appends it under a single
+//! lock acquisition, and since only that worker writes to its shard during ingestion, workers
+//! never contend with each other.
+//!
+//! Protocol: an `IngestMsg::Flush` is acknowledged once every row sent before it is visible in
+//! the store. Dropping every sender shuts the pipeline down; `IngestPipeline::join` waits for the
+//! queues to drain and reports what was appended and rejected.
+
+use crate::{OrderId, OrderRow, ShardedOrderStore, StoreError};
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+/// Most rows a worker appends per lock acquisition.
+const MAX_BATCH: usize = 256;
+
+/// Input to an ingestion pipeline.
+#[derive(Debug)]
+pub enum IngestMsg {
+    Row(OrderRow),
+    /// Reply on the sender once every earlier row is stored.
+    Flush(Sender<()>),
+}
+
+impl IngestMsg {
+    /// A flush request and the receiver its acknowledgement arrives on.
+    pub fn flush() -> (Self, Receiver<()>) {
+        let (tx, rx) = mpsc::channel();
+        (IngestMsg::Flush(tx), rx)
+    }
+}
+
+impl From<OrderRow> for IngestMsg {
+    fn from(row: OrderRow) -> Self {
+        IngestMsg::Row(row)
+    }
+}
+
+/// Outcome of an ingestion run.
+#[derive(Debug, Default)]
+pub struct IngestStats {
+    /// Rows stored, including upserts.
+    pub appended: usize,
+    /// Rows refused by the store's `IdPolicy` or validators, with the reason.
+    pub rejected: Vec<(OrderId, StoreError)>,
+    /// Bulk writes the rows were applied in.
+    pub batches: usize,
+}
+
+/// Handle to running ingestion threads.
+#[derive(Debug)]
+#[must_use = "dropping the pipeline detaches its threads; `join` to collect the stats"]
+pub struct IngestPipeline {
+    router: JoinHandle<()>,
+    workers: Vec<JoinHandle<IngestStats>>,
+}
+
+impl IngestPipeline {
+    /// Wait until every input sender is dropped and the queues are drained.
+    ///
+    /// # Panics
+    /// If an ingestion thread panicked.
+    pub fn join(self) -> IngestStats {
+        self.router.join().expect("ingest router panicked");
+        let mut stats = IngestStats::default();
+        for w in self.workers {
+            let s = w.join().expect("ingest worker panicked");
+            stats.appended += s.appended;
+            stats.rejected.extend(s.rejected);
+            stats.batches += s.batches;
+        }
+        stats
+    }
+}
+
+impl ShardedOrderStore {
+    /// Append every row received on `rx` to the shard owning its id, one worker thread per
+    /// shard. Each worker queues at most `capacity` rows (at least one) before the router, and
+    /// so the producers, block. Rows go through the store's `IdPolicy` and validators like
+    /// `add`.
+    pub fn ingest(self: &Arc<Self>, rx: Receiver<IngestMsg>, capacity: usize) -> IngestPipeline {
+        let (queues, workers): (Vec<_>, Vec<_>) = (0..self.shard_count())
+            .map(|si| {
+                let (tx, rx) = mpsc::sync_channel(capacity.max(1));
+                let store = Arc::clone(self);
+                (tx, thread::spawn(move || store.ingest_worker(si, rx)))
+            })
+            .unzip();
+        let store = Arc::clone(self);
+        let router = thread::spawn(move || store.ingest_router(rx, queues));
+        IngestPipeline { router, workers }
+    }
+
+    fn ingest_router(&self, rx: Receiver<IngestMsg>, queues: Vec<SyncSender<IngestMsg>>) {
+        for msg in rx {
+            match msg {
+                IngestMsg::Row(row) => {
+                    // A worker only exits once its queue is closed, so this cannot fail.
+                    let _ = queues[self.shard_idx(row.id)].send(IngestMsg::Row(row));
+                }
+                IngestMsg::Flush(ack) => {
+                    let (done, acks) = mpsc::channel();
+                    for q in &queues {
+                        let _ = q.send(IngestMsg::Flush(done.clone()));
+                    }
+                    drop(done);
+                    if acks.iter().take(queues.len()).count() == queues.len() {
+                        let _ = ack.send(());
+                    }
+                }
+            }
+        }
+        // Dropping `queues` closes every worker's input once it is drained.
+    }
+
+    fn ingest_worker(&self, si: usize, rx: Receiver<IngestMsg>) -> IngestStats {
+        let mut stats = IngestStats::default();
+        let mut batch = Vec::with_capacity(MAX_BATCH);
+        while let Ok(first) = rx.recv() {
+            let mut flush = None;
+            let mut next = Some(first);
+            while let Some(msg) = next {
+                match msg {
+                    IngestMsg::Row(row) => batch.push(row),
+                    IngestMsg::Flush(ack) => {
+                        flush = Some(ack);
+                        break;
+                    }
+                }
+                next = if batch.len() < MAX_BATCH {
+                    rx.try_recv().ok()
+                } else {
+                    None
+                };
+            }
+            if !batch.is_empty() {
+                let mut shard = self.write_shard(si);
+                let mut appended = 0;
+                for row in batch.drain(..) {
+                    match self.insert_locked(&mut shard, row) {
+                        Ok(_) => appended += 1,
+                        Err(e) => stats.rejected.push((row.id, e)),
+                    }
+                }
+                drop(shard);
+                stats.appended += appended;
+                stats.batches += 1;
+                self.counters[si].record_writes(appended as u64);
+            }
+            if let Some(ack) = flush {
+                let _ = ack.send(());
+            }
+        }
+        stats
+    }
+}
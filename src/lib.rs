@@ -6,45 +6,446 @@
 //!
 //! NOTE: This is a pedagogical sketch; harden with indices, generational arenas, error types,
 //! and proper concurrency primitives for production use.
+//!
+//! With the `no-unsafe` feature the kernels use only bounds-checked indexing and iterator loops,
+//! so Miri and sanitizer runs never reach an unchecked access. The `arrow`, `ffi` and `python`
+//! modules hand memory across a boundary and keep their `unsafe` either way.
+#![cfg_attr(feature = "no-unsafe", deny(unsafe_code))]
+
+/// Generate an `XSoA` kernel plus `XView`/`XMut` views for a user-defined `Copy` struct `X`.
+pub use ddd_dod_soa_derive::Soa;
 
 use crossbeam_utils::CachePadded;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt;
-use std::sync::Arc;
+use std::ops::Add;
+use std::sync::{Arc, OnceLock, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
 // ---------- Domain language (types & invariants) ----------
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+#[repr(transparent)]
 pub struct OrderId(pub u64);
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(u8)]
 pub enum Status {
     Pending,
     Completed,
     Cancelled,
 }
 
-#[derive(Copy, Clone, Debug)]
+impl Status {
+    /// Every status, in discriminant order.
+    pub const ALL: [Status; 3] = [Status::Pending, Status::Completed, Status::Cancelled];
+
+    /// Display name, as used by the text and columnar export formats.
+    pub fn name(self) -> &'static str {
+        match self {
+            Status::Pending => "Pending",
+            Status::Completed => "Completed",
+            Status::Cancelled => "Cancelled",
+        }
+    }
+
+    /// Inverse of `name`.
+    pub fn from_name(name: &str) -> Option<Status> {
+        Status::ALL.into_iter().find(|s| s.name() == name)
+    }
+
+    /// Lifecycle rule: only a pending order may move, and only to a terminal status.
+    pub fn can_transition_to(self, to: Status) -> bool {
+        self == Status::Pending && to != Status::Pending
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct Money(pub f64);
 
 impl Money {
     pub fn zero() -> Self {
         Money(0.0)
     }
-    pub fn add(self, other: Money) -> Money {
+}
+
+impl Add for Money {
+    type Output = Money;
+    fn add(self, other: Money) -> Money {
         Money(self.0 + other.0)
     }
 }
 
+mod access;
+pub mod adapters;
+mod aggregate;
+mod analytics;
+mod aos;
+mod audit;
+mod bitmap;
+mod blocks;
+mod builder;
+mod bulk;
+mod cached_aggregates;
+mod categorical;
+mod cdc;
+mod chunked;
+mod clock;
+pub mod column;
+mod composite_index;
+mod compression;
+mod concurrent;
+mod customer;
+mod database;
+mod dedup;
+mod delta;
+mod dyn_soa;
+mod events;
+mod exact;
+mod expiry;
+mod flags;
+mod gather;
+mod ingest;
+mod line_items;
+mod memory;
+mod money;
+mod option_column;
+mod outbox;
+mod pagination;
+mod payment;
+mod projection;
+mod query;
+mod query_cache;
+mod replication;
+mod routing;
+mod saga;
+mod schema;
+mod segment_ops;
+mod segments;
+mod selection;
+mod settlement;
+mod shard_stats;
+mod slice;
+mod specification;
+mod state_machine;
+mod stats;
+mod str_column;
+mod summation;
+mod transaction;
+mod validation;
+mod views;
+mod wal;
+mod write_buffer;
+pub use access::{OrderStoreReadOnly, RestrictedView};
+pub use aggregate::OrderAggregate;
+pub use analytics::{Aggregate, CustomerGroups, Histogram, StatusGroups, WindowAggregate};
+pub use aos::OrderAoS;
+#[cfg(feature = "async")]
+pub use async_repo::{AsyncOrderRepository, AsyncOrderStore};
+use audit::AuditColumns;
+pub use audit::{ActorId, AuditInfo, Context, InContext};
+pub use bitmap::Bitmap;
+pub use blocks::{Blocks, ColumnBlock, DEFAULT_BLOCK_ROWS};
+pub use builder::OrderStoreBuilder;
+pub use categorical::{CategoricalColumn, Category, CategoryDict};
+use cdc::ChangeFeed;
+pub use cdc::{ChangeEvent, ChangeKind, Field};
+pub use chunked::{ChunkedOrderSoA, ChunkedOrderView, ChunkedVec, CHUNK};
+use clock::SharedClock;
+pub use clock::{Clock, FixedClock, MonotonicClock, SystemClock};
+pub use column::Column;
+use composite_index::{CompositeIndex, IndexUpkeep, RowKey};
+pub use composite_index::{IndexColumn, IndexKey, IndexScan, IndexSpec};
+pub use compression::{CompressedSegment, DeltaColumn, DictColumn, RleColumn};
+pub use concurrent::{ConcurrentOrderStore, OrderSnapshot, DEFAULT_MERGE_THRESHOLD};
+#[cfg(feature = "csv")]
+pub use csv_io::{CsvError, CsvMapping};
+pub use customer::{
+    join_orders_customers, CustomerId, CustomerSoA, CustomerView, OrderCustomerView,
+};
+pub use database::{Database, DatabaseSnapshot, DbTransaction};
+pub use dedup::KeepPolicy;
+pub use delta::StoreDelta;
+pub use dyn_soa::{ColumnError, DynColumn, DynMut, DynSoA, DynView};
+pub use events::{EventLog, OrderEvent};
+pub use exact::EXACT_SCALE;
+#[cfg(feature = "std-thread")]
+pub use expiry::ExpirySweeper;
+pub use expiry::EXPIRY_INDEX;
+pub use flags::{FlagColumn, OrderFlags};
+pub use ingest::{IngestMsg, IngestPipeline, IngestStats};
+pub use line_items::{LineItem, LineItemSoA, LineItems};
+pub use memory::{ColumnMemory, MemoryReport};
+pub use money::{Currency, ExchangeRates, FixedRates, MoneyError, TypedMoney};
+pub use option_column::OptionColumn;
+pub use outbox::{Delivery, Outbox, Publisher};
+pub use pagination::{Cursor, InvalidCursor};
+pub use payment::{PaymentId, PaymentSoA, PaymentView};
+pub use projection::{cols, Project, Projection, Select};
+pub use query::Query;
+use query_cache::QueryCache;
+pub use query_cache::QueryCacheStats;
+pub use replication::{Follower, Leader, ReplicationError, ReplicationMsg};
+pub use routing::{HashRouter, ModuloRouter, RangeRouter, ShardRouter};
+pub use saga::{FulfillmentSaga, FulfillmentSteps, SagaState, StepOutcome};
+pub use schema::{
+    AddCurrencyColumn, AddCustomerColumn, Migration, Migrator, SchemaError, SchemaVersion,
+    Snapshot, SnapshotColumns,
+};
+pub use segments::{FrozenSegment, SegmentedOrderStore, ZoneMap, DEFAULT_SEGMENT_ROWS};
+pub use selection::{RowSelection, Selection};
+use settlement::Settlements;
+use shard_stats::ShardCounters;
+pub use shard_stats::{ShardStats, SkewReport};
+pub use slice::OrderSlice;
+pub use specification::{
+    AmountAtLeast, AmountBelow, And, CurrencyIs, ForCustomer, Not, Or, PlacedBetween,
+    Specification, StatusIs,
+};
+use state_machine::StateColumn;
+pub use state_machine::{StateId, StateMachine};
+pub use stats::{AmountStats, ColumnStats};
+pub use str_column::StrColumn;
+#[cfg(feature = "async")]
+pub use stream_ingest::BatchPolicy;
+pub use summation::Summation;
+pub use transaction::Transaction;
+use validation::Validators;
+pub use validation::{DomainError, NotInFuture, OrderCandidate, PositiveAmount, Validator};
+use views::Tracking;
+pub use views::{
+    CustomerTotals, MaterializedView, OpenOrderCount, RevenuePerDay, RowChange, ViewHandle,
+};
+pub use wal::{DurableOrderStore, WalError};
+pub use write_buffer::{WriteBuffer, WriteBufferStats};
+
+/// Owned order record for system boundaries (bulk loads, APIs, tests); the kernel stores it
+/// scattered across columns.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OrderRow {
+    pub id: OrderId,
+    pub amount: Money,
+    pub currency: Currency,
+    pub status: Status,
+    pub ts: u64,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub customer: CustomerId,
+}
+
+impl OrderRow {
+    /// Row in the default currency.
+    pub fn new(id: OrderId, amount: Money, status: Status, ts: u64) -> Self {
+        Self {
+            id,
+            amount,
+            currency: Currency::default(),
+            status,
+            ts,
+            customer: CustomerId::default(),
+        }
+    }
+
+    /// Builder: attach the ordering customer.
+    pub fn with_customer(mut self, customer: CustomerId) -> Self {
+        self.customer = customer;
+        self
+    }
+
+    /// What an upsert of `self` writes over `stored`: amount, status and timestamp are replaced
+    /// and the customer is kept. The currency is kept too unless `priced`, i.e. the amount came
+    /// as `TypedMoney` and is in `self.currency`.
+    pub(crate) fn upserted_over(self, stored: OrderRow, priced: bool) -> OrderRow {
+        OrderRow {
+            customer: stored.customer,
+            currency: if priced {
+                self.currency
+            } else {
+                stored.currency
+            },
+            ..self
+        }
+    }
+}
+
+impl From<OrderView<'_>> for OrderRow {
+    fn from(v: OrderView<'_>) -> Self {
+        Self {
+            id: v.id(),
+            amount: v.amount(),
+            currency: v.currency(),
+            status: v.status(),
+            ts: v.timestamp(),
+            customer: v.customer(),
+        }
+    }
+}
+
+/// Unvalidated append, like repeated `push_row`; see `extend_from_rows` for the checked path.
+impl Extend<OrderRow> for OrderSoA {
+    fn extend<I: IntoIterator<Item = OrderRow>>(&mut self, rows: I) {
+        let rows = rows.into_iter();
+        self.reserve(rows.size_hint().0);
+        rows.for_each(|row| {
+            self.push_row(row);
+        });
+    }
+}
+
+impl FromIterator<OrderRow> for OrderSoA {
+    fn from_iter<I: IntoIterator<Item = OrderRow>>(rows: I) -> Self {
+        let mut soa = OrderSoA::default();
+        soa.extend(rows);
+        soa
+    }
+}
+
+// ---------- Errors ----------
+
+/// Failures surfaced by the kernel and the façade instead of panicking.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StoreError {
+    /// A raw row index past the end of the columns.
+    IndexOutOfBounds { index: usize, len: usize },
+    /// The handle refers to a row that has since been moved or removed.
+    StaleHandle(RowHandle),
+    /// An order with this id is already stored.
+    DuplicateId(OrderId),
+    /// The order's amount is negative or not a number.
+    InvalidAmount(OrderId),
+    /// Columns handed to the kernel have different lengths.
+    ColumnLengthMismatch { expected: usize, found: usize },
+    /// No live order has this id.
+    UnknownId(OrderId),
+    /// The order's lifecycle does not allow moving `from` -> `to`.
+    IllegalTransition {
+        id: OrderId,
+        from: Status,
+        to: Status,
+    },
+    /// The state is not one of the store's `StateMachine`.
+    UnknownState(StateId),
+    /// The store's `StateMachine` does not allow moving the order `from` -> `to`.
+    IllegalStateTransition {
+        id: OrderId,
+        from: StateId,
+        to: StateId,
+    },
+    /// The refund would take the order's refunds past what was paid for it.
+    RefundExceedsPaid(OrderId),
+    /// The order would be fulfilled past its amount.
+    FulfillmentExceedsAmount(OrderId),
+    /// The order is no longer pending, so its terms are frozen.
+    OrderClosed(OrderId),
+    /// A registered `Validator` refused the order.
+    Invalid(OrderId, DomainError),
+    /// The order was written since the caller read version `expected`.
+    VersionConflict {
+        id: OrderId,
+        expected: u64,
+        found: u64,
+    },
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::IndexOutOfBounds { index, len } => {
+                write!(f, "row index {index} out of bounds (len {len})")
+            }
+            StoreError::StaleHandle(h) => write!(
+                f,
+                "stale row handle (index {}, generation {})",
+                h.index, h.generation
+            ),
+            StoreError::DuplicateId(id) => write!(f, "duplicate order id {}", id.0),
+            StoreError::InvalidAmount(id) => write!(f, "invalid amount for order {}", id.0),
+            StoreError::ColumnLengthMismatch { expected, found } => {
+                write!(
+                    f,
+                    "column length mismatch: expected {expected} rows, found {found}"
+                )
+            }
+            StoreError::UnknownId(id) => write!(f, "unknown order id {}", id.0),
+            StoreError::IllegalTransition { id, from, to } => {
+                write!(f, "order {} cannot move from {from:?} to {to:?}", id.0)
+            }
+            StoreError::UnknownState(s) => write!(f, "unknown state {}", s.0),
+            StoreError::IllegalStateTransition { id, from, to } => write!(
+                f,
+                "order {} cannot move from state {} to state {}",
+                id.0, from.0, to.0
+            ),
+            StoreError::RefundExceedsPaid(id) => {
+                write!(f, "refund exceeds the amount paid for order {}", id.0)
+            }
+            StoreError::FulfillmentExceedsAmount(id) => {
+                write!(f, "fulfillment exceeds the amount of order {}", id.0)
+            }
+            StoreError::OrderClosed(id) => write!(f, "order {} is closed", id.0),
+            StoreError::VersionConflict {
+                id,
+                expected,
+                found,
+            } => write!(
+                f,
+                "order {} is at version {found}, expected {expected}",
+                id.0
+            ),
+            StoreError::Invalid(id, e) => write!(f, "order {} is invalid: {e}", id.0),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {}
+
+// ---------- Row handles ----------
+
+/// Generational handle to a row in an `OrderSoA`.
+///
+/// A handle stays valid until the row it names is moved or dropped (e.g. by `retain`); after that
+/// it is rejected with `StoreError::StaleHandle` rather than resolving to whatever order now
+/// occupies the slot.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RowHandle {
+    pub index: usize,
+    pub generation: u32,
+}
+
 // ---------- SoA storage (kernel) ----------
 
 #[derive(Default, Clone)]
 pub struct OrderSoA {
     ids: Vec<OrderId>,
-    amounts: Vec<f64>,     // Money column
-    statuses: Vec<Status>, // Status column
-    timestamps: Vec<u64>,  // epoch millis
+    amounts: Vec<f64>,                 // Money column
+    statuses: Vec<Status>,             // Status column
+    timestamps: Vec<u64>,              // epoch millis
+    currencies: Vec<Currency>,         // currency of each amount
+    customers: Vec<CustomerId>,        // ordering customer (foreign key into a `CustomerSoA`)
+    item_offsets: Vec<(u32, u32)>,     // [start, end) of each order's rows in `items`
+    items: LineItemSoA,                // child SoA: line items of all orders
+    deleted: Bitmap,                   // tombstones: set bit = soft-deleted row
+    tombstones: usize,                 // number of set bits in `deleted`
+    generations: Vec<u32>,             // generation each row was written under
+    versions: Vec<u64>,                // per-row write counter for optimistic concurrency
+    generation: u32,                   // bumped whenever compaction moves or drops rows
+    id_index: HashMap<OrderId, usize>, // primary key -> latest row holding it
+    status_index_enabled: bool,
+    status_index: OnceLock<StatusIndex>, // built on first query, dropped on mutation
+    indexes: Vec<CompositeIndex>,        // composite secondary indexes, same lifecycle
+    audit: Option<AuditColumns>,         // created/updated stamps, if enabled
+    exact_amounts: Option<Vec<i64>>,     // fixed-point amounts (`EXACT_SCALE`), if enabled
+    states: Option<StateColumn>,         // lifecycle state codes, if a machine is configured
+    settlements: Option<Settlements>,    // refunded / fulfilled amounts, once any is recorded
 }
 
+/// Inverted index: for each `Status` (by discriminant), the sorted rows holding it.
+type StatusIndex = [Vec<usize>; Status::ALL.len()];
+
 impl fmt::Debug for OrderSoA {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("OrderSoA")
@@ -60,9 +461,83 @@ impl OrderSoA {
             amounts: Vec::with_capacity(cap),
             statuses: Vec::with_capacity(cap),
             timestamps: Vec::with_capacity(cap),
+            currencies: Vec::with_capacity(cap),
+            customers: Vec::with_capacity(cap),
+            item_offsets: Vec::with_capacity(cap),
+            items: LineItemSoA::default(),
+            deleted: Bitmap::with_capacity(cap),
+            tombstones: 0,
+            generations: Vec::with_capacity(cap),
+            versions: Vec::with_capacity(cap),
+            generation: 0,
+            id_index: HashMap::with_capacity(cap),
+            status_index_enabled: false,
+            status_index: OnceLock::new(),
+            indexes: Vec::new(),
+            audit: None,
+            exact_amounts: None,
+            states: None,
+            settlements: None,
+        }
+    }
+
+    /// Assemble a kernel from existing columns (snapshot loaders, interop), taking ownership of
+    /// the vectors without copying. All columns must have the same length; indexes are rebuilt.
+    pub fn from_columns(
+        ids: Vec<OrderId>,
+        amounts: Vec<f64>,
+        statuses: Vec<Status>,
+        timestamps: Vec<u64>,
+        currencies: Vec<Currency>,
+    ) -> Result<Self, StoreError> {
+        let n = ids.len();
+        let lens = [
+            amounts.len(),
+            statuses.len(),
+            timestamps.len(),
+            currencies.len(),
+        ];
+        if let Some(&found) = lens.iter().find(|&&len| len != n) {
+            return Err(StoreError::ColumnLengthMismatch { expected: n, found });
+        }
+        let mut soa = OrderSoA {
+            ids,
+            amounts,
+            statuses,
+            timestamps,
+            currencies,
+            customers: vec![CustomerId::default(); n],
+            item_offsets: vec![(0, 0); n],
+            deleted: Bitmap::filled(n, false),
+            generations: vec![0; n],
+            versions: vec![0; n],
+            ..OrderSoA::default()
+        };
+        soa.rebuild_id_index();
+        Ok(soa)
+    }
+
+    /// Builder: replace the customer column (every row defaults to `CustomerId::default()`),
+    /// e.g. after `from_columns`.
+    pub fn with_customers(mut self, customers: Vec<CustomerId>) -> Result<Self, StoreError> {
+        if customers.len() != self.len() {
+            return Err(StoreError::ColumnLengthMismatch {
+                expected: self.len(),
+                found: customers.len(),
+            });
         }
+        self.customers = customers;
+        Ok(self)
+    }
+
+    /// Builder flag: answer `find_by_status` from a lazily built status → rows index (O(k))
+    /// instead of scanning the status column (O(n)).
+    pub fn with_status_index(mut self) -> Self {
+        self.status_index_enabled = true;
+        self
     }
 
+    /// Physical row count, including soft-deleted rows awaiting `compact`.
     #[inline]
     pub fn len(&self) -> usize {
         self.ids.len()
@@ -72,85 +547,876 @@ impl OrderSoA {
         self.len() == 0
     }
 
-    /// Append a new row; returns its row index (a stable handle until removal/compaction).
-    pub fn push(&mut self, id: OrderId, amount: Money, status: Status, ts: u64) -> usize {
+    /// Rows that are not soft-deleted.
+    #[inline]
+    pub fn live_len(&self) -> usize {
+        self.len() - self.tombstones
+    }
+
+    /// Whether row `idx` is live (not tombstoned).
+    #[inline]
+    pub fn is_live(&self, idx: usize) -> bool {
+        self.tombstones == 0 || !self.deleted.get(idx)
+    }
+
+    /// Append a new row; returns a generational handle that goes stale on removal/compaction.
+    ///
+    /// The amount is recorded in the default currency (`Currency::USD`); see `push_money`.
+    pub fn push(&mut self, id: OrderId, amount: Money, status: Status, ts: u64) -> RowHandle {
+        self.push_row(OrderRow::new(id, amount, status, ts))
+    }
+
+    /// Append a row carrying an exact, currency-tagged amount.
+    pub fn push_money(
+        &mut self,
+        id: OrderId,
+        money: TypedMoney,
+        status: Status,
+        ts: u64,
+    ) -> RowHandle {
+        let handle = self.push_row(OrderRow {
+            currency: money.currency,
+            ..OrderRow::new(id, money.to_money(), status, ts)
+        });
+        if let Some(exact) = &mut self.exact_amounts {
+            *exact.last_mut().expect("just pushed") = exact::fixed_of_typed(money);
+        }
+        handle
+    }
+
+    /// Append an owned row unvalidated, like `push`, keeping its currency and customer.
+    pub fn push_row(&mut self, row: OrderRow) -> RowHandle {
+        let OrderRow {
+            id,
+            amount,
+            currency,
+            status,
+            ts,
+            customer,
+        } = row;
         self.ids.push(id);
         self.amounts.push(amount.0);
         self.statuses.push(status);
         self.timestamps.push(ts);
-        self.len() - 1
+        self.currencies.push(currency);
+        self.customers.push(customer);
+        let no_items = self.items.len() as u32;
+        self.item_offsets.push((no_items, no_items));
+        self.deleted.push(false);
+        self.generations.push(self.generation);
+        self.versions.push(0);
+        if let Some(a) = &mut self.audit {
+            a.push_new(1);
+        }
+        if let Some(exact) = &mut self.exact_amounts {
+            exact.push(exact::fixed_of_money(amount.0));
+        }
+        if let Some(s) = &mut self.states {
+            s.codes.push(s.machine.state_for(status).0);
+        }
+        if let Some(s) = &mut self.settlements {
+            s.push_zeros(1);
+        }
+        let idx = self.len() - 1;
+        self.id_index.insert(id, idx);
+        // Appends keep each posting list sorted, so a built index can be extended in place.
+        if let Some(ix) = self.status_index.get_mut() {
+            ix[status as usize].push(idx);
+        }
+        self.index_appended(idx..idx + 1);
+        self.handle_at(idx)
+    }
+
+    /// Overwrite row `index` with `row`'s fields (the id stays).
+    pub(crate) fn write_row(&mut self, index: usize, row: OrderRow) {
+        self.view_mut_at(index).write(row);
+    }
+
+    /// The checks `extend_from_rows` runs before writing anything.
+    pub(crate) fn validate_rows(&self, rows: &[OrderRow]) -> Result<(), StoreError> {
+        let mut seen = HashSet::with_capacity(rows.len());
+        for r in rows {
+            if self.contains_id(r.id) || !seen.insert(r.id) {
+                return Err(StoreError::DuplicateId(r.id));
+            }
+            if r.amount.0.is_nan() || r.amount.0 < 0.0 {
+                return Err(StoreError::InvalidAmount(r.id));
+            }
+        }
+        Ok(())
+    }
+
+    /// Bulk append. The whole batch is validated first (no id already stored or repeated within
+    /// the batch, no negative/NaN amounts); on error nothing is written. On success capacity is
+    /// reserved once and each column is extended in a single pass.
+    ///
+    /// Returns the row range the batch now occupies.
+    pub fn extend_from_rows(
+        &mut self,
+        rows: impl IntoIterator<Item = OrderRow>,
+    ) -> Result<std::ops::Range<usize>, StoreError> {
+        let rows: Vec<OrderRow> = rows.into_iter().collect();
+        self.validate_rows(&rows)?;
+
+        let start = self.len();
+        let n = rows.len();
+        self.ids.extend(rows.iter().map(|r| r.id));
+        self.amounts.extend(rows.iter().map(|r| r.amount.0));
+        self.statuses.extend(rows.iter().map(|r| r.status));
+        self.timestamps.extend(rows.iter().map(|r| r.ts));
+        self.currencies.extend(rows.iter().map(|r| r.currency));
+        self.customers.extend(rows.iter().map(|r| r.customer));
+        let no_items = self.items.len() as u32;
+        self.item_offsets
+            .extend(std::iter::repeat_n((no_items, no_items), n));
+        (0..n).for_each(|_| self.deleted.push(false));
+        self.generations
+            .extend(std::iter::repeat_n(self.generation, n));
+        self.versions.extend(std::iter::repeat_n(0, n));
+        if let Some(a) = &mut self.audit {
+            a.push_new(n);
+        }
+        if let Some(exact) = &mut self.exact_amounts {
+            exact.extend(rows.iter().map(|r| exact::fixed_of_money(r.amount.0)));
+        }
+        if let Some(s) = &mut self.states {
+            s.extend_from_statuses(&self.statuses[start..]);
+        }
+        if let Some(s) = &mut self.settlements {
+            s.push_zeros(n);
+        }
+        self.id_index.reserve(n);
+        for (i, r) in rows.iter().enumerate() {
+            self.id_index.insert(r.id, start + i);
+        }
+        if let Some(ix) = self.status_index.get_mut() {
+            for (i, r) in rows.iter().enumerate() {
+                ix[r.status as usize].push(start + i);
+            }
+        }
+        self.index_appended(start..start + n);
+        Ok(start..start + n)
+    }
+
+    /// O(1) primary-key lookup. If an id was pushed more than once, the latest row wins.
+    pub fn find_by_id(&self, id: OrderId) -> Option<OrderView<'_>> {
+        self.id_index.get(&id).map(|&i| self.view_at(i))
+    }
+
+    /// Whether any row carries `id`.
+    #[inline]
+    pub fn contains_id(&self, id: OrderId) -> bool {
+        self.id_index.contains_key(&id)
+    }
+
+    /// Zero-copy views of every row with status `s`, served from the status index if enabled.
+    pub fn find_by_status(&self, s: Status) -> impl Iterator<Item = OrderView<'_>> {
+        let indexed = self
+            .status_index_enabled
+            .then(|| self.status_index()[s as usize].iter().copied());
+        let scanned = indexed
+            .is_none()
+            .then(|| (0..self.len()).filter(move |&i| self.statuses[i] == s && self.is_live(i)));
+        indexed
+            .into_iter()
+            .flatten()
+            .chain(scanned.into_iter().flatten())
+            .map(|i| self.view_at(i))
+    }
+
+    fn status_index(&self) -> &StatusIndex {
+        self.status_index.get_or_init(|| {
+            let mut ix = StatusIndex::default();
+            for (i, &st) in self.statuses.iter().enumerate() {
+                if self.is_live(i) {
+                    ix[st as usize].push(i);
+                }
+            }
+            ix
+        })
+    }
+
+    fn rebuild_id_index(&mut self) {
+        self.id_index.clear();
+        for (i, &id) in self.ids.iter().enumerate() {
+            if self.is_live(i) {
+                self.id_index.insert(id, i);
+            }
+        }
+    }
+
+    /// Writes to row `idx` since it was appended; see `OrderStore::update_if_version`.
+    #[inline]
+    pub fn version_at(&self, idx: usize) -> u64 {
+        self.versions[idx]
+    }
+
+    /// Current handle for the row at `idx` (e.g. an index returned by `filter_indices`).
+    #[inline]
+    pub fn handle_at(&self, idx: usize) -> RowHandle {
+        RowHandle {
+            index: idx,
+            generation: self.generations[idx],
+        }
+    }
+
+    /// Resolve a handle to its current row index, rejecting stale handles (including handles to
+    /// soft-deleted rows).
+    #[inline]
+    pub fn resolve(&self, h: RowHandle) -> Result<usize, StoreError> {
+        match self.generations.get(h.index) {
+            Some(&g) if g == h.generation && self.is_live(h.index) => Ok(h.index),
+            _ => Err(StoreError::StaleHandle(h)),
+        }
+    }
+
+    /// Soft-delete: set the row's tombstone bit. O(1) apart from id-index upkeep; nothing moves,
+    /// so every other handle and index stays valid. Space is reclaimed by `compact`.
+    pub fn remove(&mut self, h: RowHandle) -> Result<(), StoreError> {
+        let idx = self.resolve(h)?;
+        self.deleted.set(idx, true);
+        self.tombstones += 1;
+        self.unindex_id(idx);
+        self.invalidate_indexes();
+        Ok(())
+    }
+
+    /// Point the id of row `idx`, which is going away, at an earlier live duplicate, if any.
+    fn unindex_id(&mut self, idx: usize) {
+        let id = self.ids[idx];
+        if self.id_index.get(&id) == Some(&idx) {
+            self.id_index.remove(&id);
+            if let Some(prev) = (0..idx)
+                .rev()
+                .find(|&i| self.ids[i] == id && self.is_live(i))
+            {
+                self.id_index.insert(id, prev);
+            }
+        }
+    }
+
+    /// Physically remove row `idx` in O(1) by moving the last row into its slot, returning it.
+    /// Handles to both rows go stale; every other row keeps its index.
+    pub fn swap_remove(&mut self, idx: usize) -> Result<OrderRow, StoreError> {
+        self.check_index(idx)?;
+        let row = OrderRow::from(self.view_at(idx));
+        if self.deleted.get(idx) {
+            self.tombstones -= 1;
+        } else {
+            self.unindex_id(idx);
+        }
+        let last = self.len() - 1;
+        let next_gen = self.generation.wrapping_add(1);
+        if idx != last {
+            let moved = self.ids[last];
+            if self.id_index.get(&moved) == Some(&last) {
+                self.id_index.insert(moved, idx);
+            }
+            self.deleted.set(idx, self.deleted.get(last));
+            self.generations[idx] = next_gen;
+        }
+        self.ids.swap_remove(idx);
+        self.amounts.swap_remove(idx);
+        self.statuses.swap_remove(idx);
+        self.timestamps.swap_remove(idx);
+        self.currencies.swap_remove(idx);
+        self.customers.swap_remove(idx);
+        self.item_offsets.swap_remove(idx);
+        self.versions.swap_remove(idx);
+        if let Some(a) = &mut self.audit {
+            a.swap_remove(idx);
+        }
+        if let Some(exact) = &mut self.exact_amounts {
+            exact.swap_remove(idx);
+        }
+        if let Some(s) = &mut self.states {
+            s.codes.swap_remove(idx);
+        }
+        if let Some(s) = &mut self.settlements {
+            s.swap_remove(idx);
+        }
+        self.generations.pop();
+        self.deleted.truncate(last);
+        // The freed last slot may be reused by a push; make old handles to it stale.
+        self.generation = next_gen;
+        self.invalidate_indexes();
+        Ok(row)
+    }
+
+    /// Physically remove row `idx` in O(n), shifting later rows down one slot so order is kept,
+    /// and return it. Handles to the removed and shifted rows go stale.
+    pub fn remove_at(&mut self, idx: usize) -> Result<OrderRow, StoreError> {
+        self.check_index(idx)?;
+        let row = OrderRow::from(self.view_at(idx));
+        if self.deleted.get(idx) {
+            self.tombstones -= 1;
+        }
+        let last = self.len() - 1;
+        let next_gen = self.generation.wrapping_add(1);
+        for i in idx..last {
+            self.deleted.set(i, self.deleted.get(i + 1));
+            self.generations[i] = next_gen;
+        }
+        self.ids.remove(idx);
+        self.amounts.remove(idx);
+        self.statuses.remove(idx);
+        self.timestamps.remove(idx);
+        self.currencies.remove(idx);
+        self.customers.remove(idx);
+        self.item_offsets.remove(idx);
+        self.versions.remove(idx);
+        if let Some(a) = &mut self.audit {
+            a.remove(idx);
+        }
+        if let Some(exact) = &mut self.exact_amounts {
+            exact.remove(idx);
+        }
+        if let Some(s) = &mut self.states {
+            s.codes.remove(idx);
+        }
+        if let Some(s) = &mut self.settlements {
+            s.remove(idx);
+        }
+        self.generations.pop();
+        self.deleted.truncate(last);
+        self.generation = next_gen;
+        self.rebuild_id_index();
+        self.invalidate_indexes();
+        Ok(row)
+    }
+
+    /// Physically drop tombstoned rows. Returns the remapping table: `remap[old] == Some(new)`
+    /// for surviving rows and `None` for reclaimed ones. Handles to moved rows go stale.
+    pub fn compact(&mut self) -> Vec<Option<usize>> {
+        let mut next = 0;
+        let remap = (0..self.len())
+            .map(|i| {
+                self.is_live(i).then(|| {
+                    next += 1;
+                    next - 1
+                })
+            })
+            .collect();
+        self.compact_where(|_, _| true);
+        remap
     }
 
     /// Zero-copy read-only view (no AoS materialization).
-    pub fn view(&self, idx: usize) -> OrderView<'_> {
-        OrderView { soa: self, idx }
+    pub fn view(&self, h: RowHandle) -> Result<OrderView<'_>, StoreError> {
+        self.resolve(h).map(|idx| self.view_at(idx))
     }
 
     /// Zero-copy mutable view (writes go back to columns).
-    pub fn view_mut(&mut self, idx: usize) -> OrderMut<'_> {
+    pub fn view_mut(&mut self, h: RowHandle) -> Result<OrderMut<'_>, StoreError> {
+        let idx = self.resolve(h)?;
+        Ok(self.view_mut_at(idx))
+    }
+
+    /// Read-only view by raw row index (kernel-internal; indices shift on compaction).
+    ///
+    /// Panics if `idx >= len()`; see `try_view_at`. A view is only ever built for an in-bounds
+    /// row, so its accessors never panic.
+    #[inline]
+    pub fn view_at(&self, idx: usize) -> OrderView<'_> {
+        assert!(
+            idx < self.len(),
+            "row {idx} out of bounds (len {})",
+            self.len()
+        );
+        OrderView { soa: self, idx }
+    }
+
+    /// Fallible `view_at`: rejects indices past the end instead of panicking.
+    pub fn try_view_at(&self, idx: usize) -> Result<OrderView<'_>, StoreError> {
+        self.check_index(idx)?;
+        Ok(self.view_at(idx))
+    }
+
+    /// Owned copy of row `idx` (live or not), or `None` past the end.
+    pub fn get_row(&self, idx: usize) -> Option<OrderRow> {
+        self.try_view_at(idx).ok().map(OrderRow::from)
+    }
+
+    /// Fallible `view_mut_at`: rejects indices past the end instead of panicking.
+    pub fn try_view_mut_at(&mut self, idx: usize) -> Result<OrderMut<'_>, StoreError> {
+        self.check_index(idx)?;
+        Ok(self.view_mut_at(idx))
+    }
+
+    #[inline]
+    fn check_index(&self, idx: usize) -> Result<(), StoreError> {
+        if idx < self.len() {
+            Ok(())
+        } else {
+            Err(StoreError::IndexOutOfBounds {
+                index: idx,
+                len: self.len(),
+            })
+        }
+    }
+
+    /// Mutable view by raw row index (kernel-internal; indices shift on compaction). Counts as
+    /// a write for the row's version.
+    pub fn view_mut_at(&mut self, idx: usize) -> OrderMut<'_> {
+        // Built indexes stay: the view's setters re-file the row in them.
+        let live = self.is_live(idx);
+        self.versions[idx] += 1;
+        self.touch_audit(idx);
         OrderMut {
             ids: &mut self.ids,
             amounts: &mut self.amounts,
+            exact_amounts: self.exact_amounts.as_deref_mut(),
+            settlements: &mut self.settlements,
             statuses: &mut self.statuses,
             timestamps: &mut self.timestamps,
+            currencies: &mut self.currencies,
+            customers: &mut self.customers,
             idx,
+            upkeep: live.then(|| IndexUpkeep {
+                row: idx,
+                status_index: self.status_index.get_mut(),
+                indexes: &mut self.indexes,
+            }),
+        }
+    }
+
+    /// Visit every live row through a mutable view, e.g. for bulk status changes. Rows are
+    /// visited in order; the status index is rebuilt on the next query. Every visited row's
+    /// version is bumped.
+    pub fn for_each_mut<F: FnMut(OrderMut<'_>)>(&mut self, mut f: F) {
+        self.invalidate_indexes();
+        let stamp = self.audit.as_ref().map(AuditColumns::stamp);
+        for idx in 0..self.len() {
+            if self.is_live(idx) {
+                self.versions[idx] += 1;
+                if let (Some(a), Some(stamp)) = (&mut self.audit, stamp) {
+                    a.touch(idx, stamp);
+                }
+                f(OrderMut {
+                    ids: &mut self.ids,
+                    amounts: &mut self.amounts,
+                    exact_amounts: self.exact_amounts.as_deref_mut(),
+                    settlements: &mut self.settlements,
+                    statuses: &mut self.statuses,
+                    timestamps: &mut self.timestamps,
+                    currencies: &mut self.currencies,
+                    customers: &mut self.customers,
+                    idx,
+                    upkeep: None,
+                });
+            }
+        }
+    }
+
+    /// Disjoint mutable borrows of the columns, for custom kernels that update several columns
+    /// in one pass. Ids stay read-only because the id index is keyed on them. Tombstoned rows are
+    /// included in the slices; check `ColumnsMut::is_live` where it matters.
+    pub fn columns_mut(&mut self) -> ColumnsMut<'_> {
+        // Statuses may be rewritten; the index is rebuilt on the next query. Any row may change,
+        // so every version moves.
+        self.invalidate_indexes();
+        self.versions.iter_mut().for_each(|v| *v += 1);
+        if let Some(a) = &mut self.audit {
+            let stamp = a.stamp();
+            (0..a.updated_at.len()).for_each(|i| a.touch(i, stamp));
+        }
+        ColumnsMut {
+            ids: &self.ids,
+            amounts: &mut self.amounts,
+            statuses: &mut self.statuses,
+            timestamps: &mut self.timestamps,
+            currencies: &mut self.currencies,
+            customers: &mut self.customers,
+            dead: (self.tombstones > 0).then_some(&self.deleted),
         }
     }
 
-    /// Iterate zero-copy views.
+    /// Iterate zero-copy views of live rows.
     pub fn iter(&self) -> impl Iterator<Item = OrderView<'_>> {
-        (0..self.len()).map(|i| self.view(i))
+        (0..self.len())
+            .filter(|&i| self.is_live(i))
+            .map(|i| self.view_at(i))
+    }
+
+    /// Tombstone mask for kernels, or `None` on the common no-deletes fast path.
+    #[inline]
+    pub fn dead_mask(&self) -> Option<&Bitmap> {
+        (self.tombstones > 0).then_some(&self.deleted)
+    }
+
+    // -------- Raw columns, for custom kernels --------
+    //
+    // Every slice has exactly `len()` elements and row `i` of one is row `i` of all the others.
+    // Tombstoned rows are included: skip the rows set in `dead_mask()`. Plain loops over the
+    // slices autovectorize like the built-in kernels below.
+
+    pub fn ids(&self) -> &[OrderId] {
+        &self.ids
+    }
+
+    pub fn amounts(&self) -> &[f64] {
+        &self.amounts
+    }
+
+    pub fn statuses(&self) -> &[Status] {
+        &self.statuses
+    }
+
+    pub fn timestamps(&self) -> &[u64] {
+        &self.timestamps
+    }
+
+    pub fn currencies(&self) -> &[Currency] {
+        &self.currencies
+    }
+
+    pub fn customers(&self) -> &[CustomerId] {
+        &self.customers
     }
 
     // -------- Hot-path kernels operating directly on columns (SoA) --------
 
-    /// Sum amounts for a given status.
+    /// Sum amounts for a given status (SIMD lanes with the `simd` feature, scalar otherwise),
+    /// with compensated summation; see `Summation`.
     pub fn sum_by_status(&self, status: Status) -> Money {
-        let mut acc = 0.0;
-        let n = self.len();
+        self.sum_by_status_with(status, Summation::default())
+    }
+
+    /// `sum_by_status`, accumulating per `mode`.
+    pub fn sum_by_status_with(&self, status: Status, mode: Summation) -> Money {
+        #[cfg(feature = "simd")]
+        if mode != Summation::Pairwise {
+            return Money(simd::sum_by_status(
+                &self.statuses,
+                &self.amounts,
+                self.dead_mask(),
+                status,
+                mode == Summation::Kahan,
+            ));
+        }
+        self.sum_by_status_scalar_with(status, mode)
+    }
+
+    /// Scalar reference for `sum_by_status`; also the fallback without the `simd` feature.
+    pub fn sum_by_status_scalar(&self, status: Status) -> Money {
+        self.sum_by_status_scalar_with(status, Summation::default())
+    }
+
+    fn sum_by_status_scalar_with(&self, status: Status, mode: Summation) -> Money {
+        let dead = self.dead_mask();
         // Tight loop over two columns; branch is predictable if status is common.
-        for i in 0..n {
-            // SAFETY: i < n for all columns; we keep columns the same length.
-            if unsafe { *self.statuses.get_unchecked(i) } == status {
-                acc += unsafe { *self.amounts.get_unchecked(i) };
+        #[cfg(not(feature = "no-unsafe"))]
+        if dead.is_none() && mode == Summation::Naive {
+            let mut acc = 0.0;
+            for i in 0..self.len() {
+                // SAFETY: i < len for all columns; we keep columns the same length.
+                if unsafe { *self.statuses.get_unchecked(i) } == status {
+                    acc += unsafe { *self.amounts.get_unchecked(i) };
+                }
             }
+            return Money(acc);
         }
-        Money(acc)
+        // Otherwise the generic kernel: its zip bounds both columns once, so a naive sum still
+        // vectorizes without unchecked reads.
+        Money(column::sum_where(
+            &self.statuses,
+            &self.amounts,
+            dead,
+            mode,
+            |s| s == status,
+        ))
     }
 
     /// Filter to indices where amount >= threshold and status matches.
     pub fn filter_indices(&self, min_amount: Money, status: Status) -> Vec<usize> {
-        let mut out = Vec::new();
-        let n = self.len();
-        for i in 0..n {
-            if self.amounts[i] >= min_amount.0 && self.statuses[i] == status {
-                out.push(i);
+        #[cfg(feature = "simd")]
+        return simd::filter_indices(
+            &self.statuses,
+            &self.amounts,
+            self.dead_mask(),
+            min_amount.0,
+            status,
+        );
+        #[cfg(not(feature = "simd"))]
+        self.filter_indices_scalar(min_amount, status)
+    }
+
+    /// Exact per-currency total for a status: rows in other currencies are skipped, and the sum
+    /// is accumulated in checked minor units.
+    pub fn sum_minor_by_status(
+        &self,
+        status: Status,
+        currency: Currency,
+    ) -> Result<TypedMoney, MoneyError> {
+        let mut acc = TypedMoney::zero(currency);
+        for i in 0..self.len() {
+            if self.statuses[i] == status && self.currencies[i] == currency && self.is_live(i) {
+                acc = acc.checked_add(self.minor_at(i, currency))?;
+            }
+        }
+        Ok(acc)
+    }
+
+    /// Exact totals for a status, one per currency present among its live rows, in currency
+    /// order. Nothing is converted, so no two currencies are ever added together.
+    pub fn sum_by_status_per_currency(
+        &self,
+        status: Status,
+    ) -> Result<BTreeMap<Currency, TypedMoney>, MoneyError> {
+        let mut totals: BTreeMap<Currency, TypedMoney> = BTreeMap::new();
+        for i in 0..self.len() {
+            if self.statuses[i] == status && self.is_live(i) {
+                let currency = self.currencies[i];
+                let amount = self.minor_at(i, currency);
+                let total = totals.entry(currency).or_insert(TypedMoney::zero(currency));
+                *total = total.checked_add(amount)?;
             }
         }
-        out
+        Ok(totals)
+    }
+
+    /// Total for a status in `target`: each currency's exact total is converted once through
+    /// `rates`, then the results are summed. Fails with `MoneyError::MissingRate` for the first
+    /// currency `rates` cannot convert.
+    pub fn sum_by_status_in(
+        &self,
+        status: Status,
+        target: Currency,
+        rates: &dyn ExchangeRates,
+    ) -> Result<TypedMoney, MoneyError> {
+        self.sum_by_status_per_currency(status)?
+            .into_values()
+            .try_fold(TypedMoney::zero(target), |acc, total| {
+                acc.checked_add(total.convert(target, rates)?)
+            })
+    }
+
+    /// Scalar reference for `filter_indices`; also the fallback without the `simd` feature.
+    pub fn filter_indices_scalar(&self, min_amount: Money, status: Status) -> Vec<usize> {
+        column::filter_where(&self.statuses, &self.amounts, self.dead_mask(), |s, a| {
+            s == status && a >= min_amount.0
+        })
     }
 
     /// Compact in-place by retaining rows whose predicate returns true. Keeps columns aligned.
+    /// Tombstoned rows are reclaimed as well; the predicate only sees live rows.
+    ///
+    /// Handles to rows that were moved or dropped become stale; rows that stay put keep theirs.
     pub fn retain<F: Fn(OrderView<'_>) -> bool>(&mut self, f: F) {
+        self.compact_where(|soa, i| f(soa.view_at(i)));
+    }
+
+    /// `retain` that reports what it did: the live rows the predicate rejected, in order, and the
+    /// remapping table `remap[old] == Some(new)` (as returned by `compact`) for fixing up side
+    /// tables keyed by row index.
+    pub fn retain_with_remap<F: Fn(OrderView<'_>) -> bool>(
+        &mut self,
+        f: F,
+    ) -> (Vec<OrderRow>, Vec<Option<usize>>) {
+        let keep: Vec<bool> = (0..self.len())
+            .map(|i| self.is_live(i) && f(self.view_at(i)))
+            .collect();
+        let mut removed = Vec::new();
+        let mut next = 0;
+        let remap = keep
+            .iter()
+            .enumerate()
+            .map(|(i, &k)| {
+                if !k && self.is_live(i) {
+                    removed.push(OrderRow::from(self.view_at(i)));
+                }
+                k.then(|| {
+                    next += 1;
+                    next - 1
+                })
+            })
+            .collect();
+        self.compact_where(|_, i| keep[i]);
+        (removed, remap)
+    }
+
+    /// Permutation that visits rows in ascending timestamp order (stable; includes tombstoned
+    /// rows, which `iter_permuted` skips). Nothing moves, so handles stay valid.
+    pub fn argsort_by_timestamp(&self) -> Vec<usize> {
+        let mut perm: Vec<usize> = (0..self.len()).collect();
+        perm.sort_by_key(|&i| self.timestamps[i]);
+        perm
+    }
+
+    /// Permutation that visits rows in ascending amount order (stable, `f64::total_cmp`).
+    pub fn argsort_by_amount(&self) -> Vec<usize> {
+        let mut perm: Vec<usize> = (0..self.len()).collect();
+        perm.sort_by(|&a, &b| self.amounts[a].total_cmp(&self.amounts[b]));
+        perm
+    }
+
+    /// Zero-copy iteration in the order given by a permutation from `argsort_*`.
+    pub fn iter_permuted<'a>(&'a self, perm: &'a [usize]) -> impl Iterator<Item = OrderView<'a>> {
+        perm.iter()
+            .copied()
+            .filter(|&i| self.is_live(i))
+            .map(|i| self.view_at(i))
+    }
+
+    /// Physically reorder every column by ascending timestamp (stable). Handles to rows that
+    /// moved go stale.
+    pub fn sort_by_timestamp(&mut self) {
+        let perm = self.argsort_by_timestamp();
+        self.apply_permutation(&perm);
+    }
+
+    /// Physically reorder every column by ascending amount (stable). Handles to rows that moved
+    /// go stale.
+    pub fn sort_by_amount(&mut self) {
+        let perm = self.argsort_by_amount();
+        self.apply_permutation(&perm);
+    }
+
+    /// Gather every column through `perm` (`new[i] = old[perm[i]]`), keeping them aligned.
+    fn apply_permutation(&mut self, perm: &[usize]) {
+        debug_assert_eq!(perm.len(), self.len());
+        if perm.iter().enumerate().all(|(i, &p)| i == p) {
+            return;
+        }
+        fn gather<T: Copy>(col: &[T], perm: &[usize]) -> Vec<T> {
+            perm.iter().map(|&i| col[i]).collect()
+        }
+        let next_gen = self.generation.wrapping_add(1);
+        self.ids = gather(&self.ids, perm);
+        self.amounts = gather(&self.amounts, perm);
+        self.statuses = gather(&self.statuses, perm);
+        self.timestamps = gather(&self.timestamps, perm);
+        self.currencies = gather(&self.currencies, perm);
+        self.customers = gather(&self.customers, perm);
+        self.item_offsets = gather(&self.item_offsets, perm);
+        self.versions = gather(&self.versions, perm);
+        if let Some(a) = &mut self.audit {
+            a.gather(perm);
+        }
+        if let Some(exact) = &mut self.exact_amounts {
+            *exact = gather(exact, perm);
+        }
+        if let Some(s) = &mut self.states {
+            s.codes = gather(&s.codes, perm);
+        }
+        if let Some(s) = &mut self.settlements {
+            *s = s.gathered(perm);
+        }
+        let mut deleted = Bitmap::with_capacity(perm.len());
+        for (i, &p) in perm.iter().enumerate() {
+            deleted.push(self.deleted.get(p));
+            self.generations[i] = if i == p {
+                self.generations[p]
+            } else {
+                next_gen
+            };
+        }
+        self.deleted = deleted;
+        self.generation = next_gen;
+        self.rebuild_id_index();
+        self.invalidate_indexes();
+    }
+
+    /// Stable in-place compaction shared by the retain family: keeps live rows for which
+    /// `keep(self, row)` is true, in order.
+    fn compact_where(&mut self, mut keep: impl FnMut(&OrderSoA, usize) -> bool) {
+        let n = self.len();
+        let next_gen = self.generation.wrapping_add(1);
         let mut write = 0usize;
-        for read in 0..self.len() {
-            if f(self.view(read)) {
+        for read in 0..n {
+            if self.is_live(read) && keep(self, read) {
                 if write != read {
                     self.ids[write] = self.ids[read];
                     self.amounts[write] = self.amounts[read];
                     self.statuses[write] = self.statuses[read];
                     self.timestamps[write] = self.timestamps[read];
+                    self.currencies[write] = self.currencies[read];
+                    self.customers[write] = self.customers[read];
+                    self.item_offsets[write] = self.item_offsets[read];
+                    self.versions[write] = self.versions[read];
+                    if let Some(a) = &mut self.audit {
+                        let info = a.get(read);
+                        a.set(write, info);
+                    }
+                    if let Some(exact) = &mut self.exact_amounts {
+                        exact[write] = exact[read];
+                    }
+                    if let Some(s) = &mut self.states {
+                        s.codes[write] = s.codes[read];
+                    }
+                    if let Some(s) = &mut self.settlements {
+                        s.copy_row(read, write);
+                    }
+                    self.generations[write] = next_gen;
                 }
                 write += 1;
             }
         }
+        if write == n {
+            return;
+        }
+        // Slots past `write` may be reused by later pushes; make old handles to them stale.
+        self.generation = next_gen;
         self.ids.truncate(write);
         self.amounts.truncate(write);
         self.statuses.truncate(write);
         self.timestamps.truncate(write);
+        self.currencies.truncate(write);
+        self.customers.truncate(write);
+        self.item_offsets.truncate(write);
+        self.versions.truncate(write);
+        if let Some(a) = &mut self.audit {
+            a.truncate(write);
+        }
+        if let Some(exact) = &mut self.exact_amounts {
+            exact.truncate(write);
+        }
+        if let Some(s) = &mut self.states {
+            s.codes.truncate(write);
+        }
+        if let Some(s) = &mut self.settlements {
+            s.truncate(write);
+        }
+        self.compact_items();
+        self.generations.truncate(write);
+        // Only live rows survive, so the compacted prefix carries no tombstones.
+        self.deleted.truncate(write);
+        self.deleted.clear_all();
+        self.tombstones = 0;
+        self.rebuild_id_index();
+        self.invalidate_indexes();
     }
 }
 
+#[cfg(feature = "arrow")]
+#[cfg_attr(feature = "no-unsafe", allow(unsafe_code))]
+pub mod arrow;
+#[cfg(feature = "async")]
+mod async_repo;
+#[cfg(feature = "bench-support")]
+pub mod bench_support;
+#[cfg(feature = "csv")]
+mod csv_io;
+#[cfg(feature = "ffi")]
+#[cfg_attr(feature = "no-unsafe", allow(unsafe_code))]
+pub mod ffi;
+#[cfg(any(test, feature = "fuzzing"))]
+#[doc(hidden)]
+pub mod fuzzing;
+#[cfg(feature = "parallel")]
+mod parallel;
+#[cfg(feature = "parquet")]
+mod parquet;
+#[cfg(feature = "python")]
+#[cfg_attr(feature = "no-unsafe", allow(unsafe_code))]
+pub mod python;
+#[cfg(feature = "serde")]
+mod serde_impl;
+#[cfg(feature = "simd")]
+mod simd;
+#[cfg(feature = "async")]
+mod stream_ingest;
+
 // ---------- Zero-copy row views (AoS façade without allocation) ----------
 
 #[derive(Copy, Clone)]
@@ -158,6 +1424,17 @@ pub struct OrderView<'a> {
     soa: &'a OrderSoA,
     idx: usize,
 }
+
+impl fmt::Debug for OrderView<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OrderView")
+            .field("id", &self.id())
+            .field("amount", &self.amount())
+            .field("status", &self.status())
+            .field("timestamp", &self.timestamp())
+            .finish()
+    }
+}
 impl<'a> OrderView<'a> {
     #[inline]
     pub fn id(&self) -> OrderId {
@@ -175,104 +1452,676 @@ impl<'a> OrderView<'a> {
     pub fn timestamp(&self) -> u64 {
         self.soa.timestamps[self.idx]
     }
+    #[inline]
+    pub fn currency(&self) -> Currency {
+        self.soa.currencies[self.idx]
+    }
+    #[inline]
+    pub fn customer(&self) -> CustomerId {
+        self.soa.customers[self.idx]
+    }
+    /// Exact amount in minor units of the row's currency.
+    #[inline]
+    pub fn money(&self) -> TypedMoney {
+        self.soa.minor_at(self.idx, self.currency())
+    }
+    /// See `OrderSoA::version_at`.
+    #[inline]
+    pub fn version(&self) -> u64 {
+        self.soa.versions[self.idx]
+    }
 }
 
 pub struct OrderMut<'a> {
     ids: &'a mut [OrderId],
     amounts: &'a mut [f64],
+    exact_amounts: Option<&'a mut [i64]>,
+    settlements: &'a mut Option<Settlements>,
     statuses: &'a mut [Status],
     timestamps: &'a mut [u64],
+    currencies: &'a mut [Currency],
+    customers: &'a mut [CustomerId],
     idx: usize,
+    /// Built indexes the setters re-file the row in; `None` where the caller dropped them.
+    upkeep: Option<IndexUpkeep<'a>>,
 }
 impl<'a> OrderMut<'a> {
+    /// Run a column write, then move the row within the built indexes.
+    #[inline]
+    fn rekeyed(&mut self, write: impl FnOnce(&mut Self)) {
+        if self.upkeep.is_none() {
+            return write(self);
+        }
+        let before = self.row_key();
+        write(self);
+        let after = self.row_key();
+        if let Some(upkeep) = &mut self.upkeep {
+            upkeep.refile(before, after);
+        }
+    }
+    /// Overwrite the row's fields with `row`'s (the id stays).
+    pub(crate) fn write(&mut self, row: OrderRow) {
+        self.set_amount(row.amount);
+        self.rekeyed(|o| o.currencies[o.idx] = row.currency);
+        self.set_status(row.status);
+        self.set_timestamp(row.ts);
+        self.set_customer(row.customer);
+    }
+    fn row_key(&self) -> RowKey {
+        let i = self.idx;
+        RowKey {
+            status: self.statuses[i],
+            currency: self.currencies[i],
+            customer: self.customers[i],
+            ts: self.timestamps[i],
+            amount: self.amounts[i],
+        }
+    }
     #[inline]
     pub fn set_amount(&mut self, m: Money) {
-        self.amounts[self.idx] = m.0;
+        self.rekeyed(|o| {
+            o.amounts[o.idx] = m.0;
+            if let Some(exact) = &mut o.exact_amounts {
+                exact[o.idx] = exact::fixed_of_money(m.0);
+            }
+        });
     }
     #[inline]
     pub fn set_status(&mut self, s: Status) {
-        self.statuses[self.idx] = s;
+        self.rekeyed(|o| o.statuses[o.idx] = s);
     }
     #[inline]
     pub fn set_timestamp(&mut self, t: u64) {
-        self.timestamps[self.idx] = t;
+        self.rekeyed(|o| o.timestamps[o.idx] = t);
+    }
+    #[inline]
+    pub fn set_customer(&mut self, c: CustomerId) {
+        self.rekeyed(|o| o.customers[o.idx] = c);
+    }
+    /// Overwrite both the amount and its currency.
+    #[inline]
+    pub fn set_money(&mut self, m: TypedMoney) {
+        self.rekeyed(|o| {
+            o.amounts[o.idx] = m.to_money().0;
+            if let Some(exact) = &mut o.exact_amounts {
+                exact[o.idx] = exact::fixed_of_typed(m);
+            }
+            o.currencies[o.idx] = m.currency;
+        });
     }
     #[inline]
     pub fn id(&self) -> OrderId {
         self.ids[self.idx]
     }
+    #[inline]
+    pub fn amount(&self) -> Money {
+        Money(self.amounts[self.idx])
+    }
+    #[inline]
+    pub fn status(&self) -> Status {
+        self.statuses[self.idx]
+    }
+    #[inline]
+    pub fn timestamp(&self) -> u64 {
+        self.timestamps[self.idx]
+    }
+    #[inline]
+    pub fn currency(&self) -> Currency {
+        self.currencies[self.idx]
+    }
+    #[inline]
+    pub fn customer(&self) -> CustomerId {
+        self.customers[self.idx]
+    }
+}
+
+/// Split borrow of an `OrderSoA`'s columns; see `OrderSoA::columns_mut`.
+pub struct ColumnsMut<'a> {
+    pub ids: &'a [OrderId],
+    pub amounts: &'a mut [f64],
+    pub statuses: &'a mut [Status],
+    pub timestamps: &'a mut [u64],
+    pub currencies: &'a mut [Currency],
+    pub customers: &'a mut [CustomerId],
+    dead: Option<&'a Bitmap>,
+}
+
+impl ColumnsMut<'_> {
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    #[inline]
+    pub fn is_live(&self, i: usize) -> bool {
+        self.dead.is_none_or(|d| !d.get(i))
+    }
 }
 
 // ---------- Repository-like façade (DDD-friendly API) ----------
 
+/// What `OrderStore::add` does with an id that is already stored.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum IdPolicy {
+    /// Fail with `StoreError::DuplicateId`.
+    Reject,
+    /// Overwrite the stored order's fields in place.
+    Upsert,
+    /// Append another row; `find_by_id` resolves to the latest one.
+    #[default]
+    AllowDuplicates,
+}
+
 #[derive(Clone, Default)]
 pub struct OrderStore {
     inner: Arc<OrderSoA>,
+    id_policy: IdPolicy,
+    changes: ChangeFeed,
+    validators: Validators,
+    tracking: Tracking,
+    outbox: Option<Outbox>,
+    clock: SharedClock,
+    context: Context,
+    epoch: u64,
+    queries: Option<QueryCache>,
+}
+
+/// Wrap an existing kernel, e.g. one loaded from a snapshot.
+impl From<OrderSoA> for OrderStore {
+    fn from(soa: OrderSoA) -> Self {
+        Self {
+            inner: Arc::new(soa),
+            id_policy: IdPolicy::default(),
+            changes: ChangeFeed::default(),
+            validators: Validators::default(),
+            tracking: Tracking::default(),
+            outbox: None,
+            clock: SharedClock::default(),
+            context: Context::default(),
+            epoch: 0,
+            queries: None,
+        }
+    }
 }
 
 impl OrderStore {
     pub fn new() -> Self {
-        Self {
-            inner: Arc::new(OrderSoA::default()),
+        Self::default()
+    }
+
+    /// Builder flag: how `add` / `add_money` treat an id that is already stored.
+    pub fn with_id_policy(mut self, policy: IdPolicy) -> Self {
+        self.id_policy = policy;
+        self
+    }
+
+    /// Builder flag: check a domain invariant on every add and update; see `Validator`.
+    pub fn with_validator(mut self, v: impl Validator + 'static) -> Self {
+        self.validators.push(v);
+        self
+    }
+
+    /// Builder flag: serve `find_by_status` from an inverted status index.
+    pub fn with_status_index(mut self) -> Self {
+        let soa = std::mem::take(query_cache::write(
+            &mut self.inner,
+            &mut self.epoch,
+            self.context,
+        ));
+        self.inner = Arc::new(soa.with_status_index());
+        self
+    }
+
+    /// Append via copy-on-write on the Arc (cheap shared reads, safe mutation). A stored id is
+    /// handled per the store's `IdPolicy`; only `Reject` can fail.
+    pub fn add(
+        &mut self,
+        id: OrderId,
+        amount: Money,
+        status: Status,
+        ts: u64,
+    ) -> Result<RowHandle, StoreError> {
+        self.insert(self.id_policy, OrderRow::new(id, amount, status, ts), false)
+    }
+
+    /// Append an order with an exact, currency-tagged amount; `IdPolicy` applies as for `add`.
+    pub fn add_money(
+        &mut self,
+        id: OrderId,
+        money: TypedMoney,
+        status: Status,
+        ts: u64,
+    ) -> Result<RowHandle, StoreError> {
+        let row = OrderRow {
+            currency: money.currency,
+            ..OrderRow::new(id, money.to_money(), status, ts)
+        };
+        self.insert(self.id_policy, row, true)
+    }
+
+    /// Insert the order, or overwrite amount, status and timestamp of the stored one, whatever
+    /// the store's `IdPolicy`. Fails only if a validator rejects the order.
+    pub fn upsert(
+        &mut self,
+        id: OrderId,
+        amount: Money,
+        status: Status,
+        ts: u64,
+    ) -> Result<RowHandle, StoreError> {
+        self.insert(
+            IdPolicy::Upsert,
+            OrderRow::new(id, amount, status, ts),
+            false,
+        )
+    }
+
+    /// Modify the live order `id` in place through a mutable view. If a validator rejects the
+    /// result, the row is restored and the error returned.
+    pub fn update_with<R>(
+        &mut self,
+        id: OrderId,
+        f: impl FnOnce(OrderMut<'_>) -> R,
+    ) -> Result<R, StoreError> {
+        let idx = self.index_of(id)?;
+        let owned = query_cache::write(&mut self.inner, &mut self.epoch, self.context);
+        let version = owned.versions[idx];
+        let audit = owned.view_at(idx).audit();
+        let before =
+            (self.changes.is_active() || !self.validators.is_empty() || self.tracking.is_active())
+                .then(|| OrderRow::from(owned.view_at(idx)));
+        let out = f(owned.view_mut_at(idx));
+        if let Some(before) = before {
+            let after = OrderRow::from(owned.view_at(idx));
+            if let Err(e) = self.validators.check(after, Some(before)) {
+                owned.write_row(idx, before);
+                owned.versions[idx] = version;
+                if let (Some(a), Some(info)) = (&mut owned.audit, audit) {
+                    a.set(idx, info);
+                }
+                return Err(e);
+            }
+            let handle = owned.handle_at(idx);
+            self.changes.emit_diff(handle, &before, &after);
+            self.track(RowChange {
+                handle,
+                before: Some(before),
+                after: Some(after),
+            });
+        }
+        Ok(out)
+    }
+
+    /// `update_with`, applied only if the order is still at `expected` version (as read from
+    /// `OrderView::version`), so a writer that read a row can detect that another write got in
+    /// first instead of overwriting it.
+    pub fn update_if_version<R>(
+        &mut self,
+        id: OrderId,
+        expected: u64,
+        f: impl FnOnce(OrderMut<'_>) -> R,
+    ) -> Result<R, StoreError> {
+        let found = self.inner.versions[self.index_of(id)?];
+        if found != expected {
+            return Err(StoreError::VersionConflict {
+                id,
+                expected,
+                found,
+            });
+        }
+        self.update_with(id, f)
+    }
+
+    /// Store `row` under `policy`: append it, or for `Upsert` write it over the stored order as
+    /// `OrderRow::upserted_over` describes.
+    fn insert(
+        &mut self,
+        policy: IdPolicy,
+        row: OrderRow,
+        priced: bool,
+    ) -> Result<RowHandle, StoreError> {
+        let id = row.id;
+        let existing = self.inner.id_index.get(&id).copied();
+        match (existing, policy) {
+            (Some(_), IdPolicy::Reject) => Err(StoreError::DuplicateId(id)),
+            (Some(idx), IdPolicy::Upsert) => {
+                let before = OrderRow::from(self.inner.view_at(idx));
+                let after = row.upserted_over(before, priced);
+                self.validators.check(after, Some(before))?;
+                let owned = query_cache::write(&mut self.inner, &mut self.epoch, self.context);
+                owned.write_row(idx, after);
+                let h = owned.handle_at(idx);
+                self.changes.emit_diff(h, &before, &after);
+                self.track(RowChange {
+                    handle: h,
+                    before: Some(before),
+                    after: Some(after),
+                });
+                Ok(h)
+            }
+            _ => {
+                self.validators.check(row, None)?;
+                let owned = query_cache::write(&mut self.inner, &mut self.epoch, self.context);
+                let h = owned.push_row(row);
+                self.changes.emit(h, ChangeKind::Inserted);
+                self.track(RowChange {
+                    handle: h,
+                    before: None,
+                    after: Some(row),
+                });
+                Ok(h)
+            }
+        }
+    }
+
+    /// Like `add`, but rejects an id that is already stored whatever the `IdPolicy`.
+    pub fn try_add(
+        &mut self,
+        id: OrderId,
+        amount: Money,
+        status: Status,
+        ts: u64,
+    ) -> Result<RowHandle, StoreError> {
+        if self.inner.contains_id(id) {
+            return Err(StoreError::DuplicateId(id));
+        }
+        self.add(id, amount, status, ts)
+    }
+
+    /// Soft-delete an order; see `OrderSoA::remove`.
+    pub fn remove(&mut self, h: RowHandle) -> Result<(), StoreError> {
+        let row = OrderRow::from(self.inner.view(h)?);
+        query_cache::write(&mut self.inner, &mut self.epoch, self.context).remove(h)?;
+        self.changes.emit(h, ChangeKind::Deleted);
+        self.track(RowChange {
+            handle: h,
+            before: Some(row),
+            after: None,
+        });
+        Ok(())
+    }
+
+    /// Reclaim soft-deleted rows; see `OrderSoA::compact`.
+    pub fn compact(&mut self) -> Vec<Option<usize>> {
+        let owned = query_cache::write(&mut self.inner, &mut self.epoch, self.context);
+        if !self.changes.is_active() {
+            return owned.compact();
+        }
+        let old: Vec<RowHandle> = (0..owned.len()).map(|i| owned.handle_at(i)).collect();
+        let remap = owned.compact();
+        for (i, new) in remap.iter().enumerate() {
+            if let Some(new) = new.filter(|&new| new != i) {
+                self.changes.emit(old[i], ChangeKind::Deleted);
+                self.changes
+                    .emit(owned.handle_at(new), ChangeKind::Inserted);
+            }
         }
+        remap
     }
 
-    /// Append via copy-on-write on the Arc (cheap shared reads, safe mutation).
-    pub fn add(&mut self, id: OrderId, amount: Money, status: Status, ts: u64) -> usize {
-        let owned = Arc::make_mut(&mut self.inner);
-        owned.push(id, amount, status, ts)
+    /// Validated bulk insert; see `OrderSoA::extend_from_rows`. All-or-nothing.
+    pub fn add_batch(
+        &mut self,
+        rows: impl IntoIterator<Item = OrderRow>,
+    ) -> Result<std::ops::Range<usize>, StoreError> {
+        let rows: Vec<OrderRow> = rows.into_iter().collect();
+        rows.iter()
+            .try_for_each(|&r| self.validators.check(r, None))?;
+        let owned = query_cache::write(&mut self.inner, &mut self.epoch, self.context);
+        let range = owned.extend_from_rows(rows)?;
+        for i in range.clone() {
+            self.changes.emit(owned.handle_at(i), ChangeKind::Inserted);
+        }
+        if self.tracking.is_active() {
+            for i in range.clone() {
+                let change = RowChange {
+                    handle: self.inner.handle_at(i),
+                    before: None,
+                    after: Some(OrderRow::from(self.inner.view_at(i))),
+                };
+                self.track(change);
+            }
+        }
+        Ok(range)
     }
 
     /// Zero-copy query returning views.
     pub fn find_by_status(&self, s: Status) -> impl Iterator<Item = OrderView<'_>> {
-        (0..self.inner.len())
-            .map(|i| self.inner.view(i))
-            .filter(move |v| v.status() == s)
+        self.inner.find_by_status(s)
+    }
+
+    /// Zero-copy views of orders with `from <= timestamp < to`.
+    pub fn find_in_time_range(&self, from: u64, to: u64) -> impl Iterator<Item = OrderView<'_>> {
+        self.inner.filter_by_time_range(from, to)
+    }
+
+    /// Zero-copy lookup by domain identity.
+    pub fn find_by_id(&self, id: OrderId) -> Option<OrderView<'_>> {
+        self.inner.find_by_id(id)
     }
 
-    /// Expose kernel for batch ops.
+    /// Row of the live order `id`.
+    fn index_of(&self, id: OrderId) -> Result<usize, StoreError> {
+        self.inner
+            .id_index
+            .get(&id)
+            .copied()
+            .ok_or(StoreError::UnknownId(id))
+    }
+
+    /// Bulk in-place update of every live order; see `OrderSoA::for_each_mut`.
+    pub fn for_each_mut<F: FnMut(OrderMut<'_>)>(&mut self, f: F) {
+        let owned = query_cache::write(&mut self.inner, &mut self.epoch, self.context);
+        if !self.changes.is_active() && !self.tracking.is_active() {
+            return owned.for_each_mut(f);
+        }
+        let before: Vec<OrderRow> = (0..owned.len())
+            .map(|i| OrderRow::from(owned.view_at(i)))
+            .collect();
+        owned.for_each_mut(f);
+        let mut changed = Vec::new();
+        for (i, &before) in before.iter().enumerate() {
+            if owned.is_live(i) {
+                let after = OrderRow::from(owned.view_at(i));
+                let handle = owned.handle_at(i);
+                self.changes.emit_diff(handle, &before, &after);
+                if after != before {
+                    changed.push(RowChange {
+                        handle,
+                        before: Some(before),
+                        after: Some(after),
+                    });
+                }
+            }
+        }
+        for change in changed {
+            self.track(change);
+        }
+    }
+
+    /// Expose kernel for batch ops. Writes through `kernel_mut` are not reported to subscribers.
     pub fn kernel(&self) -> &OrderSoA {
         &self.inner
     }
+    /// Writes through `kernel_mut` are not reported to subscribers and leave materialized views
+    /// stale.
     pub fn kernel_mut(&mut self) -> &mut OrderSoA {
-        Arc::make_mut(&mut self.inner)
+        self.tracking.stale |= self.tracking.is_active();
+        query_cache::write(&mut self.inner, &mut self.epoch, self.context)
     }
 }
 
 // ---------- Sharding to reduce false sharing & improve write scalability ----------
 
+/// Orders partitioned by id across independently locked, cache-padded shards, so threads writing
+/// to different shards neither contend on a lock nor false-share a cache line.
 pub struct ShardedOrderStore {
-    shards: Vec<CachePadded<OrderSoA>>,
+    shards: Vec<CachePadded<RwLock<OrderSoA>>>,
+    counters: Vec<CachePadded<ShardCounters>>,
+    router: Arc<dyn ShardRouter>,
+    id_policy: IdPolicy,
+    validators: Validators,
 }
 
 impl ShardedOrderStore {
     pub fn with_shards(n: usize, cap_per: usize) -> Self {
-        let mut shards = Vec::with_capacity(n);
-        for _ in 0..n {
-            shards.push(CachePadded::new(OrderSoA::with_capacity(cap_per)));
-        }
-        Self { shards }
+        OrderStore::builder()
+            .shards(n)
+            .capacity(n * cap_per)
+            .build_sharded()
     }
 
     #[inline]
     fn shard_idx(&self, id: OrderId) -> usize {
-        (id.0 as usize) % self.shards.len()
+        self.router.route(id, self.shards.len()) % self.shards.len()
+    }
+
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Shared access to one shard. A poisoned lock is recovered: writers never leave a shard
+    /// half-updated.
+    pub fn read_shard(&self, si: usize) -> RwLockReadGuard<'_, OrderSoA> {
+        self.shards[si].read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Exclusive access to one shard; other shards stay writable.
+    pub fn write_shard(&self, si: usize) -> RwLockWriteGuard<'_, OrderSoA> {
+        self.shards[si].write().unwrap_or_else(|e| e.into_inner())
     }
 
-    pub fn add(&mut self, id: OrderId, amount: Money, status: Status, ts: u64) -> (usize, usize) {
+    /// Append to the shard owning `id`; only that shard is locked. A stored id is handled per
+    /// the store's `IdPolicy` (ids never span shards, so the check is shard-local).
+    pub fn add(
+        &self,
+        id: OrderId,
+        amount: Money,
+        status: Status,
+        ts: u64,
+    ) -> Result<(usize, RowHandle), StoreError> {
         let si = self.shard_idx(id);
-        let row = self.shards[si].push(id, amount, status, ts);
-        (si, row)
+        let h = self.insert_locked(
+            &mut self.write_shard(si),
+            OrderRow::new(id, amount, status, ts),
+        )?;
+        self.counters[si].record_writes(1);
+        Ok((si, h))
+    }
+
+    /// Insert into an already locked shard under the store's `IdPolicy` and validators. An
+    /// upsert keeps the stored customer and currency, like `OrderStore::upsert`.
+    fn insert_locked(&self, shard: &mut OrderSoA, row: OrderRow) -> Result<RowHandle, StoreError> {
+        match (shard.id_index.get(&row.id).copied(), self.id_policy) {
+            (Some(_), IdPolicy::Reject) => Err(StoreError::DuplicateId(row.id)),
+            (Some(idx), IdPolicy::Upsert) => {
+                let before = OrderRow::from(shard.view_at(idx));
+                let after = row.upserted_over(before, false);
+                self.validators.check(after, Some(before))?;
+                shard.write_row(idx, after);
+                Ok(shard.handle_at(idx))
+            }
+            _ => {
+                self.validators.check(row, None)?;
+                Ok(shard.push_row(row))
+            }
+        }
+    }
+
+    /// Mutate the live order `id` in place under its shard's write lock.
+    pub fn update<R>(
+        &self,
+        id: OrderId,
+        f: impl FnOnce(&mut OrderMut<'_>) -> R,
+    ) -> Result<R, StoreError> {
+        let si = self.shard_idx(id);
+        let mut shard = self.write_shard(si);
+        let idx = *shard.id_index.get(&id).ok_or(StoreError::UnknownId(id))?;
+        let out = f(&mut shard.view_mut_at(idx));
+        self.counters[si].record_writes(1);
+        Ok(out)
     }
 
     pub fn sum_by_status(&self, status: Status) -> Money {
-        self.shards
-            .iter()
-            .map(|s| s.sum_by_status(status))
-            .fold(Money::zero(), |a, b| a.add(b))
+        (0..self.shards.len())
+            .map(|si| self.read_shard(si).sum_by_status(status))
+            .fold(Money::zero(), |a, b| a + b)
+    }
+
+    /// Live orders across all shards.
+    pub fn len(&self) -> usize {
+        (0..self.shards.len())
+            .map(|si| self.read_shard(si).live_len())
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Owned copies of every live order, shard by shard. Each shard is read-locked only while its
+    /// rows are copied out, so writers are never blocked for the whole scan.
+    pub fn iter(&self) -> impl Iterator<Item = OrderRow> + '_ {
+        (0..self.shards.len()).flat_map(move |si| {
+            let shard = self.read_shard(si);
+            shard.iter().map(OrderRow::from).collect::<Vec<_>>()
+        })
+    }
+
+    /// Lookup by id, touching only the owning shard.
+    pub fn find_by_id(&self, id: OrderId) -> Option<OrderRow> {
+        self.read_shard(self.shard_idx(id))
+            .find_by_id(id)
+            .map(OrderRow::from)
+    }
+
+    /// Matching rows as `(shard, row)` pairs, ordered by shard then row.
+    pub fn filter_indices(&self, min_amount: Money, status: Status) -> Vec<(usize, usize)> {
+        (0..self.shards.len())
+            .flat_map(|si| {
+                self.read_shard(si)
+                    .filter_indices(min_amount, status)
+                    .into_iter()
+                    .map(move |row| (si, row))
+            })
+            .collect()
+    }
+
+    /// Keep only orders matching `f`, compacting every shard. Shards are write-locked one at a
+    /// time, so writers to the other shards carry on meanwhile.
+    pub fn retain<F>(&self, f: F)
+    where
+        F: Fn(OrderView<'_>) -> bool,
+    {
+        for si in 0..self.shards.len() {
+            self.write_shard(si).retain(&f);
+        }
+    }
+
+    /// Per-status aggregates over all shards, merged from one pass per shard.
+    pub fn group_by_status(&self) -> StatusGroups {
+        let mut groups = StatusGroups::default();
+        for si in 0..self.shards.len() {
+            groups.merge(&self.read_shard(si).group_by_status());
+        }
+        groups
+    }
+
+    /// Per-customer aggregates over all shards, merged from one pass per shard.
+    pub fn group_by_customer(&self) -> CustomerGroups {
+        let mut groups = CustomerGroups::default();
+        for si in 0..self.shards.len() {
+            groups.merge(&self.read_shard(si).group_by_customer());
+        }
+        groups
     }
 }
 
+#[cfg(test)]
+mod proptests;
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -298,26 +2147,4594 @@ mod tests {
         let k = repo.kernel_mut();
         let idx = 1usize; // suppose we tracked it externally
         {
-            let mut row = k.view_mut(idx);
+            let mut row = k.view_mut_at(idx);
             row.set_status(Status::Completed);
         }
         assert_eq!(k.sum_by_status(Status::Completed).0, 60.0);
     }
 
     #[test]
-    fn sharded_store_usage() {
-        let mut sharded = ShardedOrderStore::with_shards(4, 10);
+    fn handles_go_stale_after_compaction() {
+        let mut soa = OrderSoA::default();
+        let h1 = soa.push(OrderId(1), Money(10.0), Status::Cancelled, 1000);
+        let h2 = soa.push(OrderId(2), Money(20.0), Status::Pending, 2000);
+        let h3 = soa.push(OrderId(3), Money(30.0), Status::Pending, 3000);
+        assert_eq!(soa.view(h2).unwrap().id(), OrderId(2));
 
-        // Add some orders to different shards
-        let _ = sharded.add(OrderId(1), Money(100.0), Status::Completed, 1000);
-        let _ = sharded.add(OrderId(5), Money(200.0), Status::Completed, 2000); // Different shard
-        let _ = sharded.add(OrderId(9), Money(50.0), Status::Pending, 3000); // Different shard
+        soa.retain(|v| v.status() != Status::Cancelled);
 
-        // Verify sum_by_status works across shards
-        let completed_total = sharded.sum_by_status(Status::Completed);
-        assert_eq!(completed_total.0, 300.0);
+        // Row 1 was dropped and rows 2/3 shifted down: every old handle is rejected.
+        assert_eq!(soa.view(h1).unwrap_err(), StoreError::StaleHandle(h1));
+        assert!(soa.view(h2).is_err());
+        assert!(soa.view_mut(h3).is_err());
 
-        let pending_total = sharded.sum_by_status(Status::Pending);
-        assert_eq!(pending_total.0, 50.0);
+        // Fresh handles resolve, including one pushed into a recycled slot.
+        assert_eq!(soa.view(soa.handle_at(0)).unwrap().id(), OrderId(2));
+        let h4 = soa.push(OrderId(4), Money(40.0), Status::Pending, 4000);
+        assert_eq!(h4.index, h3.index);
+        assert!(soa.view(h3).is_err());
+        soa.view_mut(h4).unwrap().set_amount(Money(41.0));
+        assert_eq!(soa.view(h4).unwrap().amount().0, 41.0);
+    }
+
+    #[test]
+    fn fallible_access() {
+        let mut repo = OrderStore::new();
+        let h = repo
+            .try_add(OrderId(1), Money(10.0), Status::Pending, 1000)
+            .unwrap();
+        assert_eq!(
+            repo.try_add(OrderId(1), Money(99.0), Status::Pending, 2000),
+            Err(StoreError::DuplicateId(OrderId(1)))
+        );
+        assert_eq!(repo.kernel().len(), 1);
+
+        let k = repo.kernel_mut();
+        assert_eq!(k.try_view_at(h.index).unwrap().id(), OrderId(1));
+        assert_eq!(
+            k.try_view_at(5).unwrap_err(),
+            StoreError::IndexOutOfBounds { index: 5, len: 1 }
+        );
+        assert!(k.try_view_mut_at(1).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "row 1 out of bounds (len 1)")]
+    fn view_at_panics_past_the_end() {
+        let mut soa = OrderSoA::default();
+        soa.push(OrderId(1), Money(10.0), Status::Pending, 1);
+        soa.view_at(1);
+    }
+
+    #[test]
+    fn id_index_tracks_compaction() {
+        let mut repo = OrderStore::new();
+        for i in 1..=5 {
+            let st = if i % 2 == 0 {
+                Status::Cancelled
+            } else {
+                Status::Pending
+            };
+            repo.add(OrderId(i), Money(i as f64), st, i * 1000).unwrap();
+        }
+        assert_eq!(repo.find_by_id(OrderId(4)).unwrap().amount().0, 4.0);
+
+        repo.kernel_mut()
+            .retain(|v| v.status() != Status::Cancelled);
+        assert!(repo.find_by_id(OrderId(2)).is_none());
+        assert!(repo.find_by_id(OrderId(4)).is_none());
+        assert_eq!(repo.find_by_id(OrderId(5)).unwrap().timestamp(), 5000);
+        assert_eq!(repo.find_by_id(OrderId(3)).unwrap().amount().0, 3.0);
+    }
+
+    #[test]
+    fn status_index_stays_consistent() {
+        let mut repo = OrderStore::new().with_status_index();
+        repo.add(OrderId(1), Money(10.0), Status::Pending, 1000)
+            .unwrap();
+        repo.add(OrderId(2), Money(20.0), Status::Completed, 2000)
+            .unwrap();
+        let ids = |r: &OrderStore, s| r.find_by_status(s).map(|v| v.id().0).collect::<Vec<_>>();
+        assert_eq!(ids(&repo, Status::Pending), vec![1]);
+
+        // Appends extend the built index; a write through a view re-files the row in it.
+        repo.add(OrderId(3), Money(30.0), Status::Pending, 3000)
+            .unwrap();
+        assert_eq!(ids(&repo, Status::Pending), vec![1, 3]);
+        repo.kernel_mut()
+            .view_mut_at(0)
+            .set_status(Status::Completed);
+        assert_eq!(ids(&repo, Status::Pending), vec![3]);
+        assert_eq!(ids(&repo, Status::Completed), vec![1, 2]);
+
+        repo.kernel_mut().retain(|v| v.id() != OrderId(1));
+        assert_eq!(ids(&repo, Status::Completed), vec![2]);
+        assert_eq!(ids(&repo, Status::Cancelled), Vec::<u64>::new());
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_kernels_match_sequential() {
+        let mut soa = OrderSoA::with_capacity(10_000);
+        let sharded = ShardedOrderStore::with_shards(4, 2_500);
+        for i in 0..10_000u64 {
+            let st = Status::ALL[(i % 3) as usize];
+            soa.push(OrderId(i), Money((i % 100) as f64), st, i);
+            sharded
+                .add(OrderId(i), Money((i % 100) as f64), st, i)
+                .unwrap();
+        }
+        let seq = soa.sum_by_status(Status::Completed).0;
+        assert_eq!(soa.sum_by_status_par(Status::Completed).0, seq);
+        assert_eq!(sharded.sum_by_status_par(Status::Completed).0, seq);
+        assert_eq!(
+            soa.filter_indices_par(Money(50.0), Status::Pending),
+            soa.filter_indices(Money(50.0), Status::Pending)
+        );
+        assert_eq!(
+            sharded
+                .filter_indices_par(Money(50.0), Status::Pending)
+                .len(),
+            soa.filter_indices(Money(50.0), Status::Pending).len()
+        );
+
+        soa.retain_par(|v| v.status() != Status::Cancelled);
+        sharded.retain_par(|v| v.status() != Status::Cancelled);
+        assert_eq!(soa.len(), 6_667);
+        assert_eq!(soa.find_by_id(OrderId(9_997)).unwrap().timestamp(), 9_997);
+        assert_eq!(sharded.sum_by_status_par(Status::Cancelled).0, 0.0);
+    }
+
+    #[test]
+    fn simd_kernels_match_scalar() {
+        // 1003 rows: exercises full lanes plus a ragged tail, with tombstones in both.
+        let mut soa = OrderSoA::default();
+        for i in 0..1003u64 {
+            let st = Status::ALL[(i % 3) as usize];
+            let h = soa.push(OrderId(i), Money((i % 17) as f64), st, i);
+            if i % 7 == 0 {
+                soa.remove(h).unwrap();
+            }
+        }
+        for st in Status::ALL {
+            assert_eq!(soa.sum_by_status(st).0, soa.sum_by_status_scalar(st).0);
+            assert_eq!(
+                soa.filter_indices(Money(8.0), st),
+                soa.filter_indices_scalar(Money(8.0), st)
+            );
+        }
+    }
+
+    #[test]
+    fn typed_money_per_currency() {
+        let usd = |c| TypedMoney::new(c, Currency::USD);
+        assert_eq!(usd(150).checked_add(usd(25)), Ok(usd(175)));
+        assert_eq!(usd(i64::MAX).checked_add(usd(1)), Err(MoneyError::Overflow));
+        assert_eq!(
+            usd(1).checked_sub(TypedMoney::new(1, Currency::EUR)),
+            Err(MoneyError::CurrencyMismatch {
+                left: Currency::USD,
+                right: Currency::EUR
+            })
+        );
+        assert_eq!(usd(-1234).to_string(), "-12.34 USD");
+        assert_eq!(TypedMoney::new(500, Currency::JPY).to_string(), "500 JPY");
+
+        let mut repo = OrderStore::new();
+        repo.add_money(OrderId(1), usd(1010), Status::Completed, 1)
+            .unwrap();
+        repo.add_money(
+            OrderId(2),
+            TypedMoney::new(700, Currency::EUR),
+            Status::Completed,
+            2,
+        )
+        .unwrap();
+        repo.add(OrderId(3), Money(0.2), Status::Completed, 3)
+            .unwrap(); // default currency
+        let k = repo.kernel();
+        assert_eq!(
+            k.sum_minor_by_status(Status::Completed, Currency::USD),
+            Ok(usd(1030))
+        );
+        assert_eq!(
+            k.sum_minor_by_status(Status::Completed, Currency::EUR)
+                .unwrap()
+                .minor_units,
+            700
+        );
+        assert_eq!(
+            repo.find_by_id(OrderId(2)).unwrap().currency(),
+            Currency::EUR
+        );
+
+        repo.kernel_mut()
+            .view_mut_at(1)
+            .set_money(TypedMoney::new(900, Currency::GBP));
+        assert_eq!(
+            repo.find_by_id(OrderId(2)).unwrap().money(),
+            TypedMoney::new(900, Currency::GBP)
+        );
+    }
+
+    #[test]
+    fn batch_insert_is_all_or_nothing() {
+        let mut repo = OrderStore::new();
+        repo.add(OrderId(1), Money(1.0), Status::Pending, 1)
+            .unwrap();
+        let rows = |ids: &[u64]| {
+            ids.iter()
+                .map(|&i| OrderRow::new(OrderId(i), Money(i as f64), Status::Completed, i))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            repo.add_batch(rows(&[2, 3, 1])),
+            Err(StoreError::DuplicateId(OrderId(1)))
+        );
+        assert_eq!(
+            repo.add_batch(rows(&[2, 3, 2])),
+            Err(StoreError::DuplicateId(OrderId(2)))
+        );
+        let mut bad = rows(&[4, 5]);
+        bad[1].amount = Money(-5.0);
+        assert_eq!(
+            repo.add_batch(bad),
+            Err(StoreError::InvalidAmount(OrderId(5)))
+        );
+        assert_eq!(repo.kernel().len(), 1);
+
+        assert_eq!(repo.add_batch(rows(&[2, 3, 4])), Ok(1..4));
+        assert_eq!(repo.kernel().sum_by_status(Status::Completed).0, 9.0);
+        assert_eq!(repo.find_by_id(OrderId(3)).unwrap().timestamp(), 3);
+    }
+
+    #[test]
+    fn tombstones_and_compaction() {
+        let mut repo = OrderStore::new();
+        let hs: Vec<_> = (0..6u64)
+            .map(|i| {
+                repo.add(OrderId(i), Money(10.0), Status::ALL[(i % 2) as usize], i)
+                    .unwrap()
+            })
+            .collect();
+        repo.remove(hs[1]).unwrap();
+        repo.remove(hs[4]).unwrap();
+        assert_eq!(repo.remove(hs[1]), Err(StoreError::StaleHandle(hs[1])));
+
+        // Deleted rows are invisible but still occupy slots; other handles are untouched.
+        let k = repo.kernel();
+        assert_eq!((k.len(), k.live_len()), (6, 4));
+        assert!(repo.find_by_id(OrderId(4)).is_none());
+        assert_eq!(k.iter().count(), 4);
+        assert_eq!(k.sum_by_status(Status::Completed).0, 20.0);
+        assert_eq!(k.sum_by_status(Status::Pending).0, 20.0);
+        assert_eq!(k.filter_indices(Money(0.0), Status::Pending), vec![0, 2]);
+        assert_eq!(repo.find_by_status(Status::Completed).count(), 2);
+        assert_eq!(k.view(hs[5]).unwrap().id(), OrderId(5));
+
+        let remap = repo.compact();
+        assert_eq!(remap, vec![Some(0), None, Some(1), Some(2), None, Some(3)]);
+        let k = repo.kernel();
+        assert_eq!((k.len(), k.live_len()), (4, 4));
+        assert!(k.view(hs[5]).is_err());
+        assert_eq!(k.view(hs[0]).unwrap().id(), OrderId(0));
+        assert_eq!(repo.find_by_id(OrderId(5)).unwrap().timestamp(), 5);
+    }
+
+    #[test]
+    fn group_by_status_single_pass() {
+        let mut soa = OrderSoA::default();
+        soa.push(OrderId(1), Money(10.0), Status::Completed, 1);
+        soa.push(OrderId(2), Money(40.0), Status::Completed, 2);
+        let gone = soa.push(OrderId(3), Money(99.0), Status::Completed, 3);
+        soa.push(OrderId(4), Money(5.0), Status::Pending, 4);
+        soa.remove(gone).unwrap();
+
+        let g = soa.group_by_status();
+        let done = g.get(Status::Completed);
+        assert_eq!(done.count, 2);
+        assert_eq!(done.total, Money(50.0));
+        assert_eq!((done.min, done.max), (Some(Money(10.0)), Some(Money(40.0))));
+        assert_eq!(done.mean(), Some(Money(25.0)));
+        assert_eq!(g.get(Status::Pending).mean(), Some(Money(5.0)));
+        assert_eq!(*g.get(Status::Cancelled), Aggregate::default());
+        assert_eq!(g.get(Status::Cancelled).mean(), None);
+        assert_eq!(g.iter().map(|(_, a)| a.count).sum::<usize>(), 3);
+    }
+
+    #[test]
+    fn time_range_queries() {
+        let mut repo = OrderStore::new();
+        for i in 0..10u64 {
+            repo.add(
+                OrderId(i),
+                Money(1.0),
+                Status::ALL[(i % 2) as usize],
+                i * 100,
+            )
+            .unwrap();
+        }
+        let ids: Vec<_> = repo
+            .find_in_time_range(200, 500)
+            .map(|v| v.id().0)
+            .collect();
+        assert_eq!(ids, vec![2, 3, 4]);
+        let k = repo.kernel();
+        assert_eq!(k.sum_in_range(200, 500, Status::Pending).0, 2.0);
+        assert_eq!(k.sum_in_range(0, u64::MAX, Status::Completed).0, 5.0);
+        assert_eq!(k.sum_in_range(500, 500, Status::Pending).0, 0.0);
+    }
+
+    #[test]
+    fn sorting_reorders_all_columns() {
+        let mut soa = OrderSoA::default();
+        let h0 = soa.push(OrderId(0), Money(30.0), Status::Pending, 300);
+        let h1 = soa.push(OrderId(1), Money(10.0), Status::Completed, 200);
+        let h2 = soa.push(OrderId(2), Money(20.0), Status::Cancelled, 100);
+        soa.remove(h1).unwrap();
+
+        // Permutation: zero-copy sorted iteration, nothing moves.
+        let perm = soa.argsort_by_amount();
+        assert_eq!(perm, vec![1, 2, 0]);
+        let ids: Vec<_> = soa.iter_permuted(&perm).map(|v| v.id().0).collect();
+        assert_eq!(ids, vec![2, 0]);
+        assert!(soa.view(h0).is_ok());
+
+        // Physical sort: columns move together, moved handles go stale.
+        soa.sort_by_timestamp();
+        let rows: Vec<_> = soa
+            .iter()
+            .map(|v| (v.id().0, v.amount().0, v.status(), v.timestamp()))
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                (2, 20.0, Status::Cancelled, 100),
+                (0, 30.0, Status::Pending, 300)
+            ]
+        );
+        assert_eq!(soa.live_len(), 2);
+        assert!(soa.view(h0).is_err());
+        assert!(soa.view(h2).is_err());
+        assert_eq!(soa.find_by_id(OrderId(0)).unwrap().timestamp(), 300);
+        assert!(soa.find_by_id(OrderId(1)).is_none());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trips_column_wise() {
+        fn rows(soa: &OrderSoA) -> Vec<(u64, f64, Status, u64, Currency)> {
+            soa.iter()
+                .map(|v| {
+                    (
+                        v.id().0,
+                        v.amount().0,
+                        v.status(),
+                        v.timestamp(),
+                        v.currency(),
+                    )
+                })
+                .collect()
+        }
+
+        let empty = OrderStore::new();
+        let json = serde_json::to_string(&empty).unwrap();
+        assert_eq!(
+            json,
+            r#"{"ids":[],"amounts":[],"statuses":[],"timestamps":[],"currencies":[],"customers":[]}"#
+        );
+        let back: OrderStore = serde_json::from_str(&json).unwrap();
+        assert!(back.kernel().is_empty());
+
+        let mut soa = OrderSoA::with_capacity(100_000);
+        for i in 0..100_000u64 {
+            let st = Status::ALL[(i % 3) as usize];
+            let h = soa.push(OrderId(i), Money(i as f64 * 0.25), st, 1_000 + i);
+            if i % 1000 == 0 {
+                soa.remove(h).unwrap();
+            }
+        }
+        soa.view_mut_at(1)
+            .set_money(TypedMoney::new(995, Currency::EUR));
+
+        let bytes = bincode::serialize(&soa).unwrap();
+        let from_bin: OrderSoA = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(from_bin.len(), soa.live_len());
+        assert_eq!(rows(&from_bin), rows(&soa));
+        assert_eq!(
+            from_bin.find_by_id(OrderId(1)).unwrap().currency(),
+            Currency::EUR
+        );
+        assert!(from_bin.find_by_id(OrderId(1000)).is_none());
+
+        let from_json: OrderSoA =
+            serde_json::from_str(&serde_json::to_string(&soa).unwrap()).unwrap();
+        assert_eq!(rows(&from_json), rows(&soa));
+
+        let skewed = r#"{"ids":[1],"amounts":[],"statuses":[],"timestamps":[],"currencies":[]}"#;
+        assert!(serde_json::from_str::<OrderSoA>(skewed).is_err());
+    }
+
+    #[cfg(feature = "arrow")]
+    #[test]
+    fn arrow_round_trip_shares_columns() {
+        use arrow_array::{Array, Float64Array};
+
+        let mut repo = OrderStore::new();
+        repo.add(OrderId(1), Money(10.0), Status::Pending, 100)
+            .unwrap();
+        repo.add_money(
+            OrderId(2),
+            TypedMoney::new(2050, Currency::EUR),
+            Status::Completed,
+            200,
+        )
+        .unwrap();
+        repo.add(OrderId(3), Money(30.0), Status::Cancelled, 300)
+            .unwrap();
+
+        let batch = repo.to_record_batch().unwrap();
+        assert_eq!(batch.num_rows(), 3);
+        assert_eq!(batch.schema(), arrow::order_schema());
+        let amounts = batch
+            .column(1)
+            .as_any()
+            .downcast_ref::<Float64Array>()
+            .unwrap();
+        // Zero-copy: the Arrow buffer aliases the kernel's amount column.
+        assert_eq!(amounts.values().as_ptr(), repo.kernel().amounts.as_ptr());
+
+        // Writing to the store copies-on-write; the batch keeps the old snapshot.
+        repo.add(OrderId(4), Money(40.0), Status::Pending, 400)
+            .unwrap();
+        assert_eq!(batch.num_rows(), 3);
+        assert_eq!(amounts.value(1), 20.5);
+
+        let back = OrderStore::from_record_batch(&batch).unwrap();
+        let rows: Vec<_> = back
+            .kernel()
+            .iter()
+            .map(|v| {
+                (
+                    v.id().0,
+                    v.amount().0,
+                    v.status(),
+                    v.timestamp(),
+                    v.currency(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                (1, 10.0, Status::Pending, 100, Currency::USD),
+                (2, 20.5, Status::Completed, 200, Currency::EUR),
+                (3, 30.0, Status::Cancelled, 300, Currency::USD),
+            ]
+        );
+
+        let h = repo.kernel().handle_at(0);
+        repo.remove(h).unwrap();
+        let compacted = repo.kernel().to_record_batch().unwrap();
+        assert_eq!(compacted.num_rows(), 3);
+        assert_eq!(
+            OrderSoA::from_record_batch(&compacted)
+                .unwrap()
+                .find_by_id(OrderId(4))
+                .unwrap()
+                .amount(),
+            Money(40.0)
+        );
+    }
+
+    #[cfg(feature = "parquet")]
+    #[test]
+    fn parquet_snapshot_round_trip() {
+        let dir = std::env::temp_dir().join(format!("ddd_dod_soa_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("orders.parquet");
+
+        let mut repo = OrderStore::new();
+        for i in 0..5_000u64 {
+            repo.add(
+                OrderId(i),
+                Money(i as f64),
+                Status::ALL[(i % 3) as usize],
+                i,
+            )
+            .unwrap();
+        }
+        repo.add_money(
+            OrderId(9_999),
+            TypedMoney::new(1, Currency::GBP),
+            Status::Pending,
+            1,
+        )
+        .unwrap();
+        repo.write_parquet(&path).unwrap();
+
+        let back = OrderStore::read_parquet(&path).unwrap();
+        assert_eq!(back.kernel().len(), 5_001);
+        assert_eq!(
+            back.kernel().sum_by_status(Status::Completed),
+            repo.kernel().sum_by_status(Status::Completed)
+        );
+        assert_eq!(
+            back.find_by_id(OrderId(9_999)).unwrap().currency(),
+            Currency::GBP
+        );
+
+        OrderStore::new().write_parquet(&path).unwrap();
+        assert!(OrderStore::read_parquet(&path).unwrap().kernel().is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn replaying_the_log_rebuilds_the_store() {
+        let mut log = EventLog::new();
+        let mut live = OrderStore::new().with_status_index();
+        let events = [
+            OrderEvent::Created(OrderRow::new(OrderId(1), Money(10.0), Status::Pending, 1)),
+            OrderEvent::Created(OrderRow::new(OrderId(2), Money(20.0), Status::Pending, 2)),
+            OrderEvent::AmountChanged {
+                id: OrderId(1),
+                amount: Money(15.0),
+            },
+            OrderEvent::StatusChanged {
+                id: OrderId(1),
+                status: Status::Completed,
+            },
+            OrderEvent::Cancelled { id: OrderId(2) },
+        ];
+        for ev in events {
+            live.apply(&ev).unwrap();
+            log.append(ev);
+        }
+
+        let rebuilt = OrderStore::replay(&log).unwrap();
+        let rows = |s: &OrderStore| {
+            s.kernel()
+                .iter()
+                .map(|v| (v.id(), v.amount(), v.status()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(rows(&rebuilt), rows(&live));
+        assert_eq!(live.find_by_status(Status::Cancelled).count(), 1);
+        assert_eq!(
+            rebuilt.kernel().sum_by_status(Status::Completed),
+            Money(15.0)
+        );
+
+        // Rejected events leave the store untouched.
+        let before = rows(&live);
+        assert_eq!(
+            live.apply(&OrderEvent::Cancelled { id: OrderId(9) }),
+            Err(StoreError::UnknownId(OrderId(9)))
+        );
+        assert_eq!(
+            live.apply(&OrderEvent::AmountChanged {
+                id: OrderId(1),
+                amount: Money(-1.0)
+            }),
+            Err(StoreError::InvalidAmount(OrderId(1)))
+        );
+        assert_eq!(rows(&live), before);
+    }
+
+    #[test]
+    fn aggregate_enforces_lifecycle() {
+        let mut repo = OrderStore::new();
+        repo.add(OrderId(1), Money(10.0), Status::Pending, 1)
+            .unwrap();
+        repo.add(OrderId(2), Money(20.0), Status::Pending, 2)
+            .unwrap();
+
+        let mut order = repo.load(OrderId(1)).unwrap();
+        order.adjust_amount(Money(12.0)).unwrap();
+        order.complete().unwrap();
+        assert_eq!(
+            order.cancel(),
+            Err(StoreError::IllegalTransition {
+                id: OrderId(1),
+                from: Status::Completed,
+                to: Status::Cancelled
+            })
+        );
+        assert_eq!(
+            order.adjust_amount(Money(1.0)),
+            Err(StoreError::OrderClosed(OrderId(1)))
+        );
+
+        let mut order = repo.load(OrderId(2)).unwrap();
+        order.cancel().unwrap();
+        assert!(order.complete().is_err());
+
+        assert_eq!(repo.kernel().sum_by_status(Status::Completed), Money(12.0));
+        assert_eq!(
+            repo.find_by_id(OrderId(2)).unwrap().status(),
+            Status::Cancelled
+        );
+        assert_eq!(
+            repo.load(OrderId(3)).err(),
+            Some(StoreError::UnknownId(OrderId(3)))
+        );
+    }
+
+    #[test]
+    fn rejected_commands_leave_version_and_audit_alone() {
+        let clock = FixedClock::new(100);
+        let mut store = OrderStore::new().with_audit().with_clock(clock.clone());
+        store
+            .add(OrderId(1), Money(10.0), Status::Cancelled, 1)
+            .unwrap();
+        let stamps = |store: &OrderStore| {
+            let v = store.find_by_id(OrderId(1)).unwrap();
+            (v.version(), v.audit().unwrap().updated_at)
+        };
+        let before = stamps(&store);
+
+        clock.advance(50);
+        let mut order = store.load(OrderId(1)).unwrap();
+        assert!(order.complete().is_err());
+        assert_eq!(
+            order.adjust_amount(Money(1.0)),
+            Err(StoreError::OrderClosed(OrderId(1)))
+        );
+        assert_eq!(stamps(&store), before);
+        assert!(store.modified_since(120).next().is_none());
+
+        store
+            .add(OrderId(2), Money(10.0), Status::Pending, 2)
+            .unwrap();
+        let mut order = store.load(OrderId(2)).unwrap();
+        order.cancel().unwrap();
+        let v = store.find_by_id(OrderId(2)).unwrap();
+        assert_eq!((v.version(), v.audit().unwrap().updated_at), (1, 150));
+    }
+
+    #[test]
+    fn concurrent_store_snapshots_are_stable() {
+        let store = ConcurrentOrderStore::new().with_merge_threshold(64);
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                for i in 0..1_000u64 {
+                    store.add(OrderId(i), Money(1.0), Status::Completed, i);
+                }
+            });
+            for _ in 0..2 {
+                s.spawn(|| {
+                    for _ in 0..100 {
+                        let snap = store.snapshot();
+                        let n = snap.len();
+                        // A snapshot never sees a later write, whatever the writer does meanwhile.
+                        assert_eq!(snap.iter().count(), n);
+                        assert_eq!(snap.sum_by_status(Status::Completed), Money(n as f64));
+                    }
+                });
+            }
+        });
+
+        let before = store.snapshot();
+        assert_eq!(before.len(), 1_000);
+        assert!(before.segments()[1].len() < 64);
+        store.merge();
+        assert!(store.snapshot().segments()[1].is_empty());
+        assert_eq!(
+            store.add_batch([OrderRow::new(OrderId(5), Money(1.0), Status::Pending, 0)]),
+            Err(StoreError::DuplicateId(OrderId(5)))
+        );
+        store.add(OrderId(1_000), Money(2.0), Status::Pending, 0);
+        assert_eq!(before.len(), 1_000);
+        assert_eq!(
+            store
+                .snapshot()
+                .find_by_id(OrderId(1_000))
+                .unwrap()
+                .amount(),
+            Money(2.0)
+        );
+    }
+
+    #[derive(Soa, Copy, Clone, Debug, PartialEq)]
+    struct Shipment {
+        order: OrderId,
+        weight_kg: f32,
+        dispatched_at: u64,
+    }
+
+    #[test]
+    fn derived_soa_round_trips() {
+        let mut soa = ShipmentSoA::with_capacity(3);
+        for i in 0..3u64 {
+            soa.push(Shipment {
+                order: OrderId(i),
+                weight_kg: i as f32 * 1.5,
+                dispatched_at: 100 * i,
+            });
+        }
+        assert_eq!(soa.len(), 3);
+        assert_eq!(soa.view(2).weight_kg(), 3.0);
+
+        soa.view_mut(1).set_dispatched_at(999);
+        assert_eq!(soa.get(1).dispatched_at, 999);
+
+        soa.retain(|v| v.order() != OrderId(0));
+        let ids: Vec<_> = soa.iter().map(|v| v.order()).collect();
+        assert_eq!(ids, vec![OrderId(1), OrderId(2)]);
+        assert_eq!(
+            soa.get(0),
+            Shipment {
+                order: OrderId(1),
+                weight_kg: 1.5,
+                dispatched_at: 999
+            }
+        );
+    }
+
+    #[test]
+    fn dyn_columns_stay_aligned() {
+        let mut soa = DynSoA::new();
+        soa.push(OrderId(1), Money(10.0), Status::Completed, 1);
+        soa.add_column::<f64>("discount").unwrap();
+        soa.add_column::<u16>("region").unwrap();
+        assert_eq!(
+            soa.add_column::<u8>("region"),
+            Err(ColumnError::Duplicate("region".into()))
+        );
+        let row = soa.push(OrderId(2), Money(20.0), Status::Pending, 2);
+        let mut m = soa.view_mut(row);
+        m.set("discount", 0.25f64).unwrap();
+        m.set("region", 7u16).unwrap();
+        m.order().set_status(Status::Completed);
+        assert!(matches!(
+            m.set("region", 7u32),
+            Err(ColumnError::TypeMismatch { .. })
+        ));
+
+        assert_eq!(soa.view(0).get::<f64>("discount"), Ok(&0.0));
+        assert_eq!(soa.view(1).get::<u16>("region"), Ok(&7));
+        assert_eq!(soa.orders().sum_by_status(Status::Completed), Money(30.0));
+        assert_eq!(soa.column::<f64>("discount").unwrap(), &[0.0, 0.25]);
+
+        soa.retain(|v| v.order().id() != OrderId(1));
+        assert_eq!(soa.len(), 1);
+        assert_eq!(soa.column::<u16>("region").unwrap(), &[7]);
+        assert_eq!(soa.view(0).order().id(), OrderId(2));
+    }
+
+    #[test]
+    fn optional_columns_skip_nulls() {
+        let col: OptionColumn<u32> = [Some(3), None, Some(1), None].into_iter().collect();
+        assert_eq!(col.null_count(), 2);
+        assert_eq!(col.iter_valid().collect::<Vec<_>>(), vec![(0, 3), (2, 1)]);
+        assert_eq!((col.min(), col.max(), col.sum()), (Some(1), Some(3), 4.0));
+
+        let mut soa = DynSoA::new();
+        soa.push(OrderId(1), Money(10.0), Status::Pending, 1);
+        soa.add_optional_column::<u64>("shipped_at").unwrap();
+        let row = soa.push(OrderId(2), Money(20.0), Status::Completed, 2);
+        soa.push(OrderId(3), Money(30.0), Status::Completed, 3);
+        soa.view_mut(row)
+            .set_opt("shipped_at", Some(42u64))
+            .unwrap();
+
+        assert_eq!(soa.view(0).get_opt::<u64>("shipped_at"), Ok(None));
+        assert_eq!(soa.view(row).get_opt::<u64>("shipped_at"), Ok(Some(42)));
+        assert!(soa.view(row).get::<u64>("shipped_at").is_err());
+        soa.retain(|v| v.order().id() != OrderId(1));
+        let shipped = soa.optional_column::<u64>("shipped_at").unwrap();
+        assert_eq!(shipped.iter().collect::<Vec<_>>(), vec![Some(42), None]);
+        assert_eq!(shipped.max(), Some(42));
+    }
+
+    #[test]
+    fn chunked_appends_never_move_rows() {
+        let mut col = ChunkedVec::new();
+        col.push(0u64);
+        let first: *const u64 = &col[0];
+        col.extend(1..2 * CHUNK as u64 + 5);
+        assert_eq!(&col[0] as *const u64, first);
+        assert_eq!(
+            col.chunks().map(<[u64]>::len).collect::<Vec<_>>(),
+            vec![CHUNK, CHUNK, 5]
+        );
+
+        let mut chunked = ChunkedOrderSoA::new();
+        let mut soa = OrderSoA::default();
+        for i in 0..col.len() as u64 {
+            let st = Status::ALL[(i % 3) as usize];
+            chunked.push(OrderId(i), Money((i % 50) as f64), st, i);
+            soa.push(OrderId(i), Money((i % 50) as f64), st, i);
+        }
+        assert_eq!(chunked.len(), soa.len());
+        assert_eq!(
+            chunked.sum_by_status(Status::Completed),
+            soa.sum_by_status(Status::Completed)
+        );
+        assert_eq!(
+            chunked.filter_indices(Money(25.0), Status::Pending),
+            soa.filter_indices(Money(25.0), Status::Pending)
+        );
+        assert_eq!(chunked.group_by_status(), soa.group_by_status());
+        assert_eq!(
+            chunked.find_by_id(OrderId(4_100)).map(OrderRow::from),
+            soa.find_by_id(OrderId(4_100)).map(OrderRow::from)
+        );
+        let row = OrderRow::new(OrderId(9_000), Money(1.0), Status::Pending, 1)
+            .with_customer(CustomerId(5));
+        let idx = chunked.push_row(row);
+        assert_eq!(OrderRow::from(chunked.view_at(idx)), row);
+    }
+
+    #[test]
+    fn query_fuses_column_predicates() {
+        let mut repo = OrderStore::new();
+        for i in 0..100u64 {
+            repo.add(
+                OrderId(i),
+                Money(i as f64),
+                Status::ALL[(i % 3) as usize],
+                i * 10,
+            )
+            .unwrap();
+        }
+        let h = repo.kernel().handle_at(3);
+        repo.remove(h).unwrap();
+
+        let q = repo
+            .query()
+            .status(Status::Pending)
+            .amount_gte(Money(30.0))
+            .ts_between(0, 600);
+        let expected: Vec<usize> = (30..60).filter(|i| i % 3 == 0).collect();
+        assert_eq!(q.indices(), expected);
+        assert_eq!(q.count(), 10);
+        assert_eq!(q.sum(), Money(expected.iter().sum::<usize>() as f64));
+        assert!(q
+            .collect_views()
+            .iter()
+            .all(|v| v.status() == Status::Pending));
+
+        assert_eq!(repo.query().count(), 99);
+        assert_eq!(
+            repo.query().amount_lt(Money(5.0)).indices(),
+            vec![0, 1, 2, 4]
+        );
+        assert_eq!(repo.query().currency(Currency::EUR).count(), 0);
+    }
+
+    #[test]
+    fn for_each_mut_bulk_updates() {
+        let mut repo = OrderStore::new().with_status_index();
+        for i in 0..10u64 {
+            repo.add(OrderId(i), Money(1.0), Status::Pending, i * 100)
+                .unwrap();
+        }
+        let h = repo.kernel().handle_at(0);
+        repo.remove(h).unwrap();
+        assert_eq!(repo.find_by_status(Status::Pending).count(), 9);
+
+        // Cancel every pending order older than t = 500.
+        repo.for_each_mut(|mut o| {
+            if o.status() == Status::Pending && o.timestamp() < 500 {
+                o.set_status(Status::Cancelled);
+            }
+        });
+        assert_eq!(repo.find_by_status(Status::Cancelled).count(), 4);
+        assert_eq!(repo.find_by_status(Status::Pending).count(), 5);
+        // The tombstoned row was not visited.
+        assert_eq!(repo.kernel().view_at(0).status(), Status::Pending);
+    }
+
+    #[test]
+    fn id_policy_governs_duplicate_adds() {
+        let mut repo = OrderStore::new().with_id_policy(IdPolicy::Reject);
+        let h = repo
+            .add(OrderId(1), Money(10.0), Status::Pending, 1)
+            .unwrap();
+        assert_eq!(
+            repo.add(OrderId(1), Money(99.0), Status::Pending, 2),
+            Err(StoreError::DuplicateId(OrderId(1)))
+        );
+        assert_eq!(repo.kernel().len(), 1);
+
+        let mut repo = repo.with_id_policy(IdPolicy::Upsert).with_status_index();
+        let eur = TypedMoney::new(500, Currency::EUR);
+        assert_eq!(repo.add_money(OrderId(1), eur, Status::Completed, 3), Ok(h));
+        let v = repo.find_by_id(OrderId(1)).unwrap();
+        assert_eq!(
+            (v.money(), v.status(), v.timestamp()),
+            (eur, Status::Completed, 3)
+        );
+        assert_eq!(repo.kernel().len(), 1);
+        assert_eq!(repo.find_by_status(Status::Completed).count(), 1);
+
+        let mut repo = repo.with_id_policy(IdPolicy::AllowDuplicates);
+        repo.add(OrderId(1), Money(1.0), Status::Pending, 4)
+            .unwrap();
+        assert_eq!(repo.kernel().len(), 2);
+        assert_eq!(repo.find_by_id(OrderId(1)).unwrap().timestamp(), 4);
+    }
+
+    #[test]
+    fn upsert_and_update_by_id() {
+        let mut repo = OrderStore::new().with_id_policy(IdPolicy::Reject);
+        let h = repo
+            .upsert(OrderId(1), Money(10.0), Status::Pending, 1)
+            .unwrap();
+        assert_eq!(
+            repo.upsert(OrderId(1), Money(12.0), Status::Pending, 2),
+            Ok(h)
+        );
+        assert_eq!(repo.kernel().len(), 1);
+        assert_eq!(repo.find_by_id(OrderId(1)).unwrap().amount(), Money(12.0));
+
+        let old = repo
+            .update_with(OrderId(1), |mut o| {
+                o.set_status(Status::Completed);
+                o.amount()
+            })
+            .unwrap();
+        assert_eq!(old, Money(12.0));
+        assert_eq!(repo.kernel().sum_by_status(Status::Completed), Money(12.0));
+        assert_eq!(
+            repo.update_with(OrderId(2), |_| ()),
+            Err(StoreError::UnknownId(OrderId(2)))
+        );
+    }
+
+    #[test]
+    fn upsert_keeps_stored_currency() {
+        let eur = TypedMoney::new(1_000, Currency::EUR);
+        let mut repo = OrderStore::new();
+        repo.add_money(OrderId(1), eur, Status::Pending, 1).unwrap();
+        repo.upsert(OrderId(1), Money(12.0), Status::Pending, 2)
+            .unwrap();
+        let v = repo.find_by_id(OrderId(1)).unwrap();
+        assert_eq!((v.amount(), v.currency()), (Money(12.0), Currency::EUR));
+
+        // A currency-tagged amount brings its own currency.
+        let mut repo = repo.with_id_policy(IdPolicy::Upsert);
+        let gbp = TypedMoney::new(500, Currency::GBP);
+        repo.add_money(OrderId(1), gbp, Status::Pending, 3).unwrap();
+        assert_eq!(repo.find_by_id(OrderId(1)).unwrap().money(), gbp);
+
+        let sharded = OrderStore::builder()
+            .shards(2)
+            .id_policy(IdPolicy::Upsert)
+            .build_sharded();
+        sharded
+            .add(OrderId(1), Money(10.0), Status::Pending, 1)
+            .unwrap();
+        sharded.update(OrderId(1), |o| o.set_money(eur)).unwrap();
+        let (si, _) = sharded
+            .add(OrderId(1), Money(12.0), Status::Pending, 2)
+            .unwrap();
+        let shard = sharded.read_shard(si);
+        assert_eq!(
+            shard.find_by_id(OrderId(1)).unwrap().currency(),
+            Currency::EUR
+        );
+    }
+
+    #[test]
+    fn transactions_commit_or_roll_back() {
+        let mut repo = OrderStore::new();
+        let h = repo
+            .add(OrderId(1), Money(10.0), Status::Pending, 1)
+            .unwrap();
+        let snapshot = repo.clone();
+
+        let mut tx = repo.begin();
+        tx.add(OrderId(2), Money(20.0), Status::Pending, 2).unwrap();
+        tx.update_with(OrderId(1), |mut o| o.set_status(Status::Completed))
+            .unwrap();
+        assert_eq!(tx.kernel().live_len(), 2);
+        drop(tx);
+        assert_eq!(repo.kernel().live_len(), 1);
+        assert_eq!(
+            repo.find_by_id(OrderId(1)).unwrap().status(),
+            Status::Pending
+        );
+
+        let mut tx = repo.begin();
+        tx.remove(h).unwrap();
+        tx.add(OrderId(3), Money(30.0), Status::Completed, 3)
+            .unwrap();
+        tx.commit();
+        assert!(repo.find_by_id(OrderId(1)).is_none());
+        assert_eq!(repo.kernel().sum_by_status(Status::Completed), Money(30.0));
+        // Readers holding the old state never saw the transaction.
+        assert_eq!(snapshot.kernel().live_len(), 1);
+        assert!(snapshot.find_by_id(OrderId(3)).is_none());
+    }
+
+    #[cfg(feature = "csv")]
+    #[test]
+    fn csv_round_trip_and_line_errors() {
+        let input = "timestamp,order_id,amount,status\n5,1,10.5,Completed\n6,2,3,Pending\n";
+        let mapping = CsvMapping {
+            id: "order_id".into(),
+            currency: None,
+            customer: None,
+            ..CsvMapping::default()
+        };
+        let soa = OrderSoA::from_csv_with(input.as_bytes(), &mapping).unwrap();
+        assert_eq!(soa.sum_by_status(Status::Completed), Money(10.5));
+        assert_eq!(soa.find_by_id(OrderId(2)).unwrap().timestamp(), 6);
+
+        let mut out = Vec::new();
+        soa.to_csv(&mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.starts_with(
+            "id,amount,status,timestamp,currency,customer\n1,10.5,Completed,5,USD,0\n"
+        ));
+        let back = OrderSoA::from_csv(text.as_bytes()).unwrap();
+        assert_eq!(
+            back.iter().map(OrderRow::from).collect::<Vec<_>>(),
+            soa.iter().map(OrderRow::from).collect::<Vec<_>>()
+        );
+
+        // Customers survive the round trip.
+        let mut owned = OrderSoA::default();
+        owned.push_row(
+            OrderRow::new(OrderId(1), Money(2.0), Status::Pending, 3).with_customer(CustomerId(9)),
+        );
+        let mut out = Vec::new();
+        owned.to_csv(&mut out).unwrap();
+        let back = OrderSoA::from_csv(out.as_slice()).unwrap();
+        assert_eq!(back.view_at(0).customer(), CustomerId(9));
+
+        let bad = "id,amount,status,timestamp\n1,1,Pending,1\n2,x,Pending,2\n";
+        match OrderSoA::from_csv_with(bad.as_bytes(), &mapping) {
+            Err(CsvError::MissingColumn(c)) => assert_eq!(c, "order_id"),
+            _ => panic!("expected a missing column"),
+        }
+        let plain = CsvMapping {
+            currency: None,
+            customer: None,
+            ..CsvMapping::default()
+        };
+        match OrderSoA::from_csv_with(bad.as_bytes(), &plain) {
+            Err(CsvError::Parse { line, column, .. }) => {
+                assert_eq!((line, column.as_str()), (3, "amount"))
+            }
+            _ => panic!("expected a parse error"),
+        }
+        let dup = "id,amount,status,timestamp\n1,1,Pending,1\n1,2,Pending,2\n";
+        assert!(matches!(
+            OrderSoA::from_csv_with(dup.as_bytes(), &plain),
+            Err(CsvError::Invalid {
+                line: 3,
+                error: StoreError::DuplicateId(OrderId(1))
+            })
+        ));
+    }
+
+    #[test]
+    fn aos_reference_matches_soa() {
+        let mut aos = OrderAoS::with_capacity(300);
+        let mut soa = OrderSoA::with_capacity(300);
+        for i in 0..300u64 {
+            let st = Status::ALL[(i % 3) as usize];
+            aos.push(OrderId(i), Money((i % 7) as f64), st, i);
+            soa.push(OrderId(i), Money((i % 7) as f64), st, i);
+        }
+        assert_eq!(
+            aos.sum_by_status(Status::Pending),
+            soa.sum_by_status(Status::Pending)
+        );
+        assert_eq!(
+            aos.filter_indices(Money(3.0), Status::Completed),
+            soa.filter_indices(Money(3.0), Status::Completed)
+        );
+        aos.retain(|r| r.amount.0 > 2.0);
+        soa.retain(|v| v.amount().0 > 2.0);
+        assert_eq!(
+            aos.rows(),
+            soa.iter().map(OrderRow::from).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn projections_expose_selected_columns() {
+        let mut soa = OrderSoA::default();
+        let h = soa.push(OrderId(1), Money(10.0), Status::Pending, 100);
+        soa.push(OrderId(2), Money(20.0), Status::Completed, 200);
+        soa.push(OrderId(3), Money(30.0), Status::Completed, 300);
+        soa.remove(h).unwrap();
+
+        let p = soa.project::<(cols::Amount, cols::Timestamp)>();
+        assert_eq!(
+            p.iter().collect::<Vec<_>>(),
+            vec![(Money(20.0), 200), (Money(30.0), 300)]
+        );
+        assert_eq!(p.get(0), None);
+        assert_eq!(p.columns().1, &[100, 200, 300]);
+
+        let statuses = soa.project::<cols::Status>();
+        assert_eq!(
+            statuses.iter().filter(|&s| s == Status::Completed).count(),
+            2
+        );
+        let (ids, _) = soa.project::<(cols::Id, cols::Currency)>().columns();
+        assert_eq!(ids.len(), 3);
+    }
+
+    #[test]
+    fn columns_mut_splits_borrows() {
+        let mut soa = OrderSoA::default().with_status_index();
+        for i in 0..6u64 {
+            soa.push(OrderId(i), Money(i as f64), Status::Pending, i);
+        }
+        let h = soa.handle_at(5);
+        soa.remove(h).unwrap();
+        assert_eq!(soa.find_by_status(Status::Pending).count(), 5);
+
+        // Expire old orders and stamp them, touching two columns in one pass.
+        let cols = soa.columns_mut();
+        for i in 0..cols.len() {
+            if cols.is_live(i) && cols.timestamps[i] < 3 {
+                cols.statuses[i] = Status::Cancelled;
+                cols.timestamps[i] = 99;
+            }
+        }
+        assert_eq!(soa.find_by_status(Status::Cancelled).count(), 3);
+        assert_eq!(soa.find_by_id(OrderId(1)).unwrap().timestamp(), 99);
+        assert_eq!(soa.find_by_status(Status::Pending).count(), 2);
+    }
+
+    #[test]
+    fn top_k_percentile_histogram() {
+        let mut soa = OrderSoA::default();
+        for (i, a) in [5.0, 1.0, 9.0, 3.0, 9.0, 7.0, 100.0]
+            .into_iter()
+            .enumerate()
+        {
+            soa.push(OrderId(i as u64), Money(a), Status::Completed, i as u64);
+        }
+        let h = soa.handle_at(6);
+        soa.remove(h).unwrap();
+
+        assert_eq!(soa.top_k_by_amount(3), vec![2, 4, 5]);
+        assert_eq!(soa.top_k_by_amount(10).len(), 6);
+        assert!(soa.top_k_by_amount(0).is_empty());
+        assert_eq!(soa.top_k_by_amount(usize::MAX / 2).len(), 6);
+        assert_eq!(soa.top_k_by_amount(usize::MAX).len(), 6);
+
+        assert_eq!(soa.percentile_amount(50.0), Some(Money(5.0)));
+        assert_eq!(soa.percentile_amount(100.0), Some(Money(9.0)));
+        assert_eq!(soa.percentile_amount(0.0), Some(Money(1.0)));
+        assert_eq!(soa.percentile_amount(101.0), None);
+
+        let hist = soa.histogram_amount(4);
+        assert_eq!((hist.lo, hist.hi), (Money(1.0), Money(9.0)));
+        assert_eq!(hist.counts, vec![1, 1, 1, 3]);
+        assert_eq!(hist.bucket_start(2), Money(5.0));
+        assert_eq!(
+            OrderSoA::default().histogram_amount(4),
+            Histogram::default()
+        );
+    }
+
+    #[test]
+    fn rollup_by_time_window() {
+        let mut soa = OrderSoA::default();
+        for (i, (ts, amt)) in [(5, 1.0), (999, 3.0), (1_000, 10.0), (3_500, 4.0)]
+            .into_iter()
+            .enumerate()
+        {
+            let st = if i == 3 {
+                Status::Cancelled
+            } else {
+                Status::Completed
+            };
+            soa.push(OrderId(i as u64), Money(amt), st, ts);
+        }
+        let w = |start, count, total| WindowAggregate {
+            start,
+            count,
+            total: Money(total),
+        };
+        assert_eq!(
+            soa.rollup_by_window(1_000, None),
+            vec![w(0, 2, 4.0), w(1_000, 1, 10.0), w(3_000, 1, 4.0)]
+        );
+        let completed = soa.rollup_by_window(1_000, Some(Status::Completed));
+        assert_eq!(completed.len(), 2);
+        assert_eq!(completed[0].mean(), Money(2.0));
+    }
+
+    #[test]
+    fn join_orders_with_customers() {
+        let mut customers = CustomerSoA::new();
+        customers.push(CustomerId(1), "Ada", 7);
+        customers.push(CustomerId(2), "Grace", 9);
+
+        let mut soa = OrderSoA::default();
+        soa.extend_from_rows([
+            OrderRow::new(OrderId(10), Money(5.0), Status::Pending, 1).with_customer(CustomerId(2)),
+            OrderRow::new(OrderId(11), Money(6.0), Status::Pending, 2).with_customer(CustomerId(1)),
+            OrderRow::new(OrderId(12), Money(7.0), Status::Pending, 3).with_customer(CustomerId(3)),
+        ])
+        .unwrap();
+        let h = soa.push(OrderId(13), Money(8.0), Status::Completed, 4);
+        soa.view_mut(h).unwrap().set_customer(CustomerId(1));
+        soa.remove(soa.handle_at(1)).unwrap();
+
+        let joined: Vec<(OrderId, &str, u16)> = join_orders_customers(&soa, &customers)
+            .map(|j| (j.order().id(), j.customer().name(), j.customer().region()))
+            .collect();
+        // Order 12's customer is unknown and order 11 is deleted.
+        assert_eq!(
+            joined,
+            vec![(OrderId(10), "Grace", 9), (OrderId(13), "Ada", 7)]
+        );
+        assert_eq!(
+            soa.find_by_id(OrderId(12)).unwrap().customer(),
+            CustomerId(3)
+        );
+        soa.compact();
+        let kept: Vec<CustomerId> = soa.iter().map(|v| v.customer()).collect();
+        assert_eq!(kept, vec![CustomerId(2), CustomerId(3), CustomerId(1)]);
+    }
+
+    #[test]
+    fn line_items_follow_their_orders() {
+        let mut soa = OrderSoA::default();
+        let a = soa.push_with_items(
+            OrderId(1),
+            Money(25.0),
+            Status::Pending,
+            1,
+            [
+                LineItem::new(100, 2, Money(5.0)),
+                LineItem::new(101, 1, Money(15.0)),
+            ],
+        );
+        soa.push(OrderId(2), Money(0.0), Status::Pending, 2);
+        soa.push_with_items(
+            OrderId(3),
+            Money(9.0),
+            Status::Completed,
+            3,
+            [LineItem::new(200, 3, Money(3.0))],
+        );
+
+        let items = soa.find_by_id(OrderId(1)).unwrap().line_items();
+        assert_eq!(items.skus(), &[100, 101]);
+        assert_eq!(items.total(), Money(25.0));
+        assert!(soa.find_by_id(OrderId(2)).unwrap().line_items().is_empty());
+        assert_eq!(
+            soa.order_totals_from_items(),
+            vec![Money(25.0), Money(0.0), Money(9.0)]
+        );
+
+        // Reordering moves only the offsets; compaction drops the removed order's items.
+        soa.sort_by_amount();
+        assert!(soa.view(a).is_err());
+        assert_eq!(soa.view_at(2).line_items().total(), Money(25.0));
+        soa.remove(soa.handle_at(2)).unwrap();
+        soa.compact();
+        assert_eq!(soa.line_item_columns().len(), 1);
+        let items = soa.find_by_id(OrderId(3)).unwrap().line_items();
+        assert_eq!(
+            items.iter().collect::<Vec<_>>(),
+            vec![LineItem::new(200, 3, Money(3.0))]
+        );
+    }
+
+    #[test]
+    fn str_columns_share_one_arena() {
+        let mut skus = StrColumn::interned();
+        for s in ["ABC-1", "XYZ-22", "ABC-1", "ABC-1"] {
+            skus.push(s);
+        }
+        assert_eq!(
+            skus.iter().collect::<Vec<_>>(),
+            ["ABC-1", "XYZ-22", "ABC-1", "ABC-1"]
+        );
+        assert_eq!(skus.arena_len(), "ABC-1XYZ-22".len());
+        skus.set(1, "ABC-1");
+        skus.compact();
+        assert_eq!(skus.arena_len(), "ABC-1".len());
+        // Equality is by value, not by arena layout or interning.
+        let mut plain = StrColumn::new();
+        for s in ["XYZ-22", "ABC-1", "ABC-1", "ABC-1"] {
+            plain.push(s);
+        }
+        plain.set(0, "ABC-1");
+        assert_eq!(plain, skus);
+        plain.set(3, "ABC-2");
+        assert_ne!(plain, skus);
+
+        let mut soa = DynSoA::new();
+        soa.push(OrderId(1), Money(1.0), Status::Pending, 1);
+        soa.add_str_column("channel", true).unwrap();
+        soa.push(OrderId(2), Money(2.0), Status::Pending, 2);
+        soa.view_mut(1).set_str("channel", "web").unwrap();
+        assert_eq!(soa.view(0).get_str("channel").unwrap(), "");
+        assert_eq!(soa.view(1).get_str("channel").unwrap(), "web");
+        assert!(soa.view(1).get::<u16>("channel").is_err());
+        soa.retain(|v| v.order().id() == OrderId(2));
+        assert_eq!(soa.str_column("channel").unwrap().get(0), "web");
+    }
+
+    #[test]
+    fn categorical_columns_group_on_codes() {
+        let mut soa = DynSoA::new();
+        soa.add_categorical_column("channel").unwrap();
+        for (i, ch) in ["web", "store", "web", "app", "web"]
+            .into_iter()
+            .enumerate()
+        {
+            let row = soa.push(
+                OrderId(i as u64),
+                Money(10.0 * (i + 1) as f64),
+                Status::Pending,
+                0,
+            );
+            soa.view_mut(row).set_category("channel", ch).unwrap();
+        }
+        soa.push(OrderId(9), Money(1.0), Status::Pending, 0);
+
+        let col = soa.categorical_column("channel").unwrap();
+        assert_eq!(
+            col.dict().names().collect::<Vec<_>>(),
+            ["", "web", "store", "app"]
+        );
+        assert_eq!(col.codes(), &[1, 2, 1, 3, 1, 0]);
+        assert_eq!(soa.view(3).get_category("channel").unwrap(), "app");
+
+        let groups = soa.group_by_category("channel").unwrap();
+        let summary: Vec<(&str, usize, f64)> = groups
+            .iter()
+            .map(|(n, g)| (*n, g.count, g.total.0))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("", 1, 1.0),
+                ("web", 3, 90.0),
+                ("store", 1, 20.0),
+                ("app", 1, 40.0)
+            ]
+        );
+
+        // Clones share the dictionary until one defines a new category.
+        let mut copy = col.clone();
+        assert!(Arc::ptr_eq(copy.dict(), col.dict()));
+        copy.push("phone");
+        assert!(!Arc::ptr_eq(copy.dict(), col.dict()));
+        assert_eq!(col.dict().code("phone"), None);
+    }
+
+    #[test]
+    fn compressed_segments_scan_like_the_kernel() {
+        let mut soa = OrderSoA::with_capacity(10_000);
+        for i in 0..10_000u64 {
+            let h = soa.push(
+                OrderId(1_000_000 + i),
+                Money((i % 50) as f64),
+                Status::ALL[(i / 1_000 % 3) as usize],
+                1_700_000_000_000 + i * 250,
+            );
+            if i % 7 == 0 {
+                soa.view_mut(h).unwrap().set_customer(CustomerId(i % 40));
+            }
+            if i % 97 == 0 {
+                soa.remove(h).unwrap();
+            }
+        }
+
+        let seg = soa.compress();
+        assert_eq!(seg.len(), soa.live_len());
+        assert!(seg.compressed_bytes() * 2 < seg.raw_bytes());
+        assert!(seg.iter().eq(soa.iter().map(OrderRow::from)));
+        for st in Status::ALL {
+            assert_eq!(seg.sum_by_status(st), soa.sum_by_status(st));
+        }
+        let back = seg.decompress();
+        assert!(back.iter().map(OrderRow::from).eq(seg.iter()));
+
+        let ts = DeltaColumn::encode([5, 3, u64::MAX, 0]);
+        assert_eq!(ts.iter().collect::<Vec<_>>(), [5, 3, u64::MAX, 0]);
+    }
+
+    #[test]
+    fn wal_recovers_every_logged_mutation() {
+        let dir = std::env::temp_dir().join(format!("ddd_dod_soa_wal_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("orders.wal");
+        let _ = std::fs::remove_file(&path);
+
+        let mut db = DurableOrderStore::open(&path).unwrap();
+        db.add(OrderId(1), Money(10.0), Status::Pending, 1).unwrap();
+        let h = db.add(OrderId(2), Money(20.0), Status::Pending, 2).unwrap();
+        db.add_batch([
+            OrderRow::new(OrderId(3), Money(30.0), Status::Pending, 3).with_customer(CustomerId(7))
+        ])
+        .unwrap();
+        assert!(matches!(
+            db.add_batch([OrderRow::new(OrderId(3), Money(1.0), Status::Pending, 4)]),
+            Err(WalError::Store(StoreError::DuplicateId(OrderId(3))))
+        ));
+        db.update_with(OrderId(1), |mut o| o.set_status(Status::Completed))
+            .unwrap();
+        db.remove(h).unwrap();
+        db.compact().unwrap();
+        db.upsert(OrderId(3), Money(35.0), Status::Pending, 6)
+            .unwrap();
+        let version = db.find_by_id(OrderId(3)).unwrap().version();
+        db.update_if_version(OrderId(3), version, |mut o| o.set_timestamp(7))
+            .unwrap();
+        assert!(matches!(
+            db.update_if_version(OrderId(3), version, |mut o| o.set_timestamp(8)),
+            Err(WalError::Store(StoreError::VersionConflict { .. }))
+        ));
+        db.for_each_mut(|mut o| o.set_amount(Money(o.amount().0 * 2.0)))
+            .unwrap();
+        assert_eq!(
+            db.transition_status(Status::Pending, Status::Cancelled, |_| true)
+                .unwrap(),
+            1
+        );
+        let expected: Vec<OrderRow> = db.kernel().iter().map(OrderRow::from).collect();
+        assert_eq!(expected[1].amount, Money(70.0));
+        assert_eq!(expected[1].ts, 7);
+        drop(db);
+
+        // Validators reject before anything is logged.
+        let capped = |c: &OrderCandidate| {
+            if c.row.amount.0 > 100.0 {
+                Err(DomainError::Violated("cap"))
+            } else {
+                Ok(())
+            }
+        };
+        let mut db = DurableOrderStore::open(&path)
+            .unwrap()
+            .with_validator(capped);
+        assert!(matches!(
+            db.update_with(OrderId(1), |mut o| o.set_amount(Money(500.0))),
+            Err(WalError::Store(StoreError::Invalid(..)))
+        ));
+        drop(db);
+
+        // A crash mid-append leaves a torn record: it is dropped, the rest replays.
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes.extend_from_slice(&[40, 0, 0, 0, 1, 2]);
+        std::fs::write(&path, &bytes).unwrap();
+        let recovered = OrderStore::recover(&path).unwrap();
+        let rows: Vec<OrderRow> = recovered.kernel().iter().map(OrderRow::from).collect();
+        assert_eq!(rows, expected);
+
+        // A checkpoint that cannot write its image leaves the store and the log in step.
+        let failed = dir.join("failed.wal");
+        let mut tmp = failed.clone().into_os_string();
+        tmp.push(".tmp");
+        std::fs::create_dir_all(&tmp).unwrap();
+        let mut db = DurableOrderStore::open(&failed).unwrap();
+        let first = db.add(OrderId(1), Money(1.0), Status::Pending, 1).unwrap();
+        db.add(OrderId(2), Money(2.0), Status::Pending, 2).unwrap();
+        db.remove(first).unwrap();
+        assert!(matches!(db.checkpoint(), Err(WalError::Io(_))));
+        db.update_with(OrderId(2), |mut o| o.set_amount(Money(99.0)))
+            .unwrap();
+        let live: Vec<OrderRow> = db.kernel().iter().map(OrderRow::from).collect();
+        drop(db);
+        let recovered = OrderStore::recover(&failed).unwrap();
+        let rows: Vec<OrderRow> = recovered.kernel().iter().map(OrderRow::from).collect();
+        assert_eq!(rows, live);
+        assert_eq!(rows[0].amount, Money(99.0));
+
+        let mut db = DurableOrderStore::open(&path).unwrap();
+        db.checkpoint().unwrap();
+        db.add(OrderId(4), Money(4.0), Status::Cancelled, 5)
+            .unwrap();
+        drop(db);
+        let recovered = OrderStore::recover(&path).unwrap();
+        assert_eq!(recovered.kernel().live_len(), 3);
+        assert_eq!(
+            recovered.find_by_id(OrderId(3)).unwrap().customer(),
+            CustomerId(7)
+        );
+
+        // Flipping a payload byte of a complete record is reported, not silently replayed.
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[10] ^= 0xff;
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(matches!(
+            OrderStore::recover(&path),
+            Err(WalError::Corrupt { offset: 0 })
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn subscribers_see_every_change() {
+        let mut repo = OrderStore::new();
+        let a = repo
+            .add(OrderId(1), Money(5.0), Status::Pending, 1)
+            .unwrap();
+        let rx = repo.subscribe();
+
+        let b = repo
+            .add(OrderId(2), Money(6.0), Status::Pending, 2)
+            .unwrap();
+        repo.update_with(OrderId(1), |mut o| {
+            o.set_status(Status::Completed);
+            o.set_amount(Money(5.0)); // unchanged: not reported
+            o.set_timestamp(9);
+        })
+        .unwrap();
+        repo.load(OrderId(2)).unwrap().cancel().unwrap();
+        repo.remove(a).unwrap();
+        let moved = repo.compact();
+        assert_eq!(moved, vec![None, Some(0)]);
+
+        use ChangeKind::*;
+        let events: Vec<(RowHandle, ChangeKind)> =
+            rx.try_iter().map(|e| (e.handle, e.kind)).collect();
+        let b2 = repo.kernel().handle_at(0);
+        assert_eq!(
+            events,
+            vec![
+                (b, Inserted),
+                (
+                    a,
+                    Updated {
+                        field: Field::Status
+                    }
+                ),
+                (
+                    a,
+                    Updated {
+                        field: Field::Timestamp
+                    }
+                ),
+                (
+                    b,
+                    Updated {
+                        field: Field::Status
+                    }
+                ),
+                (a, Deleted),
+                (b, Deleted),
+                (b2, Inserted),
+            ]
+        );
+
+        // Transactions publish on commit only; rollbacks publish nothing.
+        let mut tx = repo.begin();
+        tx.add(OrderId(3), Money(1.0), Status::Pending, 3).unwrap();
+        assert!(rx.try_recv().is_err());
+        tx.commit();
+        assert_eq!(rx.try_recv().unwrap().kind, Inserted);
+        let mut tx = repo.begin();
+        tx.add(OrderId(4), Money(1.0), Status::Pending, 4).unwrap();
+        tx.rollback();
+        repo.clone()
+            .add(OrderId(5), Money(1.0), Status::Pending, 5)
+            .unwrap();
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn async_repository_over_concurrent_store() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let repo = AsyncOrderStore::default();
+        rt.block_on(async {
+            repo.add(OrderId(1), Money(10.0), Status::Pending, 1)
+                .await
+                .unwrap();
+            repo.add(OrderId(2), Money(20.0), Status::Completed, 2)
+                .await
+                .unwrap();
+            assert_eq!(
+                repo.add(OrderId(3), Money(-1.0), Status::Pending, 3).await,
+                Err(StoreError::InvalidAmount(OrderId(3)))
+            );
+            assert_eq!(
+                repo.find_by_id(OrderId(2)).await.unwrap().amount,
+                Money(20.0)
+            );
+            assert!(repo.find_by_id(OrderId(3)).await.is_none());
+            let pending = repo.find_by_status(Status::Pending).await;
+            assert_eq!(
+                pending.iter().map(|r| r.id).collect::<Vec<_>>(),
+                [OrderId(1)]
+            );
+        });
+        assert_eq!(repo.store().snapshot().len(), 2);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn ingest_stream_batches_by_size_and_time() {
+        use std::future::Future as _;
+        use std::pin::Pin;
+        use std::task::{Context, Poll};
+        use std::time::Duration;
+
+        /// Rows, with `None` standing for a 30 ms lull in the feed.
+        struct Feed {
+            items: std::vec::IntoIter<Option<OrderRow>>,
+            lull: Option<Pin<Box<tokio::time::Sleep>>>,
+        }
+        impl futures_core::Stream for Feed {
+            type Item = OrderRow;
+            fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<OrderRow>> {
+                loop {
+                    if let Some(lull) = &mut self.lull {
+                        if lull.as_mut().poll(cx).is_pending() {
+                            return Poll::Pending;
+                        }
+                        self.lull = None;
+                    }
+                    match self.items.next() {
+                        Some(None) => {
+                            self.lull =
+                                Some(Box::pin(tokio::time::sleep(Duration::from_millis(30))))
+                        }
+                        Some(Some(row)) => return Poll::Ready(Some(row)),
+                        None => return Poll::Ready(None),
+                    }
+                }
+            }
+        }
+
+        let row = |id| Some(OrderRow::new(OrderId(id), Money(1.0), Status::Pending, id));
+        let feed = Feed {
+            items: vec![
+                row(1),
+                row(2),
+                row(3),
+                row(4),
+                row(5),
+                None,
+                row(6),
+                row(1),
+                row(7),
+            ]
+            .into_iter(),
+            lull: None,
+        };
+        let policy = BatchPolicy {
+            max_rows: 3,
+            max_delay: Duration::from_millis(5),
+        };
+        let mut store = OrderStore::new().with_id_policy(IdPolicy::Reject);
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        let stats = rt.block_on(store.ingest_stream(feed, policy));
+
+        // [1, 2, 3] fill a batch; [4, 5] time out in the lull; [6, 1, 7] falls back per row.
+        assert_eq!(stats.batches, 3);
+        assert_eq!(stats.appended, 7);
+        assert_eq!(
+            stats.rejected,
+            [(OrderId(1), StoreError::DuplicateId(OrderId(1)))]
+        );
+        assert_eq!(store.kernel().live_len(), 7);
+    }
+
+    #[test]
+    fn builder_configures_both_store_kinds() {
+        let builder = OrderStore::builder()
+            .capacity(64)
+            .id_policy(IdPolicy::Reject)
+            .status_index(true)
+            .validator(|c: &OrderCandidate| {
+                if c.row.amount.0 > 1_000.0 {
+                    Err(DomainError::Violated("amount over limit"))
+                } else {
+                    Ok(())
+                }
+            });
+
+        let mut store = builder.clone().build();
+        store
+            .add(OrderId(1), Money(10.0), Status::Pending, 1)
+            .unwrap();
+        assert_eq!(
+            store.add(OrderId(1), Money(20.0), Status::Pending, 2),
+            Err(StoreError::DuplicateId(OrderId(1)))
+        );
+        assert_eq!(
+            store.add(OrderId(2), Money(5_000.0), Status::Pending, 3),
+            Err(StoreError::Invalid(
+                OrderId(2),
+                DomainError::Violated("amount over limit")
+            ))
+        );
+        assert!(store
+            .add_batch([
+                OrderRow::new(OrderId(3), Money(1.0), Status::Pending, 4),
+                OrderRow::new(OrderId(4), Money(2_000.0), Status::Pending, 5),
+            ])
+            .is_err());
+        assert_eq!(store.kernel().len(), 1);
+        assert_eq!(store.find_by_status(Status::Pending).count(), 1);
+
+        let sharded = builder.shards(4).build_sharded();
+        assert_eq!(sharded.shard_count(), 4);
+        sharded
+            .add(OrderId(7), Money(1.0), Status::Pending, 1)
+            .unwrap();
+        assert_eq!(
+            sharded.add(OrderId(7), Money(2.0), Status::Pending, 2),
+            Err(StoreError::DuplicateId(OrderId(7)))
+        );
+        assert_eq!(
+            sharded.add(OrderId(8), Money(1_001.0), Status::Pending, 3),
+            Err(StoreError::Invalid(
+                OrderId(8),
+                DomainError::Violated("amount over limit")
+            ))
+        );
+        assert_eq!(sharded.len(), 1);
+    }
+
+    #[test]
+    fn validators_guard_adds_and_updates() {
+        let mut store = OrderStore::new()
+            .with_validator(PositiveAmount)
+            .with_validator(NotInFuture(|| 100));
+        let rx = store.subscribe();
+        let h = store
+            .add(OrderId(1), Money(10.0), Status::Pending, 50)
+            .unwrap();
+        assert_eq!(
+            store.add(OrderId(2), Money(0.0), Status::Pending, 50),
+            Err(StoreError::Invalid(
+                OrderId(2),
+                DomainError::NonPositiveAmount
+            ))
+        );
+        assert_eq!(
+            store.upsert(OrderId(1), Money(10.0), Status::Pending, 101),
+            Err(StoreError::Invalid(
+                OrderId(1),
+                DomainError::FutureTimestamp
+            ))
+        );
+        assert_eq!(
+            store.update_with(OrderId(1), |mut o| o.set_amount(Money(-1.0))),
+            Err(StoreError::Invalid(
+                OrderId(1),
+                DomainError::NonPositiveAmount
+            ))
+        );
+        // The rejected update was rolled back and never published.
+        assert_eq!(store.find_by_id(OrderId(1)).unwrap().amount(), Money(10.0));
+        store
+            .update_with(OrderId(1), |mut o| o.set_amount(Money(12.0)))
+            .unwrap();
+        let events: Vec<ChangeEvent> = rx.try_iter().collect();
+        assert_eq!(
+            events,
+            [
+                ChangeEvent {
+                    handle: h,
+                    kind: ChangeKind::Inserted
+                },
+                ChangeEvent {
+                    handle: h,
+                    kind: ChangeKind::Updated {
+                        field: Field::Amount
+                    }
+                },
+            ]
+        );
+
+        // Rules can look at the row being replaced.
+        let mut store = OrderStore::new().with_validator(|c: &OrderCandidate| match c.stored {
+            Some(old) if c.row.ts < old.ts => Err(DomainError::Violated("timestamp went back")),
+            _ => Ok(()),
+        });
+        store
+            .add(OrderId(1), Money(1.0), Status::Pending, 5)
+            .unwrap();
+        assert!(store
+            .upsert(OrderId(1), Money(2.0), Status::Pending, 4)
+            .is_err());
+        assert!(store
+            .upsert(OrderId(1), Money(2.0), Status::Pending, 6)
+            .is_ok());
+    }
+
+    #[test]
+    fn specifications_compose_and_match_views() {
+        let mut store = OrderStore::new();
+        for i in 0..40u64 {
+            let st = if i % 2 == 0 {
+                Status::Pending
+            } else {
+                Status::Completed
+            };
+            store.add(OrderId(i), Money(i as f64), st, i).unwrap();
+        }
+        store.remove(store.kernel().handle_at(38)).unwrap();
+
+        let spec = StatusIs(Status::Pending)
+            .and(AmountAtLeast(Money(10.0)).or(PlacedBetween(0, 4)))
+            .and(AmountAtLeast(Money(34.0)).not());
+        let ids: Vec<u64> = store.find(spec).map(|o| o.id().0).collect();
+        assert_eq!(ids, [0, 2, 10, 12, 14, 16, 18, 20, 22, 24, 26, 28, 30, 32]);
+        assert_eq!(store.find(AmountBelow(Money(2.0))).count(), 2);
+        // The column path and the view path agree row by row.
+        let soa = store.kernel();
+        for i in 0..soa.len() {
+            assert_eq!(
+                spec.is_satisfied_by(&soa.view_at(i)),
+                spec.matches_at(soa, i)
+            );
+        }
+
+        let custom = |o: &OrderView<'_>| o.id().0 % 10 == 5;
+        let ids: Vec<u64> = store
+            .find(custom.and(StatusIs(Status::Completed)))
+            .map(|o| o.id().0)
+            .collect();
+        assert_eq!(ids, [5, 15, 25, 35]);
+    }
+
+    #[test]
+    fn pages_resume_across_appends_and_compaction() {
+        let mut store = OrderStore::new();
+        for i in 0..10u64 {
+            store
+                .add(OrderId(i), Money(1.0), Status::Pending, i)
+                .unwrap();
+        }
+        let ids = |page: &[OrderView<'_>]| page.iter().map(|o| o.id().0).collect::<Vec<_>>();
+
+        let (page, cursor) = store.page(None, 4);
+        assert_eq!(ids(&page), [0, 1, 2, 3]);
+        let token = cursor.unwrap().to_string();
+
+        // Appends and removals behind or ahead of the cursor do not shift it.
+        store
+            .add(OrderId(10), Money(1.0), Status::Pending, 10)
+            .unwrap();
+        store.remove(store.kernel().handle_at(1)).unwrap();
+        store.remove(store.kernel().handle_at(5)).unwrap();
+        let (page, cursor) = store.page(Some(token.parse().unwrap()), 4);
+        assert_eq!(ids(&page), [4, 6, 7, 8]);
+
+        // Compaction moves rows; the cursor follows its last order.
+        store.compact();
+        let (page, cursor) = store.page(cursor, 4);
+        assert_eq!(ids(&page), [9, 10]);
+        assert_eq!(cursor, None);
+
+        assert_eq!("zz".parse::<Cursor>(), Err(InvalidCursor));
+        let empty = OrderStore::new();
+        let (page, cursor) = empty.page(None, 10);
+        assert!(page.is_empty() && cursor.is_none());
+    }
+
+    #[test]
+    fn amount_events_keep_amount_indexes_current() {
+        let mut store = OrderStore::builder()
+            .index(IndexSpec::new("amount").on(IndexColumn::Amount))
+            .build();
+        store
+            .add(OrderId(1), Money(10.0), Status::Pending, 1)
+            .unwrap();
+        let at_least = |store: &OrderStore, min: f64| {
+            store
+                .index_scan("amount")
+                .unwrap()
+                .range(Money(min)..)
+                .views()
+                .map(|o| o.id().0)
+                .collect::<Vec<_>>()
+        };
+        assert!(at_least(&store, 100.0).is_empty());
+        let event = OrderEvent::AmountChanged {
+            id: OrderId(1),
+            amount: Money(150.0),
+        };
+        store.apply(&event).unwrap();
+        assert_eq!(at_least(&store, 100.0), [1]);
+    }
+
+    #[test]
+    fn composite_index_range_scans_stay_current() {
+        let mut store = OrderStore::builder()
+            .index(
+                IndexSpec::new("status_ts")
+                    .on(IndexColumn::Status)
+                    .on(IndexColumn::Timestamp),
+            )
+            .build();
+        for i in 0..50u64 {
+            let st = Status::ALL[(i % 3) as usize];
+            store
+                .add(OrderId(i), Money(i as f64), st, 1_000 - i)
+                .unwrap();
+        }
+        let pending_between = |store: &OrderStore, from: u64, to: u64| {
+            store
+                .index_scan("status_ts")
+                .unwrap()
+                .eq(Status::Pending)
+                .range(from..to)
+                .views()
+                .map(|o| o.id().0)
+                .collect::<Vec<_>>()
+        };
+        // Key order: ascending timestamp within the status.
+        assert_eq!(
+            pending_between(&store, 960, 980),
+            [39, 36, 33, 30, 27, 24, 21]
+        );
+
+        // Appends are inserted into the built index; other writes drop and rebuild it.
+        store
+            .add(OrderId(99), Money(1.0), Status::Pending, 970)
+            .unwrap();
+        store.remove(store.kernel().handle_at(33)).unwrap();
+        store
+            .update_with(OrderId(30), |mut o| o.set_status(Status::Cancelled))
+            .unwrap();
+        assert_eq!(pending_between(&store, 960, 980), [39, 36, 99, 27, 24, 21]);
+        store.compact();
+        assert_eq!(pending_between(&store, 960, 980), [39, 36, 99, 27, 24, 21]);
+
+        let all_pending = store.index_scan("status_ts").unwrap().eq(Status::Pending);
+        assert_eq!(
+            all_pending.indices().count(),
+            store.find_by_status(Status::Pending).count()
+        );
+        assert_eq!(pending_between(&store, 980, 960), Vec::<u64>::new());
+        assert!(store.index_scan("missing").is_err());
+    }
+
+    #[test]
+    fn retain_with_remap_reports_removed_rows() {
+        let mut soa = OrderSoA::default();
+        for i in 0..6u64 {
+            soa.push(OrderId(i), Money(i as f64), Status::Pending, i);
+        }
+        soa.remove(soa.handle_at(1)).unwrap();
+        let (removed, remap) = soa.retain_with_remap(|o| o.amount().0 < 4.0);
+        let removed_ids: Vec<u64> = removed.iter().map(|r| r.id.0).collect();
+        assert_eq!(removed_ids, [4, 5]);
+        assert_eq!(removed[0].amount, Money(4.0));
+        assert_eq!(remap, [Some(0), None, Some(1), Some(2), None, None]);
+        for (old, new) in remap.iter().enumerate() {
+            if let Some(new) = *new {
+                assert_eq!(soa.view_at(new).id(), OrderId(old as u64));
+            }
+        }
+        assert_eq!(soa.len(), 3);
+    }
+
+    #[test]
+    fn swap_remove_and_remove_at_keep_columns_aligned() {
+        let mut soa = OrderSoA::default();
+        for i in 0..5u64 {
+            soa.push(
+                OrderId(i),
+                Money(i as f64),
+                Status::ALL[i as usize % 3],
+                10 + i,
+            );
+        }
+        let h3 = soa.handle_at(3);
+        soa.remove(soa.handle_at(4)).unwrap();
+
+        // The tombstoned last row moves into slot 1.
+        let row = soa.swap_remove(1).unwrap();
+        assert_eq!(
+            row,
+            OrderRow::new(OrderId(1), Money(1.0), Status::Completed, 11)
+        );
+        assert_eq!(soa.len(), 4);
+        assert_eq!(soa.live_len(), 3);
+        assert!(!soa.is_live(1));
+        assert!(soa.find_by_id(OrderId(1)).is_none());
+        assert!(soa.find_by_id(OrderId(4)).is_none());
+        assert!(soa.view(h3).is_ok());
+
+        let row = soa.remove_at(0).unwrap();
+        assert_eq!(row.id, OrderId(0));
+        let ids: Vec<u64> = soa.iter().map(|o| o.id().0).collect();
+        assert_eq!(ids, [2, 3]);
+        assert_eq!(soa.live_len(), 2);
+        assert_eq!(soa.find_by_id(OrderId(3)).unwrap().timestamp(), 13);
+        assert_eq!(
+            soa.find_by_status(Status::Pending)
+                .map(|o| o.id().0)
+                .collect::<Vec<_>>(),
+            [3]
+        );
+        assert_eq!(soa.view(h3).err(), Some(StoreError::StaleHandle(h3)));
+        assert!(soa.swap_remove(5).is_err());
+    }
+
+    #[test]
+    fn reserve_shrink_and_memory_report() {
+        let mut soa = OrderSoA::default();
+        soa.reserve(1_000);
+        let report = soa.memory_usage();
+        assert_eq!(report.used_bytes(), 0);
+        let amounts = report.column("amounts").unwrap();
+        assert!(amounts.capacity >= 1_000);
+        assert_eq!(amounts.allocated_bytes, amounts.capacity * 8);
+
+        for i in 0..10u64 {
+            soa.push(OrderId(i), Money(1.0), Status::Pending, i);
+        }
+        soa.shrink_to_fit();
+        let report = soa.memory_usage();
+        assert_eq!(report.column("timestamps").unwrap().used_bytes, 80);
+        assert!(report.allocated_bytes() < 1_000 * 8);
+        assert!(report.shards.is_empty());
+
+        let mut sharded = ShardedOrderStore::with_shards(4, 0);
+        sharded.reserve(400);
+        for i in 0..40u64 {
+            sharded
+                .add(OrderId(i), Money(1.0), Status::Pending, i)
+                .unwrap();
+        }
+        let report = sharded.memory_usage();
+        assert_eq!(report.shards.len(), 4);
+        assert_eq!(report.column("ids").unwrap().len, 40);
+        assert!(report
+            .shards
+            .iter()
+            .all(|s| s.column("ids").unwrap().capacity >= 100));
+        assert_eq!(
+            report.used_bytes(),
+            report
+                .shards
+                .iter()
+                .map(MemoryReport::used_bytes)
+                .sum::<usize>()
+        );
+    }
+
+    #[test]
+    fn owned_rows_round_trip_through_the_kernel() {
+        let rows: Vec<OrderRow> = (0..4u64)
+            .map(|i| {
+                OrderRow::new(OrderId(i), Money(i as f64), Status::Pending, i)
+                    .with_customer(CustomerId(i % 2))
+            })
+            .collect();
+        let mut soa: OrderSoA = rows.iter().copied().collect();
+        assert_eq!(soa.len(), 4);
+        assert_eq!(soa.get_row(3), Some(rows[3]));
+        assert_eq!(soa.get_row(4), None);
+
+        soa.extend(rows[..2].iter().map(|r| OrderRow { ts: 99, ..*r }));
+        assert_eq!(soa.len(), 6);
+        assert_eq!(soa.find_by_id(OrderId(1)).unwrap().timestamp(), 99);
+        let back: Vec<OrderRow> = soa.iter().map(OrderRow::from).take(4).collect();
+        assert_eq!(back, rows);
+    }
+
+    #[test]
+    fn append_split_off_and_partition_move_whole_columns() {
+        let build = |ids: std::ops::Range<u64>| {
+            let mut soa = OrderSoA::default().with_status_index();
+            for i in ids {
+                let st = if i % 2 == 0 {
+                    Status::Pending
+                } else {
+                    Status::Completed
+                };
+                soa.push_with_items(
+                    OrderId(i),
+                    Money(i as f64),
+                    st,
+                    i,
+                    [LineItem::new(i, 1, Money(i as f64))],
+                );
+            }
+            soa
+        };
+        let mut hot = build(0..4);
+        let mut cold = build(4..8);
+        cold.remove(cold.handle_at(1)).unwrap();
+        hot.append(cold);
+        assert_eq!(hot.len(), 8);
+        assert_eq!(hot.live_len(), 7);
+        assert!(hot.find_by_id(OrderId(5)).is_none());
+        assert_eq!(hot.find_by_id(OrderId(6)).unwrap().line_items().skus(), [6]);
+        assert_eq!(hot.find_by_status(Status::Pending).count(), 4);
+
+        let tail = hot.split_off(5);
+        assert_eq!((hot.len(), tail.len()), (5, 3));
+        assert_eq!((hot.live_len(), tail.live_len()), (5, 2));
+        assert!(hot.find_by_id(OrderId(6)).is_none());
+        assert_eq!(
+            tail.find_by_id(OrderId(7)).unwrap().line_items().skus(),
+            [7]
+        );
+        assert_eq!(hot.find_by_id(OrderId(4)).unwrap().line_items().skus(), [4]);
+        assert_eq!(hot.line_item_columns().len(), 5);
+
+        let (pending, rest) = hot.partition(|o| o.status() == Status::Pending);
+        let ids = |soa: &OrderSoA| soa.iter().map(|o| o.id().0).collect::<Vec<_>>();
+        assert_eq!(ids(&pending), [0, 2, 4]);
+        assert_eq!(ids(&rest), [1, 3]);
+        assert_eq!(pending.find_by_status(Status::Pending).count(), 3);
+        assert_eq!(
+            rest.find_by_id(OrderId(3)).unwrap().line_items().total(),
+            Money(3.0)
+        );
+    }
+
+    #[test]
+    fn routers_and_rebalancing() {
+        // Strided ids all land on one shard under `id % n`.
+        let mut sharded = ShardedOrderStore::with_shards(4, 0);
+        for i in 0..64u64 {
+            sharded
+                .add(OrderId(i * 4), Money(i as f64), Status::Pending, i)
+                .unwrap();
+        }
+        let sizes = |s: &ShardedOrderStore| {
+            (0..s.shard_count())
+                .map(|si| s.read_shard(si).live_len())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(sizes(&sharded), [64, 0, 0, 0]);
+
+        sharded.set_router(HashRouter);
+        assert!(sizes(&sharded).iter().all(|&n| n > 0));
+        sharded.rebalance(8);
+        assert_eq!(sharded.shard_count(), 8);
+        assert_eq!(sizes(&sharded).iter().sum::<usize>(), 64);
+        assert_eq!(sharded.find_by_id(OrderId(40)).unwrap().amount, Money(10.0));
+        assert_eq!(
+            sharded.sum_by_status(Status::Pending),
+            Money((0..64).sum::<u64>() as f64)
+        );
+
+        let ranged = OrderStore::builder()
+            .shards(3)
+            .router(RangeRouter::new(vec![100, 200]))
+            .build_sharded();
+        for id in [5, 150, 250, 99, 1_000] {
+            ranged
+                .add(OrderId(id), Money(1.0), Status::Pending, 0)
+                .unwrap();
+        }
+        assert_eq!(sizes(&ranged), [2, 1, 2]);
+
+        let custom = OrderStore::builder()
+            .shards(2)
+            .router(|id: OrderId, _: usize| usize::from(id.0 >= 10))
+            .build_sharded();
+        custom
+            .add(OrderId(3), Money(1.0), Status::Pending, 0)
+            .unwrap();
+        assert_eq!(sizes(&custom), [1, 0]);
+    }
+
+    #[test]
+    fn shard_stats_expose_hot_shards() {
+        let mut sharded = ShardedOrderStore::with_shards(4, 0);
+        for i in 0..32u64 {
+            sharded
+                .add(OrderId(i * 4 + 1), Money(1.0), Status::Pending, i)
+                .unwrap();
+        }
+        sharded
+            .update(OrderId(1), |o| o.set_amount(Money(2.0)))
+            .unwrap();
+        let stats = sharded.shard_stats();
+        assert_eq!(stats.len(), 4);
+        assert_eq!((stats[1].rows, stats[1].writes), (32, 33));
+        assert!(stats[1].bytes > 0 && stats[1].last_write_ms.is_some());
+        assert_eq!(
+            (stats[0].rows, stats[0].writes, stats[0].last_write_ms),
+            (0, 0, None)
+        );
+
+        let skew = sharded.skew_report();
+        assert_eq!(skew.hottest_shard, 1);
+        assert_eq!(skew.max_to_mean_rows, 4.0);
+        assert!(skew.is_skewed(2.0));
+
+        sharded.set_router(HashRouter);
+        let skew = sharded.skew_report();
+        assert!(skew.max_to_mean_rows < 2.0);
+        assert_eq!(skew.max_to_mean_writes, 1.0);
+    }
+
+    #[test]
+    fn ingest_routes_rows_to_shards_and_flushes() {
+        let store = Arc::new(
+            OrderStore::builder()
+                .shards(4)
+                .id_policy(IdPolicy::Reject)
+                .build_sharded(),
+        );
+        let (tx, rx) = std::sync::mpsc::channel();
+        let pipeline = store.ingest(rx, 8);
+        for i in 0..1000u64 {
+            tx.send(OrderRow::new(OrderId(i), Money(1.0), Status::Pending, i).into())
+                .unwrap();
+        }
+        tx.send(OrderRow::new(OrderId(7), Money(2.0), Status::Completed, 0).into())
+            .unwrap();
+        let (flush, done) = IngestMsg::flush();
+        tx.send(flush).unwrap();
+        done.recv().unwrap();
+        assert_eq!(store.len(), 1000);
+        assert!(store.shard_stats().iter().all(|s| s.rows == 250));
+
+        drop(tx);
+        let stats = pipeline.join();
+        assert_eq!(stats.appended, 1000);
+        assert_eq!(stats.rejected.len(), 1);
+        assert!(matches!(
+            stats.rejected[0],
+            (OrderId(7), StoreError::DuplicateId(_))
+        ));
+    }
+
+    #[test]
+    fn frozen_segments_prune_by_zone_map() {
+        let mut store = SegmentedOrderStore::new(100);
+        for i in 0..1000u64 {
+            store.push(OrderRow::new(
+                OrderId(i),
+                Money(i as f64),
+                Status::Pending,
+                i * 10,
+            ));
+        }
+        store.push(OrderRow::new(OrderId(1000), Money(5.0), Status::Pending, 5));
+        assert_eq!(store.frozen().len(), 10);
+        assert_eq!(store.len(), 1001);
+        let zone = store.frozen()[2].zone_map();
+        assert_eq!((zone.min_ts, zone.max_ts), (2000, 2990));
+        assert_eq!(zone.max_amount, Money(299.0));
+
+        // [2500, 3500) spans segments 2 and 3; the active row is always scanned.
+        assert_eq!(store.segments_in_time_range(2500, 3500).count(), 2);
+        let hits: Vec<u64> = store
+            .find_in_time_range(2500, 3500)
+            .map(|v| v.id().0)
+            .collect();
+        assert_eq!(hits, (250..350).collect::<Vec<_>>());
+        assert_eq!(store.find_in_time_range(0, 10).count(), 2);
+
+        assert_eq!(store.segments_with_amount_at_least(Money(950.0)).count(), 1);
+        assert_eq!(store.find_amount_at_least(Money(950.0)).count(), 50);
+        assert_eq!(store.find_by_id(OrderId(420)).unwrap().timestamp(), 4200);
+        assert!(store.find_by_id(OrderId(5000)).is_none());
+
+        // Compressed segments answer the same; only the segments a query reaches are decoded.
+        let mut compressed = SegmentedOrderStore::new(100).with_compression();
+        for v in store.frozen().iter().flat_map(|s| s.kernel().iter()) {
+            compressed.push(OrderRow::from(v));
+        }
+        assert!(compressed.frozen().iter().all(|s| s.compressed().is_some()));
+        let hits: Vec<u64> = compressed
+            .find_in_time_range(2500, 3500)
+            .map(|v| v.id().0)
+            .collect();
+        assert_eq!(hits, (250..350).collect::<Vec<_>>());
+        let decoded: Vec<bool> = compressed.frozen().iter().map(|s| s.is_decoded()).collect();
+        assert_eq!(decoded.iter().filter(|&&d| d).count(), 2);
+        assert!(decoded[2] && decoded[3]);
+        compressed.trim_decoded();
+        assert!(!compressed.frozen().iter().any(FrozenSegment::is_decoded));
+        assert_eq!(
+            compressed.find_by_id(OrderId(420)).unwrap().timestamp(),
+            4200
+        );
+    }
+
+    #[test]
+    fn bitmap_predicates_combine_column_at_a_time() {
+        let mut soa = OrderSoA::with_capacity(200);
+        let mut handles = Vec::new();
+        for i in 0..200u64 {
+            let status = if i % 3 == 0 {
+                Status::Completed
+            } else {
+                Status::Pending
+            };
+            handles.push(soa.push(OrderId(i), Money(i as f64), status, i));
+        }
+        soa.remove(handles[150]).unwrap();
+
+        let sel = &(&soa.eq_status(Status::Completed) & &soa.gte_amount(Money(100.0)))
+            | &soa.ts_between(0, 3);
+        let expected: Vec<u64> = (0..3)
+            .chain((100..200).filter(|i| i % 3 == 0 && *i != 150))
+            .collect();
+        let ids: Vec<u64> = soa.selected(&sel).map(|v| v.id().0).collect();
+        assert_eq!(ids, expected);
+        assert_eq!(
+            soa.sum_selected(&sel).0,
+            expected.iter().map(|&i| i as f64).sum::<f64>()
+        );
+
+        let not_pending = soa.and_live(!&soa.eq_status(Status::Pending));
+        assert_eq!(not_pending, soa.eq_status(Status::Completed));
+        assert_eq!(soa.live_selection().count_ones(), 199);
+    }
+
+    #[test]
+    fn late_materialized_query_matches_fused_scan() {
+        let mut soa = OrderSoA::with_capacity(300);
+        let mut handles = Vec::new();
+        for i in 0..300u64 {
+            let status = [Status::Pending, Status::Completed, Status::Cancelled][i as usize % 3];
+            handles.push(soa.push(OrderId(i), Money((i % 50) as f64), status, i));
+        }
+        soa.remove(handles[31]).unwrap();
+        let q = soa
+            .query()
+            .status(Status::Completed)
+            .amount_gte(Money(20.0))
+            .amount_lt(Money(40.0))
+            .ts_between(10, 250);
+        let sel = q.select();
+        assert_eq!(sel.indices().collect::<Vec<_>>(), q.indices());
+        assert_eq!(sel.count(), q.count());
+        assert_eq!(sel.sum(), q.sum());
+        let projected: Vec<(OrderId, Money)> = sel.project::<(cols::Id, cols::Amount)>().collect();
+        let viewed: Vec<(OrderId, Money)> = sel.views().map(|v| (v.id(), v.amount())).collect();
+        assert_eq!(projected, viewed);
+        assert!(!projected.iter().any(|(id, _)| id.0 == 31));
+        assert_eq!(soa.query().select().count(), 299);
+    }
+
+    #[test]
+    fn cached_aggregates_track_every_write_path() {
+        let mut store = OrderStore::new().with_cached_aggregates();
+        let check = |store: &OrderStore| {
+            assert_eq!(store.group_by_status(), store.kernel().group_by_status());
+            for s in Status::ALL {
+                assert_eq!(store.sum_by_status(s), store.kernel().sum_by_status(s));
+            }
+        };
+        let mut handles = Vec::new();
+        for i in 0..20u64 {
+            handles.push(
+                store
+                    .add(OrderId(i), Money(i as f64), Status::Pending, i)
+                    .unwrap(),
+            );
+        }
+        store
+            .add_batch((20..25).map(|i| OrderRow::new(OrderId(i), Money(1.0), Status::Pending, i)))
+            .unwrap();
+        check(&store);
+
+        // Removing the current minimum and maximum leaves the extrema to be rescanned.
+        store.remove(handles[0]).unwrap();
+        store.remove(handles[19]).unwrap();
+        check(&store);
+        store
+            .update_with(OrderId(5), |mut o| o.set_status(Status::Cancelled))
+            .unwrap();
+        store.load(OrderId(6)).unwrap().complete().unwrap();
+        store
+            .load(OrderId(7))
+            .unwrap()
+            .adjust_amount(Money(70.0))
+            .unwrap();
+        store
+            .apply(&OrderEvent::AmountChanged {
+                id: OrderId(8),
+                amount: Money(3.0),
+            })
+            .unwrap();
+        store
+            .upsert(OrderId(9), Money(4.0), Status::Completed, 9)
+            .unwrap();
+        store.for_each_mut(|mut o| {
+            if o.id().0 % 4 == 0 {
+                o.set_status(Status::Cancelled);
+            }
+        });
+        check(&store);
+        assert_eq!(
+            store.group_by_status().get(Status::Pending).max,
+            Some(Money(70.0))
+        );
+
+        // Untracked writes make the cache stale until the next tracked write rebuilds it.
+        store.kernel_mut().view_mut_at(10).set_amount(Money(1000.0));
+        check(&store);
+        store
+            .add(OrderId(99), Money(2.0), Status::Pending, 99)
+            .unwrap();
+        check(&store);
+    }
+
+    #[test]
+    fn materialized_views_follow_store_writes() {
+        let mut store = OrderStore::new();
+        for i in 0..10u64 {
+            let row = OrderRow::new(OrderId(i), Money(10.0), Status::Pending, i * 40_000)
+                .with_customer(CustomerId(i % 3));
+            store.add_batch([row]).unwrap();
+        }
+        let revenue = store.register_view(RevenuePerDay::default());
+        let open = store.register_view(OpenOrderCount::default());
+        let customers = store.register_view(CustomerTotals::default());
+        assert_eq!(store.materialized(open).0, 10);
+        assert_eq!(
+            store.materialized(customers).total(CustomerId(0)),
+            Money(40.0)
+        );
+
+        store.load(OrderId(0)).unwrap().complete().unwrap();
+        store.load(OrderId(1)).unwrap().complete().unwrap();
+        store.load(OrderId(3)).unwrap().complete().unwrap();
+        store.load(OrderId(2)).unwrap().cancel().unwrap();
+        store
+            .update_with(OrderId(1), |mut o| o.set_amount(Money(25.0)))
+            .unwrap();
+        let h = store.kernel().handle_at(9);
+        store.remove(h).unwrap();
+        assert_eq!(store.materialized(open).0, 5);
+        assert_eq!(
+            store.materialized(revenue).iter().collect::<Vec<_>>(),
+            vec![(0, Money(35.0)), (1, Money(10.0))]
+        );
+        assert_eq!(
+            store.materialized(customers).total(CustomerId(2)),
+            Money(20.0)
+        );
+
+        // Untracked writes are caught up by the next tracked one.
+        store
+            .kernel_mut()
+            .view_mut_at(4)
+            .set_status(Status::Cancelled);
+        store
+            .add(OrderId(50), Money(1.0), Status::Pending, 0)
+            .unwrap();
+        let mut fresh = store.clone();
+        fresh.refresh_views();
+        assert_eq!(store.materialized(open), fresh.materialized(open));
+        assert_eq!(store.materialized(revenue), fresh.materialized(revenue));
+        assert_eq!(store.materialized(customers), fresh.materialized(customers));
+        assert_eq!(store.materialized(open).0, 5);
+    }
+
+    #[test]
+    fn outbox_delivers_aggregate_events_in_order_with_retry() {
+        let mut store = OrderStore::new().with_outbox();
+        for i in 0..3u64 {
+            store
+                .add(OrderId(i), Money(10.0), Status::Pending, i)
+                .unwrap();
+        }
+        store.load(OrderId(0)).unwrap().complete().unwrap();
+        store
+            .load(OrderId(1))
+            .unwrap()
+            .adjust_amount(Money(12.0))
+            .unwrap();
+        assert!(store.load(OrderId(0)).unwrap().cancel().is_err());
+
+        // Rolled-back commands leave no event behind.
+        let mut tx = store.begin();
+        tx.load(OrderId(2)).unwrap().cancel().unwrap();
+        tx.rollback();
+        let mut tx = store.begin();
+        tx.load(OrderId(1)).unwrap().cancel().unwrap();
+        tx.commit();
+
+        let outbox = store.outbox_mut().unwrap();
+        let pending: Vec<OrderEvent> = outbox.pending().map(|(_, e)| *e).collect();
+        assert_eq!(
+            pending,
+            vec![
+                OrderEvent::StatusChanged {
+                    id: OrderId(0),
+                    status: Status::Completed
+                },
+                OrderEvent::AmountChanged {
+                    id: OrderId(1),
+                    amount: Money(12.0)
+                },
+                OrderEvent::Cancelled { id: OrderId(1) },
+            ]
+        );
+        let mut seen = Vec::new();
+        let mut broker = |seq: u64, _: &OrderEvent| {
+            if seq == 1 && !seen.contains(&seq) {
+                seen.push(seq);
+                return Err("broker down");
+            }
+            seen.push(seq);
+            Ok(())
+        };
+        let first = outbox.deliver(&mut broker);
+        assert_eq!(first.delivered, 1);
+        assert_eq!(first.failed, Some((1, "broker down")));
+        let second = outbox.deliver(&mut broker);
+        assert_eq!((second.delivered, second.failed), (2, None));
+        assert_eq!(seen, vec![0, 1, 1, 2]);
+        assert_eq!(outbox.attempts(1), Some(2));
+        assert_eq!(outbox.purge_acked(), 3);
+        assert!(outbox.is_empty());
+    }
+
+    #[test]
+    fn broker_adapters_feed_and_drain_the_store() {
+        use adapters::{Consumed, EventSink, EventSource};
+
+        /// In-memory topic: `committed` trails `read` until the consumer commits.
+        #[derive(Default)]
+        struct Topic {
+            events: Vec<OrderEvent>,
+            read: usize,
+            committed: usize,
+            flushed: usize,
+            fail_flush: bool,
+        }
+        impl EventSource for Topic {
+            type Error = ();
+            fn poll_events(&mut self, max: usize) -> Result<Vec<OrderEvent>, ()> {
+                let end = (self.read + max).min(self.events.len());
+                let batch = self.events[self.read..end].to_vec();
+                self.read = end;
+                Ok(batch)
+            }
+            fn commit(&mut self) -> Result<(), ()> {
+                self.committed = self.read;
+                Ok(())
+            }
+        }
+        impl EventSink for Topic {
+            type Error = &'static str;
+            fn send(&mut self, _: u64, event: &OrderEvent) -> Result<(), &'static str> {
+                self.events.push(*event);
+                Ok(())
+            }
+            fn flush(&mut self) -> Result<(), &'static str> {
+                if self.fail_flush {
+                    return Err("not acknowledged");
+                }
+                self.flushed = self.events.len();
+                Ok(())
+            }
+        }
+
+        let created =
+            |id| OrderEvent::Created(OrderRow::new(OrderId(id), Money(5.0), Status::Pending, id));
+        let mut inbound = Topic {
+            events: vec![
+                created(1),
+                created(2),
+                OrderEvent::Cancelled { id: OrderId(9) },
+                OrderEvent::StatusChanged {
+                    id: OrderId(1),
+                    status: Status::Completed,
+                },
+            ],
+            ..Topic::default()
+        };
+        let mut store = OrderStore::new().with_outbox();
+        let first = store.consume_events(&mut inbound, 3).unwrap();
+        assert_eq!(first.applied, 2);
+        assert_eq!(
+            first.rejected,
+            [(
+                OrderEvent::Cancelled { id: OrderId(9) },
+                StoreError::UnknownId(OrderId(9))
+            )]
+        );
+        assert_eq!(inbound.committed, 3);
+        assert_eq!(store.consume_events(&mut inbound, 3).unwrap().applied, 1);
+        assert_eq!(
+            store.consume_events(&mut inbound, 3).unwrap(),
+            Consumed::default()
+        );
+        assert_eq!(
+            store.find_by_id(OrderId(1)).unwrap().status(),
+            Status::Completed
+        );
+
+        // Projected events are not re-emitted; aggregate commands are.
+        assert_eq!(store.outbox().unwrap().pending_len(), 0);
+        store.load(OrderId(2)).unwrap().cancel().unwrap();
+        let mut outbound = Topic {
+            fail_flush: true,
+            ..Topic::default()
+        };
+        assert_eq!(store.publish_outbox(&mut outbound), Err("not acknowledged"));
+        assert_eq!(store.outbox().unwrap().pending_len(), 1);
+        outbound.fail_flush = false;
+        assert_eq!(store.publish_outbox(&mut outbound), Ok(1));
+        assert_eq!(store.outbox().unwrap().pending_len(), 0);
+        // At least once: the unacknowledged first attempt was sent again.
+        assert_eq!(
+            outbound.events,
+            [OrderEvent::Cancelled { id: OrderId(2) }; 2]
+        );
+        assert_eq!(outbound.flushed, 2);
+    }
+
+    #[cfg(feature = "kafka")]
+    #[test]
+    fn kafka_events_round_trip_through_payloads() {
+        use adapters::kafka::{decode, encode};
+
+        let event = OrderEvent::AmountChanged {
+            id: OrderId(42),
+            amount: Money(9.5),
+        };
+        let (key, payload) = encode(&event);
+        assert_eq!(key, "42");
+        assert_eq!(decode(&payload).unwrap(), event);
+        assert!(decode(b"not json").is_err());
+    }
+
+    #[test]
+    fn update_if_version_rejects_stale_writers() {
+        let mut store = OrderStore::new();
+        store
+            .add(OrderId(1), Money(10.0), Status::Pending, 0)
+            .unwrap();
+        let read = store.find_by_id(OrderId(1)).unwrap().version();
+        assert_eq!(read, 0);
+
+        // Two writers read version 0; the first wins, the second sees the conflict.
+        store
+            .update_if_version(OrderId(1), read, |mut o| o.set_amount(Money(11.0)))
+            .unwrap();
+        let err = store
+            .update_if_version(OrderId(1), read, |mut o| o.set_status(Status::Cancelled))
+            .unwrap_err();
+        assert_eq!(
+            err,
+            StoreError::VersionConflict {
+                id: OrderId(1),
+                expected: 0,
+                found: 1
+            }
+        );
+        let v = store.find_by_id(OrderId(1)).unwrap();
+        assert_eq!(
+            (v.amount(), v.status(), v.version()),
+            (Money(11.0), Status::Pending, 1)
+        );
+
+        // A rejected update leaves the version where it was.
+        let mut store = store.with_validator(PositiveAmount);
+        assert!(store
+            .update_if_version(OrderId(1), 1, |mut o| o.set_amount(Money(-1.0)))
+            .is_err());
+        assert_eq!(store.find_by_id(OrderId(1)).unwrap().version(), 1);
+        assert!(matches!(
+            store.update_if_version(OrderId(9), 0, |_| ()),
+            Err(StoreError::UnknownId(_))
+        ));
+    }
+
+    #[test]
+    fn project_maps_orders_into_read_models() {
+        #[derive(Debug, PartialEq)]
+        struct OrderDto {
+            id: u64,
+            total: String,
+        }
+        impl Project for OrderDto {
+            fn project(view: OrderView<'_>) -> Self {
+                OrderDto {
+                    id: view.id().0,
+                    total: format!("{:.2}", view.amount().0),
+                }
+            }
+        }
+
+        let mut store = OrderStore::new();
+        let handles: Vec<_> = (0..4u64)
+            .map(|i| {
+                store
+                    .add(OrderId(i), Money(i as f64 * 10.0), Status::Pending, i)
+                    .unwrap()
+            })
+            .collect();
+        store.remove(handles[0]).unwrap();
+        store
+            .update_with(OrderId(3), |mut o| o.set_status(Status::Completed))
+            .unwrap();
+
+        let all: Vec<OrderDto> = store.project_all();
+        assert_eq!(all.iter().map(|d| d.id).collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(
+            store.project_where::<OrderDto, _>(StatusIs(Status::Pending)),
+            vec![
+                OrderDto {
+                    id: 1,
+                    total: "10.00".into()
+                },
+                OrderDto {
+                    id: 2,
+                    total: "20.00".into()
+                },
+            ]
+        );
+        let rows: Vec<OrderRow> = store.project_where(StatusIs(Status::Completed));
+        assert_eq!(
+            rows,
+            vec![OrderRow::from(store.find_by_id(OrderId(3)).unwrap())]
+        );
+    }
+
+    #[test]
+    fn stats_summarize_live_columns() {
+        let mut soa = OrderSoA::default();
+        assert_eq!(soa.stats(), ColumnStats::default());
+        soa.push(OrderId(1), Money(2.0), Status::Pending, 30);
+        soa.push(OrderId(2), Money(4.0), Status::Completed, 10);
+        soa.push(OrderId(3), Money(4.0), Status::Completed, 20);
+        let gone = soa.push(OrderId(4), Money(1000.0), Status::Cancelled, 0);
+        soa.push(OrderId(5), Money(6.0), Status::Completed, 40);
+        soa.remove(gone).unwrap();
+
+        let stats = soa.stats();
+        assert_eq!(stats.rows, 4);
+        assert_eq!(stats.amount.min, Some(Money(2.0)));
+        assert_eq!(stats.amount.max, Some(Money(6.0)));
+        assert_eq!(stats.amount.mean, Some(Money(4.0)));
+        // Population variance of [2, 4, 4, 6] is 2.
+        assert!((stats.amount.stddev.unwrap().0 - 2f64.sqrt()).abs() < 1e-12);
+        assert_eq!((stats.min_ts, stats.max_ts), (Some(10), Some(40)));
+        assert_eq!(stats.status_count(Status::Completed), 3);
+        assert_eq!(stats.status_count(Status::Cancelled), 0);
+        assert_eq!(stats.distinct_statuses(), 2);
+    }
+
+    #[test]
+    fn saga_fulfills_or_compensates_orders_from_the_log() {
+        /// Scripted outcomes per (step, order id); unscripted steps succeed.
+        #[derive(Default)]
+        struct Steps {
+            script: HashMap<(&'static str, u64), StepOutcome>,
+            calls: Vec<(&'static str, u64)>,
+        }
+        impl Steps {
+            fn step(&mut self, name: &'static str, order: &OrderRow) -> StepOutcome {
+                self.calls.push((name, order.id.0));
+                *self
+                    .script
+                    .get(&(name, order.id.0))
+                    .unwrap_or(&StepOutcome::Done)
+            }
+        }
+        impl FulfillmentSteps for Steps {
+            fn reserve_inventory(&mut self, o: &OrderRow) -> StepOutcome {
+                self.step("reserve", o)
+            }
+            fn release_inventory(&mut self, o: &OrderRow) {
+                self.step("release", o);
+            }
+            fn charge_payment(&mut self, o: &OrderRow) -> StepOutcome {
+                self.step("charge", o)
+            }
+            fn refund_payment(&mut self, o: &OrderRow) {
+                self.step("refund", o);
+            }
+            fn ship(&mut self, o: &OrderRow) -> StepOutcome {
+                self.step("ship", o)
+            }
+        }
+
+        let mut log = EventLog::new();
+        let mut store = OrderStore::new();
+        for i in 1..=3u64 {
+            let event =
+                OrderEvent::Created(OrderRow::new(OrderId(i), Money(5.0), Status::Pending, i));
+            store.apply(&event).unwrap();
+            log.append(event);
+        }
+        let mut steps = Steps::default();
+        steps.script.insert(("ship", 2), StepOutcome::Failed);
+        steps.script.insert(("charge", 3), StepOutcome::Pending);
+        let mut saga = FulfillmentSaga::new();
+        saga.run(&mut log, &mut store, &mut steps).unwrap();
+
+        // 1 shipped; 2 failed to ship and was refunded, then released; 3 awaits payment.
+        assert_eq!(saga.state(OrderId(1)), Some(SagaState::Completed));
+        assert_eq!(saga.state(OrderId(2)), Some(SagaState::Compensated));
+        assert_eq!(saga.state(OrderId(3)), Some(SagaState::InventoryReserved));
+        let for_2: Vec<_> = steps
+            .calls
+            .iter()
+            .filter(|c| c.1 == 2)
+            .map(|c| c.0)
+            .collect();
+        assert_eq!(for_2, ["reserve", "charge", "ship", "refund", "release"]);
+        let status = |id| store.find_by_id(OrderId(id)).unwrap().status();
+        assert_eq!(
+            (status(1), status(2), status(3)),
+            (Status::Completed, Status::Cancelled, Status::Pending)
+        );
+        assert_eq!(log.len(), 5);
+
+        // Pending steps are retried; a cancellation while in flight compensates.
+        saga.run(&mut log, &mut store, &mut steps).unwrap();
+        assert_eq!(saga.attempts(OrderId(3)), Some(3));
+        let cancel = OrderEvent::Cancelled { id: OrderId(3) };
+        store.apply(&cancel).unwrap();
+        log.append(cancel);
+        saga.run(&mut log, &mut store, &mut steps).unwrap();
+        assert_eq!(saga.state(OrderId(3)), Some(SagaState::Compensated));
+        assert_eq!(steps.calls.last(), Some(&("release", 3)));
+        assert_eq!(saga.active_len(), 0);
+
+        // The saga's own events replay to the same store.
+        let replayed = OrderStore::replay(&log).unwrap();
+        assert_eq!(
+            replayed.find_by_id(OrderId(2)).unwrap().status(),
+            Status::Cancelled
+        );
+    }
+
+    #[test]
+    fn clock_drives_add_now() {
+        let clock = FixedClock::new(1_000);
+        let mut store = OrderStore::builder().clock(clock.clone()).build();
+        store
+            .add_now(OrderId(1), Money(1.0), Status::Pending)
+            .unwrap();
+        clock.advance(60);
+        store
+            .add_now(OrderId(2), Money(1.0), Status::Pending)
+            .unwrap();
+        let ts = |id| store.find_by_id(OrderId(id)).unwrap().timestamp();
+        assert_eq!((ts(1), ts(2)), (1_000, 1_060));
+
+        // A clock stepping backwards is clamped to the latest reading.
+        let wall = FixedClock::new(500);
+        let mono = MonotonicClock::new(wall.clone());
+        assert_eq!(mono.now(), 500);
+        wall.set(400);
+        assert_eq!(mono.now(), 500);
+        wall.set(700);
+        assert_eq!(mono.now(), 700);
+
+        let store = OrderStore::new().with_clock(|| 42);
+        assert_eq!(store.now(), 42);
+        assert!(SystemClock.now() > 1_600_000_000);
+    }
+
+    #[test]
+    fn expiry_cancels_stale_pending_orders() {
+        let mut store = OrderStore::new().with_outbox();
+        for (id, status, ts) in [
+            (1, Status::Pending, 10),
+            (2, Status::Completed, 5),
+            (3, Status::Pending, 50),
+            (4, Status::Pending, 5),
+            (5, Status::Pending, 100),
+        ] {
+            store.add(OrderId(id), Money(1.0), status, ts).unwrap();
+        }
+        let open = store.register_view(OpenOrderCount::default());
+
+        let events = store.expire_pending_older_than(50);
+        assert_eq!(
+            events,
+            vec![
+                OrderEvent::Cancelled { id: OrderId(4) },
+                OrderEvent::Cancelled { id: OrderId(1) },
+            ]
+        );
+        assert_eq!(store.materialized(open).0, 2);
+        assert_eq!(store.outbox().unwrap().pending_len(), 2);
+        assert_eq!(
+            store.find_by_id(OrderId(2)).unwrap().status(),
+            Status::Completed
+        );
+        assert!(store.expire_pending_older_than(50).is_empty());
+
+        // Cancels and timestamp writes move entries; the index stays built across sweeps.
+        let indexed = |s: &OrderStore| s.kernel().composite_index_memory().len;
+        assert_eq!(indexed(&store), 5);
+        store
+            .update_with(OrderId(5), |mut o| o.set_timestamp(20))
+            .unwrap();
+        assert_eq!(indexed(&store), 5);
+        assert_eq!(
+            store.expire_pending_older_than(50),
+            vec![OrderEvent::Cancelled { id: OrderId(5) }]
+        );
+        assert_eq!(indexed(&store), 5);
+        assert_eq!(store.expire_pending_older_than(101).len(), 1);
+    }
+
+    #[cfg(feature = "std-thread")]
+    #[test]
+    fn expiry_sweeper_runs_in_background() {
+        use std::sync::{mpsc, Arc, Mutex};
+        use std::time::Duration;
+
+        let clock = FixedClock::new(1_000);
+        let mut store = OrderStore::new().with_clock(clock.clone());
+        store
+            .add(OrderId(1), Money(1.0), Status::Pending, 100)
+            .unwrap();
+        store
+            .add(OrderId(2), Money(1.0), Status::Pending, 990)
+            .unwrap();
+        let store = Arc::new(Mutex::new(store));
+
+        let (tx, rx) = mpsc::channel();
+        let sweeper =
+            ExpirySweeper::spawn(store.clone(), Duration::from_millis(5), 60, move |events| {
+                let _ = tx.send(events);
+            });
+        assert_eq!(
+            rx.recv_timeout(Duration::from_secs(5)).unwrap(),
+            vec![OrderEvent::Cancelled { id: OrderId(1) }]
+        );
+        clock.advance(100);
+        assert_eq!(
+            rx.recv_timeout(Duration::from_secs(5)).unwrap(),
+            vec![OrderEvent::Cancelled { id: OrderId(2) }]
+        );
+        sweeper.stop();
+        assert_eq!(
+            store
+                .lock()
+                .unwrap()
+                .find_by_status(Status::Pending)
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn database_transactions_span_every_table() {
+        let mut db = Database::new();
+        db.customers_mut().push(CustomerId(7), "Ada", 1);
+        db.orders_mut()
+            .add(OrderId(1), Money(30.0), Status::Pending, 0)
+            .unwrap();
+        let before = db.snapshot();
+
+        // Rolled back: neither the order change nor the payment lands.
+        let mut tx = db.begin();
+        tx.payments_mut()
+            .push(PaymentId(1), OrderId(1), Money(30.0), 5);
+        tx.orders_mut()
+            .load(OrderId(1))
+            .unwrap()
+            .complete()
+            .unwrap();
+        tx.rollback();
+        assert!(db.payments().is_empty());
+        assert_eq!(
+            db.orders().find_by_id(OrderId(1)).unwrap().status(),
+            Status::Pending
+        );
+
+        let mut tx = db.begin();
+        tx.payments_mut()
+            .push(PaymentId(1), OrderId(1), Money(10.0), 5);
+        tx.payments_mut()
+            .push(PaymentId(2), OrderId(1), Money(20.0), 6);
+        tx.orders_mut()
+            .load(OrderId(1))
+            .unwrap()
+            .complete()
+            .unwrap();
+        tx.customers_mut().push(CustomerId(8), "Grace", 2);
+        // Reads inside the transaction see its own writes.
+        assert_eq!(tx.payments().paid_for(OrderId(1)), Money(30.0));
+        tx.commit();
+
+        let now = db.snapshot();
+        assert_eq!(now.payments().len(), 2);
+        assert_eq!(now.customers().len(), 2);
+        assert_eq!(
+            now.orders().find_by_id(OrderId(1)).unwrap().status(),
+            Status::Completed
+        );
+        // The earlier snapshot still sees the state it was taken at.
+        assert!(before.payments().is_empty());
+        assert_eq!(before.customers().len(), 1);
+        assert_eq!(
+            before.orders().find_by_id(OrderId(1)).unwrap().status(),
+            Status::Pending
+        );
+        assert_eq!(
+            now.payments().find_by_id(PaymentId(2)).unwrap().amount(),
+            Money(20.0)
+        );
+    }
+
+    #[test]
+    fn snapshots_migrate_to_the_current_schema() {
+        // Written before currencies and customers existed.
+        let v1 = Snapshot {
+            schema_version: SchemaVersion(1),
+            migrations: Vec::new(),
+            columns: SnapshotColumns {
+                ids: vec![OrderId(1), OrderId(2)],
+                amounts: vec![1.5, 2.5],
+                statuses: vec![Status::Pending, Status::Completed],
+                timestamps: vec![10, 20],
+                ..SnapshotColumns::default()
+            },
+        };
+        assert_eq!(
+            v1.clone().upgrade(&Migrator::empty()),
+            Err(SchemaError::MissingMigration(SchemaVersion(1)))
+        );
+        let upgraded = v1.upgrade(&Migrator::default()).unwrap();
+        assert_eq!(upgraded.schema_version, SchemaVersion::CURRENT);
+        assert_eq!(
+            upgraded.migrations,
+            ["v1_to_v2_add_currencies", "v2_to_v3_add_customers"]
+        );
+        let soa = upgraded.into_soa().unwrap();
+        let v = soa.find_by_id(OrderId(2)).unwrap();
+        assert_eq!(
+            (v.amount(), v.currency(), v.customer()),
+            (Money(2.5), Currency::default(), CustomerId::default())
+        );
+
+        // A custom migration replaces the built-in step for its version.
+        struct HouseAccount;
+        impl Migration for HouseAccount {
+            fn source_version(&self) -> SchemaVersion {
+                SchemaVersion(2)
+            }
+            fn name(&self) -> &str {
+                "house_account"
+            }
+            fn migrate(&self, columns: &mut SnapshotColumns) {
+                columns.customers = Some(vec![CustomerId(99); columns.len()]);
+            }
+        }
+        let mut current = Snapshot::of(&soa);
+        current.schema_version = SchemaVersion(2);
+        current.columns.customers = None;
+        let soa = current
+            .upgrade(&Migrator::default().with(HouseAccount))
+            .unwrap()
+            .into_soa()
+            .unwrap();
+        assert!(soa.iter().all(|v| v.customer() == CustomerId(99)));
+
+        let future = Snapshot {
+            schema_version: SchemaVersion(9),
+            ..Snapshot::of(&soa)
+        };
+        assert_eq!(
+            future.into_soa().unwrap_err(),
+            SchemaError::Unsupported {
+                found: SchemaVersion(9)
+            }
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serialized_snapshots_carry_their_schema() {
+        let old = r#"{"schema_version":1,"columns":{"ids":[7],"amounts":[3.0],"statuses":["Pending"],"timestamps":[5]}}"#;
+        let snap: Snapshot = serde_json::from_str(old).unwrap();
+        let snap = snap.upgrade(&Migrator::default()).unwrap();
+        let json = serde_json::to_string(&snap).unwrap();
+        assert!(json.contains(r#""schema_version":3"#));
+        assert!(json.contains("v2_to_v3_add_customers"));
+        let soa = snap.into_soa().unwrap();
+        assert_eq!(soa.find_by_id(OrderId(7)).unwrap().amount(), Money(3.0));
+    }
+
+    #[cfg(feature = "ffi")]
+    #[cfg_attr(feature = "no-unsafe", allow(unsafe_code))]
+    #[test]
+    fn ffi_round_trip() {
+        use crate::ffi::*;
+        use std::ptr;
+
+        // SAFETY: `store` comes from `dds_store_new`, is used on this thread only and freed once;
+        // column pointers are read before the next mutation.
+        unsafe {
+            let store = dds_store_new();
+            assert_eq!(dds_store_push(store, 1, 10.0, 1, 100), DdsResult::Ok);
+            assert_eq!(dds_store_push(store, 2, 20.0, 0, 200), DdsResult::Ok);
+            assert_eq!(dds_store_push(store, 3, 5.0, 1, 300), DdsResult::Ok);
+            assert_eq!(
+                dds_store_push(store, 4, 1.0, 7, 400),
+                DdsResult::InvalidStatus
+            );
+            assert_eq!(dds_store_len(store), 3);
+
+            let mut total = 0.0;
+            assert_eq!(dds_store_sum_by_status(store, 1, &mut total), DdsResult::Ok);
+            assert_eq!(total, 15.0);
+            assert_eq!(
+                dds_store_sum_by_status(store, 9, &mut total),
+                DdsResult::InvalidStatus
+            );
+            assert_eq!(
+                dds_store_sum_by_status(store, 1, ptr::null_mut()),
+                DdsResult::NullPointer
+            );
+
+            let mut len = 0;
+            let ids = std::slice::from_raw_parts(dds_store_ids(store, &mut len), len);
+            assert_eq!(ids, [1, 2, 3]);
+            let amounts = std::slice::from_raw_parts(dds_store_amounts(store, &mut len), len);
+            assert_eq!(amounts, [10.0, 20.0, 5.0]);
+            let statuses = std::slice::from_raw_parts(dds_store_statuses(store, &mut len), len);
+            assert_eq!(statuses, [1, 0, 1]);
+            let ts = std::slice::from_raw_parts(dds_store_timestamps(store, &mut len), len);
+            assert_eq!(ts, [100, 200, 300]);
+
+            assert_eq!(dds_store_remove(store, 1), DdsResult::Ok);
+            assert_eq!(dds_store_remove(store, 1), DdsResult::UnknownId);
+            assert!(!dds_store_is_live(store, 0) && dds_store_is_live(store, 1));
+            assert!(!dds_store_is_live(store, 99));
+            assert_eq!(dds_store_live_len(store), 2);
+            assert_eq!(dds_store_compact(store), DdsResult::Ok);
+            let ids = std::slice::from_raw_parts(dds_store_ids(store, &mut len), len);
+            assert_eq!(ids, [2, 3]);
+            dds_store_free(store);
+
+            assert_eq!(
+                dds_store_push(ptr::null_mut(), 1, 1.0, 0, 0),
+                DdsResult::NullPointer
+            );
+            assert_eq!(dds_store_len(ptr::null()), 0);
+            assert!(dds_store_amounts(ptr::null(), &mut len).is_null());
+            assert_eq!(len, 0);
+            dds_store_free(ptr::null_mut());
+        }
+    }
+
+    #[cfg(feature = "ffi")]
+    #[test]
+    fn c_header_is_generated_from_the_ffi_module() {
+        let root = env!("CARGO_MANIFEST_DIR");
+        let config = cbindgen::Config::from_file(format!("{root}/cbindgen.toml")).unwrap();
+        let mut generated = Vec::new();
+        cbindgen::Builder::new()
+            .with_config(config)
+            .with_src(format!("{root}/src/ffi.rs"))
+            .generate()
+            .unwrap()
+            .write(&mut generated);
+        let header = std::fs::read(format!("{root}/include/ddd_dod_soa.h")).unwrap();
+        assert!(
+            generated == header,
+            "include/ddd_dod_soa.h is stale; regenerate it as cbindgen.toml describes"
+        );
+    }
+
+    #[cfg(feature = "python")]
+    #[test]
+    fn python_store_round_trip() {
+        use crate::python::PyOrderStore;
+        use pyo3::prelude::*;
+        use pyo3::types::PyDict;
+
+        Python::initialize();
+        Python::attach(|py| -> PyResult<()> {
+            let locals = PyDict::new(py);
+            locals.set_item("store", Bound::new(py, PyOrderStore::default())?)?;
+            py.run(
+                c"
+store.add(1, 10.0, 'Completed', 100)
+store.add(2, 20.0, 'Pending', 200)
+store.add(3, 5.0, 'Completed', 300)
+assert len(store) == 3
+assert store.sum_by_status('Completed') == 15.0
+store.remove(1)
+assert len(store) == 2 and store.sum_by_status('Completed') == 5.0
+try:
+    store.add(4, 1.0, 'Shipped', 0)
+    raise AssertionError('unknown status accepted')
+except ValueError:
+    pass
+try:
+    store.remove(1)
+    raise AssertionError('removed twice')
+except KeyError:
+    pass
+store.compact()
+assert len(store) == 2
+",
+                Some(&locals),
+                None,
+            )
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn fuzz_inputs_replay_deterministically() {
+        // An indexed kernel, then every op kind: three pushes, set amount, remove, set status,
+        // retain, compact, both sorts, and a push of an id that is already stored. The trailing
+        // retain lacks its argument and is dropped.
+        let ops: [&[u8]; 11] = [
+            &[1],
+            &[0, 3, 60, 0],
+            &[0, 5, 70, 2],
+            &[0, 7, 3, 1],
+            &[3, 2, 99],
+            &[1, 4],
+            &[2, 5, 0],
+            &[4, 2],
+            &[5, 6, 7],
+            &[0, 3, 10, 1],
+            &[4],
+        ];
+        let all_ops = ops.concat();
+        assert_eq!(fuzzing::decode(&all_ops[1..]).len(), 11);
+        fuzzing::run(&all_ops);
+        fuzzing::run(&all_ops[..all_ops.len() - 1]);
+        fuzzing::run(&[]);
+        fuzzing::run(&[0; 64]);
+        fuzzing::run(&[255; 64]);
+    }
+
+    #[cfg(feature = "bench-support")]
+    #[test]
+    fn synthetic_workloads_are_reproducible() {
+        use crate::bench_support::{synthetic_orders, AmountDist, StatusMix};
+
+        let skewed = AmountDist::Exponential { mean: 50.0 };
+        let a = synthetic_orders(5_000, 7, StatusMix::new(1, 3, 0), skewed);
+        let b = synthetic_orders(5_000, 7, StatusMix::new(1, 3, 0), skewed);
+        let c = synthetic_orders(5_000, 8, StatusMix::new(1, 3, 0), skewed);
+        let rows = |soa: &OrderSoA| soa.iter().map(OrderRow::from).collect::<Vec<_>>();
+        assert_eq!(rows(&a), rows(&b));
+        assert_ne!(rows(&a), rows(&c));
+
+        let stats = a.stats();
+        assert_eq!(stats.status_count(Status::Cancelled), 0);
+        let completed = stats.status_count(Status::Completed) as f64 / 5_000.0;
+        assert!((completed - 0.75).abs() < 0.03, "{completed}");
+        let mean = stats.amount.mean.unwrap().0;
+        assert!((mean - 50.0).abs() < 5.0, "{mean}");
+        assert!(stats.amount.min.unwrap().0 >= 0.0);
+
+        let uniform = AmountDist::Uniform {
+            min: 10.0,
+            max: 20.0,
+        };
+        let u = synthetic_orders(1_000, 1, StatusMix::UNIFORM, uniform);
+        assert!(u.iter().all(|v| (10.0..=20.0).contains(&v.amount().0)));
+        assert!(u.iter().map(|v| v.timestamp()).eq(0..1_000));
+    }
+
+    #[test]
+    fn query_cache_serves_repeats_until_a_write() {
+        let mut store = OrderStore::new().with_query_cache(2);
+        for i in 0..10 {
+            store
+                .add(
+                    OrderId(i),
+                    Money(i as f64 * 10.0),
+                    Status::ALL[i as usize % 3],
+                    i,
+                )
+                .unwrap();
+        }
+        let big_pending = StatusIs(Status::Pending).and(AmountAtLeast(Money(30.0)));
+        fn ids<S>(store: &OrderStore, spec: S) -> Vec<u64>
+        where
+            S: Specification + std::hash::Hash + PartialEq + Clone + Send + Sync + 'static,
+        {
+            store.find_cached(spec).map(|v| v.id().0).collect()
+        }
+        let expected: Vec<u64> = store.find(big_pending).map(|v| v.id().0).collect();
+        assert_eq!(expected, [3, 6, 9]);
+
+        assert_eq!(ids(&store, big_pending), expected);
+        assert_eq!(ids(&store, big_pending), expected);
+        let stats = store.query_cache_stats().unwrap();
+        assert_eq!((stats.hits, stats.misses, stats.len), (1, 1, 1));
+
+        // Every kind of write invalidates, including ones that bypass the store's bookkeeping.
+        let epoch = store.epoch();
+        store
+            .add(OrderId(12), Money(99.0), Status::Pending, 12)
+            .unwrap();
+        assert!(store.epoch() > epoch);
+        assert_eq!(ids(&store, big_pending), [3, 6, 9, 12]);
+        store.kernel_mut().sort_by_amount();
+        store.kernel_mut().view_mut_at(0).set_amount(Money(50.0));
+        assert_eq!(ids(&store, big_pending), [0, 3, 6, 9, 12]);
+        assert_eq!(store.query_cache_stats().unwrap().misses, 3);
+
+        // Capacity 2: a third spec evicts the least recently used one.
+        let completed = StatusIs(Status::Completed);
+        let cancelled = StatusIs(Status::Cancelled);
+        ids(&store, completed);
+        ids(&store, big_pending);
+        ids(&store, cancelled);
+        assert_eq!(store.query_cache_stats().unwrap().len, 2);
+        let before = store.query_cache_stats().unwrap();
+        ids(&store, big_pending);
+        ids(&store, completed);
+        let after = store.query_cache_stats().unwrap();
+        assert_eq!(
+            (after.hits - before.hits, after.misses - before.misses),
+            (1, 1)
+        );
+        assert_eq!(AmountAtLeast(Money(-0.0)), AmountAtLeast(Money(0.0)));
+        assert_eq!(ids(&store, AmountAtLeast(Money(-0.0))).len(), 11);
+    }
+
+    #[test]
+    fn read_only_and_restricted_handles() {
+        fn send_sync_static<T: Send + Sync + 'static>(_: &T) {}
+
+        let mut store = OrderStore::new();
+        let first = store
+            .add(OrderId(1), Money(10.0), Status::Pending, 5)
+            .unwrap();
+        store
+            .add(OrderId(2), Money(20.0), Status::Completed, 6)
+            .unwrap();
+        store
+            .add(OrderId(3), Money(30.0), Status::Completed, 7)
+            .unwrap();
+        store.remove(first).unwrap();
+
+        let reporting = store.read_only();
+        let amounts = store.restricted::<(cols::Amount, cols::Timestamp)>();
+        send_sync_static(&reporting);
+        send_sync_static(&amounts);
+
+        assert_eq!(reporting.sum_by_status(Status::Completed), Money(50.0));
+        assert_eq!(reporting.find_by_id(OrderId(3)).unwrap().timestamp(), 7);
+        let rows: Vec<(Money, u64)> = amounts.projection().iter().collect();
+        assert_eq!(rows, [(Money(20.0), 6), (Money(30.0), 7)]);
+        assert_eq!(amounts.live_len(), 2);
+        let ids = reporting.restrict::<cols::Id>();
+        assert_eq!(ids.projection().get(1), Some(OrderId(2)));
+        assert_eq!(ids.projection().get(0), None);
+
+        // Handles are snapshots: the store's writes copy-on-write around them.
+        store
+            .add(OrderId(4), Money(40.0), Status::Completed, 8)
+            .unwrap();
+        assert_eq!(reporting.live_len(), 2);
+        assert_eq!(amounts.projection().iter().count(), 2);
+        assert_eq!(store.read_only().live_len(), 3);
+    }
+
+    #[test]
+    fn audit_columns_record_who_changed_what() {
+        let clock = FixedClock::new(100);
+        let mut store = OrderStore::new()
+            .with_audit()
+            .with_clock(clock.clone())
+            .with_validator(PositiveAmount);
+        let (alice, bob) = (ActorId(7), ActorId(8));
+        {
+            let mut s = store.in_context(Context::new(alice));
+            s.add(OrderId(1), Money(10.0), Status::Pending, 0).unwrap();
+            s.add(OrderId(2), Money(20.0), Status::Pending, 0).unwrap();
+        }
+        assert_eq!(store.context().actor, ActorId::SYSTEM);
+
+        clock.advance(50);
+        // Switching actors leaves a kernel shared with readers alone.
+        let reader = Arc::clone(&store.inner);
+        store.set_context(Context::new(bob));
+        assert!(Arc::ptr_eq(&reader, &store.inner));
+        drop(reader);
+        store
+            .update_with(OrderId(2), |mut m| m.set_status(Status::Completed))
+            .unwrap();
+        let audit = |store: &OrderStore, id| store.find_by_id(OrderId(id)).unwrap().audit();
+        let created = AuditInfo {
+            created_at: 100,
+            updated_at: 100,
+            updated_by: alice,
+        };
+        assert_eq!(audit(&store, 1), Some(created));
+        assert_eq!(
+            audit(&store, 2),
+            Some(AuditInfo {
+                updated_at: 150,
+                updated_by: bob,
+                ..created
+            })
+        );
+        let since = |store: &OrderStore, ts| -> Vec<OrderId> {
+            store.modified_since(ts).map(|v| v.id()).collect()
+        };
+        assert_eq!(since(&store, 120), [OrderId(2)]);
+
+        // A rejected update leaves the stamps alone; kernel writes are stamped too.
+        clock.advance(50);
+        assert!(store
+            .update_with(OrderId(1), |mut m| m.set_amount(Money(0.0)))
+            .is_err());
+        assert_eq!(audit(&store, 1), Some(created));
+        store.kernel_mut().view_mut_at(0).set_amount(Money(11.0));
+        assert_eq!(since(&store, 200), [OrderId(1)]);
+
+        // Stamps follow their rows through compaction and sorting.
+        let h = store
+            .add(OrderId(3), Money(1.0), Status::Pending, 0)
+            .unwrap();
+        store.remove(h).unwrap();
+        store.compact();
+        store.kernel_mut().sort_by_amount();
+        assert_eq!(audit(&store, 2).unwrap().updated_at, 150);
+
+        let plain = OrderStore::new();
+        assert!(!plain.kernel().is_audited());
+        assert_eq!(plain.modified_since(0).count(), 0);
+        let columns = |s: &OrderStore| s.kernel().memory_usage().columns.len();
+        assert_eq!(columns(&store), columns(&plain) + 3);
+    }
+
+    #[test]
+    fn multi_currency_totals_convert_or_fail() {
+        let mut soa = OrderSoA::default();
+        let rows = [
+            (TypedMoney::new(1_000, Currency::USD), Status::Completed),
+            (TypedMoney::new(2_000, Currency::EUR), Status::Completed),
+            (TypedMoney::new(500, Currency::JPY), Status::Completed),
+            (TypedMoney::new(300, Currency::EUR), Status::Pending),
+        ];
+        for (i, (money, st)) in rows.into_iter().enumerate() {
+            soa.push_money(OrderId(i as u64), money, st, 0);
+        }
+        let totals = soa.sum_by_status_per_currency(Status::Completed).unwrap();
+        assert_eq!(
+            totals.into_values().collect::<Vec<_>>(),
+            [
+                TypedMoney::new(2_000, Currency::EUR),
+                TypedMoney::new(500, Currency::JPY),
+                TypedMoney::new(1_000, Currency::USD),
+            ]
+        );
+
+        let rates = FixedRates::new().with_rate(Currency::EUR, Currency::USD, 1.5);
+        assert_eq!(
+            soa.sum_by_status_in(Status::Completed, Currency::USD, &rates),
+            Err(MoneyError::MissingRate {
+                from: Currency::JPY,
+                to: Currency::USD
+            })
+        );
+        // JPY -> USD falls back to the inverse of USD -> JPY.
+        let rates = rates.with_rate(Currency::USD, Currency::JPY, 100.0);
+        assert_eq!(
+            soa.sum_by_status_in(Status::Completed, Currency::USD, &rates),
+            Ok(TypedMoney::new(1_000 + 3_000 + 500, Currency::USD))
+        );
+        assert_eq!(
+            soa.sum_by_status_in(Status::Pending, Currency::USD, &rates),
+            Ok(TypedMoney::new(450, Currency::USD))
+        );
+        // Closures are rate sources too.
+        let flat = |_: Currency, _: Currency| Some(1.0);
+        assert_eq!(
+            soa.sum_by_status_in(Status::Pending, Currency::GBP, &flat),
+            Ok(TypedMoney::new(300, Currency::GBP))
+        );
+    }
+
+    #[test]
+    fn flag_column_popcount_queries() {
+        use OrderFlags as F;
+        // 130 rows: two full words plus a ragged tail.
+        let flags: Vec<OrderFlags> = (0..130u8)
+            .map(|i| OrderFlags(i % 8) | if i % 5 == 0 { F::FRAUD_HOLD } else { F::NONE })
+            .collect();
+        let mut col: FlagColumn = flags.iter().copied().collect();
+        let naive = |pred: &dyn Fn(OrderFlags) -> bool| -> Vec<usize> {
+            (0..flags.len()).filter(|&i| pred(flags[i])).collect()
+        };
+        for mask in [
+            F::NONE,
+            F::GIFT,
+            F::GIFT | F::EXPRESS,
+            F::EXPRESS | F::FRAUD_HOLD,
+        ] {
+            let all = naive(&|f| f.contains(mask));
+            let any = naive(&|f| f.intersects(mask));
+            assert_eq!(col.count_with_flags(mask), all.len());
+            assert_eq!(col.count_with_any_flags(mask), any.len());
+            assert_eq!(
+                col.filter_flags_all(mask).iter_ones().collect::<Vec<_>>(),
+                all
+            );
+            assert_eq!(
+                col.filter_flags_any(mask).iter_ones().collect::<Vec<_>>(),
+                any
+            );
+        }
+        assert_eq!(col.count_with_flags(F::NONE), 130);
+
+        col.update(3, F::GIFT, false);
+        col.update(3, F::FRAUD_HOLD, true);
+        assert_eq!(col.get(3), F::EXPRESS | F::FRAUD_HOLD);
+        assert!(col.plane(F::FRAUD_HOLD).get(3));
+
+        // Selections combine with the kernel's, tombstones included.
+        let mut soa = OrderSoA::default();
+        let handles: Vec<RowHandle> = (0..130)
+            .map(|i| soa.push(OrderId(i), Money(1.0), Status::Pending, i))
+            .collect();
+        soa.remove(handles[5]).unwrap();
+        let held = soa.and_live(col.filter_flags_all(F::FRAUD_HOLD));
+        assert_eq!(held.count_ones(), col.count_with_flags(F::FRAUD_HOLD) - 1);
+    }
+
+    #[test]
+    fn write_buffer_batches_and_applies_backpressure() {
+        let row = |id| OrderRow::new(OrderId(id), Money(1.0), Status::Pending, id);
+        let store = Arc::new(ConcurrentOrderStore::new());
+        let buf = Arc::new(WriteBuffer::new(Arc::clone(&store), 4));
+
+        let producers: Vec<_> = (0..4u64)
+            .map(|t| {
+                let buf = Arc::clone(&buf);
+                std::thread::spawn(move || (0..25).for_each(|i| buf.push(row(t * 100 + i))))
+            })
+            .collect();
+        producers.into_iter().for_each(|p| p.join().unwrap());
+
+        // 100 rows in batches of four, with nothing left staged.
+        assert!(buf.is_empty());
+        let stats = buf.stats();
+        assert_eq!((stats.rows, stats.batches), (100, 25));
+        assert_eq!(store.snapshot().len(), 100);
+
+        // A partial batch waits for `flush`; a duplicate is retried alone and set aside.
+        buf.push(row(1000));
+        buf.push(row(0));
+        assert_eq!(store.snapshot().len(), 100);
+        assert_eq!(buf.flush(), 1);
+        assert_eq!(
+            buf.take_rejected(),
+            [(OrderId(0), StoreError::DuplicateId(OrderId(0)))]
+        );
+        assert!(store.snapshot().find_by_id(OrderId(1000)).is_some());
+
+        // Dropping flushes whatever is staged.
+        buf.push(row(1001));
+        drop(buf);
+        assert_eq!(store.snapshot().len(), 102);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn write_buffer_push_async_awaits_space() {
+        let store = Arc::new(ConcurrentOrderStore::new());
+        let buf = Arc::new(WriteBuffer::new(Arc::clone(&store), 8));
+        // A blocking producer thread keeps the buffer busy while the async tasks push.
+        let blocking = {
+            let buf = Arc::clone(&buf);
+            std::thread::spawn(move || {
+                (0..200).for_each(|id| {
+                    buf.push(OrderRow::new(OrderId(id), Money(1.0), Status::Pending, id))
+                })
+            })
+        };
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let tasks: Vec<_> = (0..4u64)
+                .map(|t| {
+                    let buf = Arc::clone(&buf);
+                    tokio::spawn(async move {
+                        for i in 0..50 {
+                            let id = (t + 1) * 1000 + i;
+                            buf.push_async(OrderRow::new(
+                                OrderId(id),
+                                Money(1.0),
+                                Status::Pending,
+                                id,
+                            ))
+                            .await;
+                        }
+                    })
+                })
+                .collect();
+            for t in tasks {
+                t.await.unwrap();
+            }
+        });
+        blocking.join().unwrap();
+        buf.flush();
+        assert_eq!(store.snapshot().len(), 400);
+        assert!(buf.take_rejected().is_empty());
+    }
+
+    #[test]
+    fn delta_syncs_a_replica() {
+        let mut primary = OrderStore::new();
+        let handles: Vec<_> = (1..=4)
+            .map(|id| {
+                primary
+                    .add(OrderId(id), Money(10.0), Status::Pending, id)
+                    .unwrap()
+            })
+            .collect();
+        let mut replica = OrderStore::from(primary.kernel().clone());
+        let shipped = primary.read_only();
+
+        primary
+            .update_with(OrderId(2), |mut o| o.set_status(Status::Completed))
+            .unwrap();
+        primary.remove(handles[2]).unwrap();
+        primary
+            .add(OrderId(5), Money(7.5), Status::Pending, 5)
+            .unwrap();
+        // Touched but unchanged: not part of the delta.
+        primary.update_with(OrderId(1), |_| ()).unwrap();
+
+        let delta = primary.diff(&shipped);
+        assert_eq!(
+            delta.added,
+            [OrderRow::new(OrderId(5), Money(7.5), Status::Pending, 5)]
+        );
+        assert_eq!(
+            delta.updated,
+            [OrderRow::new(OrderId(2), Money(10.0), Status::Completed, 2)]
+        );
+        assert_eq!(delta.deleted, [OrderId(3)]);
+
+        replica.apply_delta(&delta).unwrap();
+        assert!(primary.diff(replica.kernel()).is_empty());
+        assert!(replica.diff(&primary.read_only()).is_empty());
+
+        // A delta that no longer fits is refused as a whole.
+        let stale = StoreDelta {
+            added: vec![OrderRow::new(OrderId(6), Money(1.0), Status::Pending, 6)],
+            deleted: vec![OrderId(3)],
+            ..StoreDelta::default()
+        };
+        assert_eq!(
+            replica.apply_delta(&stale),
+            Err(StoreError::UnknownId(OrderId(3)))
+        );
+        assert!(replica.find_by_id(OrderId(6)).is_none());
+    }
+
+    #[test]
+    fn followers_replicate_the_leader() {
+        let mut leader = Leader::new(OrderStore::new());
+        leader
+            .add(OrderId(1), Money(10.0), Status::Pending, 1)
+            .unwrap();
+        let mut early = leader.follower();
+        // Joining publishes the pending write; the snapshot is not applied until `sync`.
+        assert_eq!(leader.seq(), 1);
+        assert_eq!(early.lag(), 1);
+
+        leader
+            .add(OrderId(2), Money(20.0), Status::Pending, 2)
+            .unwrap();
+        leader
+            .update_with(OrderId(1), |mut o| o.set_status(Status::Completed))
+            .unwrap();
+        assert_eq!(leader.publish(), 2);
+        assert_eq!(leader.publish(), 2, "nothing new to ship");
+        assert_eq!(early.lag(), 2);
+
+        // The snapshot, then the batched delta.
+        assert_eq!(early.sync(), Ok(2));
+        assert_eq!((early.seq(), early.lag()), (2, 0));
+        assert_eq!(
+            early.find_by_id(OrderId(1)).unwrap().status(),
+            Status::Completed
+        );
+
+        // A late follower starts from a snapshot of the shipped state.
+        let mut late = leader.follower();
+        late.sync().unwrap();
+        assert_eq!(late.seq(), 2);
+        assert!(leader.diff(late.kernel()).is_empty());
+
+        // Over another transport: the stream of messages, fed by hand.
+        let rx = leader.subscribe();
+        let mut remote = Follower::new(OrderStore::new());
+        let h = leader.kernel().handle_at(1);
+        leader.remove(h).unwrap();
+        leader.publish();
+        let msgs: Vec<ReplicationMsg> = rx.try_iter().collect();
+        assert_eq!(
+            msgs.iter().map(ReplicationMsg::seq).collect::<Vec<_>>(),
+            [2, 3]
+        );
+        assert_eq!(
+            remote.apply(msgs[1].clone()),
+            Err(ReplicationError::Gap {
+                expected: 1,
+                found: 3
+            })
+        );
+        #[cfg(feature = "serde")]
+        let msgs: Vec<ReplicationMsg> =
+            serde_json::from_str(&serde_json::to_string(&msgs).unwrap()).unwrap();
+        for msg in msgs {
+            remote.apply(msg).unwrap();
+        }
+        assert!(remote.find_by_id(OrderId(2)).is_none());
+
+        // Followers keep serving once the leader is gone.
+        drop(leader);
+        assert_eq!(early.sync(), Err(ReplicationError::Disconnected));
+        assert_eq!(early.kernel().live_len(), 1);
+    }
+
+    #[test]
+    fn followers_take_the_leaders_rows_as_they_are() {
+        // The default `IdPolicy` keeps duplicates, and `add` does not check amounts.
+        let mut leader = Leader::new(OrderStore::new());
+        leader
+            .add(OrderId(1), Money(10.0), Status::Pending, 1)
+            .unwrap();
+        leader
+            .add(OrderId(1), Money(11.0), Status::Pending, 2)
+            .unwrap();
+        leader
+            .add(OrderId(2), Money(-5.0), Status::Pending, 3)
+            .unwrap();
+        let mut follower = leader.follower();
+        match leader.snapshot_msg() {
+            ReplicationMsg::Snapshot { rows, .. } => assert_eq!(
+                rows.iter().map(|r| (r.id, r.amount)).collect::<Vec<_>>(),
+                [(OrderId(1), Money(11.0)), (OrderId(2), Money(-5.0))]
+            ),
+            other => panic!("expected a snapshot, got {other:?}"),
+        }
+        assert_eq!(follower.sync(), Ok(1));
+
+        leader
+            .add(OrderId(2), Money(-6.0), Status::Pending, 4)
+            .unwrap();
+        leader
+            .add(OrderId(3), Money(-7.0), Status::Pending, 5)
+            .unwrap();
+        leader.publish();
+        assert_eq!(follower.sync(), Ok(1));
+        assert!(leader.diff(follower.kernel()).is_empty());
+        for id in 1..=3 {
+            assert_eq!(
+                follower.find_by_id(OrderId(id)).map(OrderRow::from),
+                leader.find_by_id(OrderId(id)).map(OrderRow::from)
+            );
+        }
+        assert_eq!(follower.kernel().live_len(), 3);
+
+        // Dropping the newer duplicate brings the older one back on both sides.
+        let h = leader.kernel().handle_at(3);
+        leader.remove(h).unwrap();
+        leader.publish();
+        assert_eq!(follower.sync(), Ok(1));
+        assert_eq!(
+            follower.find_by_id(OrderId(2)).unwrap().amount(),
+            Money(-5.0)
+        );
+    }
+
+    #[test]
+    fn column_kernels_agree_across_backends() {
+        use crate::column::{self, Column};
+
+        let statuses: Vec<Status> = (0..10_000).map(|i| Status::ALL[(i / 7) % 3]).collect();
+        let amounts: Vec<f64> = (0..10_000).map(|i| (i % 13) as f64).collect();
+        let ts: Vec<u64> = (0..10_000).map(|i| 1_000 + i * 3 - i % 5).collect();
+
+        let mut chunked_amounts = ChunkedVec::new();
+        chunked_amounts.extend(amounts.iter().copied());
+        let rle = RleColumn::encode(statuses.iter().copied());
+        let dict = DictColumn::encode(statuses.iter().copied());
+        let delta = DeltaColumn::encode(ts.iter().copied());
+        assert_eq!(rle.value(700), Some(statuses[700]));
+        assert_eq!(Column::value(&delta, 9_999), Some(ts[9_999]));
+
+        let completed = |s| s == Status::Completed;
+        let expected = column::sum_where(&statuses, &amounts, None, Summation::Kahan, completed);
+        assert_eq!(
+            column::sum_where(&rle, &chunked_amounts, None, Summation::Kahan, completed),
+            expected
+        );
+        assert_eq!(
+            column::sum_where(&dict, &amounts[..], None, Summation::Kahan, completed),
+            expected
+        );
+        assert_eq!(
+            column::sum(&chunked_amounts, None, Summation::Pairwise),
+            amounts.iter().sum::<f64>()
+        );
+
+        let big = |s, a| s == Status::Pending && a >= 12.0;
+        let rows = column::filter_where(&statuses, &amounts, None, big);
+        assert_eq!(
+            column::filter_where(&rle, &chunked_amounts, None, big),
+            rows
+        );
+        assert!(rows.iter().all(|&i| amounts[i] == 12.0));
+
+        assert_eq!(column::min(&delta, None), ts.iter().min().copied());
+        assert_eq!(column::max(&delta, None), ts.iter().max().copied());
+
+        // Skipped rows drop out of every kernel.
+        let mut skip = Bitmap::new();
+        (0..ts.len()).for_each(|i| skip.push(i == 0));
+        assert_eq!(column::min(&ts, Some(&skip)), Some(ts[1]));
+        assert!(column::filter(&ts, Some(&skip), |t| t <= ts[0]).is_empty());
+    }
+
+    #[test]
+    fn gather_and_scatter_through_index_lists() {
+        let mut soa = OrderSoA::with_capacity(8);
+        for i in 0..8u64 {
+            soa.push(OrderId(i), Money(i as f64 * 10.0), Status::Pending, i);
+        }
+        soa.remove(soa.handle_at(6)).unwrap();
+        let big = soa.filter_indices(Money(30.0), Status::Pending);
+        assert_eq!(big, [3, 4, 5, 7]);
+
+        let picked = soa.gather(&big);
+        assert_eq!(picked.len(), 4);
+        assert_eq!(picked.find_by_id(OrderId(7)).unwrap().amount(), Money(70.0));
+        assert_eq!(picked.sum_by_status(Status::Pending), Money(190.0));
+        // Tombstones come along as tombstones.
+        let with_dead = soa.gather(&[6, 0]);
+        assert_eq!((with_dead.len(), with_dead.live_len()), (2, 1));
+        assert!(with_dead.find_by_id(OrderId(6)).is_none());
+
+        soa.scatter_update(&big, |mut o| o.set_status(Status::Completed));
+        soa.scatter_update(&[6], |mut o| o.set_status(Status::Completed));
+        assert_eq!(soa.sum_by_status(Status::Completed), Money(190.0));
+        assert_eq!(soa.version_at(3), 1);
+        assert_eq!(soa.version_at(6), 0, "tombstoned rows are skipped");
+        assert_eq!(soa.find_by_status(Status::Pending).count(), 3);
+    }
+
+    #[test]
+    fn aggregations_accept_bitmap_and_index_selections() {
+        let mut soa = OrderSoA::with_capacity(6);
+        for (i, s) in [
+            Status::Pending,
+            Status::Completed,
+            Status::Pending,
+            Status::Cancelled,
+            Status::Completed,
+            Status::Pending,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            soa.push(OrderId(i as u64), Money(i as f64 * 10.0), s, i as u64);
+        }
+        soa.remove(soa.handle_at(5)).unwrap();
+
+        let by_bitmap = soa.gte_amount(Money(20.0));
+        let by_index: Vec<usize> = by_bitmap.iter_ones().collect();
+        assert_eq!(soa.sum_amount_selected(&by_bitmap), Money(90.0));
+        assert_eq!(soa.sum_amount_selected(&by_index), Money(90.0));
+        assert_eq!(soa.sum_amount_selected(&by_index[..1]), Money(20.0));
+
+        let groups = soa.group_by_status_selected(&by_index);
+        assert_eq!(groups, soa.group_by_status_selected(&by_bitmap));
+        assert_eq!(groups.get(Status::Completed).count, 1);
+        assert_eq!(groups.get(Status::Completed).total, Money(40.0));
+        assert_eq!(groups.get(Status::Pending).min, Some(Money(20.0)));
+        // A hand-made list naming a tombstoned row skips it.
+        assert_eq!(soa.sum_amount_selected(&[5, 4][..]), Money(40.0));
+        assert_eq!(
+            soa.query()
+                .amount_gte(Money(20.0))
+                .select()
+                .group_by_status(),
+            groups
+        );
+    }
+
+    #[test]
+    fn compensated_summation_avoids_drift() {
+        let mut soa = OrderSoA::with_capacity(100_000);
+        for i in 0..100_000u64 {
+            let h = soa.push(OrderId(i), Money(0.1), Status::Pending, i);
+            if i % 1_000 == 999 {
+                soa.remove(h).unwrap();
+            }
+        }
+        let exact = 99_900.0 * 0.1;
+        let naive = soa.sum_by_status_with(Status::Pending, Summation::Naive).0;
+        assert_ne!(naive, exact, "a naive sum drifts");
+        assert_eq!(soa.sum_by_status(Status::Pending).0, exact);
+        assert_eq!(soa.sum_by_status_scalar(Status::Pending).0, exact);
+        let pairwise = soa
+            .sum_by_status_with(Status::Pending, Summation::Pairwise)
+            .0;
+        assert!((pairwise - exact).abs() < (naive - exact).abs());
+
+        // Neumaier's form also survives addends larger than the running total.
+        let values = [1.0, 1e100, 1.0, -1e100];
+        assert_eq!(Summation::Naive.sum(values), 0.0);
+        assert_eq!(Summation::Kahan.sum(values), 2.0);
+    }
+
+    #[test]
+    fn exact_amounts_keep_sums_exact() {
+        let mut soa = OrderSoA::default().with_exact_amounts();
+        for i in 0..10 {
+            soa.push_money(
+                OrderId(i),
+                TypedMoney::new(10, Currency::USD),
+                Status::Pending,
+                i,
+            );
+        }
+        let naive = soa.sum_by_status_with(Status::Pending, Summation::Naive);
+        assert_ne!(naive, Money(1.0));
+        assert_eq!(soa.sum_by_status_exact(Status::Pending), Money(1.0));
+
+        // Past 2^53 minor units a float cannot hold the amount; the fixed-point column can.
+        let big = TypedMoney::new(9_007_199_254_740_993, Currency::USD);
+        let h = soa.push_money(OrderId(10), big, Status::Completed, 10);
+        assert_eq!(soa.view(h).unwrap().money(), big);
+        let mut plain = OrderSoA::default();
+        let p = plain.push_money(OrderId(10), big, Status::Completed, 10);
+        assert_ne!(plain.view(p).unwrap().money(), big);
+
+        soa.push_money(
+            OrderId(11),
+            TypedMoney::new(1234, Currency::JPY),
+            Status::Completed,
+            11,
+        );
+        let totals = soa.sum_by_status_per_currency(Status::Completed).unwrap();
+        assert_eq!(totals[&Currency::USD], big);
+        assert_eq!(totals[&Currency::JPY], TypedMoney::new(1234, Currency::JPY));
+
+        // Removal and compaction keep the column aligned; raw float writes are picked up.
+        soa.remove(soa.handle_at(0)).unwrap();
+        soa.compact();
+        soa.view_mut_at(0)
+            .set_money(TypedMoney::new(25, Currency::USD));
+        let cols = soa.columns_mut();
+        cols.amounts[1] = 5.0;
+        assert_eq!(
+            soa.sum_minor_by_status(Status::Pending, Currency::USD),
+            Ok(TypedMoney::new(25 + 500 + 7 * 10, Currency::USD))
+        );
+        assert!(soa
+            .memory_usage()
+            .columns
+            .iter()
+            .any(|c| c.name == "exact_amounts"));
+
+        let store = OrderStore::new().with_exact_amounts();
+        assert!(store.kernel().has_exact_amounts());
+        assert!(!OrderStore::new().kernel().has_exact_amounts());
+    }
+
+    #[test]
+    fn aggregate_commands_follow_the_state_machine() {
+        let machine = StateMachine::new("Placed", Status::Pending)
+            .with_state("Paid", Status::Pending)
+            .with_state("Delivered", Status::Completed)
+            .with_state("Cancelled", Status::Cancelled)
+            .with_transition("Placed", "Paid")
+            .with_transition("Placed", "Cancelled")
+            .with_transition("Paid", "Delivered");
+        let paid = machine.state("Paid").unwrap();
+        let mut store = OrderStore::new().with_state_machine(machine);
+        for i in 1..=2 {
+            store
+                .add(OrderId(i), Money(10.0), Status::Pending, i)
+                .unwrap();
+        }
+        let name = |s: &OrderStore, id| s.find_by_id(OrderId(id)).unwrap().state_name().to_owned();
+        let illegal = |id, to| {
+            Err(StoreError::IllegalTransition {
+                id: OrderId(id),
+                from: Status::Pending,
+                to,
+            })
+        };
+
+        // `Placed` has no edge to a completed state, `Paid` none to a cancelled one.
+        assert_eq!(
+            store.load(OrderId(1)).unwrap().complete(),
+            illegal(1, Status::Completed)
+        );
+        store.transition(OrderId(1), paid).unwrap();
+        assert_eq!(
+            store.load(OrderId(1)).unwrap().cancel(),
+            illegal(1, Status::Cancelled)
+        );
+        store.load(OrderId(1)).unwrap().complete().unwrap();
+        store.load(OrderId(2)).unwrap().cancel().unwrap();
+        assert_eq!(name(&store, 1), "Delivered");
+        assert_eq!(name(&store, 2), "Cancelled");
+
+        // A transition a validator rejects leaves the recorded state as it was.
+        let mut store = store.with_validator(|c: &OrderCandidate| match c.stored {
+            Some(_) => Err(DomainError::Violated("frozen")),
+            None => Ok(()),
+        });
+        store
+            .add(OrderId(3), Money(10.0), Status::Pending, 3)
+            .unwrap();
+        assert!(store.transition(OrderId(3), paid).is_err());
+        assert_eq!(name(&store, 3), "Placed");
+    }
+
+    #[test]
+    fn state_machine_validates_transitions() {
+        let machine = StateMachine::new("Placed", Status::Pending)
+            .with_state("Paid", Status::Pending)
+            .with_state("Shipped", Status::Pending)
+            .with_state("Delivered", Status::Completed)
+            .with_state("Refunded", Status::Cancelled)
+            .with_state("Cancelled", Status::Cancelled)
+            .with_transition("Placed", "Paid")
+            .with_transition("Placed", "Cancelled")
+            .with_transition("Paid", "Shipped")
+            .with_transition("Paid", "Refunded")
+            .with_transition("Shipped", "Delivered");
+        let state = |name| machine.state(name).unwrap();
+        let (paid, shipped, delivered) = (state("Paid"), state("Shipped"), state("Delivered"));
+        let refunded = state("Refunded");
+
+        let mut store = OrderStore::new().with_state_machine(machine.clone());
+        for i in 1..=3 {
+            store
+                .add(OrderId(i), Money(10.0), Status::Pending, i)
+                .unwrap();
+        }
+        let name = |s: &OrderStore, id| s.find_by_id(OrderId(id)).unwrap().state_name().to_owned();
+        assert_eq!(name(&store, 1), "Placed");
+
+        store.transition(OrderId(1), paid).unwrap();
+        store.transition(OrderId(1), shipped).unwrap();
+        assert_eq!(
+            store.transition(OrderId(2), delivered),
+            Err(StoreError::IllegalStateTransition {
+                id: OrderId(2),
+                from: machine.initial(),
+                to: delivered,
+            })
+        );
+        assert_eq!(
+            store.transition(OrderId(2), StateId(99)),
+            Err(StoreError::UnknownState(StateId(99)))
+        );
+        store.transition(OrderId(1), delivered).unwrap();
+        store.transition(OrderId(2), paid).unwrap();
+        store.transition(OrderId(2), refunded).unwrap();
+
+        // Kernels see the coarse status; the state codes stay aligned through compaction.
+        assert_eq!(name(&store, 1), "Delivered");
+        assert_eq!(name(&store, 2), "Refunded");
+        assert_eq!(store.sum_by_status(Status::Completed), Money(10.0));
+        assert_eq!(store.sum_by_status(Status::Cancelled), Money(10.0));
+        store.remove(store.kernel().handle_at(0)).unwrap();
+        store.compact();
+        assert_eq!(name(&store, 2), "Refunded");
+        assert_eq!(store.kernel().find_by_state(refunded).count(), 1);
+
+        // A status written around the machine files the row under that status's first state.
+        store
+            .update_with(OrderId(3), |mut o| o.set_status(Status::Cancelled))
+            .unwrap();
+        assert_eq!(name(&store, 3), "Refunded");
+
+        // Without a machine, states mirror `Status`.
+        let mut plain = OrderStore::new();
+        plain
+            .add(OrderId(1), Money(1.0), Status::Pending, 1)
+            .unwrap();
+        let cancelled = plain.kernel().state_machine().state("Cancelled").unwrap();
+        plain.transition(OrderId(1), cancelled).unwrap();
+        assert_eq!(
+            plain.find_by_id(OrderId(1)).unwrap().status(),
+            Status::Cancelled
+        );
+        assert!(plain.transition(OrderId(1), cancelled).is_err());
+    }
+
+    #[test]
+    fn refunds_and_partial_fulfillment_keep_invariants() {
+        let mut store = OrderStore::new().with_outbox();
+        store
+            .add(OrderId(1), Money(0.3), Status::Pending, 1)
+            .unwrap();
+        store
+            .add(OrderId(2), Money(100.0), Status::Pending, 2)
+            .unwrap();
+        let mut replica = store.clone();
+
+        let mut order = store.load(OrderId(1)).unwrap();
+        order.partially_fulfill(Money(0.1)).unwrap();
+        assert_eq!(
+            order.partially_fulfill(Money(0.3)),
+            Err(StoreError::FulfillmentExceedsAmount(OrderId(1)))
+        );
+        assert_eq!(
+            order.adjust_amount(Money(0.05)),
+            Err(StoreError::FulfillmentExceedsAmount(OrderId(1)))
+        );
+        // Only the fulfilled part is paid for while the order is open.
+        order.refund(Money(0.1)).unwrap();
+        assert_eq!(
+            order.refund(Money(0.01)),
+            Err(StoreError::RefundExceedsPaid(OrderId(1)))
+        );
+        order.complete().unwrap();
+        order.refund(Money(0.2)).unwrap();
+        assert_eq!(
+            order.partially_fulfill(Money(0.1)),
+            Err(StoreError::OrderClosed(OrderId(1)))
+        );
+        assert_eq!(
+            order.refund(Money(-1.0)),
+            Err(StoreError::InvalidAmount(OrderId(1)))
+        );
+
+        let view = store.find_by_id(OrderId(1)).unwrap();
+        assert!((view.amount_refunded().0 - 0.3).abs() < 1e-12);
+        assert_eq!(view.amount_fulfilled(), Money(0.1));
+        assert_eq!(
+            store.find_by_id(OrderId(2)).unwrap().amount_refunded(),
+            Money(0.0)
+        );
+
+        // The recorded events rebuild the same totals elsewhere.
+        let events: Vec<OrderEvent> = store.outbox().unwrap().pending().map(|(_, e)| *e).collect();
+        assert!(events.contains(&OrderEvent::Refunded {
+            id: OrderId(1),
+            amount: Money(0.2)
+        }));
+        for event in &events {
+            replica.apply(event).unwrap();
+        }
+        let copy = replica.find_by_id(OrderId(1)).unwrap();
+        assert_eq!(copy.amount_refunded(), view.amount_refunded());
+        assert_eq!(copy.amount_fulfilled(), Money(0.1));
+        let version = copy.version();
+        let over = OrderEvent::Refunded {
+            id: OrderId(1),
+            amount: Money(1.0),
+        };
+        assert_eq!(
+            replica.apply(&over),
+            Err(StoreError::RefundExceedsPaid(OrderId(1)))
+        );
+        let reprice = OrderEvent::AmountChanged {
+            id: OrderId(1),
+            amount: Money(0.05),
+        };
+        assert_eq!(
+            replica.apply(&reprice),
+            Err(StoreError::FulfillmentExceedsAmount(OrderId(1)))
+        );
+        assert_eq!(replica.find_by_id(OrderId(1)).unwrap().version(), version);
+    }
+
+    #[test]
+    fn customer_aggregation_ranks_top_customers() {
+        let mut soa = OrderSoA::default();
+        let spend = [
+            (1, 10.0),
+            (2, 50.0),
+            (1, 30.0),
+            (3, 5.0),
+            (2, 1.0),
+            (4, 40.0),
+        ];
+        for (i, &(c, amount)) in spend.iter().enumerate() {
+            soa.push_row(
+                OrderRow::new(OrderId(i as u64), Money(amount), Status::Pending, i as u64)
+                    .with_customer(CustomerId(c)),
+            );
+        }
+        soa.push_row(
+            OrderRow::new(OrderId(9), Money(500.0), Status::Pending, 9)
+                .with_customer(CustomerId(3)),
+        );
+        soa.remove(soa.handle_at(6)).unwrap();
+
+        let groups = soa.group_by_customer();
+        assert_eq!(groups.len(), 4);
+        let c1 = groups.get(CustomerId(1)).unwrap();
+        assert_eq!(
+            (c1.count, c1.total, c1.max),
+            (2, Money(40.0), Some(Money(30.0)))
+        );
+        assert_eq!(groups.get(CustomerId(3)).unwrap().total, Money(5.0));
+        assert!(groups.get(CustomerId(7)).is_none());
+
+        // Customers 1 and 4 tie at 40; customer 1's first order comes first.
+        let top: Vec<_> = soa
+            .top_customers(3)
+            .into_iter()
+            .map(|(c, a)| (c.0, a.total))
+            .collect();
+        assert_eq!(top, [(2, Money(51.0)), (1, Money(40.0)), (4, Money(40.0))]);
+        assert!(soa.top_customers(0).is_empty());
+        assert_eq!(soa.top_customers(10).len(), 4);
+        assert_eq!(soa.top_customers(usize::MAX).len(), 4);
+
+        let sharded = ShardedOrderStore::with_shards(3, 4);
+        for (i, &(c, amount)) in spend.iter().enumerate() {
+            let id = OrderId(i as u64);
+            sharded
+                .add(id, Money(amount), Status::Pending, i as u64)
+                .unwrap();
+            sharded
+                .update(id, |o| o.set_customer(CustomerId(c)))
+                .unwrap();
+        }
+        let merged = sharded.group_by_customer();
+        for (c, agg) in groups.iter() {
+            assert_eq!(merged.get(c), Some(agg));
+        }
+    }
+
+    #[test]
+    fn slices_run_kernels_on_row_ranges() {
+        let mut soa = OrderSoA::default();
+        for i in 0..100u64 {
+            let status = Status::ALL[(i % 3) as usize];
+            soa.push(OrderId(i), Money(i as f64), status, 1_000 - i * 10);
+        }
+        soa.sort_by_timestamp();
+        let h = soa.handle_at(soa.find_by_id(OrderId(40)).unwrap().idx);
+        soa.remove(h).unwrap();
+
+        let window = soa.slice_by_time(300, 700);
+        assert_eq!(window.rows(), soa.time_range(300, 700));
+        assert_eq!(window.len(), 40);
+        assert_eq!(window.live_len(), 39);
+        for s in Status::ALL {
+            let want = soa.sum_in_range(300, 700, s);
+            assert_eq!(window.sum_by_status(s), want);
+            assert_eq!(window.sum_by_status_with(s, Summation::Pairwise), want);
+            let groups = window.group_by_status();
+            assert_eq!(groups.get(s).total, want);
+        }
+        let rows = window.filter_indices(Money(60.0), Status::Pending);
+        let ids: Vec<_> = rows.iter().map(|&i| soa.view_at(i).id().0).collect();
+        assert_eq!(ids, vec![69, 66, 63, 60]);
+        assert!(window.iter().all(|v| (300..700).contains(&v.timestamp())));
+        assert!(window
+            .find_by_status(Status::Pending)
+            .all(|v| v.id().0 % 3 == 0));
+
+        let head = window.slice(..5);
+        assert_eq!(head.rows(), window.rows().start..window.rows().start + 5);
+        assert_eq!(head.get(0).map(|v| v.id()), Some(OrderId(70)));
+        assert!(head.get(5).is_none());
+        assert!(soa.slice_by_time(2_000, 3_000).is_empty());
+        assert_eq!(soa.slice(..).len(), soa.len());
+    }
+
+    #[test]
+    fn raw_columns_back_custom_kernels() {
+        let mut soa = OrderSoA::default();
+        let mut handles = Vec::new();
+        for i in 0..50u64 {
+            let status = Status::ALL[(i % 3) as usize];
+            handles.push(soa.push(OrderId(i), Money(i as f64), status, i));
+        }
+        assert!(soa.dead_mask().is_none());
+        soa.remove(handles[3]).unwrap();
+
+        let n = soa.len();
+        assert!([
+            soa.amounts().len(),
+            soa.statuses().len(),
+            soa.timestamps().len()
+        ]
+        .iter()
+        .chain(&[soa.currencies().len(), soa.customers().len()])
+        .all(|&l| l == n));
+        let dead = soa.dead_mask().unwrap();
+        assert!(dead.get(3));
+        let total: f64 = soa
+            .statuses()
+            .iter()
+            .zip(soa.amounts())
+            .enumerate()
+            .filter(|&(i, (&s, _))| s == Status::Pending && !dead.get(i))
+            .map(|(_, (_, &a))| a)
+            .sum();
+        assert_eq!(Money(total), soa.sum_by_status(Status::Pending));
+        assert_eq!(soa.ids()[7], OrderId(7));
+        assert_eq!(soa.timestamps()[7], 7);
+    }
+
+    #[test]
+    fn blocks_tile_the_columns() {
+        let mut soa = OrderSoA::default();
+        let mut handles = Vec::new();
+        for i in 0..1_000u64 {
+            let status = Status::ALL[(i % 3) as usize];
+            handles.push(soa.push(OrderId(i), Money(i as f64), status, i));
+        }
+        soa.remove(handles[500]).unwrap();
+
+        for ahead in [0, 2] {
+            let blocks = soa.blocks(64).prefetch(ahead);
+            assert_eq!(blocks.len(), 16);
+            let mut total = 0.0;
+            let mut next = 0;
+            for block in blocks {
+                assert_eq!(block.rows().start, next);
+                assert!(block.len() == 64 || block.rows().end == 1_000);
+                next = block.rows().end;
+                for (i, (&s, &a)) in block.statuses.iter().zip(block.amounts).enumerate() {
+                    if s == Status::Pending && block.is_live(i) {
+                        total += a;
+                    }
+                }
+                assert_eq!(block.ids[0], OrderId(block.rows().start as u64));
+            }
+            assert_eq!(next, 1_000);
+            assert_eq!(Money(total), soa.sum_by_status(Status::Pending));
+        }
+        assert_eq!(OrderSoA::default().blocks(DEFAULT_BLOCK_ROWS).count(), 0);
+    }
+
+    #[test]
+    fn dedup_by_id_keeps_one_row_per_id() {
+        let replayed = |keep| {
+            let mut soa = OrderSoA::default();
+            for (id, amount, ts) in [(1, 10.0, 5), (2, 20.0, 1), (1, 11.0, 9), (3, 30.0, 2)] {
+                soa.push(OrderId(id), Money(amount), Status::Pending, ts);
+            }
+            // A late, out-of-order redelivery of order 1, and a duplicate of order 2 at the same
+            // timestamp.
+            soa.push(OrderId(1), Money(9.0), Status::Pending, 1);
+            soa.push(OrderId(2), Money(21.0), Status::Pending, 1);
+            let h = soa.push(OrderId(4), Money(40.0), Status::Pending, 3);
+            soa.push(OrderId(4), Money(41.0), Status::Pending, 4);
+            soa.remove(h).unwrap();
+            let removed = soa.dedup_by_id(keep);
+            let mut rows: Vec<_> = soa.iter().map(|v| (v.id().0, v.amount().0)).collect();
+            rows.sort_by_key(|&(id, _)| id);
+            (removed, rows, soa)
+        };
+
+        let (removed, rows, soa) = replayed(KeepPolicy::Latest);
+        assert_eq!(removed, 3);
+        assert_eq!(rows, vec![(1, 11.0), (2, 21.0), (3, 30.0), (4, 41.0)]);
+        assert_eq!(soa.len(), 4);
+        assert_eq!(soa.find_by_id(OrderId(1)).unwrap().amount(), Money(11.0));
+
+        let (removed, rows, _) = replayed(KeepPolicy::Earliest);
+        assert_eq!(removed, 3);
+        assert_eq!(rows, vec![(1, 9.0), (2, 20.0), (3, 30.0), (4, 41.0)]);
+
+        let mut soa = OrderSoA::default();
+        let h = soa.push(OrderId(1), Money(1.0), Status::Pending, 1);
+        assert_eq!(soa.dedup_by_id(KeepPolicy::default()), 0);
+        assert!(soa.view(h).is_ok());
+    }
+
+    #[test]
+    fn bulk_status_transitions() {
+        let mut store = OrderStore::new().with_status_index();
+        for i in 0..10u64 {
+            let status = if i < 8 {
+                Status::Pending
+            } else {
+                Status::Completed
+            };
+            store
+                .add(OrderId(i), Money(i as f64), status, i * 10)
+                .unwrap();
+        }
+        let rx = store.subscribe();
+        let stale = |v: OrderView<'_>| v.timestamp() < 50;
+        let versions: Vec<_> = store.kernel().iter().map(|v| v.version()).collect();
+
+        assert_eq!(
+            store.transition_status(Status::Pending, Status::Cancelled, stale),
+            Ok(5)
+        );
+        let cancelled: Vec<_> = store
+            .find_by_status(Status::Cancelled)
+            .map(|v| v.id().0)
+            .collect();
+        assert_eq!(cancelled, vec![0, 1, 2, 3, 4]);
+        assert_eq!(store.find_by_status(Status::Pending).count(), 3);
+        let events: Vec<_> = rx.try_iter().collect();
+        assert_eq!(events.len(), 5);
+        assert!(events.iter().all(|e| e.kind
+            == ChangeKind::Updated {
+                field: Field::Status
+            }));
+        let bumped = store
+            .kernel()
+            .iter()
+            .zip(&versions)
+            .filter(|(v, &old)| v.version() > old);
+        assert_eq!(bumped.count(), 5);
+
+        // Nothing left to move, and a no-op transition touches nothing.
+        assert_eq!(
+            store.transition_status(Status::Pending, Status::Cancelled, stale),
+            Ok(0)
+        );
+        assert_eq!(
+            store.transition_status(Status::Pending, Status::Pending, |_| true),
+            Ok(0)
+        );
+
+        // A rejected row keeps the whole batch from applying.
+        let mut store = store.with_validator(|c: &OrderCandidate| {
+            if c.row.status == Status::Completed && c.row.amount.0 < 6.0 {
+                Err(DomainError::Violated("small orders cannot complete"))
+            } else {
+                Ok(())
+            }
+        });
+        let all = |_: OrderView<'_>| true;
+        let err = store.transition_status(Status::Pending, Status::Completed, all);
+        assert!(matches!(err, Err(StoreError::Invalid(OrderId(5), _))));
+        assert_eq!(store.find_by_status(Status::Pending).count(), 3);
+
+        let mut soa = store.kernel().clone();
+        assert_eq!(
+            soa.transition_status(Status::Cancelled, Status::Pending, all),
+            5
+        );
+        assert_eq!(soa.find_by_status(Status::Pending).count(), 8);
+    }
+
+    #[test]
+    fn commands_and_events_are_validated() {
+        let cap = |c: &OrderCandidate| {
+            if c.row.amount.0 > 100.0 {
+                Err(DomainError::Violated("cap"))
+            } else {
+                Ok(())
+            }
+        };
+        let no_completion = |c: &OrderCandidate| {
+            if c.row.status == Status::Completed {
+                Err(DomainError::Violated("frozen"))
+            } else {
+                Ok(())
+            }
+        };
+        let mut store = OrderStore::new()
+            .with_validator(cap)
+            .with_validator(no_completion);
+        store
+            .add(OrderId(1), Money(50.0), Status::Pending, 1)
+            .unwrap();
+        let rejected = |what| Err(StoreError::Invalid(OrderId(1), DomainError::Violated(what)));
+
+        let event = OrderEvent::AmountChanged {
+            id: OrderId(1),
+            amount: Money(500.0),
+        };
+        assert_eq!(store.apply(&event), rejected("cap"));
+        let event = OrderEvent::StatusChanged {
+            id: OrderId(1),
+            status: Status::Completed,
+        };
+        assert_eq!(store.apply(&event), rejected("frozen"));
+
+        let mut order = store.load(OrderId(1)).unwrap();
+        assert_eq!(order.adjust_amount(Money(500.0)), rejected("cap"));
+        assert_eq!(order.complete(), rejected("frozen"));
+        assert_eq!(order.adjust_amount(Money(80.0)), Ok(()));
+        assert_eq!(order.partially_fulfill(Money(10.0)), Ok(()));
+        assert_eq!(order.cancel(), Ok(()));
+
+        let v = store.find_by_id(OrderId(1)).unwrap();
+        assert_eq!((v.amount(), v.status()), (Money(80.0), Status::Cancelled));
+    }
+
+    #[test]
+    fn sharded_queries_match_single_store() {
+        let mut single = OrderStore::new();
+        let sharded = ShardedOrderStore::with_shards(3, 100);
+        for i in 0..300u64 {
+            let st = Status::ALL[(i % 3) as usize];
+            single
+                .add(OrderId(i), Money((i % 17) as f64), st, i)
+                .unwrap();
+            sharded
+                .add(OrderId(i), Money((i % 17) as f64), st, i)
+                .unwrap();
+        }
+        assert_eq!(sharded.group_by_status(), single.kernel().group_by_status());
+        assert_eq!(
+            sharded.filter_indices(Money(8.0), Status::Pending).len(),
+            single
+                .kernel()
+                .filter_indices(Money(8.0), Status::Pending)
+                .len()
+        );
+        assert_eq!(
+            sharded.find_by_id(OrderId(42)),
+            single.find_by_id(OrderId(42)).map(OrderRow::from)
+        );
+        assert_eq!(sharded.find_by_id(OrderId(300)), None);
+
+        sharded.retain(|v| v.amount().0 >= 8.0);
+        single.kernel_mut().retain(|v| v.amount().0 >= 8.0);
+        let mut rows: Vec<OrderRow> = sharded.iter().collect();
+        rows.sort_by_key(|r| r.id.0);
+        assert_eq!(
+            rows,
+            single
+                .kernel()
+                .iter()
+                .map(OrderRow::from)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(sharded.len(), single.kernel().live_len());
+    }
+
+    #[test]
+    fn sharded_store_usage() {
+        let sharded = ShardedOrderStore::with_shards(4, 10);
+
+        // Add some orders to different shards
+        let _ = sharded.add(OrderId(1), Money(100.0), Status::Completed, 1000);
+        let _ = sharded.add(OrderId(5), Money(200.0), Status::Completed, 2000); // Different shard
+        let _ = sharded.add(OrderId(9), Money(50.0), Status::Pending, 3000); // Different shard
+
+        // Verify sum_by_status works across shards
+        let completed_total = sharded.sum_by_status(Status::Completed);
+        assert_eq!(completed_total.0, 300.0);
+
+        let pending_total = sharded.sum_by_status(Status::Pending);
+        assert_eq!(pending_total.0, 50.0);
+
+        // Writers on different threads only lock the shard they touch.
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<ShardedOrderStore>();
+        std::thread::scope(|s| {
+            for t in 0..4u64 {
+                let sharded = &sharded;
+                s.spawn(move || {
+                    for i in 0..1_000u64 {
+                        sharded
+                            .add(OrderId(100 + i * 4 + t), Money(1.0), Status::Cancelled, i)
+                            .unwrap();
+                    }
+                });
+            }
+        });
+        assert_eq!(sharded.sum_by_status(Status::Cancelled).0, 4_000.0);
+        assert!((0..sharded.shard_count()).all(|si| sharded.read_shard(si).len() >= 1_000));
+
+        sharded
+            .update(OrderId(9), |o| o.set_status(Status::Completed))
+            .unwrap();
+        assert_eq!(sharded.sum_by_status(Status::Completed).0, 350.0);
+        assert_eq!(
+            sharded.update(OrderId(7), |_| ()),
+            Err(StoreError::UnknownId(OrderId(7)))
+        );
     }
 }
@@ -0,0 +1,222 @@
+//! Line items: a child SoA of `OrderSoA`.
+//!
+//! All items live in one `LineItemSoA` owned by the order kernel; each order row stores the
+//! `[start, end)` range of its items, and an order's items are appended together so the range is
+//! contiguous. `OrderView::line_items` slices the item columns for that range without copying.
+//! Items of dropped orders are reclaimed when the kernel compacts. Snapshots (serde, Arrow) carry
+//! only the order columns.
+
+use crate::memory::ColumnMemory;
+use crate::{Money, OrderId, OrderSoA, OrderView, RowHandle, Status};
+
+/// Owned line item, for appending.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LineItem {
+    pub sku: u64,
+    pub qty: u32,
+    pub unit_price: Money,
+}
+
+impl LineItem {
+    pub fn new(sku: u64, qty: u32, unit_price: Money) -> Self {
+        Self {
+            sku,
+            qty,
+            unit_price,
+        }
+    }
+
+    /// `qty * unit_price`.
+    pub fn total(&self) -> Money {
+        Money(self.qty as f64 * self.unit_price.0)
+    }
+}
+
+/// Columns of every order's line items, back to back.
+#[derive(Clone, Debug, Default)]
+pub struct LineItemSoA {
+    skus: Vec<u64>,
+    qtys: Vec<u32>,
+    unit_prices: Vec<f64>,
+}
+
+impl LineItemSoA {
+    pub fn len(&self) -> usize {
+        self.skus.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.skus.is_empty()
+    }
+
+    pub(crate) fn append(&mut self, other: &mut LineItemSoA) {
+        self.skus.append(&mut other.skus);
+        self.qtys.append(&mut other.qtys);
+        self.unit_prices.append(&mut other.unit_prices);
+    }
+
+    /// Append `src`'s items in `range`, returning where they now live.
+    pub(crate) fn copy_from(&mut self, src: &LineItemSoA, range: (u32, u32)) -> (u32, u32) {
+        let start = self.len() as u32;
+        let r = range.0 as usize..range.1 as usize;
+        self.skus.extend_from_slice(&src.skus[r.clone()]);
+        self.qtys.extend_from_slice(&src.qtys[r.clone()]);
+        self.unit_prices.extend_from_slice(&src.unit_prices[r]);
+        (start, self.len() as u32)
+    }
+
+    pub(crate) fn shrink_to_fit(&mut self) {
+        self.skus.shrink_to_fit();
+        self.qtys.shrink_to_fit();
+        self.unit_prices.shrink_to_fit();
+    }
+
+    pub(crate) fn memory(&self, out: &mut Vec<ColumnMemory>) {
+        out.push(ColumnMemory::of_vec("item_skus", &self.skus));
+        out.push(ColumnMemory::of_vec("item_qtys", &self.qtys));
+        out.push(ColumnMemory::of_vec("item_unit_prices", &self.unit_prices));
+    }
+
+    fn push(&mut self, item: LineItem) {
+        self.skus.push(item.sku);
+        self.qtys.push(item.qty);
+        self.unit_prices.push(item.unit_price.0);
+    }
+
+    /// Zero-copy view of the items in `start..end`.
+    fn slice(&self, (start, end): (u32, u32)) -> LineItems<'_> {
+        let r = start as usize..end as usize;
+        LineItems {
+            skus: &self.skus[r.clone()],
+            qtys: &self.qtys[r.clone()],
+            unit_prices: &self.unit_prices[r],
+        }
+    }
+}
+
+/// The line items of one order, as column slices.
+#[derive(Copy, Clone, Debug)]
+pub struct LineItems<'a> {
+    skus: &'a [u64],
+    qtys: &'a [u32],
+    unit_prices: &'a [f64],
+}
+
+impl<'a> LineItems<'a> {
+    pub fn len(&self) -> usize {
+        self.skus.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.skus.is_empty()
+    }
+
+    pub fn skus(&self) -> &'a [u64] {
+        self.skus
+    }
+
+    pub fn qtys(&self) -> &'a [u32] {
+        self.qtys
+    }
+
+    pub fn unit_prices(&self) -> &'a [f64] {
+        self.unit_prices
+    }
+
+    pub fn get(&self, i: usize) -> Option<LineItem> {
+        (i < self.len())
+            .then(|| LineItem::new(self.skus[i], self.qtys[i], Money(self.unit_prices[i])))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = LineItem> + 'a {
+        let items = *self;
+        (0..items.len())
+            .map(move |i| LineItem::new(items.skus[i], items.qtys[i], Money(items.unit_prices[i])))
+    }
+
+    /// Sum of `qty * unit_price`, reading only the quantity and price columns.
+    pub fn total(&self) -> Money {
+        Money(
+            self.qtys
+                .iter()
+                .zip(self.unit_prices)
+                .map(|(&q, &p)| q as f64 * p)
+                .sum(),
+        )
+    }
+}
+
+impl OrderSoA {
+    /// Append an order together with its line items; the items are stored contiguously.
+    pub fn push_with_items(
+        &mut self,
+        id: OrderId,
+        amount: Money,
+        status: Status,
+        ts: u64,
+        items: impl IntoIterator<Item = LineItem>,
+    ) -> RowHandle {
+        let start = self.items.len() as u32;
+        for item in items {
+            self.items.push(item);
+        }
+        let h = self.push(id, amount, status, ts);
+        self.item_offsets[h.index] = (start, self.items.len() as u32);
+        h
+    }
+
+    /// The line item columns for all orders.
+    pub fn line_item_columns(&self) -> &LineItemSoA {
+        &self.items
+    }
+
+    /// Per-row order totals recomputed from line items (`0` for orders without items and for
+    /// deleted rows), indexed like the order columns. One pass over the offsets and the item
+    /// quantity/price columns.
+    pub fn order_totals_from_items(&self) -> Vec<Money> {
+        self.item_offsets
+            .iter()
+            .enumerate()
+            .map(|(i, &range)| {
+                if self.is_live(i) {
+                    self.items.slice(range).total()
+                } else {
+                    Money(0.0)
+                }
+            })
+            .collect()
+    }
+
+    /// Rebuild the item columns so only the items of the remaining rows are kept, in row order.
+    pub(crate) fn compact_items(&mut self) {
+        let kept: usize = self
+            .item_offsets
+            .iter()
+            .map(|&(s, e)| (e - s) as usize)
+            .sum();
+        if kept == self.items.len() && self.item_offsets.windows(2).all(|w| w[0].1 == w[1].0) {
+            return;
+        }
+        let mut items = LineItemSoA {
+            skus: Vec::with_capacity(kept),
+            qtys: Vec::with_capacity(kept),
+            unit_prices: Vec::with_capacity(kept),
+        };
+        for range in &mut self.item_offsets {
+            let start = items.len() as u32;
+            for item in self.items.slice(*range).iter() {
+                items.push(item);
+            }
+            *range = (start, items.len() as u32);
+        }
+        self.items = items;
+    }
+}
+
+impl<'a> OrderView<'a> {
+    /// This order's line items, borrowed from the kernel's item columns.
+    #[inline]
+    pub fn line_items(&self) -> LineItems<'a> {
+        self.soa.items.slice(self.soa.item_offsets[self.idx])
+    }
+}
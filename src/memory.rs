@@ -0,0 +1,228 @@
+//! Capacity management and memory introspection.
+//!
+//! `reserve` / `shrink_to_fit` act on every row-aligned column at once, so a long-lived store can
+//! pre-size before a bulk load and give back slack after a purge. `memory_usage` reports heap
+//! bytes column by column (used vs. allocated), and per shard for `ShardedOrderStore`. Vectors are
+//! measured exactly; hash maps are estimated from their capacity.
+
+use crate::{OrderId, OrderSoA, ShardedOrderStore};
+use std::collections::HashMap;
+use std::mem::size_of;
+
+/// Heap footprint of one column or index.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ColumnMemory {
+    pub name: &'static str,
+    pub len: usize,
+    pub capacity: usize,
+    /// Bytes holding `len` elements.
+    pub used_bytes: usize,
+    /// Bytes allocated for `capacity` elements.
+    pub allocated_bytes: usize,
+}
+
+impl ColumnMemory {
+    pub(crate) fn of_vec<T>(name: &'static str, v: &Vec<T>) -> Self {
+        Self::sized(name, v.len(), v.capacity(), size_of::<T>())
+    }
+
+    pub(crate) fn sized(name: &'static str, len: usize, capacity: usize, elem: usize) -> Self {
+        Self {
+            name,
+            len,
+            capacity,
+            used_bytes: len * elem,
+            allocated_bytes: capacity * elem,
+        }
+    }
+}
+
+/// Heap footprint of a kernel, or of a sharded store (columns summed over shards, plus one
+/// report per shard).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryReport {
+    pub columns: Vec<ColumnMemory>,
+    /// Empty for a single kernel.
+    pub shards: Vec<MemoryReport>,
+}
+
+impl MemoryReport {
+    pub fn used_bytes(&self) -> usize {
+        self.columns.iter().map(|c| c.used_bytes).sum()
+    }
+
+    pub fn allocated_bytes(&self) -> usize {
+        self.columns.iter().map(|c| c.allocated_bytes).sum()
+    }
+
+    pub fn column(&self, name: &str) -> Option<&ColumnMemory> {
+        self.columns.iter().find(|c| c.name == name)
+    }
+
+    /// Add `other`'s columns into this report's, matching by name.
+    fn absorb(&mut self, other: &MemoryReport) {
+        for c in &other.columns {
+            match self.columns.iter_mut().find(|mine| mine.name == c.name) {
+                Some(mine) => {
+                    mine.len += c.len;
+                    mine.capacity += c.capacity;
+                    mine.used_bytes += c.used_bytes;
+                    mine.allocated_bytes += c.allocated_bytes;
+                }
+                None => self.columns.push(*c),
+            }
+        }
+    }
+}
+
+/// Estimated heap bytes of the primary-key index: one slot plus one control byte per bucket.
+fn id_index_memory(ix: &HashMap<OrderId, usize>) -> ColumnMemory {
+    ColumnMemory::sized(
+        "id_index",
+        ix.len(),
+        ix.capacity(),
+        size_of::<(OrderId, usize)>() + 1,
+    )
+}
+
+impl OrderSoA {
+    /// Reserve room for `additional` more rows in every row-aligned column and the id index.
+    pub fn reserve(&mut self, additional: usize) {
+        self.ids.reserve(additional);
+        self.amounts.reserve(additional);
+        self.statuses.reserve(additional);
+        self.timestamps.reserve(additional);
+        self.currencies.reserve(additional);
+        self.customers.reserve(additional);
+        self.item_offsets.reserve(additional);
+        self.deleted.reserve(additional);
+        self.generations.reserve(additional);
+        self.versions.reserve(additional);
+        if let Some(a) = &mut self.audit {
+            a.reserve(additional);
+        }
+        if let Some(exact) = &mut self.exact_amounts {
+            exact.reserve(additional);
+        }
+        if let Some(s) = &mut self.states {
+            s.codes.reserve(additional);
+        }
+        if let Some(s) = &mut self.settlements {
+            s.reserve(additional);
+        }
+        self.id_index.reserve(additional);
+    }
+
+    /// Release spare capacity of every column, the line items and the id index.
+    pub fn shrink_to_fit(&mut self) {
+        self.ids.shrink_to_fit();
+        self.amounts.shrink_to_fit();
+        self.statuses.shrink_to_fit();
+        self.timestamps.shrink_to_fit();
+        self.currencies.shrink_to_fit();
+        self.customers.shrink_to_fit();
+        self.item_offsets.shrink_to_fit();
+        self.items.shrink_to_fit();
+        self.deleted.shrink_to_fit();
+        self.generations.shrink_to_fit();
+        self.versions.shrink_to_fit();
+        if let Some(a) = &mut self.audit {
+            a.shrink_to_fit();
+        }
+        if let Some(exact) = &mut self.exact_amounts {
+            exact.shrink_to_fit();
+        }
+        if let Some(s) = &mut self.states {
+            s.codes.shrink_to_fit();
+        }
+        if let Some(s) = &mut self.settlements {
+            s.shrink_to_fit();
+        }
+        self.id_index.shrink_to_fit();
+    }
+
+    /// Bytes per column; see `MemoryReport`. Indexes that are not built report zero.
+    pub fn memory_usage(&self) -> MemoryReport {
+        let mut columns = vec![
+            ColumnMemory::of_vec("ids", &self.ids),
+            ColumnMemory::of_vec("amounts", &self.amounts),
+            ColumnMemory::of_vec("statuses", &self.statuses),
+            ColumnMemory::of_vec("timestamps", &self.timestamps),
+            ColumnMemory::of_vec("currencies", &self.currencies),
+            ColumnMemory::of_vec("customers", &self.customers),
+            ColumnMemory::of_vec("item_offsets", &self.item_offsets),
+        ];
+        self.items.memory(&mut columns);
+        columns.push(ColumnMemory::sized(
+            "deleted",
+            self.deleted.words().len(),
+            self.deleted.word_capacity(),
+            size_of::<u64>(),
+        ));
+        columns.push(ColumnMemory::of_vec("generations", &self.generations));
+        columns.push(ColumnMemory::of_vec("versions", &self.versions));
+        if let Some(a) = &self.audit {
+            columns.push(ColumnMemory::of_vec("created_at", &a.created_at));
+            columns.push(ColumnMemory::of_vec("updated_at", &a.updated_at));
+            columns.push(ColumnMemory::of_vec("updated_by", &a.updated_by));
+        }
+        if let Some(exact) = &self.exact_amounts {
+            columns.push(ColumnMemory::of_vec("exact_amounts", exact));
+        }
+        if let Some(s) = &self.states {
+            columns.push(ColumnMemory::of_vec("states", &s.codes));
+        }
+        if let Some(s) = &self.settlements {
+            columns.push(ColumnMemory::of_vec("amount_refunded", &s.refunded));
+            columns.push(ColumnMemory::of_vec("amount_fulfilled", &s.fulfilled));
+        }
+        columns.push(id_index_memory(&self.id_index));
+        let (len, capacity) = self.status_index.get().map_or((0, 0), |ix| {
+            ix.iter().fold((0, 0), |(len, cap), list| {
+                (len + list.len(), cap + list.capacity())
+            })
+        });
+        columns.push(ColumnMemory::sized(
+            "status_index",
+            len,
+            capacity,
+            size_of::<usize>(),
+        ));
+        columns.push(self.composite_index_memory());
+        MemoryReport {
+            columns,
+            shards: Vec::new(),
+        }
+    }
+}
+
+impl ShardedOrderStore {
+    /// Reserve room for `additional` more rows, split evenly across shards.
+    pub fn reserve(&mut self, additional: usize) {
+        let per_shard = additional.div_ceil(self.shards.len());
+        for s in &mut self.shards {
+            s.get_mut()
+                .unwrap_or_else(|e| e.into_inner())
+                .reserve(per_shard);
+        }
+    }
+
+    pub fn shrink_to_fit(&mut self) {
+        for s in &mut self.shards {
+            s.get_mut()
+                .unwrap_or_else(|e| e.into_inner())
+                .shrink_to_fit();
+        }
+    }
+
+    /// Column totals across shards, with each shard's own report in `shards`.
+    pub fn memory_usage(&self) -> MemoryReport {
+        let mut report = MemoryReport::default();
+        for si in 0..self.shards.len() {
+            let shard = self.read_shard(si).memory_usage();
+            report.absorb(&shard);
+            report.shards.push(shard);
+        }
+        report
+    }
+}
@@ -0,0 +1,236 @@
+//! Currency-aware money in integer minor units.
+//!
+//! `Money(f64)` stays the kernel's column type for hot-path arithmetic; `TypedMoney` is the exact
+//! façade type: an `i64` count of minor units (cents, pence, ...) tagged with its `Currency`, with
+//! checked arithmetic that refuses to mix currencies or overflow. Converting between currencies
+//! takes an `ExchangeRates` source; a pair it has no rate for is an error, never a silent 1:1.
+//! Kernels built `with_exact_amounts` also keep a fixed-point copy of the amount column, so
+//! `TypedMoney` values and totals read from them never pass through a float.
+
+use crate::Money;
+use std::collections::HashMap;
+use std::fmt;
+
+/// ISO-4217 style three-letter currency code.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Currency(pub [u8; 3]);
+
+impl Currency {
+    pub const USD: Currency = Currency(*b"USD");
+    pub const EUR: Currency = Currency(*b"EUR");
+    pub const GBP: Currency = Currency(*b"GBP");
+    pub const JPY: Currency = Currency(*b"JPY");
+
+    /// Number of decimal places in one major unit (2 for cents, 0 for yen).
+    pub fn minor_exponent(self) -> u32 {
+        match &self.0 {
+            b"JPY" | b"KRW" => 0,
+            b"BHD" | b"KWD" | b"OMR" => 3,
+            _ => 2,
+        }
+    }
+
+    /// Minor units per major unit, e.g. 100 for USD.
+    pub fn minor_per_major(self) -> i64 {
+        10i64.pow(self.minor_exponent())
+    }
+
+    pub fn code(&self) -> &str {
+        std::str::from_utf8(&self.0).unwrap_or("???")
+    }
+
+    /// Parse a three-letter code; no registry check beyond the length.
+    pub fn from_code(code: &str) -> Option<Currency> {
+        code.as_bytes().try_into().ok().map(Currency)
+    }
+}
+
+impl Default for Currency {
+    fn default() -> Self {
+        Currency::USD
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+/// Failures from checked money arithmetic.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MoneyError {
+    CurrencyMismatch {
+        left: Currency,
+        right: Currency,
+    },
+    Overflow,
+    /// The `ExchangeRates` source has no rate from `from` to `to`.
+    MissingRate {
+        from: Currency,
+        to: Currency,
+    },
+}
+
+impl fmt::Display for MoneyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoneyError::CurrencyMismatch { left, right } => {
+                write!(f, "cannot combine {left} with {right}")
+            }
+            MoneyError::Overflow => f.write_str("money arithmetic overflowed"),
+            MoneyError::MissingRate { from, to } => {
+                write!(f, "no exchange rate from {from} to {to}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for MoneyError {}
+
+/// Exact amount: integer minor units plus currency.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TypedMoney {
+    pub minor_units: i64,
+    pub currency: Currency,
+}
+
+impl TypedMoney {
+    pub fn new(minor_units: i64, currency: Currency) -> Self {
+        Self {
+            minor_units,
+            currency,
+        }
+    }
+
+    pub fn zero(currency: Currency) -> Self {
+        Self::new(0, currency)
+    }
+
+    /// Round a major-unit float (the kernel's column representation) to minor units.
+    pub fn from_money(m: Money, currency: Currency) -> Self {
+        let minor = (m.0 * currency.minor_per_major() as f64).round() as i64;
+        Self::new(minor, currency)
+    }
+
+    /// Major-unit float for the kernel's amount column.
+    pub fn to_money(self) -> Money {
+        Money(self.minor_units as f64 / self.currency.minor_per_major() as f64)
+    }
+
+    pub fn checked_add(self, other: TypedMoney) -> Result<TypedMoney, MoneyError> {
+        self.same_currency(other)?;
+        self.minor_units
+            .checked_add(other.minor_units)
+            .map(|m| Self::new(m, self.currency))
+            .ok_or(MoneyError::Overflow)
+    }
+
+    pub fn checked_sub(self, other: TypedMoney) -> Result<TypedMoney, MoneyError> {
+        self.same_currency(other)?;
+        self.minor_units
+            .checked_sub(other.minor_units)
+            .map(|m| Self::new(m, self.currency))
+            .ok_or(MoneyError::Overflow)
+    }
+
+    /// The amount in `to`, at `rates`' rate and rounded to `to`'s minor units.
+    pub fn convert(
+        self,
+        to: Currency,
+        rates: &dyn ExchangeRates,
+    ) -> Result<TypedMoney, MoneyError> {
+        if self.currency == to {
+            return Ok(self);
+        }
+        let rate = rates
+            .rate(self.currency, to)
+            .ok_or(MoneyError::MissingRate {
+                from: self.currency,
+                to,
+            })?;
+        let major = self.minor_units as f64 / self.currency.minor_per_major() as f64;
+        let minor = (major * rate * to.minor_per_major() as f64).round();
+        // `as` saturates, so anything outside i64 must be caught before the cast.
+        if !minor.is_finite() || minor.abs() >= i64::MAX as f64 {
+            return Err(MoneyError::Overflow);
+        }
+        Ok(Self::new(minor as i64, to))
+    }
+
+    fn same_currency(self, other: TypedMoney) -> Result<(), MoneyError> {
+        if self.currency == other.currency {
+            Ok(())
+        } else {
+            Err(MoneyError::CurrencyMismatch {
+                left: self.currency,
+                right: other.currency,
+            })
+        }
+    }
+}
+
+impl fmt::Display for TypedMoney {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let exp = self.currency.minor_exponent() as usize;
+        let per = self.currency.minor_per_major().unsigned_abs();
+        let sign = if self.minor_units < 0 { "-" } else { "" };
+        let abs = self.minor_units.unsigned_abs();
+        if exp == 0 {
+            write!(f, "{sign}{abs} {}", self.currency)
+        } else {
+            write!(
+                f,
+                "{sign}{}.{:0exp$} {}",
+                abs / per,
+                abs % per,
+                self.currency
+            )
+        }
+    }
+}
+
+/// A source of exchange rates, e.g. a table refreshed from a market feed.
+pub trait ExchangeRates {
+    /// Units of `to` per unit of `from` (in major units), or `None` if unknown. Never asked for
+    /// `from == to`.
+    fn rate(&self, from: Currency, to: Currency) -> Option<f64>;
+}
+
+impl<F: Fn(Currency, Currency) -> Option<f64>> ExchangeRates for F {
+    fn rate(&self, from: Currency, to: Currency) -> Option<f64> {
+        self(from, to)
+    }
+}
+
+/// A fixed rate table. A pair without its own entry falls back to the inverse of the opposite
+/// pair's.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FixedRates {
+    rates: HashMap<(Currency, Currency), f64>,
+}
+
+impl FixedRates {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builder: one `from` buys `rate` of `to`.
+    pub fn with_rate(mut self, from: Currency, to: Currency, rate: f64) -> Self {
+        self.set_rate(from, to, rate);
+        self
+    }
+
+    pub fn set_rate(&mut self, from: Currency, to: Currency, rate: f64) {
+        self.rates.insert((from, to), rate);
+    }
+}
+
+impl ExchangeRates for FixedRates {
+    fn rate(&self, from: Currency, to: Currency) -> Option<f64> {
+        self.rates
+            .get(&(from, to))
+            .copied()
+            .or_else(|| self.rates.get(&(to, from)).map(|r| 1.0 / r))
+    }
+}
@@ -0,0 +1,132 @@
+//! Nullable columns: dense values plus a validity bitmap (set bit = value present).
+//!
+//! Null slots hold `T::default()` so the value vector stays contiguous and kernels can run over
+//! it unconditionally, consulting the bitmap only to skip nulls.
+
+use crate::dyn_soa::DynColumn;
+use crate::Bitmap;
+use std::any::{type_name, Any};
+
+/// A column of `Option<T>` stored as `Vec<T>` + `Bitmap`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OptionColumn<T> {
+    values: Vec<T>,
+    validity: Bitmap,
+}
+
+impl<T: Copy + Default> OptionColumn<T> {
+    pub fn new() -> Self {
+        Self {
+            values: Vec::new(),
+            validity: Bitmap::new(),
+        }
+    }
+
+    pub fn with_capacity(cap: usize) -> Self {
+        Self {
+            values: Vec::with_capacity(cap),
+            validity: Bitmap::with_capacity(cap),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn push(&mut self, value: Option<T>) {
+        self.values.push(value.unwrap_or_default());
+        self.validity.push(value.is_some());
+    }
+
+    #[inline]
+    pub fn get(&self, i: usize) -> Option<T> {
+        self.validity.get(i).then(|| self.values[i])
+    }
+
+    #[inline]
+    pub fn set(&mut self, i: usize, value: Option<T>) {
+        self.values[i] = value.unwrap_or_default();
+        self.validity.set(i, value.is_some());
+    }
+
+    pub fn null_count(&self) -> usize {
+        self.len() - self.validity.count_ones()
+    }
+
+    /// Raw values; null slots hold `T::default()`.
+    pub fn values(&self) -> &[T] {
+        &self.values
+    }
+
+    pub fn validity(&self) -> &Bitmap {
+        &self.validity
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Option<T>> + '_ {
+        (0..self.len()).map(|i| self.get(i))
+    }
+
+    /// `(row, value)` for non-null rows only, walking the bitmap a word at a time.
+    pub fn iter_valid(&self) -> impl Iterator<Item = (usize, T)> + '_ {
+        self.validity.iter_ones().map(|i| (i, self.values[i]))
+    }
+}
+
+impl<T: Copy + Default + PartialOrd> OptionColumn<T> {
+    /// Smallest non-null value.
+    pub fn min(&self) -> Option<T> {
+        self.iter_valid()
+            .map(|(_, v)| v)
+            .reduce(|a, b| if b < a { b } else { a })
+    }
+
+    /// Largest non-null value.
+    pub fn max(&self) -> Option<T> {
+        self.iter_valid()
+            .map(|(_, v)| v)
+            .reduce(|a, b| if b > a { b } else { a })
+    }
+}
+
+impl<T: Copy + Default + Into<f64>> OptionColumn<T> {
+    /// Sum of non-null values.
+    pub fn sum(&self) -> f64 {
+        self.iter_valid().map(|(_, v)| v.into()).sum()
+    }
+}
+
+impl<T: Copy + Default> FromIterator<Option<T>> for OptionColumn<T> {
+    fn from_iter<I: IntoIterator<Item = Option<T>>>(iter: I) -> Self {
+        let mut col = Self::new();
+        iter.into_iter().for_each(|v| col.push(v));
+        col
+    }
+}
+
+impl<T: Copy + Default + Send + Sync + 'static> DynColumn for OptionColumn<T> {
+    fn len(&self) -> usize {
+        self.values.len()
+    }
+    fn push_default(&mut self) {
+        self.push(None);
+    }
+    fn retain_mask(&mut self, keep: &[bool]) {
+        *self = (0..self.len())
+            .filter(|&i| keep[i])
+            .map(|i| self.get(i))
+            .collect();
+    }
+    fn type_name(&self) -> &'static str {
+        type_name::<Option<T>>()
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
@@ -0,0 +1,137 @@
+//! Transactional outbox for domain events.
+//!
+//! A store built `with_outbox` records an `OrderEvent` for every successful `OrderAggregate`
+//! command, in the same write as the state change: inside a `Transaction` the events commit or
+//! roll back together with the rows. The outbox is a small SoA table (sequence number, event,
+//! delivery attempts) drained in order by `deliver`, which hands each pending event to a
+//! `Publisher` and marks it acknowledged once the publisher returns `Ok`. A failure stops the
+//! run and leaves that event and all later ones pending for the next attempt, so delivery is
+//! ordered and at-least-once: an event may be published again if its acknowledgement is lost,
+//! but never skipped.
+
+use crate::{OrderEvent, OrderStore};
+
+/// Downstream sink for outbox events, e.g. a message broker client.
+pub trait Publisher {
+    type Error;
+
+    /// Publish one event; `Ok` acknowledges it. `seq` identifies the event for deduplication.
+    fn publish(&mut self, seq: u64, event: &OrderEvent) -> Result<(), Self::Error>;
+}
+
+impl<E, F: FnMut(u64, &OrderEvent) -> Result<(), E>> Publisher for F {
+    type Error = E;
+
+    fn publish(&mut self, seq: u64, event: &OrderEvent) -> Result<(), E> {
+        self(seq, event)
+    }
+}
+
+/// Outcome of one `Outbox::deliver` run.
+#[derive(Debug, PartialEq)]
+pub struct Delivery<E> {
+    /// Events acknowledged in this run.
+    pub delivered: usize,
+    /// The event that failed, if any, with the publisher's error.
+    pub failed: Option<(u64, E)>,
+}
+
+/// Ordered table of events awaiting publication; see the module docs.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Outbox {
+    seqs: Vec<u64>,
+    events: Vec<OrderEvent>,
+    attempts: Vec<u32>,
+    /// Entries before this one are acknowledged.
+    acked: usize,
+    next_seq: u64,
+}
+
+impl Outbox {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue `event`; returns its sequence number.
+    pub fn record(&mut self, event: OrderEvent) -> u64 {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.seqs.push(seq);
+        self.events.push(event);
+        self.attempts.push(0);
+        seq
+    }
+
+    /// Entries held, acknowledged ones included until `purge_acked`.
+    pub fn len(&self) -> usize {
+        self.seqs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.seqs.is_empty()
+    }
+
+    pub fn pending_len(&self) -> usize {
+        self.seqs.len() - self.acked
+    }
+
+    /// Unacknowledged events with their sequence numbers, oldest first.
+    pub fn pending(&self) -> impl Iterator<Item = (u64, &OrderEvent)> {
+        self.seqs[self.acked..]
+            .iter()
+            .copied()
+            .zip(&self.events[self.acked..])
+    }
+
+    /// Delivery attempts so far for event `seq`, if it is still held.
+    pub fn attempts(&self, seq: u64) -> Option<u32> {
+        let i = self.seqs.binary_search(&seq).ok()?;
+        Some(self.attempts[i])
+    }
+
+    /// Publish pending events in order until one fails; see the module docs.
+    pub fn deliver<P: Publisher + ?Sized>(&mut self, publisher: &mut P) -> Delivery<P::Error> {
+        let start = self.acked;
+        while self.acked < self.seqs.len() {
+            let i = self.acked;
+            self.attempts[i] += 1;
+            if let Err(e) = publisher.publish(self.seqs[i], &self.events[i]) {
+                return Delivery {
+                    delivered: i - start,
+                    failed: Some((self.seqs[i], e)),
+                };
+            }
+            self.acked += 1;
+        }
+        Delivery {
+            delivered: self.acked - start,
+            failed: None,
+        }
+    }
+
+    /// Drop acknowledged entries; returns how many.
+    pub fn purge_acked(&mut self) -> usize {
+        let n = self.acked;
+        self.seqs.drain(..n);
+        self.events.drain(..n);
+        self.attempts.drain(..n);
+        self.acked = 0;
+        n
+    }
+}
+
+impl OrderStore {
+    /// Builder flag: record the events of aggregate commands in an `Outbox`.
+    pub fn with_outbox(mut self) -> Self {
+        self.outbox = Some(Outbox::new());
+        self
+    }
+
+    pub fn outbox(&self) -> Option<&Outbox> {
+        self.outbox.as_ref()
+    }
+
+    pub fn outbox_mut(&mut self) -> Option<&mut Outbox> {
+        self.outbox.as_mut()
+    }
+}
@@ -0,0 +1,103 @@
+//! Cursor pagination in storage order.
+//!
+//! `OrderStore::page(cursor, limit)` returns up to `limit` live orders as views plus a `Cursor`
+//! for the next page, so an API can walk millions of orders a page at a time. A cursor is the
+//! position after the last row served: appends and removals never move rows, so it stays exact
+//! across them, and appended orders show up on later pages. Compaction and sorting do move rows;
+//! a cursor taken before one resumes after its last order's new position (or, if that order is
+//! gone too, at its old position).
+
+use crate::{OrderId, OrderSoA, OrderStore, OrderView};
+use std::fmt;
+use std::str::FromStr;
+
+/// Where the next page starts. Round-trips through `Display` / `FromStr` as an opaque token.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Cursor {
+    next: usize,
+    generation: u32,
+    last: OrderId,
+}
+
+impl fmt::Display for Cursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:x}.{:x}.{:x}", self.next, self.generation, self.last.0)
+    }
+}
+
+/// The token was not produced by `Cursor`'s `Display`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct InvalidCursor;
+
+impl fmt::Display for InvalidCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid page cursor")
+    }
+}
+
+impl std::error::Error for InvalidCursor {}
+
+impl FromStr for Cursor {
+    type Err = InvalidCursor;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split('.');
+        let mut field = || parts.next().ok_or(InvalidCursor);
+        let next = usize::from_str_radix(field()?, 16).map_err(|_| InvalidCursor)?;
+        let generation = u32::from_str_radix(field()?, 16).map_err(|_| InvalidCursor)?;
+        let last = u64::from_str_radix(field()?, 16).map_err(|_| InvalidCursor)?;
+        if parts.next().is_some() {
+            return Err(InvalidCursor);
+        }
+        Ok(Cursor {
+            next,
+            generation,
+            last: OrderId(last),
+        })
+    }
+}
+
+impl OrderSoA {
+    /// Row a cursor resumes at; see the module docs.
+    fn resume_at(&self, c: Cursor) -> usize {
+        if c.generation == self.generation {
+            return c.next;
+        }
+        match self.id_index.get(&c.last) {
+            Some(&i) => i + 1,
+            None => c.next.min(self.len()),
+        }
+    }
+
+    /// Up to `limit` (at least one) live rows starting at `cursor` (the first page for `None`),
+    /// and the cursor for the next page if any rows remain.
+    pub fn page(
+        &self,
+        cursor: Option<Cursor>,
+        limit: usize,
+    ) -> (Vec<OrderView<'_>>, Option<Cursor>) {
+        let start = cursor.map_or(0, |c| self.resume_at(c));
+        let mut live = (start..self.len()).filter(|&i| self.is_live(i));
+        let rows: Vec<usize> = live.by_ref().take(limit.max(1)).collect();
+        let next = match rows.last() {
+            Some(&last) if live.next().is_some() => Some(Cursor {
+                next: last + 1,
+                generation: self.generation,
+                last: self.ids[last],
+            }),
+            _ => None,
+        };
+        (rows.into_iter().map(|i| self.view_at(i)).collect(), next)
+    }
+}
+
+impl OrderStore {
+    /// One page of orders in storage order; see `OrderSoA::page`.
+    pub fn page(
+        &self,
+        cursor: Option<Cursor>,
+        limit: usize,
+    ) -> (Vec<OrderView<'_>>, Option<Cursor>) {
+        self.inner.page(cursor, limit)
+    }
+}
@@ -0,0 +1,82 @@
+//! Data-parallel variants of the column kernels (feature `parallel`, backed by rayon).
+//!
+//! Each kernel splits the columns into chunks processed on the rayon pool; results are merged in
+//! row order, so indices come back exactly as the sequential kernels return them. Float sums may
+//! differ in the last bits because the reduction order changes.
+
+use crate::{Money, OrderSoA, OrderView, ShardedOrderStore, Status};
+use rayon::prelude::*;
+
+impl OrderSoA {
+    /// Parallel `sum_by_status`.
+    pub fn sum_by_status_par(&self, status: Status) -> Money {
+        let acc = (0..self.len())
+            .into_par_iter()
+            .filter(|&i| self.statuses[i] == status && self.is_live(i))
+            .map(|i| self.amounts[i])
+            .sum();
+        Money(acc)
+    }
+
+    /// Parallel `filter_indices`; indices are returned in ascending order.
+    pub fn filter_indices_par(&self, min_amount: Money, status: Status) -> Vec<usize> {
+        (0..self.len())
+            .into_par_iter()
+            .filter(|&i| {
+                self.amounts[i] >= min_amount.0 && self.statuses[i] == status && self.is_live(i)
+            })
+            .collect()
+    }
+
+    /// Parallel `retain`: the predicate is evaluated in parallel, compaction stays sequential.
+    /// Like `retain`, tombstoned rows are reclaimed too.
+    pub fn retain_par<F>(&mut self, f: F)
+    where
+        F: Fn(OrderView<'_>) -> bool + Sync,
+    {
+        let keep: Vec<bool> = (0..self.len())
+            .into_par_iter()
+            .map(|i| self.is_live(i) && f(self.view_at(i)))
+            .collect();
+        self.compact_where(|_, i| keep[i]);
+    }
+}
+
+impl ShardedOrderStore {
+    /// Sum a status across shards, one shard per task.
+    pub fn sum_by_status_par(&self, status: Status) -> Money {
+        self.shards
+            .par_iter()
+            .map(|s| {
+                s.read()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .sum_by_status(status)
+            })
+            .reduce(Money::zero, |a, b| a + b)
+    }
+
+    /// Matching rows as `(shard, row)` pairs, ordered by shard then row.
+    pub fn filter_indices_par(&self, min_amount: Money, status: Status) -> Vec<(usize, usize)> {
+        self.shards
+            .par_iter()
+            .enumerate()
+            .flat_map_iter(|(si, s)| {
+                let s = s.read().unwrap_or_else(|e| e.into_inner());
+                s.filter_indices(min_amount, status)
+                    .into_iter()
+                    .map(move |row| (si, row))
+            })
+            .collect()
+    }
+
+    /// Retain across shards, compacting each shard on its own task under that shard's write
+    /// lock, like `retain`.
+    pub fn retain_par<F>(&self, f: F)
+    where
+        F: Fn(OrderView<'_>) -> bool + Sync,
+    {
+        (0..self.shards.len())
+            .into_par_iter()
+            .for_each(|si| self.write_shard(si).retain(&f));
+    }
+}
@@ -0,0 +1,36 @@
+//! Parquet snapshots (feature `parquet`).
+//!
+//! A snapshot is the Arrow export of the store (`crate::arrow`) written with the Arrow schema
+//! embedded, so the column layout survives the round trip end-to-end: one Parquet column chunk
+//! per SoA column, status dictionary-encoded.
+
+use crate::{OrderSoA, OrderStore};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::ArrowWriter;
+use parquet::errors::ParquetError;
+use std::fs::File;
+use std::path::Path;
+
+impl OrderStore {
+    /// Write the live rows to a Parquet file at `path`, replacing it if it exists.
+    pub fn write_parquet(&self, path: impl AsRef<Path>) -> Result<(), ParquetError> {
+        let batch = self.to_record_batch()?;
+        let mut writer = ArrowWriter::try_new(File::create(path)?, batch.schema(), None)?;
+        writer.write(&batch)?;
+        writer.close()?;
+        Ok(())
+    }
+
+    /// Load a snapshot written by `write_parquet`.
+    pub fn read_parquet(path: impl AsRef<Path>) -> Result<OrderStore, ParquetError> {
+        let builder = ParquetRecordBatchReaderBuilder::try_new(File::open(path)?)?;
+        let rows = builder.metadata().file_metadata().num_rows().max(1) as usize;
+        // One batch spanning every row group, so each column lands in a single vector.
+        let mut reader = builder.with_batch_size(rows).build()?;
+        let soa = match reader.next() {
+            Some(batch) => OrderSoA::from_record_batch(&batch?)?,
+            None => OrderSoA::default(),
+        };
+        Ok(OrderStore::from(soa))
+    }
+}
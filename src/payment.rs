@@ -0,0 +1,122 @@
+//! Payments: a third table alongside orders and customers.
+//!
+//! Each payment references the order it settles through its `orders` column, the way orders
+//! reference customers. Several payments may settle one order (e.g. split tenders); `paid_for`
+//! sums them with one pass over two columns.
+
+use crate::{Money, OrderId};
+use std::collections::HashMap;
+use std::fmt;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct PaymentId(pub u64);
+
+/// Column store of payments.
+#[derive(Default, Clone)]
+pub struct PaymentSoA {
+    ids: Vec<PaymentId>,
+    orders: Vec<OrderId>,
+    amounts: Vec<f64>,
+    timestamps: Vec<u64>,
+    id_index: HashMap<PaymentId, usize>, // primary key -> latest row holding it
+}
+
+impl fmt::Debug for PaymentSoA {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PaymentSoA")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl PaymentSoA {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a payment; returns its row. A repeated id shadows the earlier row in lookups.
+    pub fn push(&mut self, id: PaymentId, order: OrderId, amount: Money, ts: u64) -> usize {
+        self.ids.push(id);
+        self.orders.push(order);
+        self.amounts.push(amount.0);
+        self.timestamps.push(ts);
+        let idx = self.ids.len() - 1;
+        self.id_index.insert(id, idx);
+        idx
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// O(1) lookup through the id index.
+    pub fn find_by_id(&self, id: PaymentId) -> Option<PaymentView<'_>> {
+        self.id_index.get(&id).map(|&idx| self.view_at(idx))
+    }
+
+    /// # Panics
+    /// If `idx` is out of bounds.
+    pub fn view_at(&self, idx: usize) -> PaymentView<'_> {
+        assert!(idx < self.len(), "payment row {idx} out of bounds");
+        PaymentView { soa: self, idx }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = PaymentView<'_>> {
+        (0..self.len()).map(|idx| PaymentView { soa: self, idx })
+    }
+
+    /// Total of the payments settling `order`.
+    pub fn paid_for(&self, order: OrderId) -> Money {
+        Money(
+            self.orders
+                .iter()
+                .zip(&self.amounts)
+                .filter(|(o, _)| **o == order)
+                .map(|(_, a)| a)
+                .sum(),
+        )
+    }
+}
+
+/// Zero-copy view of one payment row.
+#[derive(Copy, Clone)]
+pub struct PaymentView<'a> {
+    soa: &'a PaymentSoA,
+    idx: usize,
+}
+
+impl fmt::Debug for PaymentView<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PaymentView")
+            .field("id", &self.id())
+            .field("order", &self.order())
+            .field("amount", &self.amount())
+            .field("ts", &self.timestamp())
+            .finish()
+    }
+}
+
+impl PaymentView<'_> {
+    #[inline]
+    pub fn id(&self) -> PaymentId {
+        self.soa.ids[self.idx]
+    }
+    #[inline]
+    pub fn order(&self) -> OrderId {
+        self.soa.orders[self.idx]
+    }
+    #[inline]
+    pub fn amount(&self) -> Money {
+        Money(self.soa.amounts[self.idx])
+    }
+    #[inline]
+    pub fn timestamp(&self) -> u64 {
+        self.soa.timestamps[self.idx]
+    }
+}
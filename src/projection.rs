@@ -0,0 +1,164 @@
+//! Column projections: read views that borrow only the columns they name.
+//!
+//! `soa.project::<(cols::Amount, cols::Timestamp)>()` yields rows of `(Money, u64)`; code holding
+//! the projection has no path to the other columns, and the selection is checked at compile
+//! time.
+//!
+//! `Project` is the row-shaped counterpart for read models: a DTO that builds itself from an
+//! `OrderView`, filled for every matching order by `OrderStore::project_all` / `project_where`.
+
+use crate::{Bitmap, Money, OrderId, OrderRow, OrderSoA, OrderStore, OrderView, Specification};
+
+/// Column markers for `OrderSoA::project`.
+pub mod cols {
+    /// `OrderId` column.
+    pub struct Id;
+    /// Amount column, read as `Money`.
+    pub struct Amount;
+    /// `Status` column.
+    pub struct Status;
+    /// Timestamp column.
+    pub struct Timestamp;
+    /// `Currency` column.
+    pub struct Currency;
+    /// `CustomerId` column.
+    pub struct Customer;
+}
+
+/// A column, or tuple of columns, that can be projected out of an `OrderSoA`.
+pub trait Select<'a> {
+    /// The borrowed column slices.
+    type Cols: Copy;
+    /// One projected row.
+    type Row;
+    fn cols(soa: &'a OrderSoA) -> Self::Cols;
+    fn row(cols: &Self::Cols, i: usize) -> Self::Row;
+}
+
+macro_rules! select_column {
+    ($marker:ty, $field:ident, $elem:ty, $row:ty, $conv:expr) => {
+        impl<'a> Select<'a> for $marker {
+            type Cols = &'a [$elem];
+            type Row = $row;
+            #[inline]
+            fn cols(soa: &'a OrderSoA) -> Self::Cols {
+                &soa.$field
+            }
+            #[inline]
+            fn row(cols: &Self::Cols, i: usize) -> $row {
+                $conv(cols[i])
+            }
+        }
+    };
+}
+
+select_column!(cols::Id, ids, OrderId, OrderId, |v| v);
+select_column!(cols::Amount, amounts, f64, Money, Money);
+select_column!(cols::Status, statuses, crate::Status, crate::Status, |v| v);
+select_column!(cols::Timestamp, timestamps, u64, u64, |v| v);
+select_column!(
+    cols::Currency,
+    currencies,
+    crate::Currency,
+    crate::Currency,
+    |v| v
+);
+select_column!(
+    cols::Customer,
+    customers,
+    crate::CustomerId,
+    crate::CustomerId,
+    |v| v
+);
+
+macro_rules! select_tuple {
+    ($($s:ident . $n:tt),+) => {
+        impl<'a, $($s: Select<'a>),+> Select<'a> for ($($s,)+) {
+            type Cols = ($($s::Cols,)+);
+            type Row = ($($s::Row,)+);
+            #[inline]
+            fn cols(soa: &'a OrderSoA) -> Self::Cols {
+                ($($s::cols(soa),)+)
+            }
+            #[inline]
+            fn row(cols: &Self::Cols, i: usize) -> Self::Row {
+                ($($s::row(&cols.$n, i),)+)
+            }
+        }
+    };
+}
+
+select_tuple!(A.0);
+select_tuple!(A.0, B.1);
+select_tuple!(A.0, B.1, C.2);
+select_tuple!(A.0, B.1, C.2, D.3);
+select_tuple!(A.0, B.1, C.2, D.3, E.4);
+
+/// Read-only view of a subset of the columns; like the full kernel it hides tombstoned rows.
+pub struct Projection<'a, S: Select<'a>> {
+    cols: S::Cols,
+    dead: Option<&'a Bitmap>,
+    len: usize,
+}
+
+impl<'a, S: Select<'a>> Projection<'a, S> {
+    /// Physical row count, tombstones included (as `OrderSoA::len`).
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The selected column slices, for custom kernels. Tombstoned rows are included.
+    pub fn columns(&self) -> S::Cols {
+        self.cols
+    }
+
+    /// Row `i`, or `None` if it is out of range or deleted.
+    pub fn get(&self, i: usize) -> Option<S::Row> {
+        (i < self.len && self.dead.is_none_or(|d| !d.get(i))).then(|| S::row(&self.cols, i))
+    }
+
+    /// Live rows in order.
+    pub fn iter(&self) -> impl Iterator<Item = S::Row> + '_ {
+        (0..self.len).filter_map(|i| self.get(i))
+    }
+}
+
+impl OrderSoA {
+    /// Borrow only the columns in `S`, e.g. `project::<(cols::Amount, cols::Timestamp)>()`.
+    pub fn project<'a, S: Select<'a>>(&'a self) -> Projection<'a, S> {
+        Projection {
+            cols: S::cols(self),
+            dead: self.dead_mask(),
+            len: self.len(),
+        }
+    }
+}
+
+/// A read-model type built from one order, e.g. an API response body.
+pub trait Project {
+    fn project(view: OrderView<'_>) -> Self;
+}
+
+impl Project for OrderRow {
+    fn project(view: OrderView<'_>) -> Self {
+        OrderRow::from(view)
+    }
+}
+
+impl OrderStore {
+    /// Every live order as a `T`, in row order.
+    pub fn project_all<T: Project>(&self) -> Vec<T> {
+        let mut out = Vec::with_capacity(self.inner.live_len());
+        out.extend(self.inner.iter().map(T::project));
+        out
+    }
+
+    /// Orders satisfying `spec` as `T`s, in row order; see `find`.
+    pub fn project_where<T: Project, S: Specification>(&self, spec: S) -> Vec<T> {
+        self.inner.find(spec).map(T::project).collect()
+    }
+}
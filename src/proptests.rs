@@ -0,0 +1,42 @@
+//! Property tests: random operation sequences against a naive AoS model; see `fuzzing`.
+
+use crate::fuzzing::{apply, check, Model, Op};
+use crate::{OrderSoA, Status};
+use proptest::prelude::*;
+
+fn status() -> impl Strategy<Value = Status> {
+    prop::sample::select(Status::ALL.to_vec())
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        6 => (0..20u64, 0..100u32, status())
+            .prop_map(|(id, amount, status)| Op::Push { id, amount, status }),
+        2 => any::<prop::sample::Index>().prop_map(|i| Op::Remove(i.index(usize::MAX))),
+        2 => (any::<prop::sample::Index>(), status())
+            .prop_map(|(i, s)| Op::SetStatus(i.index(usize::MAX), s)),
+        1 => status().prop_map(Op::Retain),
+        1 => Just(Op::Compact),
+        1 => (any::<prop::sample::Index>(), 0..100u32)
+            .prop_map(|(i, a)| Op::SetAmount(i.index(usize::MAX), a)),
+        1 => Just(Op::SortByAmount),
+        1 => Just(Op::SortByTimestamp),
+    ]
+}
+
+proptest! {
+    #[test]
+    fn columns_stay_aligned_with_model(ops in prop::collection::vec(op(), 1..80), indexed: bool) {
+        let mut soa = if indexed {
+            OrderSoA::default().with_status_index()
+        } else {
+            OrderSoA::default()
+        };
+        let mut model = Model::new();
+        let mut ts = 0;
+        for op in ops {
+            apply(&mut soa, &mut model, &mut ts, op);
+            check(&soa, &model);
+        }
+    }
+}
@@ -0,0 +1,133 @@
+//! Python bindings (feature `python`), built on PyO3 and the `numpy` crate.
+//!
+//! The extension module `ddd_dod_soa` exposes an `OrderStore` class. `amounts()` and
+//! `timestamps()` return read-only NumPy arrays that point straight into the store's columns:
+//! each array keeps the current `Arc<OrderSoA>` alive, and copy-on-write in `OrderStore` means
+//! later writes clone the kernel instead of moving memory under the array. An array is thus a
+//! zero-copy snapshot, ready for NumPy or `pandas.DataFrame`. Tombstoned rows stay in the
+//! columns until `compact()`; `live_mask()` selects the live ones.
+//!
+//! Build the module with maturin (`maturin develop --features python,pyo3/extension-module`),
+//! or with `cargo rustc --release --features python --crate-type cdylib` and rename the library
+//! to `ddd_dod_soa.so`. Statuses cross the boundary by name (`"Pending"`, ...).
+
+use crate::{Money, OrderId, OrderSoA, OrderStore, Status, StoreError};
+use numpy::ndarray::ArrayView1;
+use numpy::PyArray1;
+use pyo3::exceptions::{PyKeyError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::sync::Arc;
+
+fn status(name: &str) -> PyResult<Status> {
+    Status::from_name(name).ok_or_else(|| PyValueError::new_err(format!("unknown status {name:?}")))
+}
+
+fn store_err(e: StoreError) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
+
+/// Python-side `OrderStore`.
+#[pyclass(name = "OrderStore", module = "ddd_dod_soa")]
+#[derive(Default)]
+pub struct PyOrderStore {
+    store: OrderStore,
+}
+
+/// Owner of the kernel snapshot a NumPy array borrows from.
+#[pyclass(frozen)]
+struct Columns {
+    soa: Arc<OrderSoA>,
+}
+
+/// A read-only array over `col`, kept alive by `owner`.
+fn borrowed<'py, T: numpy::Element>(
+    owner: Bound<'py, Columns>,
+    col: impl FnOnce(&OrderSoA) -> &[T],
+) -> PyResult<Bound<'py, PyArray1<T>>> {
+    let view = ArrayView1::from(col(&owner.get().soa));
+    // SAFETY: `owner` holds an `Arc<OrderSoA>` whose columns are never written while shared
+    // (`OrderStore` writes copy-on-write), so the memory is valid and unchanged for as long as
+    // the array keeps `owner` alive.
+    let array = unsafe { PyArray1::borrow_from_array(&view, owner.clone().into_any()) };
+    let kwargs = PyDict::new(array.py());
+    kwargs.set_item("write", false)?;
+    array.call_method("setflags", (), Some(&kwargs))?;
+    Ok(array)
+}
+
+#[pymethods]
+impl PyOrderStore {
+    #[new]
+    fn new() -> Self {
+        Self::default()
+    }
+
+    /// Live order count.
+    fn __len__(&self) -> usize {
+        self.store.kernel().live_len()
+    }
+
+    /// Append an order; raises `ValueError` if the store rejects it.
+    fn add(&mut self, id: u64, amount: f64, status_name: &str, ts: u64) -> PyResult<()> {
+        let st = status(status_name)?;
+        self.store
+            .add(OrderId(id), Money(amount), st, ts)
+            .map(drop)
+            .map_err(store_err)
+    }
+
+    /// Soft-delete the order with `id`; raises `KeyError` if there is none.
+    fn remove(&mut self, id: u64) -> PyResult<()> {
+        let kernel = self.store.kernel();
+        let Some(h) = kernel
+            .id_index
+            .get(&OrderId(id))
+            .map(|&i| kernel.handle_at(i))
+        else {
+            return Err(PyKeyError::new_err(id));
+        };
+        self.store.remove(h).map_err(store_err)
+    }
+
+    /// Reclaim soft-deleted rows, so every column entry is a live order.
+    fn compact(&mut self) {
+        self.store.compact();
+    }
+
+    fn sum_by_status(&self, status_name: &str) -> PyResult<f64> {
+        Ok(self.store.kernel().sum_by_status(status(status_name)?).0)
+    }
+
+    /// The amount column as a read-only `float64` array, without copying.
+    fn amounts<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyArray1<f64>>> {
+        borrowed(self.columns(py)?, |s| &s.amounts[..])
+    }
+
+    /// The timestamp column as a read-only `uint64` array, without copying.
+    fn timestamps<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyArray1<u64>>> {
+        borrowed(self.columns(py)?, |s| &s.timestamps[..])
+    }
+
+    /// Which column entries are live orders, as a `bool` array (a copy).
+    fn live_mask<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<bool>> {
+        let soa = self.store.kernel();
+        PyArray1::from_iter(py, (0..soa.len()).map(|i| soa.is_live(i)))
+    }
+}
+
+impl PyOrderStore {
+    fn columns<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, Columns>> {
+        Bound::new(
+            py,
+            Columns {
+                soa: Arc::clone(&self.store.inner),
+            },
+        )
+    }
+}
+
+#[pymodule]
+fn ddd_dod_soa(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyOrderStore>()
+}
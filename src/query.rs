@@ -0,0 +1,139 @@
+//! Composable column queries.
+//!
+//! `Query` only records predicates; the terminal methods (`indices`, `collect_views`, `count`,
+//! `sum`) evaluate all of them in a single fused pass over the columns, touching only the
+//! columns that are actually constrained. `select` instead evaluates them column at a time into
+//! a selection bitmap, deferring materialization to the returned `Selection`.
+
+use crate::summation::Neumaier;
+use crate::{Currency, Money, OrderSoA, OrderStore, OrderView, Selection, Status};
+
+/// Predicates over one `OrderSoA`, combined with AND.
+#[derive(Copy, Clone)]
+#[must_use = "a query does nothing until a terminal method runs it"]
+pub struct Query<'a> {
+    soa: &'a OrderSoA,
+    status: Option<Status>,
+    currency: Option<Currency>,
+    min_amount: Option<f64>,
+    max_amount: Option<f64>,
+    ts_range: Option<(u64, u64)>,
+}
+
+impl<'a> Query<'a> {
+    pub fn status(mut self, status: Status) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    pub fn currency(mut self, currency: Currency) -> Self {
+        self.currency = Some(currency);
+        self
+    }
+
+    /// `amount >= min`.
+    pub fn amount_gte(mut self, min: Money) -> Self {
+        self.min_amount = Some(min.0);
+        self
+    }
+
+    /// `amount < max`.
+    pub fn amount_lt(mut self, max: Money) -> Self {
+        self.max_amount = Some(max.0);
+        self
+    }
+
+    /// `from <= timestamp < to`, like `filter_by_time_range`.
+    pub fn ts_between(mut self, from: u64, to: u64) -> Self {
+        self.ts_range = Some((from, to));
+        self
+    }
+
+    /// Run the scan, calling `f` with each matching row in ascending order.
+    fn scan(&self, mut f: impl FnMut(usize)) {
+        let soa = self.soa;
+        for i in 0..soa.len() {
+            // Non-short-circuiting `&` keeps the loop body branch-light; unconstrained columns
+            // are never read.
+            let hit = self.status.is_none_or(|s| soa.statuses[i] == s)
+                & self.currency.is_none_or(|c| soa.currencies[i] == c)
+                & self.min_amount.is_none_or(|m| soa.amounts[i] >= m)
+                & self.max_amount.is_none_or(|m| soa.amounts[i] < m)
+                & self
+                    .ts_range
+                    .is_none_or(|(from, to)| (from..to).contains(&soa.timestamps[i]));
+            if hit && soa.is_live(i) {
+                f(i);
+            }
+        }
+    }
+
+    /// Matching row indices, ascending.
+    pub fn indices(&self) -> Vec<usize> {
+        let mut out = Vec::new();
+        self.scan(|i| out.push(i));
+        out
+    }
+
+    /// Zero-copy views of the matching rows.
+    pub fn collect_views(&self) -> Vec<OrderView<'a>> {
+        let mut out = Vec::new();
+        self.scan(|i| out.push(self.soa.view_at(i)));
+        out
+    }
+
+    pub fn count(&self) -> usize {
+        let mut n = 0;
+        self.scan(|_| n += 1);
+        n
+    }
+
+    /// Total amount of the matching rows.
+    pub fn sum(&self) -> Money {
+        let mut acc = Neumaier::default();
+        self.scan(|i| acc = acc.add(self.soa.amounts[i]));
+        Money(acc.total())
+    }
+
+    /// Evaluate each predicate over its column into a bitmap and AND them; see `Selection`.
+    pub fn select(&self) -> Selection<'a> {
+        let soa = self.soa;
+        let preds = [
+            self.status.map(|s| soa.eq_status(s)),
+            self.currency.map(|c| soa.eq_currency(c)),
+            self.min_amount.map(|m| soa.gte_amount(Money(m))),
+            self.max_amount.map(|m| soa.lt_amount(Money(m))),
+            self.ts_range.map(|(from, to)| soa.ts_between(from, to)),
+        ];
+        let bits = preds
+            .into_iter()
+            .flatten()
+            .reduce(|mut acc, b| {
+                acc &= &b;
+                acc
+            })
+            .unwrap_or_else(|| soa.live_selection());
+        Selection::new(soa, bits)
+    }
+}
+
+impl OrderSoA {
+    /// Start a query matching every live row.
+    pub fn query(&self) -> Query<'_> {
+        Query {
+            soa: self,
+            status: None,
+            currency: None,
+            min_amount: None,
+            max_amount: None,
+            ts_range: None,
+        }
+    }
+}
+
+impl OrderStore {
+    /// Start a query over the store; see `Query`.
+    pub fn query(&self) -> Query<'_> {
+        self.inner.query()
+    }
+}
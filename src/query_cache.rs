@@ -0,0 +1,175 @@
+//! Memoized `find` results for repeated identical queries, such as a dashboard polling the
+//! same specification.
+//!
+//! With `with_query_cache(capacity)`, `find_cached(spec)` remembers the matching row positions
+//! of up to `capacity` distinct specifications, evicting the least recently used. Entries are
+//! keyed by the specification's value plus the store's `epoch`, which every write through the
+//! store (including `kernel_mut`) bumps, so a mutation invalidates the whole cache and the next
+//! query rescans. Only specifications that are `Hash + PartialEq` can be cached; that covers the
+//! built-in leaf specs and their `And` / `Or` / `Not` combinations, but not closures.
+
+use crate::{Context, OrderSoA, OrderStore, OrderView, Specification};
+use std::any::{Any, TypeId};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+
+/// Cache counters since the cache was enabled, for tuning `capacity`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct QueryCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    /// Entries currently cached.
+    pub len: usize,
+}
+
+struct Entry {
+    /// The specification, to tell hash collisions from hits.
+    spec: Box<dyn Any + Send + Sync>,
+    rows: Arc<[usize]>,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct State {
+    /// Store epoch the entries were computed at.
+    epoch: u64,
+    tick: u64,
+    entries: HashMap<u64, Entry>,
+    hits: u64,
+    misses: u64,
+}
+
+/// LRU of `find` results; see the module docs.
+pub(crate) struct QueryCache {
+    capacity: usize,
+    state: Mutex<State>,
+}
+
+/// A clone starts empty: entries are cheap to recompute and may not outlive the original's
+/// epoch.
+impl Clone for QueryCache {
+    fn clone(&self) -> Self {
+        QueryCache::new(self.capacity)
+    }
+}
+
+impl QueryCache {
+    fn new(capacity: usize) -> Self {
+        QueryCache {
+            capacity: capacity.max(1),
+            state: Mutex::default(),
+        }
+    }
+
+    /// Row positions matching `spec` at `epoch`, computing them with `scan` on a miss.
+    fn rows<S>(&self, spec: &S, epoch: u64, scan: impl FnOnce() -> Vec<usize>) -> Arc<[usize]>
+    where
+        S: Hash + PartialEq + Clone + Send + Sync + 'static,
+    {
+        let mut hasher = DefaultHasher::new();
+        (TypeId::of::<S>(), spec).hash(&mut hasher);
+        let key = hasher.finish();
+
+        let mut st = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if st.epoch != epoch {
+            st.entries.clear();
+            st.epoch = epoch;
+        }
+        st.tick += 1;
+        let tick = st.tick;
+        if let Some(e) = st.entries.get_mut(&key) {
+            if e.spec.downcast_ref::<S>() == Some(spec) {
+                e.last_used = tick;
+                let rows = Arc::clone(&e.rows);
+                st.hits += 1;
+                return rows;
+            }
+        }
+        st.misses += 1;
+        let rows: Arc<[usize]> = scan().into();
+        if st.entries.len() >= self.capacity && !st.entries.contains_key(&key) {
+            let lru = st
+                .entries
+                .iter()
+                .min_by_key(|(_, e)| e.last_used)
+                .map(|(&k, _)| k);
+            if let Some(k) = lru {
+                st.entries.remove(&k);
+            }
+        }
+        st.entries.insert(
+            key,
+            Entry {
+                spec: Box::new(spec.clone()),
+                rows: Arc::clone(&rows),
+                last_used: tick,
+            },
+        );
+        rows
+    }
+
+    fn stats(&self) -> QueryCacheStats {
+        let st = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        QueryCacheStats {
+            hits: st.hits,
+            misses: st.misses,
+            len: st.entries.len(),
+        }
+    }
+}
+
+/// Copy-on-write access to a store's kernel for a write: bumps `epoch` so cached queries miss,
+/// and points the audit stamps, if any, at the store's `ctx`. A free function over the fields,
+/// so callers keep borrowing the store's other fields.
+#[inline]
+pub(crate) fn write<'a>(
+    inner: &'a mut Arc<OrderSoA>,
+    epoch: &mut u64,
+    ctx: Context,
+) -> &'a mut OrderSoA {
+    *epoch += 1;
+    let soa = Arc::make_mut(inner);
+    if let Some(a) = &mut soa.audit {
+        a.actor = ctx.actor;
+    }
+    soa
+}
+
+impl OrderStore {
+    /// Builder flag: remember the results of up to `capacity` distinct `find_cached` queries.
+    pub fn with_query_cache(mut self, capacity: usize) -> Self {
+        self.queries = Some(QueryCache::new(capacity));
+        self
+    }
+
+    /// Write counter: changes whenever the store's data may have, e.g. for use as an ETag.
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// `find`, answered from the query cache when `spec` was seen since the last write. Without
+    /// `with_query_cache` this is `find`.
+    pub fn find_cached<S>(&self, spec: S) -> impl Iterator<Item = OrderView<'_>>
+    where
+        S: Specification + Hash + PartialEq + Clone + Send + Sync + 'static,
+    {
+        let soa = &*self.inner;
+        let scan = || -> Vec<usize> {
+            (0..soa.len())
+                .filter(|&i| soa.is_live(i) && spec.matches_at(soa, i))
+                .collect()
+        };
+        let rows: Arc<[usize]> = match &self.queries {
+            Some(cache) => cache.rows(&spec, self.epoch, scan),
+            None => scan().into(),
+        };
+        (0..rows.len()).map(move |k| soa.view_at(rows[k]))
+    }
+
+    /// Hit and miss counts; `None` without `with_query_cache`.
+    pub fn query_cache_stats(&self) -> Option<QueryCacheStats> {
+        self.queries.as_ref().map(QueryCache::stats)
+    }
+}
@@ -0,0 +1,293 @@
+//! Read replicas: a leader store shipping its changes to followers.
+//!
+//! A `Leader` owns the writable store and derefs to it. `publish` diffs the store against the
+//! state it last shipped and sends the resulting `StoreDelta` to every follower, numbered by a
+//! sequence that grows by one per delta; writes in between are batched into a single delta. A
+//! follower that joins late is caught up with a full snapshot of the shipped state first, so
+//! deltas need only be kept for as long as it takes to send them. Shipping snapshots is cheap
+//! because `read_only` shares the kernel rather than copying it. Snapshots and deltas alike carry
+//! one row per id, the one `find_by_id` returns, so a leader keeping duplicate ids replicates as
+//! if it had upserted them.
+//!
+//! `Leader::follower` connects an in-process `Follower` over a channel; `sync` applies whatever
+//! has arrived. For other transports, `subscribe` hands out the raw stream of
+//! `ReplicationMsg`s (serde-serializable with feature `serde`), and `Follower::apply` consumes
+//! them on the far side. A follower refuses a delta that is not the next in sequence, since
+//! applying it would silently diverge; resynchronize it from `Leader::snapshot_msg`.
+
+use crate::{OrderRow, OrderStore, OrderStoreReadOnly, StoreDelta, StoreError};
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+use std::sync::Arc;
+
+/// What a leader sends its followers.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ReplicationMsg {
+    /// The leader's live orders as of delta `seq`; replaces the follower's state.
+    Snapshot { seq: u64, rows: Vec<OrderRow> },
+    /// The changes from delta `seq - 1` to delta `seq`.
+    Delta { seq: u64, delta: StoreDelta },
+}
+
+impl ReplicationMsg {
+    pub fn seq(&self) -> u64 {
+        match self {
+            ReplicationMsg::Snapshot { seq, .. } | ReplicationMsg::Delta { seq, .. } => *seq,
+        }
+    }
+}
+
+/// Failures applying replication messages on a follower.
+#[derive(Debug, PartialEq)]
+pub enum ReplicationError {
+    /// The follower is at delta `expected - 1` and received delta `found`; it needs a snapshot.
+    Gap { expected: u64, found: u64 },
+    /// The follower's store refused the delta; it has diverged and needs a snapshot.
+    Store(StoreError),
+    /// The leader is gone; the follower keeps serving what it has.
+    Disconnected,
+}
+
+impl fmt::Display for ReplicationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplicationError::Gap { expected, found } => {
+                write!(f, "expected delta {expected}, received {found}")
+            }
+            ReplicationError::Store(e) => write!(f, "{e}"),
+            ReplicationError::Disconnected => write!(f, "leader disconnected"),
+        }
+    }
+}
+
+impl std::error::Error for ReplicationError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ReplicationError::Store(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<StoreError> for ReplicationError {
+    fn from(e: StoreError) -> Self {
+        ReplicationError::Store(e)
+    }
+}
+
+/// The writable side of a replicated store; see the module docs.
+pub struct Leader {
+    store: OrderStore,
+    /// State as of delta `seq`.
+    shipped: OrderStoreReadOnly,
+    seq: u64,
+    /// `seq`, shared with in-process followers for their lag.
+    head: Arc<AtomicU64>,
+    subscribers: Vec<Sender<ReplicationMsg>>,
+}
+
+impl fmt::Debug for Leader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Leader")
+            .field("seq", &self.seq)
+            .field("subscribers", &self.subscribers.len())
+            .finish()
+    }
+}
+
+impl Leader {
+    /// Replicate `store`, whose current state is sequence 0.
+    pub fn new(store: OrderStore) -> Self {
+        Leader {
+            shipped: store.read_only(),
+            store,
+            seq: 0,
+            head: Arc::new(AtomicU64::new(0)),
+            subscribers: Vec::new(),
+        }
+    }
+
+    /// The last delta published.
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    /// Subscribers still connected, as of the last send.
+    pub fn subscribers(&self) -> usize {
+        self.subscribers.len()
+    }
+
+    /// Ship the writes since the last publish as one delta, returning the sequence followers
+    /// will be at once they apply it. Nothing is sent if the store is unchanged.
+    pub fn publish(&mut self) -> u64 {
+        let delta = self.store.diff(&self.shipped);
+        if !delta.is_empty() {
+            self.seq += 1;
+            self.shipped = self.store.read_only();
+            self.head.store(self.seq, Ordering::Release);
+            let msg = ReplicationMsg::Delta {
+                seq: self.seq,
+                delta,
+            };
+            self.subscribers.retain(|tx| tx.send(msg.clone()).is_ok());
+        }
+        self.seq
+    }
+
+    /// A full copy of the state as of `seq`, for bringing a new or diverged follower up to date.
+    /// Like deltas, it holds one row per id, the one `find_by_id` returns; older duplicates stay
+    /// on the leader.
+    pub fn snapshot_msg(&self) -> ReplicationMsg {
+        let shipped = &self.shipped;
+        let rows = (0..shipped.len())
+            .filter(|&i| shipped.id_index.get(&shipped.ids[i]) == Some(&i))
+            .map(|i| OrderRow::from(shipped.view_at(i)))
+            .collect();
+        ReplicationMsg::Snapshot {
+            seq: self.seq,
+            rows,
+        }
+    }
+
+    /// Publish pending writes, then open a stream starting with a snapshot, followed by every
+    /// later delta. The stream ends when the leader drops.
+    pub fn subscribe(&mut self) -> Receiver<ReplicationMsg> {
+        self.publish();
+        let (tx, rx) = channel();
+        tx.send(self.snapshot_msg()).expect("receiver held above");
+        self.subscribers.push(tx);
+        rx
+    }
+
+    /// An in-process read replica fed by `subscribe`.
+    pub fn follower(&mut self) -> Follower {
+        let rx = self.subscribe();
+        let mut f = Follower::new(OrderStore::new());
+        f.source = Some((rx, Arc::clone(&self.head)));
+        f
+    }
+
+    pub fn into_inner(self) -> OrderStore {
+        self.store
+    }
+}
+
+impl Deref for Leader {
+    type Target = OrderStore;
+
+    fn deref(&self) -> &OrderStore {
+        &self.store
+    }
+}
+
+impl DerefMut for Leader {
+    fn deref_mut(&mut self) -> &mut OrderStore {
+        &mut self.store
+    }
+}
+
+/// A read replica; derefs to its store for queries. See the module docs.
+pub struct Follower {
+    store: OrderStore,
+    /// The delta the store reflects.
+    seq: u64,
+    /// The leader's channel and published sequence, when in-process.
+    source: Option<(Receiver<ReplicationMsg>, Arc<AtomicU64>)>,
+}
+
+impl fmt::Debug for Follower {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Follower")
+            .field("seq", &self.seq)
+            .field("lag", &self.lag())
+            .finish()
+    }
+}
+
+impl Follower {
+    /// A follower fed by hand through `apply`, replicating into `store`. Its configuration
+    /// (indexes, views, validators) is kept; its contents are replaced by the first snapshot.
+    /// Snapshots and deltas are trusted as the leader's state: only their ids and sequence are
+    /// checked, and validators guard local writes alone.
+    pub fn new(store: OrderStore) -> Self {
+        Follower {
+            store,
+            seq: 0,
+            source: None,
+        }
+    }
+
+    /// The delta the follower reflects.
+    pub fn seq(&self) -> u64 {
+        self.seq
+    }
+
+    /// Deltas the leader has published that this follower has not applied; 0 for followers fed
+    /// by hand, which cannot see the leader.
+    pub fn lag(&self) -> u64 {
+        self.source.as_ref().map_or(0, |(_, head)| {
+            head.load(Ordering::Acquire).saturating_sub(self.seq)
+        })
+    }
+
+    /// Apply one message from the leader. On error the store is unchanged.
+    pub fn apply(&mut self, msg: ReplicationMsg) -> Result<(), ReplicationError> {
+        match msg {
+            ReplicationMsg::Snapshot { seq, rows } => {
+                let mut soa = self.store.kernel().empty_like(rows.len());
+                for row in rows {
+                    soa.push_row(row);
+                }
+                *self.store.kernel_mut() = soa;
+                self.store.refresh_views();
+                self.seq = seq;
+            }
+            ReplicationMsg::Delta { seq, delta } => {
+                if seq != self.seq + 1 {
+                    return Err(ReplicationError::Gap {
+                        expected: self.seq + 1,
+                        found: seq,
+                    });
+                }
+                self.store.replay_delta(&delta)?;
+                self.seq = seq;
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply every message received from an in-process leader, returning how many. `Disconnected`
+    /// once the leader is gone and everything it sent is applied.
+    pub fn sync(&mut self) -> Result<usize, ReplicationError> {
+        let mut applied = 0;
+        loop {
+            let received = match &self.source {
+                Some((rx, _)) => rx.try_recv(),
+                None => return Ok(applied),
+            };
+            match received {
+                Ok(msg) => {
+                    self.apply(msg)?;
+                    applied += 1;
+                }
+                Err(TryRecvError::Empty) => return Ok(applied),
+                Err(TryRecvError::Disconnected) => return Err(ReplicationError::Disconnected),
+            }
+        }
+    }
+
+    pub fn into_inner(self) -> OrderStore {
+        self.store
+    }
+}
+
+impl Deref for Follower {
+    type Target = OrderStore;
+
+    fn deref(&self) -> &OrderStore {
+        &self.store
+    }
+}
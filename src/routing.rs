@@ -0,0 +1,124 @@
+//! Shard routing and rebalancing for `ShardedOrderStore`.
+//!
+//! A `ShardRouter` maps an order id to a shard. The default `ModuloRouter` (`id % n`) is cheap
+//! but piles dense or strided ids onto a few shards; `HashRouter` scatters them, and
+//! `RangeRouter` keeps id ranges together for range-partitioned workloads. Closures
+//! `Fn(OrderId, usize) -> usize` work as custom routers.
+//!
+//! `rebalance(n)` redistributes every live row over `n` fresh shards under the current router,
+//! and `set_router` swaps the router and re-routes in place, so a store can grow with its
+//! workload. Both take `&mut self`: no reader sees a half-moved store.
+
+use crate::{OrderId, OrderSoA, ShardedOrderStore};
+use crossbeam_utils::CachePadded;
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+/// Chooses the shard owning an id. `route` must be deterministic and return a value below
+/// `shards`; larger values wrap.
+pub trait ShardRouter: Send + Sync {
+    fn route(&self, id: OrderId, shards: usize) -> usize;
+}
+
+impl fmt::Debug for dyn ShardRouter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("dyn ShardRouter")
+    }
+}
+
+impl<F: Fn(OrderId, usize) -> usize + Send + Sync> ShardRouter for F {
+    fn route(&self, id: OrderId, shards: usize) -> usize {
+        self(id, shards)
+    }
+}
+
+/// `id % shards`.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ModuloRouter;
+
+impl ShardRouter for ModuloRouter {
+    #[inline]
+    fn route(&self, id: OrderId, shards: usize) -> usize {
+        (id.0 % shards as u64) as usize
+    }
+}
+
+/// Multiplicative hash of the id (Fibonacci hashing), then reduced to the shard count.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct HashRouter;
+
+impl ShardRouter for HashRouter {
+    #[inline]
+    fn route(&self, id: OrderId, shards: usize) -> usize {
+        let h = id.0.wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        ((h as u128 * shards as u128) >> 64) as usize
+    }
+}
+
+/// Contiguous id ranges: ids below `bounds[0]` go to shard 0, below `bounds[1]` to shard 1,
+/// and so on; the rest go to the last shard.
+#[derive(Clone, Debug, Default)]
+pub struct RangeRouter {
+    bounds: Vec<u64>,
+}
+
+impl RangeRouter {
+    /// `bounds` are exclusive upper ids per shard, ascending.
+    pub fn new(mut bounds: Vec<u64>) -> Self {
+        bounds.sort_unstable();
+        Self { bounds }
+    }
+}
+
+impl ShardRouter for RangeRouter {
+    #[inline]
+    fn route(&self, id: OrderId, shards: usize) -> usize {
+        self.bounds.partition_point(|&b| b <= id.0).min(shards - 1)
+    }
+}
+
+impl ShardedOrderStore {
+    /// Move every live row onto `n` new shards (at least one) placed by the current router.
+    /// Tombstones are dropped, handles go stale and shard write counters restart.
+    pub fn rebalance(&mut self, n: usize) {
+        let n = n.max(1);
+        let old: Vec<OrderSoA> = std::mem::take(&mut self.shards)
+            .into_iter()
+            .map(|s| {
+                CachePadded::into_inner(s)
+                    .into_inner()
+                    .unwrap_or_else(|e| e.into_inner())
+            })
+            .collect();
+        let template = old.first().map(|s| s.empty_like(0)).unwrap_or_default();
+        let per_shard = old
+            .iter()
+            .map(OrderSoA::live_len)
+            .sum::<usize>()
+            .div_ceil(n);
+        let mut shards: Vec<OrderSoA> = (0..n)
+            .map(|_| {
+                let mut s = template.empty_like(0);
+                s.reserve(per_shard);
+                s
+            })
+            .collect();
+        for soa in &old {
+            for i in (0..soa.len()).filter(|&i| soa.is_live(i)) {
+                let si = self.router.route(soa.ids[i], n) % n;
+                shards[si].push_copied(soa, i);
+            }
+        }
+        self.shards = shards
+            .into_iter()
+            .map(|s| CachePadded::new(RwLock::new(s)))
+            .collect();
+        self.counters = (0..n).map(|_| Default::default()).collect();
+    }
+
+    /// Route by `router` from now on, moving existing rows to where it places them.
+    pub fn set_router(&mut self, router: impl ShardRouter + 'static) {
+        self.router = Arc::new(router);
+        self.rebalance(self.shards.len());
+    }
+}
@@ -0,0 +1,196 @@
+//! Order fulfillment as a process manager (saga).
+//!
+//! Fulfilling an order spans three external systems: inventory is reserved, payment is charged,
+//! then the order ships. None of them shares a transaction with the store, so `FulfillmentSaga`
+//! drives the steps one at a time from the `EventLog` and records how far each order got in its
+//! own SoA table (the order as created, state, step attempts). A `Created` event starts a saga;
+//! a step may finish, report that it is still waiting (the saga stays put and is retried on the
+//! next `run`), or fail. A failure compensates the completed steps in reverse (refund, then
+//! release the reservation) and cancels the order; a `Cancelled` event for an order still in
+//! flight does the same compensation. A shipped order is completed. Outcomes are appended to the
+//! log as events and applied to the store, so the log stays the single record of what happened
+//! and the store remains its projection.
+
+use crate::{EventLog, OrderEvent, OrderId, OrderRow, OrderStore, Status, StoreError};
+use std::collections::HashMap;
+
+/// What a fulfillment step reported.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StepOutcome {
+    Done,
+    /// Not finished yet (e.g. awaiting a payment provider); retry on the next `run`.
+    Pending,
+    Failed,
+}
+
+/// The external systems a fulfillment touches. Compensations cannot fail; they are expected to
+/// be retried internally until they succeed.
+pub trait FulfillmentSteps {
+    fn reserve_inventory(&mut self, order: &OrderRow) -> StepOutcome;
+    fn release_inventory(&mut self, order: &OrderRow);
+    fn charge_payment(&mut self, order: &OrderRow) -> StepOutcome;
+    fn refund_payment(&mut self, order: &OrderRow);
+    fn ship(&mut self, order: &OrderRow) -> StepOutcome;
+}
+
+/// How far one order's fulfillment got.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SagaState {
+    Started,
+    InventoryReserved,
+    PaymentCharged,
+    /// Shipped and completed.
+    Completed,
+    /// Failed or cancelled; every completed step has been undone.
+    Compensated,
+}
+
+impl SagaState {
+    /// Whether the saga still has steps to run.
+    pub fn is_active(self) -> bool {
+        self < SagaState::Completed
+    }
+}
+
+/// Fulfillment process manager; see the module docs.
+#[derive(Clone, Debug, Default)]
+pub struct FulfillmentSaga {
+    /// The order as created; what the steps are run for.
+    orders: Vec<OrderRow>,
+    states: Vec<SagaState>,
+    /// Step invocations so far, pending ones included.
+    attempts: Vec<u32>,
+    by_order: HashMap<OrderId, usize>,
+    /// Next log position to read.
+    cursor: usize,
+}
+
+impl FulfillmentSaga {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sagas started so far, finished ones included.
+    pub fn len(&self) -> usize {
+        self.orders.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.orders.is_empty()
+    }
+
+    pub fn state(&self, id: OrderId) -> Option<SagaState> {
+        self.by_order.get(&id).map(|&i| self.states[i])
+    }
+
+    /// Step invocations made for order `id`'s saga.
+    pub fn attempts(&self, id: OrderId) -> Option<u32> {
+        self.by_order.get(&id).map(|&i| self.attempts[i])
+    }
+
+    /// Sagas with steps left to run.
+    pub fn active_len(&self) -> usize {
+        self.states.iter().filter(|s| s.is_active()).count()
+    }
+
+    /// Read the events appended since the last run, then advance every active saga as far as its
+    /// steps allow. Events the saga emits are appended to `log` and applied to `store`.
+    pub fn run<S: FulfillmentSteps + ?Sized>(
+        &mut self,
+        log: &mut EventLog,
+        store: &mut OrderStore,
+        steps: &mut S,
+    ) -> Result<(), StoreError> {
+        while let Some(&event) = log.events().get(self.cursor) {
+            self.cursor += 1;
+            match event {
+                OrderEvent::Created(row) if !self.by_order.contains_key(&row.id) => {
+                    self.by_order.insert(row.id, self.orders.len());
+                    self.orders.push(row);
+                    self.states.push(SagaState::Started);
+                    self.attempts.push(0);
+                }
+                OrderEvent::Cancelled { id } => {
+                    if let Some(&i) = self.by_order.get(&id) {
+                        if self.states[i].is_active() {
+                            self.compensate(i, steps);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        for i in 0..self.orders.len() {
+            if self.states[i].is_active() {
+                self.advance(i, log, store, steps)?;
+            }
+        }
+        // Skip over the events just emitted; they only confirm states recorded above.
+        self.cursor = log.len();
+        Ok(())
+    }
+
+    /// Run saga `i`'s remaining steps until one is pending, fails or the order ships.
+    fn advance<S: FulfillmentSteps + ?Sized>(
+        &mut self,
+        i: usize,
+        log: &mut EventLog,
+        store: &mut OrderStore,
+        steps: &mut S,
+    ) -> Result<(), StoreError> {
+        let order = self.orders[i];
+        let id = order.id;
+        if store.find_by_id(id).is_none() {
+            // Removed from the store: nothing left to fulfill.
+            self.compensate(i, steps);
+            return Ok(());
+        }
+        while self.states[i].is_active() {
+            self.attempts[i] += 1;
+            let (outcome, next) = match self.states[i] {
+                SagaState::Started => (
+                    steps.reserve_inventory(&order),
+                    SagaState::InventoryReserved,
+                ),
+                SagaState::InventoryReserved => {
+                    (steps.charge_payment(&order), SagaState::PaymentCharged)
+                }
+                _ => (steps.ship(&order), SagaState::Completed),
+            };
+            match outcome {
+                StepOutcome::Done => self.states[i] = next,
+                StepOutcome::Pending => return Ok(()),
+                StepOutcome::Failed => {
+                    self.compensate(i, steps);
+                    return emit(log, store, OrderEvent::Cancelled { id });
+                }
+            }
+        }
+        emit(
+            log,
+            store,
+            OrderEvent::StatusChanged {
+                id,
+                status: Status::Completed,
+            },
+        )
+    }
+
+    /// Undo saga `i`'s completed steps, newest first.
+    fn compensate<S: FulfillmentSteps + ?Sized>(&mut self, i: usize, steps: &mut S) {
+        let order = self.orders[i];
+        if self.states[i] >= SagaState::PaymentCharged {
+            steps.refund_payment(&order);
+        }
+        if self.states[i] >= SagaState::InventoryReserved {
+            steps.release_inventory(&order);
+        }
+        self.states[i] = SagaState::Compensated;
+    }
+}
+
+fn emit(log: &mut EventLog, store: &mut OrderStore, event: OrderEvent) -> Result<(), StoreError> {
+    store.apply(&event)?;
+    log.append(event);
+    Ok(())
+}
@@ -0,0 +1,272 @@
+//! Versioned snapshots and schema migrations.
+//!
+//! A `Snapshot` is the column data of an `OrderSoA`'s live rows tagged with the `SchemaVersion`
+//! it was written under. Columns are added over time: version 1 had ids, amounts, statuses and
+//! timestamps; version 2 added currencies; version 3 added customers. Columns a writer did not
+//! know about are absent (`None`) on read. `Snapshot::upgrade` runs a `Migrator`'s chain of
+//! `Migration`s, one per version step, until the snapshot is at `SchemaVersion::CURRENT`, and
+//! appends each applied migration's name to the snapshot's history, so a snapshot written back
+//! out records how it got there.
+//!
+//! Adding a column means: add an optional field to `SnapshotColumns`, bump `CURRENT`, and
+//! register a migration from the previous version that fills the field with defaults.
+//!
+//! With the `serde` feature, snapshots (de)serialize column-wise like `OrderSoA` itself.
+
+use crate::{Currency, CustomerId, OrderId, OrderSoA, Status, StoreError};
+use std::fmt;
+
+/// Schema a snapshot was written under.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct SchemaVersion(pub u32);
+
+impl SchemaVersion {
+    /// The schema `Snapshot::of` writes.
+    pub const CURRENT: SchemaVersion = SchemaVersion(3);
+
+    fn next(self) -> SchemaVersion {
+        SchemaVersion(self.0 + 1)
+    }
+}
+
+impl fmt::Display for SchemaVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "v{}", self.0)
+    }
+}
+
+/// Snapshot columns; a column the writer's schema lacked is `None` until a migration fills it.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SnapshotColumns {
+    pub ids: Vec<OrderId>,
+    pub amounts: Vec<f64>,
+    pub statuses: Vec<Status>,
+    pub timestamps: Vec<u64>,
+    /// Since v2.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub currencies: Option<Vec<Currency>>,
+    /// Since v3.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub customers: Option<Vec<CustomerId>>,
+}
+
+impl SnapshotColumns {
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+}
+
+/// Column data plus the schema it follows; see the module docs.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Snapshot {
+    pub schema_version: SchemaVersion,
+    /// Names of the migrations applied to this data so far, oldest first.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub migrations: Vec<String>,
+    pub columns: SnapshotColumns,
+}
+
+/// One step of schema evolution: rewrites columns from `source_version()` to the next version.
+pub trait Migration: Send + Sync {
+    /// The version this migration upgrades from.
+    fn source_version(&self) -> SchemaVersion;
+
+    /// Recorded in `Snapshot::migrations` once applied.
+    fn name(&self) -> &str;
+
+    fn migrate(&self, columns: &mut SnapshotColumns);
+}
+
+/// v1 → v2: every order was in the default currency.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct AddCurrencyColumn;
+
+impl Migration for AddCurrencyColumn {
+    fn source_version(&self) -> SchemaVersion {
+        SchemaVersion(1)
+    }
+
+    fn name(&self) -> &str {
+        "v1_to_v2_add_currencies"
+    }
+
+    fn migrate(&self, columns: &mut SnapshotColumns) {
+        let n = columns.len();
+        columns
+            .currencies
+            .get_or_insert_with(|| vec![Currency::default(); n]);
+    }
+}
+
+/// v2 → v3: orders predating customers belong to `CustomerId::default()`.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct AddCustomerColumn;
+
+impl Migration for AddCustomerColumn {
+    fn source_version(&self) -> SchemaVersion {
+        SchemaVersion(2)
+    }
+
+    fn name(&self) -> &str {
+        "v2_to_v3_add_customers"
+    }
+
+    fn migrate(&self, columns: &mut SnapshotColumns) {
+        let n = columns.len();
+        columns
+            .customers
+            .get_or_insert_with(|| vec![CustomerId::default(); n]);
+    }
+}
+
+/// The migrations available for upgrading snapshots, at most one per source version.
+pub struct Migrator {
+    migrations: Vec<Box<dyn Migration>>,
+}
+
+impl fmt::Debug for Migrator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.migrations.iter().map(|m| m.name()))
+            .finish()
+    }
+}
+
+impl Default for Migrator {
+    /// The built-in migrations for every version step of this crate's schema.
+    fn default() -> Self {
+        Self::empty()
+            .with(AddCurrencyColumn)
+            .with(AddCustomerColumn)
+    }
+}
+
+impl Migrator {
+    /// No migrations; only current snapshots load.
+    pub fn empty() -> Self {
+        Self {
+            migrations: Vec::new(),
+        }
+    }
+
+    /// Register `migration`, replacing any registered for the same source version.
+    pub fn with(mut self, migration: impl Migration + 'static) -> Self {
+        self.migrations
+            .retain(|m| m.source_version() != migration.source_version());
+        self.migrations.push(Box::new(migration));
+        self
+    }
+
+    fn step(&self, from: SchemaVersion) -> Option<&dyn Migration> {
+        self.migrations
+            .iter()
+            .find(|m| m.source_version() == from)
+            .map(|m| &**m)
+    }
+}
+
+/// Why a snapshot could not be brought to the current schema or loaded.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SchemaError {
+    /// Written by a newer version of the crate.
+    Unsupported { found: SchemaVersion },
+    /// No migration is registered from this version.
+    MissingMigration(SchemaVersion),
+    /// A column is still absent after migrating.
+    MissingColumn(&'static str),
+    /// The columns do not form a valid kernel.
+    Store(StoreError),
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaError::Unsupported { found } => write!(
+                f,
+                "snapshot schema {found} is newer than {}",
+                SchemaVersion::CURRENT
+            ),
+            SchemaError::MissingMigration(v) => write!(f, "no migration from schema {v}"),
+            SchemaError::MissingColumn(c) => write!(f, "snapshot has no `{c}` column"),
+            SchemaError::Store(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SchemaError::Store(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<StoreError> for SchemaError {
+    fn from(e: StoreError) -> Self {
+        SchemaError::Store(e)
+    }
+}
+
+impl Snapshot {
+    /// The live rows of `soa` under the current schema.
+    pub fn of(soa: &OrderSoA) -> Self {
+        fn live<T: Copy>(soa: &OrderSoA, col: &[T]) -> Vec<T> {
+            (0..soa.len())
+                .filter(|&i| soa.is_live(i))
+                .map(|i| col[i])
+                .collect()
+        }
+        Snapshot {
+            schema_version: SchemaVersion::CURRENT,
+            migrations: Vec::new(),
+            columns: SnapshotColumns {
+                ids: live(soa, &soa.ids),
+                amounts: live(soa, &soa.amounts),
+                statuses: live(soa, &soa.statuses),
+                timestamps: live(soa, &soa.timestamps),
+                currencies: Some(live(soa, &soa.currencies)),
+                customers: Some(live(soa, &soa.customers)),
+            },
+        }
+    }
+
+    /// Apply `migrator`'s migrations until the snapshot is at `SchemaVersion::CURRENT`,
+    /// recording each one. A current snapshot is returned unchanged.
+    pub fn upgrade(mut self, migrator: &Migrator) -> Result<Snapshot, SchemaError> {
+        if self.schema_version > SchemaVersion::CURRENT {
+            return Err(SchemaError::Unsupported {
+                found: self.schema_version,
+            });
+        }
+        while self.schema_version < SchemaVersion::CURRENT {
+            let m = migrator
+                .step(self.schema_version)
+                .ok_or(SchemaError::MissingMigration(self.schema_version))?;
+            m.migrate(&mut self.columns);
+            self.migrations.push(m.name().to_owned());
+            self.schema_version = self.schema_version.next();
+        }
+        Ok(self)
+    }
+
+    /// Build a kernel from a snapshot, upgrading it with the built-in migrations first (a
+    /// snapshot already upgraded with a custom `Migrator` passes through unchanged).
+    pub fn into_soa(self) -> Result<OrderSoA, SchemaError> {
+        let c = self.upgrade(&Migrator::default())?.columns;
+        let currencies = c
+            .currencies
+            .ok_or(SchemaError::MissingColumn("currencies"))?;
+        let customers = c.customers.ok_or(SchemaError::MissingColumn("customers"))?;
+        let soa = OrderSoA::from_columns(c.ids, c.amounts, c.statuses, c.timestamps, currencies)?;
+        Ok(soa.with_customers(customers)?)
+    }
+}
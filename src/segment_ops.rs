@@ -0,0 +1,168 @@
+//! Whole-kernel merge and split.
+//!
+//! Segment-based layouts (hot/cold tiers, shard rebalancing) move rows between kernels in bulk.
+//! `append` and `split_off` do it column by column with `Vec::append` / `Vec::split_off`, i.e.
+//! one memcpy per column; `partition` gathers the live rows into two new kernels in one pass.
+//! The resulting kernels keep the source's index configuration; handles into moved rows go
+//! stale.
+
+use crate::settlement::Settlements;
+use crate::state_machine::StateColumn;
+use crate::{exact, AuditColumns, Bitmap, OrderRow, OrderSoA, OrderView};
+
+impl OrderSoA {
+    /// An empty kernel with the same indexes enabled.
+    pub(crate) fn empty_like(&self, cap: usize) -> OrderSoA {
+        let mut soa = OrderSoA::with_capacity(cap);
+        soa.status_index_enabled = self.status_index_enabled;
+        for spec in self.index_specs() {
+            soa.add_index(spec.clone());
+        }
+        soa.audit = self.audit.as_ref().map(AuditColumns::empty_like);
+        soa.exact_amounts = self.exact_amounts.as_ref().map(|_| Vec::with_capacity(cap));
+        soa.states = self.states.as_ref().map(StateColumn::empty_like);
+        soa
+    }
+
+    /// Move every row of `other` (tombstones included) to the end of this kernel. Where an id
+    /// is in both, the appended row becomes the one `find_by_id` returns.
+    pub fn append(&mut self, mut other: OrderSoA) {
+        let start = self.len();
+        let item_base = self.items.len() as u32;
+        if let Some(exact) = &mut self.exact_amounts {
+            match &mut other.exact_amounts {
+                Some(theirs) => exact.append(theirs),
+                None => exact.extend(other.amounts.iter().map(|&a| exact::fixed_of_money(a))),
+            }
+        }
+        if self.settlements.is_some() || other.settlements.is_some() {
+            self.settlements
+                .get_or_insert_with(|| Settlements::zeros(start))
+                .append(other.settlements.take(), other.len());
+        }
+        if let Some(s) = &mut self.states {
+            match &mut other.states {
+                Some(theirs) if s.same_machine(theirs) => s.codes.append(&mut theirs.codes),
+                _ => s.extend_from_statuses(&other.statuses),
+            }
+        }
+        self.ids.append(&mut other.ids);
+        self.amounts.append(&mut other.amounts);
+        self.statuses.append(&mut other.statuses);
+        self.timestamps.append(&mut other.timestamps);
+        self.currencies.append(&mut other.currencies);
+        self.customers.append(&mut other.customers);
+        self.item_offsets.extend(
+            other
+                .item_offsets
+                .iter()
+                .map(|&(s, e)| (s + item_base, e + item_base)),
+        );
+        self.items.append(&mut other.items);
+        let appended = other.versions.len();
+        self.versions.append(&mut other.versions);
+        if let Some(a) = &mut self.audit {
+            a.append(other.audit.take(), appended);
+        }
+        for i in 0..other.deleted.len() {
+            self.deleted.push(other.deleted.get(i));
+        }
+        self.tombstones += other.tombstones;
+        self.generations.extend(std::iter::repeat_n(
+            self.generation,
+            other.generations.len(),
+        ));
+        for (i, &id) in self.ids[start..].iter().enumerate() {
+            if !self.deleted.get(start + i) {
+                self.id_index.insert(id, start + i);
+            }
+        }
+        self.invalidate_indexes();
+    }
+
+    /// Move rows `at..` (tombstones included) into a new kernel, leaving `..at` here.
+    ///
+    /// # Panics
+    /// If `at > len()`.
+    pub fn split_off(&mut self, at: usize) -> OrderSoA {
+        assert!(at <= self.len(), "split index {at} past len {}", self.len());
+        let mut tail = self.empty_like(0);
+        tail.ids = self.ids.split_off(at);
+        tail.amounts = self.amounts.split_off(at);
+        tail.statuses = self.statuses.split_off(at);
+        tail.timestamps = self.timestamps.split_off(at);
+        tail.currencies = self.currencies.split_off(at);
+        tail.customers = self.customers.split_off(at);
+        tail.versions = self.versions.split_off(at);
+        tail.audit = self.audit.as_mut().map(|a| a.split_off(at));
+        tail.exact_amounts = self.exact_amounts.as_mut().map(|e| e.split_off(at));
+        tail.settlements = self.settlements.as_mut().map(|s| s.split_off(at));
+        if let (Some(s), Some(t)) = (&mut self.states, &mut tail.states) {
+            t.codes = s.codes.split_off(at);
+        }
+        let offsets = self.item_offsets.split_off(at);
+        tail.item_offsets = offsets
+            .iter()
+            .map(|&range| tail.items.copy_from(&self.items, range))
+            .collect();
+        tail.deleted = Bitmap::with_capacity(tail.ids.len());
+        for i in at..self.deleted.len() {
+            tail.deleted.push(self.deleted.get(i));
+        }
+        tail.tombstones = tail.deleted.count_ones();
+        tail.generations = vec![0; tail.ids.len()];
+        tail.rebuild_id_index();
+
+        self.deleted.truncate(at);
+        self.tombstones -= tail.tombstones;
+        self.generations.truncate(at);
+        self.compact_items();
+        // Freed slots may be reused by later pushes; make old handles to them stale.
+        self.generation = self.generation.wrapping_add(1);
+        self.rebuild_id_index();
+        self.invalidate_indexes();
+        tail
+    }
+
+    /// Split the live rows into those matching `pred` and the rest, keeping their order.
+    /// Tombstoned rows are dropped.
+    pub fn partition<F: Fn(OrderView<'_>) -> bool>(self, pred: F) -> (OrderSoA, OrderSoA) {
+        let mut yes = self.empty_like(0);
+        let mut no = self.empty_like(0);
+        for i in (0..self.len()).filter(|&i| self.is_live(i)) {
+            let out = if pred(self.view_at(i)) {
+                &mut yes
+            } else {
+                &mut no
+            };
+            out.push_copied(&self, i);
+        }
+        (yes, no)
+    }
+
+    /// Append a copy of `src`'s row `i`, line items and version included, as a live row.
+    pub(crate) fn push_copied(&mut self, src: &OrderSoA, i: usize) {
+        let items = self.items.copy_from(&src.items, src.item_offsets[i]);
+        self.push_row(OrderRow::from(src.view_at(i)));
+        *self.item_offsets.last_mut().expect("just pushed") = items;
+        *self.versions.last_mut().expect("just pushed") = src.versions[i];
+        if let (Some(a), Some(info)) = (&mut self.audit, src.view_at(i).audit()) {
+            a.set(self.versions.len() - 1, info);
+        }
+        if let Some(exact) = &mut self.exact_amounts {
+            *exact.last_mut().expect("just pushed") = src.fixed_at(i);
+        }
+        if let Some(theirs) = &src.settlements {
+            let n = self.len();
+            let s = self
+                .settlements
+                .get_or_insert_with(|| Settlements::zeros(n));
+            s.refunded[n - 1] = theirs.refunded[i];
+            s.fulfilled[n - 1] = theirs.fulfilled[i];
+        }
+        if let Some(code) = self.adopted_state(src, i) {
+            let s = self.states.as_mut().expect("adopted_state checked");
+            *s.codes.last_mut().expect("just pushed") = code;
+        }
+    }
+}
@@ -0,0 +1,269 @@
+//! Segment lifecycle with zone-map pruning.
+//!
+//! `SegmentedOrderStore` appends into one mutable *active* kernel. Once it holds `freeze_at`
+//! rows it is compacted and *frozen*: it becomes an immutable `FrozenSegment` that carries a
+//! `ZoneMap`, the min/max of its id, timestamp and amount columns. Range and threshold queries
+//! consult the zone maps first and only scan segments whose bounds can match, so a query over
+//! the last hour of a mostly time-ordered store touches one or two segments instead of all of
+//! them. This is the min/max pruning columnar formats do per row group.
+//!
+//! With `with_compression`, a frozen segment keeps its rows as a `CompressedSegment` instead of
+//! raw columns. Its zone map is taken before compressing, so pruned segments are never decoded;
+//! a segment a query does reach is decompressed on first scan and the kernel kept alongside,
+//! until `trim_decoded` returns every segment to its compressed form.
+
+use crate::{CompressedSegment, Money, OrderId, OrderRow, OrderSoA, OrderView};
+use std::sync::{Arc, OnceLock};
+
+/// Default number of rows at which the active segment is frozen.
+pub const DEFAULT_SEGMENT_ROWS: usize = 4096;
+
+/// Min/max statistics of a frozen segment's live rows.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ZoneMap {
+    pub rows: usize,
+    pub min_id: OrderId,
+    pub max_id: OrderId,
+    pub min_ts: u64,
+    pub max_ts: u64,
+    pub min_amount: Money,
+    pub max_amount: Money,
+}
+
+impl ZoneMap {
+    fn of(soa: &OrderSoA) -> Self {
+        let mut zone = ZoneMap {
+            rows: 0,
+            min_id: OrderId(u64::MAX),
+            max_id: OrderId(0),
+            min_ts: u64::MAX,
+            max_ts: 0,
+            min_amount: Money(f64::INFINITY),
+            max_amount: Money(f64::NEG_INFINITY),
+        };
+        for v in soa.iter() {
+            zone.rows += 1;
+            zone.min_id = zone.min_id.min(v.id());
+            zone.max_id = zone.max_id.max(v.id());
+            zone.min_ts = zone.min_ts.min(v.timestamp());
+            zone.max_ts = zone.max_ts.max(v.timestamp());
+            zone.min_amount = Money(zone.min_amount.0.min(v.amount().0));
+            zone.max_amount = Money(zone.max_amount.0.max(v.amount().0));
+        }
+        zone
+    }
+
+    /// Whether some row may have `from <= timestamp < to`.
+    pub fn may_overlap_time(&self, from: u64, to: u64) -> bool {
+        self.rows > 0 && self.min_ts < to && self.max_ts >= from
+    }
+
+    /// Whether some row may have `amount >= min`.
+    pub fn may_have_amount_at_least(&self, min: Money) -> bool {
+        self.rows > 0 && self.max_amount.0 >= min.0
+    }
+
+    pub fn may_contain_id(&self, id: OrderId) -> bool {
+        self.rows > 0 && self.min_id <= id && id <= self.max_id
+    }
+}
+
+/// How a frozen segment holds its rows.
+#[derive(Clone, Debug)]
+enum Storage {
+    Raw(Arc<OrderSoA>),
+    /// Encoded rows, and the kernel decoded from them once a scan needed it.
+    Compressed {
+        segment: Arc<CompressedSegment>,
+        decoded: OnceLock<Arc<OrderSoA>>,
+    },
+}
+
+/// An immutable, compacted kernel and its zone map. Cloning shares the columns.
+#[derive(Clone, Debug)]
+pub struct FrozenSegment {
+    storage: Storage,
+    zone: ZoneMap,
+}
+
+impl FrozenSegment {
+    /// Freeze `soa`, dropping its tombstones.
+    pub fn freeze(mut soa: OrderSoA) -> Self {
+        soa.compact();
+        soa.shrink_to_fit();
+        let zone = ZoneMap::of(&soa);
+        Self {
+            storage: Storage::Raw(Arc::new(soa)),
+            zone,
+        }
+    }
+
+    /// Freeze `soa` into a `CompressedSegment`, dropping its tombstones; see the module docs.
+    pub fn freeze_compressed(soa: OrderSoA) -> Self {
+        Self {
+            zone: ZoneMap::of(&soa),
+            storage: Storage::Compressed {
+                segment: Arc::new(soa.compress()),
+                decoded: OnceLock::new(),
+            },
+        }
+    }
+
+    /// The segment's rows as a kernel, decoding a compressed segment on first use.
+    pub fn kernel(&self) -> &OrderSoA {
+        match &self.storage {
+            Storage::Raw(soa) => soa,
+            Storage::Compressed { segment, decoded } => {
+                decoded.get_or_init(|| Arc::new(segment.decompress()))
+            }
+        }
+    }
+
+    /// The encoded rows, if the segment was frozen compressed.
+    pub fn compressed(&self) -> Option<&CompressedSegment> {
+        match &self.storage {
+            Storage::Raw(_) => None,
+            Storage::Compressed { segment, .. } => Some(segment),
+        }
+    }
+
+    /// Whether a compressed segment currently holds a decoded kernel too.
+    pub fn is_decoded(&self) -> bool {
+        match &self.storage {
+            Storage::Raw(_) => true,
+            Storage::Compressed { decoded, .. } => decoded.get().is_some(),
+        }
+    }
+
+    pub fn zone_map(&self) -> &ZoneMap {
+        &self.zone
+    }
+}
+
+/// Orders in frozen segments (oldest first) plus one active segment taking appends.
+#[derive(Clone, Debug)]
+pub struct SegmentedOrderStore {
+    frozen: Vec<FrozenSegment>,
+    active: OrderSoA,
+    freeze_at: usize,
+    compress: bool,
+}
+
+impl Default for SegmentedOrderStore {
+    fn default() -> Self {
+        Self::new(DEFAULT_SEGMENT_ROWS)
+    }
+}
+
+impl SegmentedOrderStore {
+    /// Freeze the active segment whenever it reaches `freeze_at` rows (at least one).
+    pub fn new(freeze_at: usize) -> Self {
+        let freeze_at = freeze_at.max(1);
+        Self {
+            frozen: Vec::new(),
+            active: OrderSoA::with_capacity(freeze_at),
+            freeze_at,
+            compress: false,
+        }
+    }
+
+    /// Builder flag: compress segments as they freeze; see the module docs.
+    pub fn with_compression(mut self) -> Self {
+        self.compress = true;
+        self
+    }
+
+    pub fn push(&mut self, row: OrderRow) {
+        self.active.push_row(row);
+        if self.active.len() >= self.freeze_at {
+            self.freeze();
+        }
+    }
+
+    /// Freeze the active segment now, if it holds any live row.
+    pub fn freeze(&mut self) {
+        if self.active.live_len() == 0 {
+            return;
+        }
+        let active = std::mem::replace(&mut self.active, OrderSoA::with_capacity(self.freeze_at));
+        self.frozen.push(if self.compress {
+            FrozenSegment::freeze_compressed(active)
+        } else {
+            FrozenSegment::freeze(active)
+        });
+    }
+
+    /// Drop the kernels decoded from compressed segments, returning them to their encoded size.
+    pub fn trim_decoded(&mut self) {
+        for s in &mut self.frozen {
+            if let Storage::Compressed { decoded, .. } = &mut s.storage {
+                decoded.take();
+            }
+        }
+    }
+
+    pub fn frozen(&self) -> &[FrozenSegment] {
+        &self.frozen
+    }
+
+    pub fn active(&self) -> &OrderSoA {
+        &self.active
+    }
+
+    /// Live rows across all segments.
+    pub fn len(&self) -> usize {
+        self.frozen.iter().map(|s| s.zone.rows).sum::<usize>() + self.active.live_len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Frozen segments whose zone map admits `from <= timestamp < to`.
+    pub fn segments_in_time_range(
+        &self,
+        from: u64,
+        to: u64,
+    ) -> impl Iterator<Item = &FrozenSegment> {
+        self.frozen
+            .iter()
+            .filter(move |s| s.zone.may_overlap_time(from, to))
+    }
+
+    /// Frozen segments whose zone map admits `amount >= min`.
+    pub fn segments_with_amount_at_least(
+        &self,
+        min: Money,
+    ) -> impl Iterator<Item = &FrozenSegment> {
+        self.frozen
+            .iter()
+            .filter(move |s| s.zone.may_have_amount_at_least(min))
+    }
+
+    /// Views of orders with `from <= timestamp < to`, skipping segments outside the range.
+    pub fn find_in_time_range(&self, from: u64, to: u64) -> impl Iterator<Item = OrderView<'_>> {
+        self.segments_in_time_range(from, to)
+            .map(FrozenSegment::kernel)
+            .chain(std::iter::once(&self.active))
+            .flat_map(move |soa| soa.filter_by_time_range(from, to))
+    }
+
+    /// Views of orders with `amount >= min`, skipping segments whose maximum is below it.
+    pub fn find_amount_at_least(&self, min: Money) -> impl Iterator<Item = OrderView<'_>> {
+        self.segments_with_amount_at_least(min)
+            .map(FrozenSegment::kernel)
+            .chain(std::iter::once(&self.active))
+            .flat_map(move |soa| soa.iter().filter(move |v| v.amount().0 >= min.0))
+    }
+
+    /// Lookup by id, newest segment first.
+    pub fn find_by_id(&self, id: OrderId) -> Option<OrderView<'_>> {
+        self.active.find_by_id(id).or_else(|| {
+            self.frozen
+                .iter()
+                .rev()
+                .filter(|s| s.zone.may_contain_id(id))
+                .find_map(|s| s.kernel().find_by_id(id))
+        })
+    }
+}
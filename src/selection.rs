@@ -0,0 +1,184 @@
+//! Column-at-a-time predicate evaluation.
+//!
+//! Each kernel scans one column and packs its verdicts into a `Bitmap` selection vector, 64 rows
+//! per word, with tombstoned rows already cleared. Compound filters are built by combining the
+//! bitmaps with `&`, `|` and `!` (negations are live-masked again by `and_live`), so a
+//! predicate over three columns reads each column once in a tight loop instead of building an
+//! `OrderView` per row. `selected` and `sum_selected` consume a selection.
+//!
+//! `Query::select` runs a whole query this way and returns a `Selection`: every predicate is
+//! evaluated on its column first, and views or projected rows are only materialized for the
+//! surviving indices (late materialization).
+//!
+//! The `*_selected` aggregations take a selection (a `Bitmap`, or the index list `filter_indices`
+//! returns) and read only the columns they aggregate, so filter-then-aggregate never evaluates
+//! the predicate twice.
+
+use crate::{
+    Bitmap, Currency, Money, OrderSoA, OrderView, Select, Status, StatusGroups, Summation,
+};
+
+impl OrderSoA {
+    /// `pred` over `column`, with tombstoned rows cleared.
+    fn select_where<T: Copy>(&self, column: &[T], pred: impl Fn(T) -> bool) -> Bitmap {
+        self.and_live(Bitmap::from_fn(column.len(), |i| pred(column[i])))
+    }
+
+    /// Clear the bits of tombstoned rows, e.g. after negating a selection.
+    pub fn and_live(&self, mut sel: Bitmap) -> Bitmap {
+        if let Some(dead) = self.dead_mask() {
+            sel &= &!dead;
+        }
+        sel
+    }
+
+    /// Every live row.
+    pub fn live_selection(&self) -> Bitmap {
+        self.and_live(Bitmap::filled(self.len(), true))
+    }
+
+    /// Rows with `status == s`.
+    pub fn eq_status(&self, s: Status) -> Bitmap {
+        self.select_where(&self.statuses, |st| st == s)
+    }
+
+    /// Rows with `amount >= min`.
+    pub fn gte_amount(&self, min: Money) -> Bitmap {
+        self.select_where(&self.amounts, |a| a >= min.0)
+    }
+
+    /// Rows with `amount < max`.
+    pub fn lt_amount(&self, max: Money) -> Bitmap {
+        self.select_where(&self.amounts, |a| a < max.0)
+    }
+
+    pub fn eq_currency(&self, c: Currency) -> Bitmap {
+        self.select_where(&self.currencies, |cur| cur == c)
+    }
+
+    /// Rows with `from <= timestamp < to`, like `filter_by_time_range`.
+    pub fn ts_between(&self, from: u64, to: u64) -> Bitmap {
+        self.select_where(&self.timestamps, |ts| ts >= from && ts < to)
+    }
+
+    /// Zero-copy views of the selected rows, ascending.
+    ///
+    /// # Panics
+    /// If `sel` is longer than the kernel.
+    pub fn selected<'a>(&'a self, sel: &'a Bitmap) -> impl Iterator<Item = OrderView<'a>> {
+        assert!(sel.len() <= self.len(), "selection longer than kernel");
+        sel.iter_ones().map(move |i| self.view_at(i))
+    }
+
+    /// Sum of the selected rows' amounts, touching only the amount column.
+    pub fn sum_selected(&self, sel: &Bitmap) -> Money {
+        self.sum_amount_selected(sel)
+    }
+
+    /// Sum of the amounts of the rows in `sel` (a `Bitmap` or an index list), reading only the
+    /// amount column; tombstoned rows are skipped.
+    ///
+    /// # Panics
+    /// If `sel` names a row past the end of the kernel.
+    pub fn sum_amount_selected<S: RowSelection + ?Sized>(&self, sel: &S) -> Money {
+        Money(Summation::default().sum(self.live_rows(sel).map(|i| self.amounts[i])))
+    }
+
+    /// `group_by_status` over the rows in `sel` only, reading just the status and amount columns.
+    ///
+    /// # Panics
+    /// If `sel` names a row past the end of the kernel.
+    pub fn group_by_status_selected<S: RowSelection + ?Sized>(&self, sel: &S) -> StatusGroups {
+        let mut groups = StatusGroups::default();
+        for i in self.live_rows(sel) {
+            groups.observe(self.statuses[i], self.amounts[i]);
+        }
+        groups
+    }
+
+    fn live_rows<'a, S: RowSelection + ?Sized>(
+        &'a self,
+        sel: &'a S,
+    ) -> impl Iterator<Item = usize> + 'a {
+        let dead = self.dead_mask();
+        sel.rows().filter(move |&i| dead.is_none_or(|d| !d.get(i)))
+    }
+}
+
+/// Rows picked by an earlier filter, for the `*_selected` aggregations: a `Bitmap` from the
+/// predicate kernels, or row indices from `filter_indices`. Either way the predicate columns are
+/// not read again.
+pub trait RowSelection {
+    /// The selected rows: ascending for a bitmap, in list order (repeats included) for indices.
+    fn rows(&self) -> impl Iterator<Item = usize> + '_;
+}
+
+impl RowSelection for Bitmap {
+    fn rows(&self) -> impl Iterator<Item = usize> + '_ {
+        self.iter_ones()
+    }
+}
+
+impl RowSelection for [usize] {
+    fn rows(&self) -> impl Iterator<Item = usize> + '_ {
+        self.iter().copied()
+    }
+}
+
+impl RowSelection for Vec<usize> {
+    fn rows(&self) -> impl Iterator<Item = usize> + '_ {
+        self.iter().copied()
+    }
+}
+
+/// The rows of one kernel that passed every predicate of a query. Nothing beyond the predicate
+/// columns has been read yet; the accessors touch only what they return.
+#[derive(Clone, Debug)]
+pub struct Selection<'a> {
+    soa: &'a OrderSoA,
+    bits: Bitmap,
+}
+
+impl<'a> Selection<'a> {
+    pub(crate) fn new(soa: &'a OrderSoA, bits: Bitmap) -> Self {
+        Self { soa, bits }
+    }
+
+    pub fn bitmap(&self) -> &Bitmap {
+        &self.bits
+    }
+
+    pub fn count(&self) -> usize {
+        self.bits.count_ones()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.bits.any()
+    }
+
+    /// Surviving row indices, ascending.
+    pub fn indices(&self) -> impl Iterator<Item = usize> + '_ {
+        self.bits.iter_ones()
+    }
+
+    /// Zero-copy views of the surviving rows.
+    pub fn views(&self) -> impl Iterator<Item = OrderView<'a>> + '_ {
+        let soa = self.soa;
+        self.bits.iter_ones().map(move |i| soa.view_at(i))
+    }
+
+    /// The columns in `S` for each surviving row, e.g. `project::<(cols::Id, cols::Amount)>()`.
+    pub fn project<S: Select<'a> + 'a>(&self) -> impl Iterator<Item = S::Row> + '_ {
+        let cols = S::cols(self.soa);
+        self.bits.iter_ones().map(move |i| S::row(&cols, i))
+    }
+
+    pub fn sum(&self) -> Money {
+        self.soa.sum_amount_selected(&self.bits)
+    }
+
+    /// Per-status aggregates of the surviving rows.
+    pub fn group_by_status(&self) -> StatusGroups {
+        self.soa.group_by_status_selected(&self.bits)
+    }
+}
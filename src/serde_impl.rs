@@ -0,0 +1,102 @@
+//! Column-wise serde support (feature `serde`).
+//!
+//! An `OrderSoA` is written as one array per column rather than an array of row objects, so the
+//! snapshot mirrors the in-memory layout and (de)serializers stream each column contiguously.
+//! Only live rows are written; indexes and handle generations are rebuilt on load.
+
+use crate::{Currency, CustomerId, OrderId, OrderSoA, OrderStore, Status};
+use serde::de::{self, Deserializer};
+use serde::ser::Serializer;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+#[derive(Serialize)]
+struct ColumnsRef<'a> {
+    ids: Cow<'a, [OrderId]>,
+    amounts: Cow<'a, [f64]>,
+    statuses: Cow<'a, [Status]>,
+    timestamps: Cow<'a, [u64]>,
+    currencies: Cow<'a, [Currency]>,
+    customers: Cow<'a, [CustomerId]>,
+}
+
+#[derive(Deserialize)]
+struct Columns {
+    ids: Vec<OrderId>,
+    amounts: Vec<f64>,
+    statuses: Vec<Status>,
+    timestamps: Vec<u64>,
+    currencies: Vec<Currency>,
+    /// Absent (read as empty) in snapshots taken before orders carried a customer.
+    #[serde(default)]
+    customers: Vec<CustomerId>,
+}
+
+/// Borrow a column as-is, or copy out its live rows when tombstones are present.
+fn live<'a, T: Copy>(soa: &OrderSoA, col: &'a [T]) -> Cow<'a, [T]> {
+    if soa.tombstones == 0 {
+        Cow::Borrowed(col)
+    } else {
+        Cow::Owned(
+            col.iter()
+                .enumerate()
+                .filter(|&(i, _)| soa.is_live(i))
+                .map(|(_, &v)| v)
+                .collect(),
+        )
+    }
+}
+
+impl Serialize for OrderSoA {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ColumnsRef {
+            ids: live(self, &self.ids),
+            amounts: live(self, &self.amounts),
+            statuses: live(self, &self.statuses),
+            timestamps: live(self, &self.timestamps),
+            currencies: live(self, &self.currencies),
+            customers: live(self, &self.customers),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for OrderSoA {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let c = Columns::deserialize(deserializer)?;
+        let soa = OrderSoA::from_columns(c.ids, c.amounts, c.statuses, c.timestamps, c.currencies);
+        if c.customers.is_empty() {
+            soa
+        } else {
+            soa.and_then(|soa| soa.with_customers(c.customers))
+        }
+        .map_err(de::Error::custom)
+    }
+}
+
+impl Serialize for OrderStore {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.inner.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for OrderStore {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        OrderSoA::deserialize(deserializer).map(OrderStore::from)
+    }
+}
+
+/// Currencies travel as their three-letter code.
+impl Serialize for Currency {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.code())
+    }
+}
+
+impl<'de> Deserialize<'de> for Currency {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = <Cow<'de, str>>::deserialize(deserializer)?;
+        Currency::from_code(&code)
+            .ok_or_else(|| de::Error::invalid_value(de::Unexpected::Str(&code), &"a 3-letter code"))
+    }
+}
@@ -0,0 +1,255 @@
+//! Refunds and partial fulfillment.
+//!
+//! Two more money columns, `amount_refunded` and `amount_fulfilled`, allocated on the first
+//! refund or fulfillment a kernel records (rows before read as zero). They are written only
+//! through `OrderAggregate::refund` / `partially_fulfill` (or by replaying the events those
+//! emit), which enforce the invariants:
+//!
+//! - only a pending order can be fulfilled, and never past its amount; an order cannot be
+//!   re-priced below what is already fulfilled through `OrderAggregate::adjust_amount` or
+//!   `OrderStore::apply`, though direct amount writes (`update_with`, `columns_mut`) are not
+//!   checked;
+//! - an order can be refunded up to what was paid for it: its whole amount once completed, the
+//!   fulfilled part otherwise (e.g. the shipped half of a cancelled order);
+//! - amounts must be positive.
+//!
+//! Limits are compared in fixed point (`EXACT_SCALE`), so refunding 0.1 and then 0.2 of a 0.3
+//! order is allowed. Like the audit columns, these live in memory only; the `Refunded` and
+//! `PartiallyFulfilled` events are the durable record.
+
+use crate::exact::fixed_of_money;
+use crate::{Money, OrderId, OrderMut, OrderSoA, OrderView, Status, StoreError};
+
+/// The refund and fulfillment columns, row-aligned with the kernel's.
+#[derive(Clone, Default)]
+pub(crate) struct Settlements {
+    pub(crate) refunded: Vec<f64>,
+    pub(crate) fulfilled: Vec<f64>,
+}
+
+impl Settlements {
+    /// Columns for `len` rows with nothing refunded or fulfilled.
+    pub(crate) fn zeros(len: usize) -> Self {
+        Settlements {
+            refunded: vec![0.0; len],
+            fulfilled: vec![0.0; len],
+        }
+    }
+
+    pub(crate) fn push_zeros(&mut self, n: usize) {
+        self.refunded.extend(std::iter::repeat_n(0.0, n));
+        self.fulfilled.extend(std::iter::repeat_n(0.0, n));
+    }
+
+    pub(crate) fn swap_remove(&mut self, idx: usize) {
+        self.refunded.swap_remove(idx);
+        self.fulfilled.swap_remove(idx);
+    }
+
+    pub(crate) fn remove(&mut self, idx: usize) {
+        self.refunded.remove(idx);
+        self.fulfilled.remove(idx);
+    }
+
+    /// The values of `rows`, in that order.
+    pub(crate) fn gathered(&self, rows: &[usize]) -> Self {
+        Settlements {
+            refunded: rows.iter().map(|&i| self.refunded[i]).collect(),
+            fulfilled: rows.iter().map(|&i| self.fulfilled[i]).collect(),
+        }
+    }
+
+    /// Copy row `from` over row `to`.
+    pub(crate) fn copy_row(&mut self, from: usize, to: usize) {
+        self.refunded[to] = self.refunded[from];
+        self.fulfilled[to] = self.fulfilled[from];
+    }
+
+    pub(crate) fn truncate(&mut self, len: usize) {
+        self.refunded.truncate(len);
+        self.fulfilled.truncate(len);
+    }
+
+    /// Append `other`'s `n` rows, or `n` zero rows if it had none recorded.
+    pub(crate) fn append(&mut self, other: Option<Settlements>, n: usize) {
+        match other {
+            Some(mut other) => {
+                self.refunded.append(&mut other.refunded);
+                self.fulfilled.append(&mut other.fulfilled);
+            }
+            None => self.push_zeros(n),
+        }
+    }
+
+    pub(crate) fn split_off(&mut self, at: usize) -> Settlements {
+        Settlements {
+            refunded: self.refunded.split_off(at),
+            fulfilled: self.fulfilled.split_off(at),
+        }
+    }
+
+    pub(crate) fn reserve(&mut self, additional: usize) {
+        self.refunded.reserve(additional);
+        self.fulfilled.reserve(additional);
+    }
+
+    pub(crate) fn shrink_to_fit(&mut self) {
+        self.refunded.shrink_to_fit();
+        self.fulfilled.shrink_to_fit();
+    }
+}
+
+impl OrderSoA {
+    /// Amount refunded on row `idx` so far.
+    pub fn amount_refunded_at(&self, idx: usize) -> Money {
+        Money(self.settlements.as_ref().map_or(0.0, |s| s.refunded[idx]))
+    }
+
+    /// Amount fulfilled on row `idx` so far.
+    pub fn amount_fulfilled_at(&self, idx: usize) -> Money {
+        Money(self.settlements.as_ref().map_or(0.0, |s| s.fulfilled[idx]))
+    }
+}
+
+impl OrderView<'_> {
+    /// See `OrderAggregate::refund`.
+    pub fn amount_refunded(&self) -> Money {
+        self.soa.amount_refunded_at(self.idx)
+    }
+
+    /// See `OrderAggregate::partially_fulfill`.
+    pub fn amount_fulfilled(&self) -> Money {
+        self.soa.amount_fulfilled_at(self.idx)
+    }
+}
+
+/// What the invariants look at, read from a view or a mutable view.
+struct Totals {
+    id: OrderId,
+    status: Status,
+    amount: f64,
+    refunded: f64,
+    fulfilled: f64,
+}
+
+impl Totals {
+    fn check_positive(&self, m: Money) -> Result<(), StoreError> {
+        if m.0.is_nan() || m.0 <= 0.0 {
+            return Err(StoreError::InvalidAmount(self.id));
+        }
+        Ok(())
+    }
+
+    /// The refunded total after refunding `amount`, if allowed.
+    fn refund(&self, amount: Money) -> Result<f64, StoreError> {
+        self.check_positive(amount)?;
+        let paid = match self.status {
+            Status::Completed => self.amount,
+            _ => self.fulfilled,
+        };
+        let refunded = self.refunded + amount.0;
+        if fixed_of_money(refunded) > fixed_of_money(paid) {
+            return Err(StoreError::RefundExceedsPaid(self.id));
+        }
+        Ok(refunded)
+    }
+
+    /// Whether the order may be re-priced to `amount`: not below what is fulfilled.
+    fn reprice(&self, amount: Money) -> Result<(), StoreError> {
+        if fixed_of_money(amount.0) < fixed_of_money(self.fulfilled) {
+            return Err(StoreError::FulfillmentExceedsAmount(self.id));
+        }
+        Ok(())
+    }
+
+    /// The fulfilled total after fulfilling `amount` more, if allowed.
+    fn fulfill(&self, amount: Money) -> Result<f64, StoreError> {
+        self.check_positive(amount)?;
+        if self.status != Status::Pending {
+            return Err(StoreError::OrderClosed(self.id));
+        }
+        let fulfilled = self.fulfilled + amount.0;
+        if fixed_of_money(fulfilled) > fixed_of_money(self.amount) {
+            return Err(StoreError::FulfillmentExceedsAmount(self.id));
+        }
+        Ok(fulfilled)
+    }
+}
+
+impl OrderView<'_> {
+    fn totals(&self) -> Totals {
+        Totals {
+            id: self.id(),
+            status: self.status(),
+            amount: self.amount().0,
+            refunded: self.amount_refunded().0,
+            fulfilled: self.amount_fulfilled().0,
+        }
+    }
+
+    /// Whether `OrderMut::record_refund` would accept `amount`.
+    pub(crate) fn check_refund(&self, amount: Money) -> Result<(), StoreError> {
+        self.totals().refund(amount).map(drop)
+    }
+
+    /// Whether `OrderMut::record_fulfillment` would accept `amount`.
+    pub(crate) fn check_fulfillment(&self, amount: Money) -> Result<(), StoreError> {
+        self.totals().fulfill(amount).map(drop)
+    }
+
+    /// Whether the order may be re-priced to `amount`: not below its fulfilled total.
+    pub(crate) fn check_reprice(&self, amount: Money) -> Result<(), StoreError> {
+        self.totals().reprice(amount)
+    }
+}
+
+impl OrderMut<'_> {
+    fn settlements(&mut self) -> &mut Settlements {
+        let len = self.ids.len();
+        self.settlements
+            .get_or_insert_with(|| Settlements::zeros(len))
+    }
+
+    fn totals(&self) -> Totals {
+        Totals {
+            id: self.id(),
+            status: self.status(),
+            amount: self.amount().0,
+            refunded: self.amount_refunded(),
+            fulfilled: self.amount_fulfilled(),
+        }
+    }
+
+    /// See `OrderView::check_reprice`.
+    pub(crate) fn check_reprice(&self, amount: Money) -> Result<(), StoreError> {
+        self.totals().reprice(amount)
+    }
+
+    pub(crate) fn amount_refunded(&self) -> f64 {
+        self.settlements
+            .as_ref()
+            .map_or(0.0, |s| s.refunded[self.idx])
+    }
+
+    pub(crate) fn amount_fulfilled(&self) -> f64 {
+        self.settlements
+            .as_ref()
+            .map_or(0.0, |s| s.fulfilled[self.idx])
+    }
+
+    /// Refund `amount` if the invariants allow it; see the module docs.
+    pub(crate) fn record_refund(&mut self, amount: Money) -> Result<(), StoreError> {
+        let refunded = self.totals().refund(amount)?;
+        let idx = self.idx;
+        self.settlements().refunded[idx] = refunded;
+        Ok(())
+    }
+
+    /// Fulfill `amount` more of a pending order if the invariants allow it; see the module docs.
+    pub(crate) fn record_fulfillment(&mut self, amount: Money) -> Result<(), StoreError> {
+        let fulfilled = self.totals().fulfill(amount)?;
+        let idx = self.idx;
+        self.settlements().fulfilled[idx] = fulfilled;
+        Ok(())
+    }
+}
@@ -0,0 +1,109 @@
+//! Per-shard load metrics for `ShardedOrderStore`.
+//!
+//! Every write through the store (`add`, `update`, `ingest`) bumps its shard's counters: a write
+//! count and the wall-clock time of the latest write. `shard_stats` snapshots them together with
+//! each shard's size, and `skew_report` condenses the snapshot into max/mean ratios so hot
+//! shards, e.g. from strided ids under `ModuloRouter`, show up before they hurt.
+
+use crate::ShardedOrderStore;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Write counters of one shard, updated with relaxed atomics outside the shard's lock.
+#[derive(Debug, Default)]
+pub(crate) struct ShardCounters {
+    writes: AtomicU64,
+    /// Milliseconds since the Unix epoch; 0 before the first write.
+    last_write_ms: AtomicU64,
+}
+
+impl ShardCounters {
+    pub(crate) fn record_writes(&self, n: u64) {
+        self.writes.fetch_add(n, Ordering::Relaxed);
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis() as u64);
+        self.last_write_ms.fetch_max(now, Ordering::Relaxed);
+    }
+}
+
+/// Point-in-time metrics of one shard.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ShardStats {
+    pub shard: usize,
+    /// Live rows.
+    pub rows: usize,
+    /// Heap bytes in use; see `OrderSoA::memory_usage`.
+    pub bytes: usize,
+    /// Writes since the shard was created (or last rebalanced).
+    pub writes: u64,
+    /// Wall-clock time of the latest write, in milliseconds since the Unix epoch.
+    pub last_write_ms: Option<u64>,
+}
+
+/// How unevenly rows and writes are spread. A ratio of 1.0 is perfectly even; `n` shards with
+/// everything on one give `n`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SkewReport {
+    pub max_to_mean_rows: f64,
+    pub max_to_mean_writes: f64,
+    /// Shard with the most writes (lowest index on ties).
+    pub hottest_shard: usize,
+}
+
+impl SkewReport {
+    /// Whether rows or writes exceed `threshold` times their mean on some shard.
+    pub fn is_skewed(&self, threshold: f64) -> bool {
+        self.max_to_mean_rows > threshold || self.max_to_mean_writes > threshold
+    }
+}
+
+fn max_to_mean(values: impl Iterator<Item = u64> + Clone) -> f64 {
+    let (n, total) = values
+        .clone()
+        .fold((0u64, 0u64), |(n, t), v| (n + 1, t + v));
+    let max = values.max().unwrap_or(0);
+    if total == 0 {
+        1.0
+    } else {
+        max as f64 * n as f64 / total as f64
+    }
+}
+
+impl ShardedOrderStore {
+    /// Current metrics of every shard, in shard order.
+    pub fn shard_stats(&self) -> Vec<ShardStats> {
+        (0..self.shards.len())
+            .map(|si| {
+                let (rows, bytes) = {
+                    let shard = self.read_shard(si);
+                    (shard.live_len(), shard.memory_usage().used_bytes())
+                };
+                let c = &self.counters[si];
+                let last = c.last_write_ms.load(Ordering::Relaxed);
+                ShardStats {
+                    shard: si,
+                    rows,
+                    bytes,
+                    writes: c.writes.load(Ordering::Relaxed),
+                    last_write_ms: (last != 0).then_some(last),
+                }
+            })
+            .collect()
+    }
+
+    /// Skew of the current `shard_stats`.
+    pub fn skew_report(&self) -> SkewReport {
+        let stats = self.shard_stats();
+        let hottest_shard = stats
+            .iter()
+            .rev()
+            .max_by_key(|s| s.writes)
+            .map_or(0, |s| s.shard);
+        SkewReport {
+            max_to_mean_rows: max_to_mean(stats.iter().map(|s| s.rows as u64)),
+            max_to_mean_writes: max_to_mean(stats.iter().map(|s| s.writes)),
+            hottest_shard,
+        }
+    }
+}
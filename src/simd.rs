@@ -0,0 +1,161 @@
+//! Explicit-SIMD column kernels (feature `simd`, backed by `wide`).
+//!
+//! Rows are processed in blocks of 16: one `u8x16` compare turns the status bytes into a 16-bit
+//! match mask, which then selects amounts four lanes at a time through a small table of lane
+//! masks, so the hot loop never branches per row. Tails shorter than a block fall back to the
+//! scalar loop. Tombstoned rows are masked out block-wise: 16-row blocks line up with the
+//! tombstone bitmap's 64-bit words, so the dead mask is a shift away.
+
+use crate::summation::Neumaier;
+use crate::{Bitmap, Status};
+use wide::{f64x4, u8x16};
+
+const BLOCK: usize = 16;
+
+/// `LANE_MASKS[m]` has all-ones in lane `j` iff bit `j` of `m` is set.
+const LANE_MASKS: [[u64; 4]; 16] = {
+    let mut t = [[0u64; 4]; 16];
+    let mut m = 0;
+    while m < 16 {
+        let mut j = 0;
+        while j < 4 {
+            if m & (1 << j) != 0 {
+                t[m][j] = u64::MAX;
+            }
+            j += 1;
+        }
+        m += 1;
+    }
+    t
+};
+
+#[inline(always)]
+fn lane_mask(bits: u32) -> f64x4 {
+    let m = LANE_MASKS[(bits & 0xF) as usize];
+    f64x4::new(m.map(f64::from_bits))
+}
+
+#[inline(always)]
+fn lanes(a: &[f64]) -> f64x4 {
+    f64x4::new([a[0], a[1], a[2], a[3]])
+}
+
+#[cfg(not(feature = "no-unsafe"))]
+#[inline]
+fn status_bytes(statuses: &[Status]) -> &[u8] {
+    // SAFETY: `Status` is `#[repr(u8)]`, so a `[Status]` has the layout of a `[u8]` of equal length.
+    unsafe { std::slice::from_raw_parts(statuses.as_ptr().cast::<u8>(), statuses.len()) }
+}
+
+/// Safe stand-in for the reinterpreting cast: one byte copy of the column.
+#[cfg(feature = "no-unsafe")]
+fn status_bytes(statuses: &[Status]) -> Vec<u8> {
+    statuses.iter().map(|&s| s as u8).collect()
+}
+
+/// 16-bit mask of live rows in block `b`.
+#[inline(always)]
+fn live_mask(dead: Option<&Bitmap>, b: usize) -> u32 {
+    match dead {
+        None => 0xFFFF,
+        Some(d) => !(d.words()[b / 4] >> ((b % 4) * 16)) as u32 & 0xFFFF,
+    }
+}
+
+/// 16-bit mask of rows in the block whose status equals `target`.
+#[inline(always)]
+fn status_mask(block: &[u8], target: u8x16) -> u32 {
+    let bytes: [u8; BLOCK] = block.try_into().unwrap();
+    u8x16::new(bytes).simd_eq(target).to_bitmask()
+}
+
+/// `compensated` keeps a Neumaier error term per lane (`Summation::Kahan`); otherwise lanes
+/// accumulate plainly (`Summation::Naive`).
+pub(crate) fn sum_by_status(
+    statuses: &[Status],
+    amounts: &[f64],
+    dead: Option<&Bitmap>,
+    status: Status,
+    compensated: bool,
+) -> f64 {
+    debug_assert_eq!(statuses.len(), amounts.len());
+    let target = u8x16::splat(status as u8);
+    // Independent accumulators hide the latency of the dependent adds.
+    let mut acc = [f64x4::ZERO; 4];
+    let mut comp = [f64x4::ZERO; 4];
+    let bytes = status_bytes(statuses);
+    let s_blocks = bytes.chunks_exact(BLOCK);
+    let a_blocks = amounts.chunks_exact(BLOCK);
+    let (s_tail, a_tail) = (s_blocks.remainder(), a_blocks.remainder());
+    for (b, (s, a)) in s_blocks.zip(a_blocks).enumerate() {
+        let bits = status_mask(s, target) & live_mask(dead, b);
+        if bits == 0 {
+            continue;
+        }
+        for k in 0..4 {
+            let x = lanes(&a[k * 4..]) & lane_mask(bits >> (k * 4));
+            if compensated {
+                let t = acc[k] + x;
+                let big = acc[k].abs().simd_ge(x.abs());
+                comp[k] += big.bitselect((acc[k] - t) + x, (x - t) + acc[k]);
+                acc[k] = t;
+            } else {
+                acc[k] += x;
+            }
+        }
+    }
+    let base = statuses.len() - s_tail.len();
+    let tail = s_tail
+        .iter()
+        .zip(a_tail)
+        .enumerate()
+        .filter(|&(i, (&s, _))| s == status as u8 && dead.is_none_or(|d| !d.get(base + i)))
+        .map(|(_, (_, &a))| a);
+    if !compensated {
+        return ((acc[0] + acc[1]) + (acc[2] + acc[3])).reduce_add() + tail.sum::<f64>();
+    }
+    let lanes = acc.iter().chain(&comp).flat_map(|v| v.to_array());
+    lanes
+        .chain(tail)
+        .fold(Neumaier::default(), Neumaier::add)
+        .total()
+}
+
+pub(crate) fn filter_indices(
+    statuses: &[Status],
+    amounts: &[f64],
+    dead: Option<&Bitmap>,
+    min_amount: f64,
+    status: Status,
+) -> Vec<usize> {
+    debug_assert_eq!(statuses.len(), amounts.len());
+    let target = u8x16::splat(status as u8);
+    let min = f64x4::splat(min_amount);
+    let mut out = Vec::new();
+    let bytes = status_bytes(statuses);
+    let s_blocks = bytes.chunks_exact(BLOCK);
+    let a_blocks = amounts.chunks_exact(BLOCK);
+    let (s_tail, a_tail) = (s_blocks.remainder(), a_blocks.remainder());
+    for (b, (s, a)) in s_blocks.zip(a_blocks).enumerate() {
+        let mut bits = status_mask(s, target) & live_mask(dead, b);
+        if bits == 0 {
+            continue;
+        }
+        let mut ge = 0u32;
+        for k in 0..4 {
+            ge |= lanes(&a[k * 4..]).simd_ge(min).to_bitmask() << (k * 4);
+        }
+        bits &= ge;
+        while bits != 0 {
+            out.push(b * BLOCK + bits.trailing_zeros() as usize);
+            bits &= bits - 1;
+        }
+    }
+    let base = statuses.len() - s_tail.len();
+    for (i, (&s, &a)) in s_tail.iter().zip(a_tail).enumerate() {
+        if a >= min_amount && s == status as u8 && dead.is_none_or(|d| !d.get(base + i)) {
+            out.push(base + i);
+        }
+    }
+    out
+}
@@ -0,0 +1,199 @@
+//! Zero-copy sub-views over a contiguous run of rows.
+//!
+//! `OrderSoA::slice` borrows a row range without copying any column: an `OrderSlice` holds the
+//! kernel and the range, and its kernels run on the sub-slices of the columns, so SIMD and
+//! autovectorized loops apply unchanged. Only the tombstone mask is re-based, and only when the
+//! kernel has tombstones.
+//!
+//! On a kernel sorted by timestamp (`sort_by_timestamp`) a time window is a contiguous range:
+//! `time_range` binary-searches it, and `slice_by_time` answers a window query by running the
+//! kernels on that range instead of testing every row's timestamp.
+
+use crate::{column, Bitmap, Money, OrderSoA, OrderView, Status, StatusGroups, Summation};
+use std::ops::{Bound, Range, RangeBounds};
+
+/// A contiguous row range of one kernel, borrowed; see the module docs.
+#[derive(Copy, Clone, Debug)]
+pub struct OrderSlice<'a> {
+    soa: &'a OrderSoA,
+    start: usize,
+    end: usize,
+}
+
+/// `range` resolved against `len` rows.
+///
+/// # Panics
+/// If the range is decreasing or ends past `len`, as slice indexing does.
+fn resolve(range: impl RangeBounds<usize>, len: usize) -> Range<usize> {
+    let start = match range.start_bound() {
+        Bound::Included(&s) => s,
+        Bound::Excluded(&s) => s + 1,
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&e) => e + 1,
+        Bound::Excluded(&e) => e,
+        Bound::Unbounded => len,
+    };
+    assert!(
+        start <= end && end <= len,
+        "slice {start}..{end} out of range for {len} rows"
+    );
+    start..end
+}
+
+impl OrderSoA {
+    /// Zero-copy view of the rows in `range`.
+    ///
+    /// # Panics
+    /// If `range` is out of bounds.
+    pub fn slice(&self, range: impl RangeBounds<usize>) -> OrderSlice<'_> {
+        let Range { start, end } = resolve(range, self.len());
+        OrderSlice {
+            soa: self,
+            start,
+            end,
+        }
+    }
+
+    /// Rows with `from <= timestamp < to`, found by binary search. Only meaningful when the
+    /// timestamp column is sorted ascending, e.g. after `sort_by_timestamp`.
+    pub fn time_range(&self, from: u64, to: u64) -> Range<usize> {
+        let start = self.timestamps.partition_point(|&ts| ts < from);
+        let end = start + self.timestamps[start..].partition_point(|&ts| ts < to);
+        start..end
+    }
+
+    /// `slice(time_range(from, to))`: the window as a slice, on a timestamp-sorted kernel.
+    pub fn slice_by_time(&self, from: u64, to: u64) -> OrderSlice<'_> {
+        self.slice(self.time_range(from, to))
+    }
+}
+
+impl<'a> OrderSlice<'a> {
+    /// Number of rows in the slice, tombstoned ones included.
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// The kernel rows the slice covers.
+    pub fn rows(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    /// Number of live rows in the slice.
+    pub fn live_len(&self) -> usize {
+        self.rows().filter(|&i| self.soa.is_live(i)).count()
+    }
+
+    /// A narrower slice; `range` is relative to this one.
+    ///
+    /// # Panics
+    /// If `range` is out of bounds.
+    pub fn slice(&self, range: impl RangeBounds<usize>) -> OrderSlice<'a> {
+        let Range { start, end } = resolve(range, self.len());
+        OrderSlice {
+            soa: self.soa,
+            start: self.start + start,
+            end: self.start + end,
+        }
+    }
+
+    /// View of the slice's `i`-th row, if in bounds and live.
+    pub fn get(&self, i: usize) -> Option<OrderView<'a>> {
+        let idx = self.start.checked_add(i).filter(|&idx| idx < self.end)?;
+        self.soa.is_live(idx).then(|| self.soa.view_at(idx))
+    }
+
+    /// Zero-copy views of the live rows, in row order.
+    pub fn iter(&self) -> impl Iterator<Item = OrderView<'a>> {
+        let soa = self.soa;
+        self.rows()
+            .filter(move |&i| soa.is_live(i))
+            .map(move |i| soa.view_at(i))
+    }
+
+    /// Live rows with status `s`, in row order.
+    pub fn find_by_status(&self, s: Status) -> impl Iterator<Item = OrderView<'a>> {
+        self.iter().filter(move |v| v.status() == s)
+    }
+
+    /// The kernel's tombstones over the slice's rows, indexed from the slice start.
+    fn dead_mask(&self) -> Option<Bitmap> {
+        let dead = self.soa.dead_mask()?;
+        Some(Bitmap::from_fn(self.len(), |i| dead.get(self.start + i)))
+    }
+
+    fn statuses(&self) -> &'a [Status] {
+        &self.soa.statuses[self.start..self.end]
+    }
+
+    fn amounts(&self) -> &'a [f64] {
+        &self.soa.amounts[self.start..self.end]
+    }
+
+    /// `OrderSoA::sum_by_status` over the slice.
+    pub fn sum_by_status(&self, status: Status) -> Money {
+        self.sum_by_status_with(status, Summation::default())
+    }
+
+    /// `OrderSoA::sum_by_status_with` over the slice.
+    pub fn sum_by_status_with(&self, status: Status, mode: Summation) -> Money {
+        let dead = self.dead_mask();
+        #[cfg(feature = "simd")]
+        if mode != Summation::Pairwise {
+            return Money(crate::simd::sum_by_status(
+                self.statuses(),
+                self.amounts(),
+                dead.as_ref(),
+                status,
+                mode == Summation::Kahan,
+            ));
+        }
+        Money(column::sum_where(
+            self.statuses(),
+            self.amounts(),
+            dead.as_ref(),
+            mode,
+            |s| s == status,
+        ))
+    }
+
+    /// `OrderSoA::filter_indices` over the slice. Indices are kernel rows, so they can be passed
+    /// to `view_at` or the `*_selected` aggregations.
+    pub fn filter_indices(&self, min_amount: Money, status: Status) -> Vec<usize> {
+        let dead = self.dead_mask();
+        #[cfg(feature = "simd")]
+        let mut rows = crate::simd::filter_indices(
+            self.statuses(),
+            self.amounts(),
+            dead.as_ref(),
+            min_amount.0,
+            status,
+        );
+        #[cfg(not(feature = "simd"))]
+        let mut rows =
+            column::filter_where(self.statuses(), self.amounts(), dead.as_ref(), |s, a| {
+                s == status && a >= min_amount.0
+            });
+        for row in &mut rows {
+            *row += self.start;
+        }
+        rows
+    }
+
+    /// `OrderSoA::group_by_status` over the slice.
+    pub fn group_by_status(&self) -> StatusGroups {
+        let mut groups = StatusGroups::default();
+        for (i, (&st, &amt)) in self.statuses().iter().zip(self.amounts()).enumerate() {
+            if self.soa.is_live(self.start + i) {
+                groups.observe(st, amt);
+            }
+        }
+        groups
+    }
+}
@@ -0,0 +1,216 @@
+//! Specification pattern over the kernel.
+//!
+//! A `Specification` is a named business rule ("pending EUR orders over 100") that can be tested
+//! against one order and composed with `and` / `or` / `not`. `OrderStore::find(spec)` runs it
+//! over the store. The built-in leaf specs (`StatusIs`, `AmountAtLeast`, ...) compile to a read
+//! of the single column they constrain, and the combinators keep that through, so a spec built
+//! from them never materializes an `OrderView` for a row it rejects. Custom specs — including
+//! closures over `&OrderView` — fall back to evaluating a view per row.
+//! The built-in specs are `Hash`, so `OrderStore::find_cached` can memoize them.
+
+use crate::{Currency, CustomerId, Money, OrderSoA, OrderStore, OrderView, Status};
+use std::hash::{Hash, Hasher};
+
+/// Hash key of an amount threshold, consistent with `==` (`-0.0` and `0.0` hash alike).
+fn money_bits(m: Money) -> u64 {
+    (m.0 + 0.0).to_bits()
+}
+
+/// A predicate over orders; see the module docs.
+pub trait Specification {
+    fn is_satisfied_by(&self, order: &OrderView<'_>) -> bool;
+
+    /// Evaluate against row `i` of `soa` (live or not). Override to read columns directly
+    /// instead of building a view.
+    #[inline]
+    fn matches_at(&self, soa: &OrderSoA, i: usize) -> bool {
+        self.is_satisfied_by(&soa.view_at(i))
+    }
+
+    fn and<S: Specification>(self, other: S) -> And<Self, S>
+    where
+        Self: Sized,
+    {
+        And(self, other)
+    }
+
+    fn or<S: Specification>(self, other: S) -> Or<Self, S>
+    where
+        Self: Sized,
+    {
+        Or(self, other)
+    }
+
+    fn not(self) -> Not<Self>
+    where
+        Self: Sized,
+    {
+        Not(self)
+    }
+}
+
+impl<F: Fn(&OrderView<'_>) -> bool> Specification for F {
+    fn is_satisfied_by(&self, order: &OrderView<'_>) -> bool {
+        self(order)
+    }
+}
+
+/// Both specs hold.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct And<A, B>(pub A, pub B);
+
+impl<A: Specification, B: Specification> Specification for And<A, B> {
+    fn is_satisfied_by(&self, order: &OrderView<'_>) -> bool {
+        self.0.is_satisfied_by(order) && self.1.is_satisfied_by(order)
+    }
+
+    #[inline]
+    fn matches_at(&self, soa: &OrderSoA, i: usize) -> bool {
+        self.0.matches_at(soa, i) && self.1.matches_at(soa, i)
+    }
+}
+
+/// Either spec holds.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Or<A, B>(pub A, pub B);
+
+impl<A: Specification, B: Specification> Specification for Or<A, B> {
+    fn is_satisfied_by(&self, order: &OrderView<'_>) -> bool {
+        self.0.is_satisfied_by(order) || self.1.is_satisfied_by(order)
+    }
+
+    #[inline]
+    fn matches_at(&self, soa: &OrderSoA, i: usize) -> bool {
+        self.0.matches_at(soa, i) || self.1.matches_at(soa, i)
+    }
+}
+
+/// The spec does not hold.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Not<A>(pub A);
+
+impl<A: Specification> Specification for Not<A> {
+    fn is_satisfied_by(&self, order: &OrderView<'_>) -> bool {
+        !self.0.is_satisfied_by(order)
+    }
+
+    #[inline]
+    fn matches_at(&self, soa: &OrderSoA, i: usize) -> bool {
+        !self.0.matches_at(soa, i)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct StatusIs(pub Status);
+
+impl Specification for StatusIs {
+    fn is_satisfied_by(&self, o: &OrderView<'_>) -> bool {
+        o.status() == self.0
+    }
+
+    #[inline]
+    fn matches_at(&self, soa: &OrderSoA, i: usize) -> bool {
+        soa.statuses[i] == self.0
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CurrencyIs(pub Currency);
+
+impl Specification for CurrencyIs {
+    fn is_satisfied_by(&self, o: &OrderView<'_>) -> bool {
+        o.currency() == self.0
+    }
+
+    #[inline]
+    fn matches_at(&self, soa: &OrderSoA, i: usize) -> bool {
+        soa.currencies[i] == self.0
+    }
+}
+
+/// `amount >= self.0`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AmountAtLeast(pub Money);
+
+impl Hash for AmountAtLeast {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        money_bits(self.0).hash(state);
+    }
+}
+
+impl Specification for AmountAtLeast {
+    fn is_satisfied_by(&self, o: &OrderView<'_>) -> bool {
+        o.amount().0 >= self.0 .0
+    }
+
+    #[inline]
+    fn matches_at(&self, soa: &OrderSoA, i: usize) -> bool {
+        soa.amounts[i] >= self.0 .0
+    }
+}
+
+/// `amount < self.0`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AmountBelow(pub Money);
+
+impl Hash for AmountBelow {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        money_bits(self.0).hash(state);
+    }
+}
+
+impl Specification for AmountBelow {
+    fn is_satisfied_by(&self, o: &OrderView<'_>) -> bool {
+        o.amount().0 < self.0 .0
+    }
+
+    #[inline]
+    fn matches_at(&self, soa: &OrderSoA, i: usize) -> bool {
+        soa.amounts[i] < self.0 .0
+    }
+}
+
+/// `from <= timestamp < to`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PlacedBetween(pub u64, pub u64);
+
+impl Specification for PlacedBetween {
+    fn is_satisfied_by(&self, o: &OrderView<'_>) -> bool {
+        (self.0..self.1).contains(&o.timestamp())
+    }
+
+    #[inline]
+    fn matches_at(&self, soa: &OrderSoA, i: usize) -> bool {
+        (self.0..self.1).contains(&soa.timestamps[i])
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ForCustomer(pub CustomerId);
+
+impl Specification for ForCustomer {
+    fn is_satisfied_by(&self, o: &OrderView<'_>) -> bool {
+        o.customer() == self.0
+    }
+
+    #[inline]
+    fn matches_at(&self, soa: &OrderSoA, i: usize) -> bool {
+        soa.customers[i] == self.0
+    }
+}
+
+impl OrderSoA {
+    /// Live rows satisfying `spec`, ascending.
+    pub fn find<S: Specification>(&self, spec: S) -> impl Iterator<Item = OrderView<'_>> {
+        (0..self.len())
+            .filter(move |&i| self.is_live(i) && spec.matches_at(self, i))
+            .map(move |i| self.view_at(i))
+    }
+}
+
+impl OrderStore {
+    /// Orders satisfying `spec`; see `Specification`.
+    pub fn find<S: Specification>(&self, spec: S) -> impl Iterator<Item = OrderView<'_>> {
+        self.inner.find(spec)
+    }
+}
@@ -0,0 +1,338 @@
+//! Order lifecycles configured per store.
+//!
+//! `Status` is fixed at compile time and deliberately coarse: the kernels scan, index and SIMD-sum
+//! a one-byte column of it. Real order flows have more steps (paid, shipped, refunded, ...), so a
+//! `StateMachine` names those states, files each under the `Status` the kernels should see it
+//! as, and lists the transitions allowed between them. A store built `with_state_machine` keeps
+//! one `u16` state code per row next to the status column, with the names held once in the
+//! machine (dictionary encoding, as in `CategoricalColumn`); `OrderStore::transition` moves an
+//! order along an allowed edge and updates both columns, as do the `OrderAggregate` commands,
+//! which take the first allowed edge into a state under the status they move to.
+//!
+//! Without a configured machine every store follows `StateMachine::default()`: one state per
+//! `Status`, with pending orders moving to completed or cancelled. Writes that set the status
+//! directly (`set_status`, `transition_status`, `columns_mut`) bypass the machine; a row whose
+//! stored state no longer files under its status reads as the first state that does. Like the
+//! audit columns, state codes live in memory only: snapshots and exports carry the status, which
+//! rows are re-filed from.
+
+use crate::audit::RowStamp;
+use crate::{
+    query_cache, IndexUpkeep, OrderId, OrderMut, OrderSoA, OrderStore, OrderView, Status,
+    StoreError,
+};
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock};
+
+/// Code of a state within its `StateMachine`, in the order states were added.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct StateId(pub u16);
+
+/// Named states, each filed under a `Status`, and the transitions allowed between them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StateMachine {
+    names: Vec<Box<str>>,
+    codes: HashMap<Box<str>, StateId>,
+    statuses: Vec<Status>,
+    /// `next[s]`: the states `s` may move to.
+    next: Vec<Vec<StateId>>,
+}
+
+impl Default for StateMachine {
+    /// The built-in lifecycle: a state per `Status`, coded by discriminant, following
+    /// `Status::can_transition_to`.
+    fn default() -> Self {
+        let mut machine = StateMachine::new(Status::Pending.name(), Status::Pending);
+        for status in Status::ALL.into_iter().skip(1) {
+            machine = machine
+                .with_state(status.name(), status)
+                .with_transition(Status::Pending.name(), status.name());
+        }
+        machine
+    }
+}
+
+static LIFECYCLE: LazyLock<StateMachine> = LazyLock::new(StateMachine::default);
+
+impl StateMachine {
+    /// A machine with one state, `initial`, filed under `status`.
+    pub fn new(initial: &str, status: Status) -> Self {
+        StateMachine {
+            names: Vec::new(),
+            codes: HashMap::new(),
+            statuses: Vec::new(),
+            next: Vec::new(),
+        }
+        .with_state(initial, status)
+    }
+
+    /// Builder: add state `name`, filed under `status`. Orders added with a status start in the
+    /// first state filed under it, so add each status's entry state first.
+    ///
+    /// # Panics
+    /// If `name` is already a state, or the machine already holds `u16::MAX + 1` states.
+    pub fn with_state(mut self, name: &str, status: Status) -> Self {
+        assert!(
+            !self.codes.contains_key(name),
+            "state `{name}` defined twice"
+        );
+        let id = StateId(u16::try_from(self.names.len()).expect("more than 65536 states"));
+        self.names.push(name.into());
+        self.codes.insert(name.into(), id);
+        self.statuses.push(status);
+        self.next.push(Vec::new());
+        self
+    }
+
+    /// Builder: allow moving from state `from` to state `to`.
+    ///
+    /// # Panics
+    /// If either is not a state of the machine.
+    pub fn with_transition(mut self, from: &str, to: &str) -> Self {
+        let lookup = |name| {
+            self.state(name)
+                .unwrap_or_else(|| panic!("unknown state `{name}`"))
+        };
+        let (from, to) = (lookup(from), lookup(to));
+        let next = &mut self.next[from.0 as usize];
+        if !next.contains(&to) {
+            next.push(to);
+        }
+        self
+    }
+
+    /// Number of states.
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Always `false`: a machine has at least its initial state.
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// The state new orders start in.
+    pub fn initial(&self) -> StateId {
+        StateId(0)
+    }
+
+    /// The state called `name`.
+    pub fn state(&self, name: &str) -> Option<StateId> {
+        self.codes.get(name).copied()
+    }
+
+    pub fn name(&self, state: StateId) -> Option<&str> {
+        self.names.get(state.0 as usize).map(|n| &**n)
+    }
+
+    /// The `Status` the kernels see `state` as.
+    pub fn status(&self, state: StateId) -> Option<Status> {
+        self.statuses.get(state.0 as usize).copied()
+    }
+
+    /// States in code order.
+    pub fn states(&self) -> impl Iterator<Item = (StateId, &str)> {
+        self.names
+            .iter()
+            .enumerate()
+            .map(|(i, n)| (StateId(i as u16), &**n))
+    }
+
+    /// The states `from` may move to, in the order they were allowed.
+    pub fn transitions_from(&self, from: StateId) -> &[StateId] {
+        self.next.get(from.0 as usize).map_or(&[], |n| &n[..])
+    }
+
+    pub fn allows(&self, from: StateId, to: StateId) -> bool {
+        self.transitions_from(from).contains(&to)
+    }
+
+    /// The first state `from` may move to that is filed under `status`.
+    pub(crate) fn next_under(&self, from: StateId, status: Status) -> Option<StateId> {
+        self.transitions_from(from)
+            .iter()
+            .copied()
+            .find(|&to| self.status(to) == Some(status))
+    }
+
+    /// The state a row with `status` is filed as when no state was recorded for it: the first
+    /// state under `status`, or the initial state if there is none.
+    pub fn state_for(&self, status: Status) -> StateId {
+        self.statuses
+            .iter()
+            .position(|&s| s == status)
+            .map_or(self.initial(), |i| StateId(i as u16))
+    }
+}
+
+/// Per-row state codes, row-aligned with the kernel's columns, and the machine they index.
+#[derive(Clone)]
+pub(crate) struct StateColumn {
+    pub(crate) machine: Arc<StateMachine>,
+    pub(crate) codes: Vec<u16>,
+}
+
+impl StateColumn {
+    /// Codes for rows with `statuses`, filed by status.
+    pub(crate) fn new(machine: Arc<StateMachine>, statuses: &[Status]) -> Self {
+        let codes = statuses.iter().map(|&s| machine.state_for(s).0).collect();
+        StateColumn { machine, codes }
+    }
+
+    /// Empty codes over the same machine.
+    pub(crate) fn empty_like(&self) -> Self {
+        StateColumn::new(Arc::clone(&self.machine), &[])
+    }
+
+    /// Append rows with `statuses`, filed by status.
+    pub(crate) fn extend_from_statuses(&mut self, statuses: &[Status]) {
+        let machine = &self.machine;
+        self.codes
+            .extend(statuses.iter().map(|&s| machine.state_for(s).0));
+    }
+
+    /// Whether `other` codes states of the same machine.
+    pub(crate) fn same_machine(&self, other: &StateColumn) -> bool {
+        Arc::ptr_eq(&self.machine, &other.machine) || self.machine == other.machine
+    }
+}
+
+/// The machine `states` codes, or the built-in lifecycle if no machine is configured.
+pub(crate) fn machine_of(states: Option<&StateColumn>) -> &StateMachine {
+    states.map_or(&LIFECYCLE, |s| &s.machine)
+}
+
+/// The state of row `idx`, whose status is `status`; see `OrderSoA::state_at`.
+pub(crate) fn state_of(states: Option<&StateColumn>, idx: usize, status: Status) -> StateId {
+    match states {
+        Some(s) => {
+            let code = StateId(s.codes[idx]);
+            if s.machine.status(code) == Some(status) {
+                code
+            } else {
+                s.machine.state_for(status)
+            }
+        }
+        None => LIFECYCLE.state_for(status),
+    }
+}
+
+impl OrderSoA {
+    /// Builder flag: record each row's state in `machine`; see the module docs. Existing rows
+    /// are filed by status.
+    pub fn with_state_machine(mut self, machine: StateMachine) -> Self {
+        self.states = Some(StateColumn::new(Arc::new(machine), &self.statuses));
+        self
+    }
+
+    /// The lifecycle rows follow: the configured machine, or the built-in default.
+    pub fn state_machine(&self) -> &StateMachine {
+        machine_of(self.states.as_ref())
+    }
+
+    /// Row `idx`'s state: the recorded one while it still files under the row's status, the
+    /// first state under that status otherwise.
+    pub fn state_at(&self, idx: usize) -> StateId {
+        state_of(self.states.as_ref(), idx, self.statuses[idx])
+    }
+
+    /// Live rows in `state`, in row order.
+    pub fn find_by_state(&self, state: StateId) -> impl Iterator<Item = OrderView<'_>> {
+        (0..self.len())
+            .filter(move |&i| self.is_live(i) && self.state_at(i) == state)
+            .map(|i| self.view_at(i))
+    }
+
+    /// `view_mut_at`, along with the state codes, for commands that move the row's state. Unlike
+    /// `view_mut_at` this does not count as a write: the command stamps the row through the
+    /// returned `RowStamp` once it succeeds.
+    pub(crate) fn view_mut_with_states(
+        &mut self,
+        idx: usize,
+    ) -> (OrderMut<'_>, Option<&mut StateColumn>, RowStamp<'_>) {
+        let live = self.is_live(idx);
+        let row = OrderMut {
+            ids: &mut self.ids,
+            amounts: &mut self.amounts,
+            exact_amounts: self.exact_amounts.as_deref_mut(),
+            settlements: &mut self.settlements,
+            statuses: &mut self.statuses,
+            timestamps: &mut self.timestamps,
+            currencies: &mut self.currencies,
+            customers: &mut self.customers,
+            idx,
+            upkeep: live.then(|| IndexUpkeep {
+                row: idx,
+                status_index: self.status_index.get_mut(),
+                indexes: &mut self.indexes,
+            }),
+        };
+        let stamp = RowStamp {
+            idx,
+            version: &mut self.versions[idx],
+            audit: self.audit.as_mut(),
+        };
+        (row, self.states.as_mut(), stamp)
+    }
+
+    /// The state code a copy of `src`'s row `i` gets here: kept if both kernels use the same
+    /// machine, re-filed by status otherwise.
+    pub(crate) fn adopted_state(&self, src: &OrderSoA, i: usize) -> Option<u16> {
+        let ours = self.states.as_ref()?;
+        Some(match &src.states {
+            Some(theirs) if ours.same_machine(theirs) => src.state_at(i).0,
+            _ => ours.machine.state_for(src.statuses[i]).0,
+        })
+    }
+}
+
+impl OrderView<'_> {
+    /// The order's lifecycle state; see `OrderSoA::state_at`.
+    pub fn state(&self) -> StateId {
+        self.soa.state_at(self.idx)
+    }
+
+    /// Name of `state()` in the kernel's state machine.
+    pub fn state_name(&self) -> &str {
+        self.soa
+            .state_machine()
+            .name(self.state())
+            .expect("state_at returns states of the machine")
+    }
+}
+
+impl OrderStore {
+    /// Builder flag: follow `machine` instead of the built-in lifecycle; see the module docs.
+    pub fn with_state_machine(mut self, machine: StateMachine) -> Self {
+        let soa = query_cache::write(&mut self.inner, &mut self.epoch, self.context);
+        soa.states = Some(StateColumn::new(Arc::new(machine), &soa.statuses));
+        self
+    }
+
+    /// Move order `id` to state `to`, setting its status to the one `to` is filed under. Fails
+    /// with `UnknownState` if `to` is not a state of the store's machine and with
+    /// `IllegalStateTransition` if the machine does not allow the move; validators and change
+    /// subscribers see the status write as with `update_with`, with the state already recorded.
+    pub fn transition(&mut self, id: OrderId, to: StateId) -> Result<(), StoreError> {
+        let idx = self.index_of(id)?;
+        let machine = self.inner.state_machine();
+        let status = machine.status(to).ok_or(StoreError::UnknownState(to))?;
+        let from = self.inner.state_at(idx);
+        if !machine.allows(from, to) {
+            return Err(StoreError::IllegalStateTransition { id, from, to });
+        }
+        let owned = query_cache::write(&mut self.inner, &mut self.epoch, self.context);
+        let prev = owned
+            .states
+            .as_mut()
+            .map(|s| std::mem::replace(&mut s.codes[idx], to.0));
+        let out = self.update_with(id, |mut o| o.set_status(status));
+        if let (Err(_), Some(prev)) = (&out, prev) {
+            let owned = query_cache::write(&mut self.inner, &mut self.epoch, self.context);
+            if let Some(s) = &mut owned.states {
+                s.codes[idx] = prev;
+            }
+        }
+        out
+    }
+}
@@ -0,0 +1,81 @@
+//! Per-column statistics of the live rows.
+//!
+//! `OrderSoA::stats` reads the amount, timestamp and status columns once each and summarizes
+//! them: the numeric range and spread of the amounts, the time span, and how many rows carry
+//! each status. The figures are cheap enough to recompute on demand and serve both operators
+//! (what does this store hold?) and planners (is a status selective enough to use its index?).
+//! Every `OrderSoA` column is non-nullable, so there are no null counts to report; nullable
+//! columns live in `OptionColumn`, which reports them through `null_count`.
+
+use crate::{column, Money, OrderSoA, Status};
+
+/// Summary of the amount column. Every field is `None` for an empty kernel.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct AmountStats {
+    pub min: Option<Money>,
+    pub max: Option<Money>,
+    pub mean: Option<Money>,
+    /// Population standard deviation.
+    pub stddev: Option<Money>,
+}
+
+/// Column summaries returned by `OrderSoA::stats`.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct ColumnStats {
+    /// Live rows summarized.
+    pub rows: usize,
+    pub amount: AmountStats,
+    /// `None` for an empty kernel.
+    pub min_ts: Option<u64>,
+    /// `None` for an empty kernel.
+    pub max_ts: Option<u64>,
+    /// Live rows per status, indexed by `Status as usize`; see `status_count`.
+    pub status_counts: [usize; Status::ALL.len()],
+}
+
+impl ColumnStats {
+    pub fn status_count(&self, status: Status) -> usize {
+        self.status_counts[status as usize]
+    }
+
+    /// Statuses that occur at least once.
+    pub fn distinct_statuses(&self) -> usize {
+        self.status_counts.iter().filter(|&&n| n > 0).count()
+    }
+}
+
+impl OrderSoA {
+    /// Summaries of the live rows' columns; see the module docs.
+    pub fn stats(&self) -> ColumnStats {
+        let mut stats = ColumnStats::default();
+        let live = || (0..self.len()).filter(|&i| self.is_live(i));
+
+        // Welford's update keeps the variance stable for large, tightly clustered amounts.
+        let (mut n, mut mean, mut m2) = (0usize, 0.0f64, 0.0f64);
+        let (mut min, mut max) = (f64::INFINITY, f64::NEG_INFINITY);
+        for a in live().map(|i| self.amounts[i]) {
+            n += 1;
+            let delta = a - mean;
+            mean += delta / n as f64;
+            m2 += delta * (a - mean);
+            min = min.min(a);
+            max = max.max(a);
+        }
+        if n > 0 {
+            stats.amount = AmountStats {
+                min: Some(Money(min)),
+                max: Some(Money(max)),
+                mean: Some(Money(mean)),
+                stddev: Some(Money((m2 / n as f64).sqrt())),
+            };
+        }
+        stats.rows = n;
+
+        stats.min_ts = column::min(&self.timestamps, self.dead_mask());
+        stats.max_ts = column::max(&self.timestamps, self.dead_mask());
+        for s in live().map(|i| self.statuses[i]) {
+            stats.status_counts[s as usize] += 1;
+        }
+        stats
+    }
+}
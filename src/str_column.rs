@@ -0,0 +1,158 @@
+//! Variable-length string columns: one append-only byte arena plus a `(start, end)` pair per row.
+//!
+//! Pushing a value copies its bytes into the arena, so a column of a million names is two
+//! allocations rather than a million `String`s. An interned column additionally reuses the bytes
+//! of a value it has seen before, so low-cardinality fields (SKUs, regions) store each distinct
+//! value once. Overwriting a row appends the new value and repoints the row; the old bytes are
+//! reclaimed when the column is compacted.
+
+use crate::dyn_soa::DynColumn;
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt;
+
+/// A column of `&str` backed by a byte arena. Columns compare by their values, row by row,
+/// whatever their arenas or interning.
+#[derive(Clone, Default)]
+pub struct StrColumn {
+    bytes: String,
+    spans: Vec<(u32, u32)>,
+    /// `Some` when interning: each distinct value -> its span in `bytes`.
+    dict: Option<HashMap<Box<str>, (u32, u32)>>,
+}
+
+impl PartialEq for StrColumn {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl Eq for StrColumn {}
+
+impl fmt::Debug for StrColumn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl StrColumn {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A column that stores each distinct value's bytes once.
+    pub fn interned() -> Self {
+        Self {
+            dict: Some(HashMap::new()),
+            ..Self::default()
+        }
+    }
+
+    pub fn is_interned(&self) -> bool {
+        self.dict.is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.spans.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+
+    /// Bytes held by the arena, including values no row points at any more.
+    pub fn arena_len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Append a value; returns its row.
+    pub fn push(&mut self, value: &str) -> usize {
+        let span = self.store(value);
+        self.spans.push(span);
+        self.spans.len() - 1
+    }
+
+    /// Repoint row `i` at `value`.
+    ///
+    /// # Panics
+    /// If `i` is out of bounds.
+    pub fn set(&mut self, i: usize, value: &str) {
+        assert!(i < self.len(), "string row {i} out of bounds");
+        self.spans[i] = self.store(value);
+    }
+
+    #[inline]
+    pub fn get(&self, i: usize) -> &str {
+        let (start, end) = self.spans[i];
+        &self.bytes[start as usize..end as usize]
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> + '_ {
+        (0..self.len()).map(|i| self.get(i))
+    }
+
+    /// Rewrite the arena so it holds only bytes rows still point at.
+    pub fn compact(&mut self) {
+        let mut next = if self.is_interned() {
+            Self::interned()
+        } else {
+            Self::new()
+        };
+        self.iter().for_each(|s| {
+            next.push(s);
+        });
+        *self = next;
+    }
+
+    fn store(&mut self, value: &str) -> (u32, u32) {
+        if let Some(&span) = self.dict.as_ref().and_then(|d| d.get(value)) {
+            return span;
+        }
+        let start = self.bytes.len();
+        self.bytes.push_str(value);
+        let span = (
+            u32::try_from(start).expect("string arena exceeds 4 GiB"),
+            u32::try_from(self.bytes.len()).expect("string arena exceeds 4 GiB"),
+        );
+        if let Some(dict) = &mut self.dict {
+            dict.insert(value.into(), span);
+        }
+        span
+    }
+}
+
+impl<'a> FromIterator<&'a str> for StrColumn {
+    fn from_iter<I: IntoIterator<Item = &'a str>>(iter: I) -> Self {
+        let mut col = Self::new();
+        iter.into_iter().for_each(|s| {
+            col.push(s);
+        });
+        col
+    }
+}
+
+impl DynColumn for StrColumn {
+    fn len(&self) -> usize {
+        self.spans.len()
+    }
+    fn push_default(&mut self) {
+        self.push("");
+    }
+    fn retain_mask(&mut self, keep: &[bool]) {
+        let mut i = 0;
+        self.spans.retain(|_| {
+            i += 1;
+            keep[i - 1]
+        });
+        self.compact();
+    }
+    fn type_name(&self) -> &'static str {
+        "str"
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
@@ -0,0 +1,93 @@
+//! Streaming ingestion into an `OrderStore` (feature `async`).
+//!
+//! `ingest_stream` drains a `Stream` of rows, e.g. the decoded output of a Kafka or NATS
+//! consumer, in batches: a batch is applied once it holds `max_rows` rows, once `max_delay` has
+//! passed since its first row arrived, or when the stream ends. Each batch is one `add_batch`,
+//! so one copy-on-write and one index update instead of one per row. If the store would refuse
+//! any row of a batch (a stored id, a validator), that batch alone falls back to row-by-row
+//! inserts under the store's `IdPolicy`, so only the offending rows are rejected.
+//!
+//! The `max_delay` bound runs on tokio's timer: the runtime needs its time driver enabled.
+
+use crate::{IngestStats, OrderRow, OrderStore};
+use futures_core::Stream;
+use std::future::poll_fn;
+use std::pin::pin;
+use std::time::Duration;
+use tokio::time::{timeout_at, Instant};
+
+/// When `ingest_stream` applies the rows it has collected.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct BatchPolicy {
+    /// Largest batch (at least one row).
+    pub max_rows: usize,
+    /// Longest a row waits for its batch to fill.
+    pub max_delay: Duration,
+}
+
+impl Default for BatchPolicy {
+    fn default() -> Self {
+        BatchPolicy {
+            max_rows: 1024,
+            max_delay: Duration::from_millis(50),
+        }
+    }
+}
+
+impl OrderStore {
+    /// Store every row of `rows`, batched per `policy`, until the stream ends; see the module
+    /// docs. Rows the store refuses are reported in the stats, not raised.
+    pub async fn ingest_stream(
+        &mut self,
+        rows: impl Stream<Item = OrderRow>,
+        policy: BatchPolicy,
+    ) -> IngestStats {
+        let mut rows = pin!(rows);
+        let max_rows = policy.max_rows.max(1);
+        let mut stats = IngestStats::default();
+        let mut batch = Vec::with_capacity(max_rows);
+        let mut deadline = Instant::now();
+        loop {
+            let next = poll_fn(|cx| rows.as_mut().poll_next(cx));
+            let row = if batch.is_empty() {
+                next.await
+            } else {
+                match timeout_at(deadline, next).await {
+                    Ok(row) => row,
+                    Err(_) => {
+                        self.ingest_batch(&mut batch, &mut stats);
+                        continue;
+                    }
+                }
+            };
+            let Some(row) = row else { break };
+            if batch.is_empty() {
+                deadline = Instant::now() + policy.max_delay;
+            }
+            batch.push(row);
+            if batch.len() >= max_rows {
+                self.ingest_batch(&mut batch, &mut stats);
+            }
+        }
+        if !batch.is_empty() {
+            self.ingest_batch(&mut batch, &mut stats);
+        }
+        stats
+    }
+
+    /// Apply and empty `batch`: in bulk if the store takes every row, else row by row.
+    fn ingest_batch(&mut self, batch: &mut Vec<OrderRow>, stats: &mut IngestStats) {
+        stats.batches += 1;
+        if self.add_batch(batch.iter().copied()).is_ok() {
+            stats.appended += batch.len();
+            batch.clear();
+            return;
+        }
+        for row in batch.drain(..) {
+            match self.insert(self.id_policy, row, false) {
+                Ok(_) => stats.appended += 1,
+                Err(e) => stats.rejected.push((row.id, e)),
+            }
+        }
+    }
+}
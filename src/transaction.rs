@@ -0,0 +1,72 @@
+//! Unit of work over an `OrderStore`.
+//!
+//! A `Transaction` writes to its own copy of the store. The copy shares the kernel `Arc` until
+//! the first write, which clones it (the usual copy-on-write), so the original stays untouched
+//! until `commit` swaps the copy in. Dropping the transaction without committing discards every
+//! change. Change subscribers hear about the transaction's writes only once it commits.
+
+use crate::{ChangeFeed, OrderStore};
+use std::mem;
+use std::ops::{Deref, DerefMut};
+
+/// Pending changes to an `OrderStore`; dereferences to the working copy, so the whole store API
+/// (reads included, which see the transaction's own writes) is available.
+#[must_use = "a transaction is rolled back unless `commit` is called"]
+pub struct Transaction<'a> {
+    store: &'a mut OrderStore,
+    working: OrderStore,
+}
+
+impl OrderStore {
+    /// Start a transaction. The store is borrowed until it commits or rolls back.
+    pub fn begin(&mut self) -> Transaction<'_> {
+        Transaction {
+            working: self.fork(),
+            store: self,
+        }
+    }
+
+    /// A working copy whose change events are held back until `join`.
+    pub(crate) fn fork(&self) -> OrderStore {
+        let mut working = self.clone();
+        if self.changes.is_active() {
+            working.changes = ChangeFeed::holding();
+        }
+        working
+    }
+
+    /// Replace the store with a copy from `fork`, then publish the copy's held change events to
+    /// this store's subscribers.
+    pub(crate) fn join(&mut self, mut working: OrderStore) {
+        let held = working.changes.take_held();
+        working.changes = mem::take(&mut self.changes);
+        *self = working;
+        for event in held {
+            self.changes.emit(event.handle, event.kind);
+        }
+    }
+}
+
+impl Transaction<'_> {
+    /// Publish every change at once.
+    pub fn commit(self) {
+        self.store.join(self.working);
+    }
+
+    /// Discard every change; the same as dropping the transaction.
+    pub fn rollback(self) {}
+}
+
+impl Deref for Transaction<'_> {
+    type Target = OrderStore;
+
+    fn deref(&self) -> &OrderStore {
+        &self.working
+    }
+}
+
+impl DerefMut for Transaction<'_> {
+    fn deref_mut(&mut self) -> &mut OrderStore {
+        &mut self.working
+    }
+}
@@ -0,0 +1,124 @@
+//! Domain validation at the façade boundary.
+//!
+//! A `Validator` states one invariant over an `OrderCandidate`: the row an `add`, `upsert`,
+//! `update_with`, `apply` or `OrderAggregate` command is about to write, plus the stored row it
+//! replaces. Refunds and fulfillments leave the row's fields as they are, so their candidate is
+//! the stored row itself. Validators are registered on the store (`OrderStore::with_validator`,
+//! `OrderStoreBuilder::validator`) and run in registration order; the first `DomainError` aborts
+//! the write, surfacing as `StoreError::Invalid`, and the store is left untouched. Writes through
+//! `kernel_mut` and bulk kernels such as `for_each_mut` are not validated.
+
+use crate::{OrderRow, StoreError};
+use std::fmt;
+use std::sync::Arc;
+
+/// A broken domain invariant.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DomainError {
+    /// The amount is zero, negative or not a number.
+    NonPositiveAmount,
+    /// The timestamp lies after the validator's notion of now.
+    FutureTimestamp,
+    /// Any other rule, named by the validator.
+    Violated(&'static str),
+}
+
+impl fmt::Display for DomainError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DomainError::NonPositiveAmount => f.write_str("amount must be positive"),
+            DomainError::FutureTimestamp => f.write_str("timestamp is in the future"),
+            DomainError::Violated(rule) => f.write_str(rule),
+        }
+    }
+}
+
+impl std::error::Error for DomainError {}
+
+/// An order about to be written.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct OrderCandidate {
+    /// The row as it will be stored.
+    pub row: OrderRow,
+    /// The stored row being overwritten, for updates and upserts.
+    pub stored: Option<OrderRow>,
+}
+
+impl OrderCandidate {
+    pub fn is_update(&self) -> bool {
+        self.stored.is_some()
+    }
+}
+
+/// One domain invariant; see the module docs.
+pub trait Validator: Send + Sync {
+    fn validate(&self, candidate: &OrderCandidate) -> Result<(), DomainError>;
+}
+
+impl<F> Validator for F
+where
+    F: Fn(&OrderCandidate) -> Result<(), DomainError> + Send + Sync,
+{
+    fn validate(&self, candidate: &OrderCandidate) -> Result<(), DomainError> {
+        self(candidate)
+    }
+}
+
+/// Amounts must be greater than zero.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct PositiveAmount;
+
+impl Validator for PositiveAmount {
+    fn validate(&self, candidate: &OrderCandidate) -> Result<(), DomainError> {
+        if candidate.row.amount.0 > 0.0 {
+            Ok(())
+        } else {
+            Err(DomainError::NonPositiveAmount)
+        }
+    }
+}
+
+/// Timestamps must not exceed `now()`, read on every check.
+#[derive(Copy, Clone, Debug)]
+pub struct NotInFuture<C>(pub C);
+
+impl<C: Fn() -> u64 + Send + Sync> Validator for NotInFuture<C> {
+    fn validate(&self, candidate: &OrderCandidate) -> Result<(), DomainError> {
+        if candidate.row.ts <= (self.0)() {
+            Ok(())
+        } else {
+            Err(DomainError::FutureTimestamp)
+        }
+    }
+}
+
+/// The validators registered on one store.
+#[derive(Clone, Default)]
+pub(crate) struct Validators(Vec<Arc<dyn Validator>>);
+
+impl fmt::Debug for Validators {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} validator(s)", self.0.len())
+    }
+}
+
+impl Validators {
+    pub(crate) fn push(&mut self, v: impl Validator + 'static) {
+        self.0.push(Arc::new(v));
+    }
+
+    #[inline]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The first validator's error, if any rejects the candidate.
+    #[inline]
+    pub(crate) fn check(&self, row: OrderRow, stored: Option<OrderRow>) -> Result<(), StoreError> {
+        let candidate = OrderCandidate { row, stored };
+        self.0
+            .iter()
+            .try_for_each(|v| v.validate(&candidate))
+            .map_err(|e| StoreError::Invalid(row.id, e))
+    }
+}
@@ -0,0 +1,314 @@
+//! Incrementally maintained materialized views.
+//!
+//! A `MaterializedView` is a fold over row changes: `init` resets it to the empty store and
+//! `apply` folds in one `RowChange` (a row's image before and after a write). A view registered
+//! with `OrderStore::register_view` is first replayed over the live rows, then kept current by
+//! every insert, update and delete made through the store's API, the same hook that maintains
+//! the cached per-status aggregates. Writes through `kernel_mut` leave views stale until the next
+//! tracked write or `refresh_views` replays them from scratch.
+//!
+//! `RevenuePerDay`, `OpenOrderCount` and `CustomerTotals` are ready-made examples.
+
+use crate::cached_aggregates::CachedAggregates;
+use crate::{CustomerId, Money, OrderRow, OrderSoA, OrderStore, RowHandle, Status};
+use std::any::Any;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::marker::PhantomData;
+
+/// One row-level write: `before` is `None` for an insert, `after` is `None` for a delete.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RowChange {
+    pub handle: RowHandle,
+    pub before: Option<OrderRow>,
+    pub after: Option<OrderRow>,
+}
+
+/// A fold over the store's row changes; see the module docs.
+pub trait MaterializedView: Clone + Send + Sync + 'static {
+    /// Reset to the view of an empty store.
+    fn init(&mut self);
+
+    fn apply(&mut self, change: &RowChange);
+}
+
+/// Object-safe face of `MaterializedView`, so views of different types share one registry.
+trait ErasedView: Send + Sync {
+    fn init(&mut self);
+    fn apply(&mut self, change: &RowChange);
+    fn clone_box(&self) -> Box<dyn ErasedView>;
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<V: MaterializedView> ErasedView for V {
+    fn init(&mut self) {
+        MaterializedView::init(self)
+    }
+
+    fn apply(&mut self, change: &RowChange) {
+        MaterializedView::apply(self, change)
+    }
+
+    fn clone_box(&self) -> Box<dyn ErasedView> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+impl Clone for Box<dyn ErasedView> {
+    fn clone(&self) -> Self {
+        self.clone_box()
+    }
+}
+
+/// Typed reference to a view registered with one store.
+pub struct ViewHandle<V> {
+    index: usize,
+    _view: PhantomData<fn() -> V>,
+}
+
+impl<V> Copy for ViewHandle<V> {}
+
+impl<V> Clone for ViewHandle<V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<V> fmt::Debug for ViewHandle<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ViewHandle").field(&self.index).finish()
+    }
+}
+
+/// Everything a store derives incrementally from its writes.
+#[derive(Clone, Default)]
+pub(crate) struct Tracking {
+    pub(crate) aggregates: Option<CachedAggregates>,
+    views: Vec<Box<dyn ErasedView>>,
+    /// Set by untracked writes; cleared by `rebuild`.
+    pub(crate) stale: bool,
+}
+
+impl Tracking {
+    /// Whether writers need to report row changes at all.
+    #[inline]
+    pub(crate) fn is_active(&self) -> bool {
+        self.aggregates.is_some() || !self.views.is_empty()
+    }
+
+    /// Fold `change` into everything tracked, unless a rebuild is pending anyway.
+    pub(crate) fn apply(&mut self, change: &RowChange) {
+        if self.stale {
+            return;
+        }
+        if let Some(cache) = &mut self.aggregates {
+            cache.apply(change);
+        }
+        for view in &mut self.views {
+            view.apply(change);
+        }
+    }
+
+    fn rebuild(&mut self, soa: &OrderSoA) {
+        if let Some(cache) = &mut self.aggregates {
+            *cache = CachedAggregates::scan(soa);
+        }
+        for view in &mut self.views {
+            view.init();
+            replay(soa, view.as_mut());
+        }
+        self.stale = false;
+    }
+}
+
+/// Feed every live row of `soa` to `view` as an insert.
+fn replay(soa: &OrderSoA, view: &mut dyn ErasedView) {
+    for i in (0..soa.len()).filter(|&i| soa.is_live(i)) {
+        view.apply(&RowChange {
+            handle: soa.handle_at(i),
+            before: None,
+            after: Some(OrderRow::from(soa.view_at(i))),
+        });
+    }
+}
+
+impl OrderStore {
+    /// Register `view`, bring it up to date with the stored orders and keep it so.
+    pub fn register_view<V: MaterializedView>(&mut self, mut view: V) -> ViewHandle<V> {
+        view.init();
+        replay(&self.inner, &mut view);
+        self.tracking.views.push(Box::new(view));
+        ViewHandle {
+            index: self.tracking.views.len() - 1,
+            _view: PhantomData,
+        }
+    }
+
+    /// Current state of a registered view.
+    ///
+    /// # Panics
+    /// If `handle` was issued by a different store.
+    pub fn materialized<V: MaterializedView>(&self, handle: ViewHandle<V>) -> &V {
+        self.tracking
+            .views
+            .get(handle.index)
+            .and_then(|v| v.as_any().downcast_ref())
+            .expect("view handle from another store")
+    }
+
+    /// Rebuild every view and the cached aggregates from the stored orders, e.g. after writes
+    /// through `kernel_mut`.
+    pub fn refresh_views(&mut self) {
+        self.tracking.rebuild(&self.inner);
+    }
+
+    /// Report a write made through the store's API. A stale tracker is rebuilt instead; the
+    /// kernel already holds the write, so the rebuild covers it.
+    pub(crate) fn track(&mut self, change: RowChange) {
+        if self.tracking.stale {
+            self.tracking.rebuild(&self.inner);
+        } else {
+            self.tracking.apply(&change);
+        }
+    }
+}
+
+/// Apply `f` with sign -1 to the `before` image and +1 to the `after` image.
+fn fold_signed(change: &RowChange, mut f: impl FnMut(&OrderRow, i64)) {
+    if let Some(before) = &change.before {
+        f(before, -1);
+    }
+    if let Some(after) = &change.after {
+        f(after, 1);
+    }
+}
+
+/// Add (`sign` 1) or remove (`sign` -1) `amount` from a group's running count and total.
+/// Returns whether the group is now empty and should be dropped from its map.
+fn adjust((count, total): &mut (usize, Money), sign: i64, amount: Money) -> bool {
+    if sign < 0 {
+        *count -= 1;
+        total.0 -= amount.0;
+    } else {
+        *count += 1;
+        total.0 += amount.0;
+    }
+    *count == 0
+}
+
+/// Revenue of completed orders per day, keyed by `timestamp / day_length`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RevenuePerDay {
+    day_length: u64,
+    days: BTreeMap<u64, (usize, Money)>,
+}
+
+impl Default for RevenuePerDay {
+    /// Days of 86 400 timestamp units (timestamps in seconds).
+    fn default() -> Self {
+        Self::with_day_length(86_400)
+    }
+}
+
+impl RevenuePerDay {
+    pub fn with_day_length(day_length: u64) -> Self {
+        Self {
+            day_length: day_length.max(1),
+            days: BTreeMap::new(),
+        }
+    }
+
+    /// Revenue of day number `day`.
+    pub fn revenue(&self, day: u64) -> Money {
+        self.days
+            .get(&day)
+            .map_or(Money::zero(), |&(_, total)| total)
+    }
+
+    /// Days with revenue, ascending.
+    pub fn iter(&self) -> impl Iterator<Item = (u64, Money)> + '_ {
+        self.days.iter().map(|(&day, &(_, total))| (day, total))
+    }
+}
+
+impl MaterializedView for RevenuePerDay {
+    fn init(&mut self) {
+        self.days.clear();
+    }
+
+    fn apply(&mut self, change: &RowChange) {
+        fold_signed(change, |row, sign| {
+            if row.status != Status::Completed {
+                return;
+            }
+            let day = row.ts / self.day_length;
+            let entry = self.days.entry(day).or_default();
+            if adjust(entry, sign, row.amount) {
+                self.days.remove(&day);
+            }
+        });
+    }
+}
+
+/// Number of pending orders.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct OpenOrderCount(pub usize);
+
+impl MaterializedView for OpenOrderCount {
+    fn init(&mut self) {
+        self.0 = 0;
+    }
+
+    fn apply(&mut self, change: &RowChange) {
+        fold_signed(change, |row, sign| {
+            if row.status == Status::Pending {
+                self.0 = self.0.wrapping_add_signed(sign as isize);
+            }
+        });
+    }
+}
+
+/// Amount of non-cancelled orders per customer.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CustomerTotals {
+    totals: HashMap<CustomerId, (usize, Money)>,
+}
+
+impl CustomerTotals {
+    pub fn total(&self, customer: CustomerId) -> Money {
+        self.totals
+            .get(&customer)
+            .map_or(Money::zero(), |&(_, total)| total)
+    }
+
+    /// Customers with at least one non-cancelled order.
+    pub fn len(&self) -> usize {
+        self.totals.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.totals.is_empty()
+    }
+}
+
+impl MaterializedView for CustomerTotals {
+    fn init(&mut self) {
+        self.totals.clear();
+    }
+
+    fn apply(&mut self, change: &RowChange) {
+        fold_signed(change, |row, sign| {
+            if row.status == Status::Cancelled {
+                return;
+            }
+            let entry = self.totals.entry(row.customer).or_default();
+            if adjust(entry, sign, row.amount) {
+                self.totals.remove(&row.customer);
+            }
+        });
+    }
+}
@@ -0,0 +1,536 @@
+//! Write-ahead log: a tiny embedded durable order book.
+//!
+//! `DurableOrderStore` wraps an `OrderStore` and appends a record for every mutation to a log
+//! file *before* applying it; reads go straight to the wrapped store. `OrderStore::recover`
+//! replays a log into a fresh store. Records are physical (a row image or a row index), so replay
+//! reproduces the exact row layout, handles included, whatever the `IdPolicy` was.
+//!
+//! File format: a sequence of `[len: u32][crc32(payload): u32][payload]`, little-endian. A record
+//! cut short by a crash is ignored on recovery and trimmed when the log is reopened; a complete
+//! record whose checksum does not match is reported as corruption. `checkpoint` rewrites the log
+//! as one insert per live row, which keeps it from growing without bound.
+//!
+//! The wrapper offers the store's row writes (`add`, `upsert`, `update_with`, `for_each_mut`,
+//! `transition_status`, ...), each logged as row images and then applied through the store, so
+//! validators, subscribers and views see them as usual. Aggregate commands and state-machine
+//! `transition` are not offered: settlement totals, state codes and outbox entries are not part
+//! of a row image, so the log could not restore them.
+
+use crate::{
+    Currency, CustomerId, IdPolicy, Money, OrderId, OrderMut, OrderRow, OrderSoA, OrderStore,
+    OrderView, RowHandle, Status, StoreError, TypedMoney, Validator,
+};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::ops::{Deref, Range};
+use std::path::{Path, PathBuf};
+use std::{fmt, mem};
+
+/// Failures opening, appending to or replaying a log.
+#[derive(Debug)]
+pub enum WalError {
+    Io(io::Error),
+    /// A complete record failed its checksum or did not decode, at this byte offset.
+    Corrupt {
+        offset: u64,
+    },
+    /// The mutation was rejected by the store; nothing was logged.
+    Store(StoreError),
+}
+
+impl fmt::Display for WalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WalError::Io(e) => write!(f, "wal i/o: {e}"),
+            WalError::Corrupt { offset } => write!(f, "corrupt wal record at byte {offset}"),
+            WalError::Store(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for WalError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            WalError::Io(e) => Some(e),
+            WalError::Store(e) => Some(e),
+            WalError::Corrupt { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for WalError {
+    fn from(e: io::Error) -> Self {
+        WalError::Io(e)
+    }
+}
+
+impl From<StoreError> for WalError {
+    fn from(e: StoreError) -> Self {
+        WalError::Store(e)
+    }
+}
+
+/// One logged mutation.
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Record {
+    /// Append a row.
+    Push(OrderRow),
+    /// Overwrite row `index` (its id excepted) with an after-image.
+    Set {
+        index: usize,
+        row: OrderRow,
+    },
+    /// Tombstone row `index`.
+    Remove {
+        index: usize,
+    },
+    Compact,
+}
+
+const PUSH: u8 = 1;
+const SET: u8 = 2;
+const REMOVE: u8 = 3;
+const COMPACT: u8 = 4;
+
+impl Record {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let start = out.len();
+        out.extend_from_slice(&[0; 8]); // length and checksum, filled in below
+        match *self {
+            Record::Push(row) => {
+                out.push(PUSH);
+                encode_row(&row, out);
+            }
+            Record::Set { index, row } => {
+                out.push(SET);
+                out.extend_from_slice(&(index as u64).to_le_bytes());
+                encode_row(&row, out);
+            }
+            Record::Remove { index } => {
+                out.push(REMOVE);
+                out.extend_from_slice(&(index as u64).to_le_bytes());
+            }
+            Record::Compact => out.push(COMPACT),
+        }
+        let payload = &out[start + 8..];
+        let (len, crc) = (payload.len() as u32, crc32(payload));
+        out[start..start + 4].copy_from_slice(&len.to_le_bytes());
+        out[start + 4..start + 8].copy_from_slice(&crc.to_le_bytes());
+    }
+
+    fn decode(payload: &[u8]) -> Option<Record> {
+        let (&tag, mut rest) = payload.split_first()?;
+        let record = match tag {
+            PUSH => Record::Push(decode_row(&mut rest)?),
+            SET => Record::Set {
+                index: take_u64(&mut rest)? as usize,
+                row: decode_row(&mut rest)?,
+            },
+            REMOVE => Record::Remove {
+                index: take_u64(&mut rest)? as usize,
+            },
+            COMPACT => Record::Compact,
+            _ => return None,
+        };
+        rest.is_empty().then_some(record)
+    }
+}
+
+fn encode_row(row: &OrderRow, out: &mut Vec<u8>) {
+    out.extend_from_slice(&row.id.0.to_le_bytes());
+    out.extend_from_slice(&row.amount.0.to_le_bytes());
+    out.extend_from_slice(&row.currency.0);
+    out.push(row.status as u8);
+    out.extend_from_slice(&row.ts.to_le_bytes());
+    out.extend_from_slice(&row.customer.0.to_le_bytes());
+}
+
+fn decode_row(buf: &mut &[u8]) -> Option<OrderRow> {
+    let id = OrderId(take_u64(buf)?);
+    let amount = Money(f64::from_bits(take_u64(buf)?));
+    let currency = Currency(take(buf)?);
+    let [status] = take(buf)?;
+    Some(OrderRow {
+        id,
+        amount,
+        currency,
+        status: *Status::ALL.get(status as usize)?,
+        ts: take_u64(buf)?,
+        customer: CustomerId(take_u64(buf)?),
+    })
+}
+
+fn take<const N: usize>(buf: &mut &[u8]) -> Option<[u8; N]> {
+    let (head, rest) = buf.split_first_chunk::<N>()?;
+    *buf = rest;
+    Some(*head)
+}
+
+fn take_u64(buf: &mut &[u8]) -> Option<u64> {
+    take(buf).map(u64::from_le_bytes)
+}
+
+/// CRC-32 (IEEE), bitwise: records are small, so a lookup table is not worth its weight.
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &b in bytes {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB8_8320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+/// Replay every complete record of a log into a fresh store. Returns the store and the byte
+/// length the records cover; a torn final record is left out.
+fn replay_log(bytes: &[u8]) -> Result<(OrderStore, usize), WalError> {
+    let mut store = OrderStore::new();
+    let mut pos = 0;
+    while let Some(header) = bytes.get(pos..pos + 8) {
+        let len = u32::from_le_bytes(header[..4].try_into().unwrap()) as usize;
+        let crc = u32::from_le_bytes(header[4..].try_into().unwrap());
+        let Some(payload) = bytes.get(pos + 8..pos + 8 + len) else {
+            break;
+        };
+        if crc32(payload) != crc {
+            return Err(WalError::Corrupt { offset: pos as u64 });
+        }
+        let record = Record::decode(payload).ok_or(WalError::Corrupt { offset: pos as u64 })?;
+        replay(&mut store, record).map_err(|_| WalError::Corrupt { offset: pos as u64 })?;
+        pos += 8 + len;
+    }
+    Ok((store, pos))
+}
+
+fn replay(store: &mut OrderStore, record: Record) -> Result<(), StoreError> {
+    let soa = store.kernel_mut();
+    match record {
+        Record::Push(row) => {
+            soa.push_row(row);
+        }
+        Record::Set { index, row } => {
+            soa.check_index(index)?;
+            soa.write_row(index, row);
+        }
+        Record::Remove { index } => {
+            soa.check_index(index)?;
+            soa.remove(soa.handle_at(index))?;
+        }
+        Record::Compact => {
+            soa.compact();
+        }
+    }
+    Ok(())
+}
+
+impl OrderStore {
+    /// Rebuild a store from the log at `path` (a missing file is an empty log). A torn final
+    /// record, left by a crash mid-append, is ignored.
+    pub fn recover(path: impl AsRef<Path>) -> Result<OrderStore, WalError> {
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+        replay_log(&bytes).map(|(store, _)| store)
+    }
+}
+
+/// An `OrderStore` whose mutations are logged to a file first. Derefs to the store for reads.
+pub struct DurableOrderStore {
+    store: OrderStore,
+    path: PathBuf,
+    file: File,
+    buf: Vec<u8>,
+}
+
+impl fmt::Debug for DurableOrderStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DurableOrderStore")
+            .field("path", &self.path)
+            .field("len", &self.store.kernel().len())
+            .finish()
+    }
+}
+
+impl Deref for DurableOrderStore {
+    type Target = OrderStore;
+
+    fn deref(&self) -> &OrderStore {
+        &self.store
+    }
+}
+
+impl DurableOrderStore {
+    /// Recover the store logged at `path` (creating the file if needed) and keep appending to it.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, WalError> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&path)?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        let (store, good) = replay_log(&bytes)?;
+        if good < bytes.len() {
+            file.set_len(good as u64)?;
+        }
+        Ok(Self {
+            store,
+            path,
+            file,
+            buf: Vec::new(),
+        })
+    }
+
+    /// Builder: the `IdPolicy` applied to `add`. Not itself logged; reopen with the same policy.
+    pub fn with_id_policy(mut self, policy: IdPolicy) -> Self {
+        self.store = mem::take(&mut self.store).with_id_policy(policy);
+        self
+    }
+
+    /// Builder: see `OrderStore::with_validator`. A rejected write is not logged. Recovery does
+    /// not run validators, since the log holds only writes they accepted.
+    pub fn with_validator(mut self, v: impl Validator + 'static) -> Self {
+        self.store = mem::take(&mut self.store).with_validator(v);
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn log(&mut self, records: &[Record]) -> Result<(), WalError> {
+        self.buf.clear();
+        records.iter().for_each(|r| r.encode(&mut self.buf));
+        self.file.write_all(&self.buf)?;
+        Ok(())
+    }
+
+    /// Flush logged records to stable storage. Appends reach the OS on every mutation; call this
+    /// where a power loss must not lose them.
+    pub fn sync(&self) -> io::Result<()> {
+        self.file.sync_data()
+    }
+
+    /// See `OrderStore::add`.
+    pub fn add(
+        &mut self,
+        id: OrderId,
+        amount: Money,
+        status: Status,
+        ts: u64,
+    ) -> Result<RowHandle, WalError> {
+        self.insert(
+            self.store.id_policy,
+            OrderRow::new(id, amount, status, ts),
+            false,
+        )
+    }
+
+    /// See `OrderStore::add_money`.
+    pub fn add_money(
+        &mut self,
+        id: OrderId,
+        money: TypedMoney,
+        status: Status,
+        ts: u64,
+    ) -> Result<RowHandle, WalError> {
+        let row = OrderRow {
+            currency: money.currency,
+            ..OrderRow::new(id, money.to_money(), status, ts)
+        };
+        self.insert(self.store.id_policy, row, true)
+    }
+
+    /// See `OrderStore::upsert`.
+    pub fn upsert(
+        &mut self,
+        id: OrderId,
+        amount: Money,
+        status: Status,
+        ts: u64,
+    ) -> Result<RowHandle, WalError> {
+        self.insert(
+            IdPolicy::Upsert,
+            OrderRow::new(id, amount, status, ts),
+            false,
+        )
+    }
+
+    fn insert(
+        &mut self,
+        policy: IdPolicy,
+        row: OrderRow,
+        priced: bool,
+    ) -> Result<RowHandle, WalError> {
+        let soa = self.store.kernel();
+        let existing = soa.id_index.get(&row.id).copied();
+        let record = match (existing, policy) {
+            (Some(_), IdPolicy::Reject) => return Err(StoreError::DuplicateId(row.id).into()),
+            (Some(index), IdPolicy::Upsert) => {
+                let stored = OrderRow::from(soa.view_at(index));
+                let row = row.upserted_over(stored, priced);
+                self.store.validators.check(row, Some(stored))?;
+                Record::Set { index, row }
+            }
+            _ => {
+                self.store.validators.check(row, None)?;
+                Record::Push(row)
+            }
+        };
+        self.log(&[record])?;
+        Ok(self
+            .store
+            .insert(policy, row, priced)
+            .expect("checked before logging"))
+    }
+
+    /// See `OrderStore::add_batch`. The batch is validated, then logged, then applied.
+    pub fn add_batch(
+        &mut self,
+        rows: impl IntoIterator<Item = OrderRow>,
+    ) -> Result<Range<usize>, WalError> {
+        let rows: Vec<OrderRow> = rows.into_iter().collect();
+        rows.iter()
+            .try_for_each(|&r| self.store.validators.check(r, None))?;
+        self.store.kernel().validate_rows(&rows)?;
+        let records: Vec<Record> = rows.iter().map(|&r| Record::Push(r)).collect();
+        self.log(&records)?;
+        Ok(self.store.add_batch(rows).expect("checked before logging"))
+    }
+
+    /// See `OrderStore::update_with`. `f` runs against a scratch copy of the row; its
+    /// after-image is logged, then written through the store.
+    pub fn update_with<R>(
+        &mut self,
+        id: OrderId,
+        f: impl FnOnce(OrderMut<'_>) -> R,
+    ) -> Result<R, WalError> {
+        let index = self.store.index_of(id)?;
+        let stored = OrderRow::from(self.store.kernel().view_at(index));
+        let mut scratch = OrderSoA::with_capacity(1);
+        scratch.push_row(stored);
+        let out = f(scratch.view_mut_at(0));
+        let row = OrderRow::from(scratch.view_at(0));
+        self.store.validators.check(row, Some(stored))?;
+        self.log(&[Record::Set { index, row }])?;
+        self.store
+            .update_with(id, |mut o| o.write(row))
+            .expect("checked before logging");
+        Ok(out)
+    }
+
+    /// See `OrderStore::update_if_version`.
+    pub fn update_if_version<R>(
+        &mut self,
+        id: OrderId,
+        expected: u64,
+        f: impl FnOnce(OrderMut<'_>) -> R,
+    ) -> Result<R, WalError> {
+        let found = self.store.kernel().versions[self.store.index_of(id)?];
+        if found != expected {
+            return Err(StoreError::VersionConflict {
+                id,
+                expected,
+                found,
+            }
+            .into());
+        }
+        self.update_with(id, f)
+    }
+
+    /// See `OrderStore::for_each_mut`. `f` runs against a scratch copy of the live rows; the
+    /// after-images of the rows it changed are logged, then written through the store.
+    pub fn for_each_mut<F: FnMut(OrderMut<'_>)>(&mut self, f: F) -> Result<(), WalError> {
+        let soa = self.store.kernel();
+        let live: Vec<usize> = (0..soa.len()).filter(|&i| soa.is_live(i)).collect();
+        let mut scratch = OrderSoA::with_capacity(live.len());
+        for &i in &live {
+            scratch.push_row(OrderRow::from(soa.view_at(i)));
+        }
+        scratch.for_each_mut(f);
+        let after: Vec<OrderRow> = scratch.iter().map(OrderRow::from).collect();
+        let records: Vec<Record> = live
+            .iter()
+            .zip(&after)
+            .filter(|&(&index, row)| OrderRow::from(soa.view_at(index)) != *row)
+            .map(|(&index, &row)| Record::Set { index, row })
+            .collect();
+        self.log(&records)?;
+        let mut after = after.into_iter();
+        self.store.for_each_mut(|mut o| {
+            o.write(after.next().expect("one image per live row"));
+        });
+        Ok(())
+    }
+
+    /// See `OrderStore::transition_status`. Every rewritten row is validated, then logged, then
+    /// written through the store.
+    pub fn transition_status(
+        &mut self,
+        from: Status,
+        to: Status,
+        pred: impl Fn(OrderView<'_>) -> bool,
+    ) -> Result<usize, WalError> {
+        let soa = self.store.kernel();
+        let rows = soa.transition_candidates(from, to, &pred);
+        let mut records = Vec::with_capacity(rows.len());
+        for &index in &rows {
+            let stored = OrderRow::from(soa.view_at(index));
+            let row = OrderRow {
+                status: to,
+                ..stored
+            };
+            self.store.validators.check(row, Some(stored))?;
+            records.push(Record::Set { index, row });
+        }
+        self.log(&records)?;
+        Ok(self
+            .store
+            .transition_status(from, to, pred)
+            .expect("checked before logging"))
+    }
+
+    /// See `OrderStore::remove`.
+    pub fn remove(&mut self, h: RowHandle) -> Result<(), WalError> {
+        let index = self.store.kernel().resolve(h)?;
+        self.log(&[Record::Remove { index }])?;
+        self.store.remove(h)?;
+        Ok(())
+    }
+
+    /// See `OrderStore::compact`.
+    pub fn compact(&mut self) -> Result<Vec<Option<usize>>, WalError> {
+        self.log(&[Record::Compact])?;
+        Ok(self.store.compact())
+    }
+
+    /// Compact the store and replace the log with one insert per live row, written to a
+    /// temporary file, synced and renamed over the log so a crash leaves either log intact. If
+    /// any step fails, the store is left uncompacted, as the old log describes it.
+    pub fn checkpoint(&mut self) -> Result<(), WalError> {
+        // The live rows, in order, are the compacted layout. The store is compacted only once
+        // the log describes it, so a failed checkpoint leaves the old log and layout in step.
+        self.buf.clear();
+        for v in self.store.kernel().iter() {
+            Record::Push(OrderRow::from(v)).encode(&mut self.buf);
+        }
+        let mut tmp_path = self.path.clone().into_os_string();
+        tmp_path.push(".tmp");
+        let mut tmp = File::create(&tmp_path)?;
+        tmp.write_all(&self.buf)?;
+        tmp.sync_all()?;
+        fs::rename(&tmp_path, &self.path)?;
+        self.store.compact();
+        self.file = OpenOptions::new().append(true).open(&self.path)?;
+        Ok(())
+    }
+
+    /// Stop logging and hand back the store.
+    pub fn into_inner(self) -> OrderStore {
+        self.store
+    }
+}
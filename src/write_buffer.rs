@@ -0,0 +1,201 @@
+//! Bounded staging in front of a `ConcurrentOrderStore`.
+//!
+//! Every write to a `ConcurrentOrderStore` copies its tail segment and publishes a new snapshot,
+//! so many small writes under heavy read traffic mean many copies. A `WriteBuffer` shared by the
+//! producers stages their rows instead and applies them with one `add_batch` once `capacity`
+//! rows have gathered: the producer whose row fills the buffer performs the flush, outside the
+//! staging lock. Flushes run one at a time, in arrival order. While one is in progress the
+//! buffer refills, and once it is full again producers wait (`push` blocks, `push_async`
+//! awaits, `try_push` hands the row back) until the flush completes: the store is never more
+//! than `capacity` rows behind, and producers slow to the pace at which it absorbs batches.
+//!
+//! Rows are not visible to readers until flushed; `flush` forces a partial batch out, and
+//! dropping the buffer flushes what is left. A batch the store refuses (a duplicate id) is
+//! retried row by row, and the refused rows are kept for `take_rejected`.
+
+use crate::{ConcurrentOrderStore, OrderId, OrderRow, StoreError};
+use std::mem;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+
+/// Counters since the buffer was created.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct WriteBufferStats {
+    /// Rows stored by flushes.
+    pub rows: usize,
+    /// Flushes that applied at least one row.
+    pub batches: usize,
+    /// Times a producer found the buffer full.
+    pub waits: usize,
+}
+
+#[derive(Default)]
+struct Staging {
+    rows: Vec<OrderRow>,
+    flushing: bool,
+    stats: WriteBufferStats,
+    rejected: Vec<(OrderId, StoreError)>,
+}
+
+/// Staged, batched writes with backpressure; see the module docs.
+pub struct WriteBuffer {
+    store: Arc<ConcurrentOrderStore>,
+    capacity: usize,
+    staging: Mutex<Staging>,
+    /// Signalled whenever a flush completes.
+    flushed: Condvar,
+    #[cfg(feature = "async")]
+    flushed_async: tokio::sync::Notify,
+}
+
+impl WriteBuffer {
+    /// A buffer applying batches of `capacity` rows (at least one) to `store`.
+    pub fn new(store: Arc<ConcurrentOrderStore>, capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            store,
+            capacity,
+            staging: Mutex::new(Staging {
+                rows: Vec::with_capacity(capacity),
+                ..Staging::default()
+            }),
+            flushed: Condvar::new(),
+            #[cfg(feature = "async")]
+            flushed_async: tokio::sync::Notify::new(),
+        }
+    }
+
+    pub fn store(&self) -> &Arc<ConcurrentOrderStore> {
+        &self.store
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Rows staged and not yet flushed.
+    pub fn len(&self) -> usize {
+        self.lock().rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn stats(&self) -> WriteBufferStats {
+        self.lock().stats
+    }
+
+    /// Rows the store refused since the last call, with the reason.
+    pub fn take_rejected(&self) -> Vec<(OrderId, StoreError)> {
+        mem::take(&mut self.lock().rejected)
+    }
+
+    /// Stage `row`, waiting while the buffer is full. Flushes if `row` fills the buffer.
+    pub fn push(&self, row: OrderRow) {
+        let mut st = self.lock();
+        if st.rows.len() >= self.capacity {
+            st.stats.waits += 1;
+            while st.rows.len() >= self.capacity {
+                st = self.flushed.wait(st).unwrap_or_else(|e| e.into_inner());
+            }
+        }
+        self.stage(st, row);
+    }
+
+    /// `push` without waiting: hands `row` back if the buffer is full.
+    pub fn try_push(&self, row: OrderRow) -> Result<(), OrderRow> {
+        let mut st = self.lock();
+        if st.rows.len() >= self.capacity {
+            st.stats.waits += 1;
+            return Err(row);
+        }
+        self.stage(st, row);
+        Ok(())
+    }
+
+    /// `push` for async producers: awaits instead of blocking the thread while the buffer is
+    /// full. A push that fills the buffer still flushes inline.
+    #[cfg(feature = "async")]
+    pub async fn push_async(&self, mut row: OrderRow) {
+        loop {
+            let flushed = self.flushed_async.notified();
+            let mut flushed = std::pin::pin!(flushed);
+            // Register before checking, so a flush finishing in between is not missed.
+            flushed.as_mut().enable();
+            match self.try_push(row) {
+                Ok(()) => return,
+                Err(back) => row = back,
+            }
+            flushed.await;
+        }
+    }
+
+    /// Apply every staged row now, after any flush already in progress; returns how many were
+    /// stored.
+    pub fn flush(&self) -> usize {
+        let mut st = self.lock();
+        while st.flushing {
+            st = self.flushed.wait(st).unwrap_or_else(|e| e.into_inner());
+        }
+        let before = st.stats.rows;
+        if !st.rows.is_empty() {
+            st = self.drain(st);
+        }
+        st.stats.rows - before
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Staging> {
+        self.staging.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn stage(&self, mut st: MutexGuard<'_, Staging>, row: OrderRow) {
+        st.rows.push(row);
+        if st.rows.len() >= self.capacity && !st.flushing {
+            drop(self.drain(st));
+        }
+    }
+
+    /// Flush batches until fewer than `capacity` rows are staged (always at least one batch).
+    /// The caller holds the lock with no flush in progress.
+    fn drain<'a>(&'a self, mut st: MutexGuard<'a, Staging>) -> MutexGuard<'a, Staging> {
+        st.flushing = true;
+        loop {
+            let batch = mem::replace(&mut st.rows, Vec::with_capacity(self.capacity));
+            drop(st);
+            let (stored, rejected) = self.apply(batch);
+            st = self.lock();
+            st.stats.rows += stored;
+            st.stats.batches += usize::from(stored > 0);
+            st.rejected.extend(rejected);
+            if st.rows.len() < self.capacity {
+                break;
+            }
+        }
+        st.flushing = false;
+        self.flushed.notify_all();
+        #[cfg(feature = "async")]
+        self.flushed_async.notify_waiters();
+        st
+    }
+
+    /// Store `batch` in one write, or row by row if the store refuses it as a whole.
+    fn apply(&self, batch: Vec<OrderRow>) -> (usize, Vec<(OrderId, StoreError)>) {
+        let n = batch.len();
+        if self.store.add_batch(batch.iter().copied()).is_ok() {
+            return (n, Vec::new());
+        }
+        let mut rejected = Vec::new();
+        for row in batch {
+            if let Err(e) = self.store.add_batch([row]) {
+                rejected.push((row.id, e));
+            }
+        }
+        (n - rejected.len(), rejected)
+    }
+}
+
+impl Drop for WriteBuffer {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}